@@ -1,4 +1,5 @@
 use ropey::Rope;
+use std::{cmp, collections::hash_map::DefaultHasher, hash::Hasher, ops::Range};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct OpaqueDiff {
@@ -58,6 +59,53 @@ impl OpaqueDiff {
             new_char_length: self.old_char_length,
         }
     }
+
+    /// Combines this diff with one applied right after it, into a single diff
+    /// spanning both edits. The indices of `next` are relative to the text
+    /// this diff produced.
+    pub fn merge(&self, next: &OpaqueDiff) -> Self {
+        if self.is_empty() {
+            return next.clone();
+        } else if next.is_empty() {
+            return self.clone();
+        }
+
+        let span_end = |diff: &OpaqueDiff| {
+            (
+                diff.char_index + diff.new_char_length,
+                diff.byte_index + diff.new_byte_length,
+            )
+        };
+        let (char_end, byte_end) = span_end(self);
+        let (next_char_end, next_byte_end) = (
+            next.char_index + next.old_char_length,
+            next.byte_index + next.old_byte_length,
+        );
+
+        // The unchanged text `next` touches outside of this diff's span
+        let char_index = cmp::min(self.char_index, next.char_index);
+        let byte_index = cmp::min(self.byte_index, next.byte_index);
+        let (max_char_end, max_byte_end) = (
+            cmp::max(char_end, next_char_end),
+            cmp::max(byte_end, next_byte_end),
+        );
+        Self {
+            byte_index,
+            old_byte_length: self.old_byte_length
+                + (self.byte_index - byte_index)
+                + (max_byte_end - byte_end),
+            new_byte_length: max_byte_end + next.new_byte_length
+                - next.old_byte_length
+                - byte_index,
+            char_index,
+            old_char_length: self.old_char_length
+                + (self.char_index - char_index)
+                + (max_char_end - char_end),
+            new_char_length: max_char_end + next.new_char_length
+                - next.old_char_length
+                - char_index,
+        }
+    }
 }
 
 pub struct DeleteOperation {
@@ -74,6 +122,239 @@ impl DeleteOperation {
     }
 }
 
+/// A block of lines that differ between two texts. `old_lines` are replaced by
+/// `new_lines`, either of which may be empty.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineHunk {
+    pub old_lines: Range<usize>,
+    pub new_lines: Range<usize>,
+}
+
+/// Computes a set of edits turning `old` into `new`.
+///
+/// The texts are first compared line by line using Myers' algorithm, then
+/// each changed block of lines is narrowed down to the characters that
+/// actually differ. The returned diffs are ordered by position and meant to
+/// be applied in order, i.e. the indices of each diff take into account the
+/// ones preceding it. Identical texts produce no diffs.
+pub fn diff_ropes(old: &Rope, new: &Rope) -> Vec<OpaqueDiff> {
+    line_hunks(old, new)
+        .into_iter()
+        .filter_map(|hunk| diff_lines(old, new, hunk.old_lines, hunk.new_lines))
+        .collect()
+}
+
+/// Turns `text` into `new_text` with a single edit spanning from the first to
+/// the last change, e.g. to reload a file as one undoable revision. Returns an
+/// empty diff if the texts are identical.
+pub fn replace_text(text: &mut Rope, new_text: &Rope) -> OpaqueDiff {
+    let diffs = diff_ropes(text, new_text);
+    let (first, last) = match (diffs.first(), diffs.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return OpaqueDiff::empty(),
+    };
+
+    // The texts are identical before the first change and after the last one
+    let start = first.char_index;
+    let new_end = last.char_index + last.new_char_length;
+    let old_end = text.len_chars() - (new_text.len_chars() - new_end);
+    let old_byte_length = text.char_to_byte(old_end) - first.byte_index;
+
+    text.remove(start..old_end);
+    text.insert(start, &new_text.slice(start..new_end).to_string());
+    OpaqueDiff::new(
+        first.byte_index,
+        old_byte_length,
+        new_text.char_to_byte(new_end) - first.byte_index,
+        start,
+        old_end - start,
+        new_end - start,
+    )
+}
+
+/// Compares two texts line by line using Myers' algorithm, returning the
+/// blocks of lines that changed ordered by position. Identical texts produce
+/// no hunks.
+pub fn line_hunks(old: &Rope, new: &Rope) -> Vec<LineHunk> {
+    if old == new {
+        return Vec::new();
+    }
+
+    let old_lines = line_hashes(old);
+    let new_lines = line_hashes(new);
+
+    // Skip the lines the texts have in common at the start and the end
+    let equal = |old_index: usize, new_index: usize| {
+        old_lines[old_index] == new_lines[new_index] && old.line(old_index) == new.line(new_index)
+    };
+    let prefix = (0..cmp::min(old_lines.len(), new_lines.len()))
+        .take_while(|&index| equal(index, index))
+        .count();
+    let suffix = (0..cmp::min(old_lines.len(), new_lines.len()) - prefix)
+        .take_while(|&offset| equal(old_lines.len() - offset - 1, new_lines.len() - offset - 1))
+        .count();
+    let old_range = prefix..old_lines.len() - suffix;
+    let new_range = prefix..new_lines.len() - suffix;
+
+    // Find the matching lines in between, falling back to replacing the
+    // whole block if the texts are too different
+    let matches = matching_lines(old_range.len(), new_range.len(), |old_index, new_index| {
+        equal(old_range.start + old_index, new_range.start + new_index)
+    })
+    .unwrap_or_default();
+
+    // The blocks between consecutive matching lines are the ones that changed
+    let mut hunks = Vec::new();
+    let (mut old_line, mut new_line) = (old_range.start, new_range.start);
+    for (old_match, new_match) in matches
+        .into_iter()
+        .map(|(old_index, new_index)| (old_range.start + old_index, new_range.start + new_index))
+        .chain(std::iter::once((old_range.end, new_range.end)))
+    {
+        if old_line < old_match || new_line < new_match {
+            hunks.push(LineHunk {
+                old_lines: old_line..old_match,
+                new_lines: new_line..new_match,
+            });
+        }
+        old_line = old_match + 1;
+        new_line = new_match + 1;
+    }
+    hunks
+}
+
+/// Narrows down a block of changed lines to the range of characters that
+/// differ, returning a diff relative to the new text.
+fn diff_lines(
+    old: &Rope,
+    new: &Rope,
+    old_lines: Range<usize>,
+    new_lines: Range<usize>,
+) -> Option<OpaqueDiff> {
+    let (mut old_start, mut old_end) = (
+        old.line_to_char(old_lines.start),
+        old.line_to_char(old_lines.end),
+    );
+    let (mut new_start, mut new_end) = (
+        new.line_to_char(new_lines.start),
+        new.line_to_char(new_lines.end),
+    );
+
+    let prefix = old
+        .slice(old_start..old_end)
+        .chars()
+        .zip(new.slice(new_start..new_end).chars())
+        .take_while(|(old_char, new_char)| old_char == new_char)
+        .count();
+    old_start += prefix;
+    new_start += prefix;
+
+    let mut old_chars = old.slice(old_start..old_end).chars_at(old_end - old_start);
+    let mut new_chars = new.slice(new_start..new_end).chars_at(new_end - new_start);
+    while let (Some(old_char), Some(new_char)) = (old_chars.prev(), new_chars.prev()) {
+        if old_char != new_char {
+            break;
+        }
+        old_end -= 1;
+        new_end -= 1;
+    }
+
+    if old_start == old_end && new_start == new_end {
+        return None;
+    }
+
+    let byte_index = new.char_to_byte(new_start);
+    Some(OpaqueDiff::new(
+        byte_index,
+        old.char_to_byte(old_end) - old.char_to_byte(old_start),
+        new.char_to_byte(new_end) - byte_index,
+        new_start,
+        old_end - old_start,
+        new_end - new_start,
+    ))
+}
+
+/// Finds a longest sequence of matching lines between two texts with
+/// `old_length` and `new_length` lines using Myers' O(ND) algorithm. Returns
+/// `None` if the texts differ by more than `MAX_EDIT_DISTANCE` lines.
+fn matching_lines(
+    old_length: usize,
+    new_length: usize,
+    equal: impl Fn(usize, usize) -> bool,
+) -> Option<Vec<(usize, usize)>> {
+    let (old_length, new_length) = (old_length as isize, new_length as isize);
+    let max_distance = cmp::min(old_length + new_length, MAX_EDIT_DISTANCE);
+
+    // The furthest reaching x for each diagonal k = x - y. We store a copy
+    // of the diagonals reachable at each step in `trace` to backtrack later.
+    let offset = max_distance + 1;
+    let mut furthest = vec![0isize; 2 * offset as usize + 1];
+    let mut trace = Vec::new();
+    let furthest_at = |furthest: &[isize], k: isize| furthest[(offset + k) as usize];
+
+    let mut distance = None;
+    'search: for d in 0..=max_distance {
+        trace.push(furthest[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d
+                || (k != d && furthest_at(&furthest, k - 1) < furthest_at(&furthest, k + 1))
+            {
+                furthest_at(&furthest, k + 1)
+            } else {
+                furthest_at(&furthest, k - 1) + 1
+            };
+            let mut y = x - k;
+            while x < old_length && y < new_length && equal(x as usize, y as usize) {
+                x += 1;
+                y += 1;
+            }
+            furthest[(offset + k) as usize] = x;
+            if x >= old_length && y >= new_length {
+                distance = Some(d);
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back through the trace collecting the diagonal moves
+    let mut matches = Vec::new();
+    let (mut x, mut y) = (old_length, new_length);
+    for d in (0..=distance?).rev() {
+        let furthest = &trace[d as usize];
+        let furthest_at = |k: isize| furthest[(k + d + 1) as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && furthest_at(k - 1) < furthest_at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = furthest_at(previous_k);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            matches.push((x as usize, y as usize));
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    matches.reverse();
+    Some(matches)
+}
+
+fn line_hashes(text: &Rope) -> Vec<u64> {
+    text.lines()
+        .map(|line| {
+            let mut hasher = DefaultHasher::new();
+            line.chunks()
+                .for_each(|chunk| hasher.write(chunk.as_bytes()));
+            hasher.finish()
+        })
+        .collect()
+}
+
+const MAX_EDIT_DISTANCE: isize = 1024;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,4 +366,193 @@ mod tests {
         assert_eq!(std::mem::size_of::<Rope>(), 8);
         assert_eq!(std::mem::size_of::<DeleteOperation>(), 56);
     }
+
+    fn apply_diffs(old: &Rope, new: &Rope, diffs: &[OpaqueDiff]) -> Rope {
+        let mut text = old.clone();
+        for diff in diffs {
+            let start = diff.char_index;
+            assert_eq!(diff.byte_index, text.char_to_byte(start));
+            assert_eq!(
+                diff.old_byte_length,
+                text.char_to_byte(start + diff.old_char_length) - diff.byte_index
+            );
+            text.remove(start..start + diff.old_char_length);
+            text.insert(
+                start,
+                &new.slice(start..start + diff.new_char_length).to_string(),
+            );
+        }
+        text
+    }
+
+    fn apply_diff(text: &mut Rope, diff: &OpaqueDiff, replacement: &str) {
+        text.remove(diff.char_index..diff.char_index + diff.old_char_length);
+        text.insert(diff.char_index, replacement);
+    }
+
+    #[test]
+    fn merge_diffs() {
+        let old = Rope::from("The flowers were blooming.\n");
+        let edits = [
+            (
+                OpaqueDiff::new(4, 7, 5, 4, 7, 5),
+                "roses",
+                OpaqueDiff::new(0, 3, 1, 0, 3, 1),
+                "A",
+            ),
+            (
+                OpaqueDiff::new(4, 0, 4, 4, 0, 4),
+                "red ",
+                OpaqueDiff::new(17, 4, 3, 17, 4, 3),
+                "are",
+            ),
+            (
+                OpaqueDiff::new(4, 7, 0, 4, 7, 0),
+                "",
+                OpaqueDiff::new(4, 1, 7, 4, 1, 7),
+                "plants ",
+            ),
+        ];
+        for (first, first_text, second, second_text) in edits {
+            let mut text = old.clone();
+            apply_diff(&mut text, &first, first_text);
+            apply_diff(&mut text, &second, second_text);
+
+            let merged = first.merge(&second);
+            let mut merged_text = old.clone();
+            let start = merged.char_index;
+            let replacement = text
+                .slice(start..start + merged.new_char_length)
+                .to_string();
+            apply_diff(&mut merged_text, &merged, &replacement);
+            assert_eq!(text, merged_text);
+        }
+
+        let diff = OpaqueDiff::new(4, 7, 5, 4, 7, 5);
+        assert_eq!(diff, diff.merge(&OpaqueDiff::empty()));
+        assert_eq!(diff, OpaqueDiff::empty().merge(&diff));
+    }
+
+    #[test]
+    fn diff_identical_ropes() {
+        let text = Rope::from("The flowers were blooming.\nAt the market\n");
+        assert!(diff_ropes(&text, &text.clone()).is_empty());
+        assert!(diff_ropes(&Rope::new(), &Rope::new()).is_empty());
+    }
+
+    #[test]
+    fn diff_single_change() {
+        let old = Rope::from("Buy a milk goat\nAt the market\nToday\n");
+        let new = Rope::from("Buy a milk goat\nAt the supermarket\nToday\n");
+        assert_eq!(
+            vec![OpaqueDiff::new(23, 0, 5, 23, 0, 5)],
+            diff_ropes(&old, &new)
+        );
+
+        let new = Rope::from("Buy a milk goat\nToday\n");
+        assert_eq!(
+            vec![OpaqueDiff::new(16, 14, 0, 16, 14, 0)],
+            diff_ropes(&old, &new)
+        );
+    }
+
+    #[test]
+    fn diff_multiple_changes() {
+        let pairs = [
+            ("", "The flowers were blooming.\n"),
+            ("The flowers were blooming.\n", ""),
+            ("a\nb\nc\nd\ne\nf\n", "a\nB\nc\nd\nx\ny\ne\n"),
+            ("a\nb\nc\nd\ne\nf\n", "f\ne\nd\nc\nb\na\n"),
+            ("豈 更\n車 Ⅷ\n", "豈 更 車\n👨‍👨‍👧‍👧\n車 Ⅷ"),
+            (
+                "fn main() {\n}\n",
+                "use std::fmt;\n\nfn main() {\n    todo!()\n}\n",
+            ),
+        ];
+        for (old, new) in pairs {
+            let (old, new) = (Rope::from(old), Rope::from(new));
+            let diffs = diff_ropes(&old, &new);
+            assert!(!diffs.is_empty());
+            assert_eq!(new, apply_diffs(&old, &new, &diffs));
+        }
+    }
+
+    #[test]
+    fn replace_text_with_single_diff() {
+        let mut text = Rope::from("a\nb\nc\nd\ne\nf\n");
+        let new = Rope::from("a\nB\nc\nd\nx\ny\ne\n");
+        let diff = replace_text(&mut text, &new);
+        assert_eq!(new, text);
+        assert_eq!(OpaqueDiff::new(2, 10, 12, 2, 10, 12), diff);
+
+        assert!(replace_text(&mut text, &new.clone()).is_empty());
+
+        let mut text = Rope::from("豈 更\n車 Ⅷ\n");
+        let new = Rope::from("豈 更 車\n👨‍👨‍👧‍👧\n車 Ⅷ");
+        let diff = replace_text(&mut text, &new);
+        assert_eq!(new, text);
+        assert_eq!(new.char_to_byte(diff.char_index), diff.byte_index);
+    }
+
+    #[test]
+    fn line_hunks_of_changes() {
+        let old = Rope::from("a\nb\nc\nd\ne\nf\n");
+        assert!(line_hunks(&old, &old.clone()).is_empty());
+
+        let new = Rope::from("a\nB\nc\nd\nx\ny\ne\n");
+        assert_eq!(
+            vec![
+                LineHunk {
+                    old_lines: 1..2,
+                    new_lines: 1..2
+                },
+                LineHunk {
+                    old_lines: 4..4,
+                    new_lines: 4..6
+                },
+                LineHunk {
+                    old_lines: 5..6,
+                    new_lines: 7..7
+                },
+            ],
+            line_hunks(&old, &new)
+        );
+    }
+
+    #[test]
+    fn line_hunks_against_empty_text() {
+        let text = Rope::from("The flowers were blooming.\nAt the market\n");
+        assert_eq!(
+            vec![LineHunk {
+                old_lines: 0..0,
+                new_lines: 0..2
+            }],
+            line_hunks(&Rope::new(), &text)
+        );
+        assert_eq!(
+            vec![LineHunk {
+                old_lines: 0..2,
+                new_lines: 0..0
+            }],
+            line_hunks(&text, &Rope::new())
+        );
+    }
+
+    #[test]
+    fn diff_large_ropes() {
+        let old: Rope = (0..100_000)
+            .map(|index| format!("line {}\n", index))
+            .collect::<String>()
+            .into();
+        let new: Rope = (0..100_000)
+            .map(|index| match index % 10_000 {
+                0 => format!("changed line {}\n", index),
+                _ => format!("line {}\n", index),
+            })
+            .collect::<String>()
+            .into();
+        let diffs = diff_ropes(&old, &new);
+        assert_eq!(10, diffs.len());
+        assert_eq!(new, apply_diffs(&old, &new, &diffs));
+    }
 }
@@ -0,0 +1,164 @@
+use ropey::Rope;
+use std::cmp;
+
+use crate::{CharIndex, OpaqueDiff};
+
+/// The places a text was recently edited, to jump back to them. An edit on
+/// the same line as the most recent one moves it rather than adding a point,
+/// so typing a sentence leaves a single point.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EditPoints {
+    // Oldest first
+    points: Vec<CharIndex>,
+    // The point last jumped to, while cycling through them
+    current: Option<usize>,
+}
+
+impl EditPoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an edit which left the cursor at `char_index` of the edited
+    /// `text`. Only the latest `MAX_EDIT_POINTS` edits are kept.
+    pub fn record(&mut self, text: &Rope, char_index: CharIndex) {
+        self.current = None;
+        let line_index = text.char_to_line(char_index);
+        if let Some(last) = self.points.last_mut() {
+            if text.char_to_line(cmp::min(*last, text.len_chars())) == line_index {
+                *last = char_index;
+                return;
+            }
+        }
+        if self.points.len() == MAX_EDIT_POINTS {
+            self.points.remove(0);
+        }
+        self.points.push(char_index);
+    }
+
+    /// Moves the points to stay on the same text after an edit. A point in
+    /// text that was replaced or deleted moves to the start of the edit.
+    pub fn reconcile(&mut self, diff: &OpaqueDiff) {
+        let OpaqueDiff {
+            char_index,
+            old_char_length,
+            new_char_length,
+            ..
+        } = *diff;
+        for point in self.points.iter_mut() {
+            if *point >= char_index + old_char_length {
+                *point = *point + new_char_length - old_char_length;
+            } else if *point > char_index {
+                *point = char_index;
+            }
+        }
+        self.points.dedup();
+        self.current = self.current.filter(|&current| current < self.points.len());
+    }
+
+    /// Drops the points of the edits an undo reverted, those in the text it
+    /// changed, and moves the others like [`reconcile`](Self::reconcile)
+    pub fn reconcile_undo(&mut self, diff: &OpaqueDiff) {
+        let changed_end = diff.char_index + cmp::max(diff.old_char_length, diff.new_char_length);
+        self.points
+            .retain(|&point| point < diff.char_index || point > changed_end);
+        self.current = None;
+        self.reconcile(diff);
+    }
+
+    /// The point to jump to from the cursor at `char_index`. It's the most
+    /// recent edit, or the one before the point the cursor is at if it was
+    /// just jumped to, cycling back to the most recent after the oldest.
+    pub fn previous(&mut self, char_index: CharIndex) -> Option<CharIndex> {
+        let last = self.points.len().checked_sub(1)?;
+        let index = match self.current {
+            Some(current) if self.points[current] == char_index => {
+                current.checked_sub(1).unwrap_or(last)
+            }
+            _ if self.points[last] == char_index => last.checked_sub(1).unwrap_or(last),
+            _ => last,
+        };
+        self.current = Some(index);
+        Some(self.points[index])
+    }
+}
+
+const MAX_EDIT_POINTS: usize = 16;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cursor;
+
+    fn points(text: &Rope, char_indices: &[CharIndex]) -> EditPoints {
+        let mut edit_points = EditPoints::new();
+        for &char_index in char_indices {
+            edit_points.record(text, char_index);
+        }
+        edit_points
+    }
+
+    #[test]
+    fn edits_on_the_same_line_share_a_point() {
+        let text = Rope::from("one\ntwo\nthree\n");
+        let edit_points = points(&text, &[1, 2, 5, 9, 10, 1]);
+        assert_eq!(vec![2, 5, 10, 1], edit_points.points);
+
+        let text = Rope::from("x\n".repeat(MAX_EDIT_POINTS + 4));
+        let char_indices: Vec<_> = (0..MAX_EDIT_POINTS + 4).map(|line| line * 2).collect();
+        let edit_points = points(&text, &char_indices);
+        assert_eq!(&char_indices[4..], edit_points.points.as_slice());
+    }
+
+    #[test]
+    fn reconcile_edit_points_after_edits() {
+        let mut text = Rope::from("one\ntwo\nthree\nfour\n");
+        let mut edit_points = points(&text, &[2, 6, 12, 16]);
+
+        // An insertion moves the points after it
+        let diff = Cursor::with_range(5..6).insert_chars(&mut text, "xx".chars());
+        edit_points.reconcile(&diff);
+        assert_eq!(vec![2, 8, 14, 18], edit_points.points);
+
+        // Points in deleted text move to the start of the deletion
+        let diff = Cursor::new().delete_range(&mut text, 1..11).diff;
+        edit_points.reconcile(&diff);
+        assert_eq!(vec![1, 4, 8], edit_points.points);
+        assert_eq!(Rope::from("ohree\nfour\n"), text);
+    }
+
+    #[test]
+    fn undoing_an_edit_drops_its_point() {
+        let mut text = Rope::from("one\ntwo\nthree\n");
+        let mut edit_points = points(&text, &[2]);
+        let mut cursor = Cursor::with_range(5..6);
+        let diff = cursor.insert_chars(&mut text, "xyz".chars());
+        edit_points.reconcile(&diff);
+        edit_points.record(&text, 8);
+        assert_eq!(vec![2, 8], edit_points.points);
+
+        // Undoing the insertion deletes it again
+        let undo = Cursor::new().delete_range(&mut text, 5..8).diff;
+        edit_points.reconcile_undo(&undo);
+        assert_eq!(vec![2], edit_points.points);
+    }
+
+    #[test]
+    fn jump_through_edit_points() {
+        let text = Rope::from("one\ntwo\nthree\n");
+        let mut edit_points = points(&text, &[1, 5, 9]);
+        assert_eq!(Some(9), edit_points.previous(0));
+        assert_eq!(Some(5), edit_points.previous(9));
+        assert_eq!(Some(1), edit_points.previous(5));
+        assert_eq!(Some(9), edit_points.previous(1));
+
+        // Moving away starts again from the most recent edit
+        edit_points.previous(9);
+        assert_eq!(Some(9), edit_points.previous(3));
+
+        // Already at the most recent edit
+        let mut edit_points = points(&text, &[1, 5]);
+        assert_eq!(Some(1), edit_points.previous(5));
+        assert_eq!(None, EditPoints::new().previous(0));
+    }
+}
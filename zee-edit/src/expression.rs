@@ -0,0 +1,383 @@
+use ropey::Rope;
+use std::{fmt, iter::Peekable, ops::Range, str::CharIndices};
+
+use crate::CharIndex;
+
+/// The value of an arithmetic expression. Integers stay integers unless an
+/// operation needs a fraction, e.g. `7 / 2`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn to_float(self) -> f64 {
+        match self {
+            Self::Integer(value) => value as f64,
+            Self::Float(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Integer(value) => write!(formatter, "{}", value),
+            // Keep a fractional part on whole floats, so `1.5 * 2` is `3.0`
+            Self::Float(value) if value.fract() == 0.0 && value.abs() < 1e16 => {
+                write!(formatter, "{:.1}", value)
+            }
+            Self::Float(value) => write!(formatter, "{}", value),
+        }
+    }
+}
+
+/// Why an expression couldn't be evaluated
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExpressionError {
+    /// The expression isn't well formed, at the given byte offset
+    Parse {
+        offset: usize,
+        message: String,
+    },
+    DivisionByZero,
+    /// The result doesn't fit in a number
+    Overflow,
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Parse { offset, message } => {
+                write!(formatter, "{} at column {}", message, offset + 1)
+            }
+            Self::DivisionByZero => write!(formatter, "Division by zero"),
+            Self::Overflow => write!(formatter, "The result is too large"),
+        }
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+/// Evaluates an arithmetic expression with `+ - * / % ^`, parentheses and
+/// integer or float literals, e.g. `2 + 3 * 4`. `^` is exponentiation and
+/// binds tighter than a leading minus, so `-2 ^ 2` is `-4`.
+pub fn evaluate(input: &str) -> Result<Number, ExpressionError> {
+    let mut parser = Parser {
+        input,
+        chars: input.char_indices().peekable(),
+    };
+    let value = parser.expression()?;
+    match parser.peek() {
+        None => Ok(value),
+        Some((offset, character)) => {
+            Err(parser.error(offset, format!("Unexpected `{}`", character)))
+        }
+    }
+}
+
+/// The arithmetic expression around `char_index` on its line, i.e. the run of
+/// digits, operators, parentheses and spaces containing it, without the
+/// surrounding spaces. Returns `None` if there's no number there.
+pub fn expression_at(text: &Rope, char_index: CharIndex) -> Option<Range<CharIndex>> {
+    let is_expression_char =
+        |character: char| character.is_ascii_digit() || "+-*/%^(). ".contains(character);
+
+    let line_index = text.char_to_line(char_index);
+    let line_start = text.line_to_char(line_index);
+    let line_end = line_start + text.line(line_index).len_chars();
+
+    let mut start = char_index;
+    while start > line_start && is_expression_char(text.char(start - 1)) {
+        start -= 1;
+    }
+    let mut end = char_index;
+    while end < line_end && is_expression_char(text.char(end)) {
+        end += 1;
+    }
+    while start < end && text.char(start) == ' ' {
+        start += 1;
+    }
+    while end > start && text.char(end - 1) == ' ' {
+        end -= 1;
+    }
+
+    text.slice(start..end)
+        .chars()
+        .any(|character| character.is_ascii_digit())
+        .then(|| start..end)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    // expression = term (("+" | "-") term)*
+    fn expression(&mut self) -> Result<Number, ExpressionError> {
+        let mut value = self.term()?;
+        while let Some((_, operator @ ('+' | '-'))) = self.peek() {
+            self.chars.next();
+            let rhs = self.term()?;
+            value = match operator {
+                '+' => add(value, rhs)?,
+                _ => subtract(value, rhs)?,
+            };
+        }
+        Ok(value)
+    }
+
+    // term = unary (("*" | "/" | "%") unary)*
+    fn term(&mut self) -> Result<Number, ExpressionError> {
+        let mut value = self.unary()?;
+        while let Some((_, operator @ ('*' | '/' | '%'))) = self.peek() {
+            self.chars.next();
+            let rhs = self.unary()?;
+            value = match operator {
+                '*' => multiply(value, rhs)?,
+                '/' => divide(value, rhs)?,
+                _ => remainder(value, rhs)?,
+            };
+        }
+        Ok(value)
+    }
+
+    // unary = ("-" | "+") unary | power
+    fn unary(&mut self) -> Result<Number, ExpressionError> {
+        match self.peek() {
+            Some((_, '-')) => {
+                self.chars.next();
+                subtract(Number::Integer(0), self.unary()?)
+            }
+            Some((_, '+')) => {
+                self.chars.next();
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    // power = atom ("^" unary)?, right associative
+    fn power(&mut self) -> Result<Number, ExpressionError> {
+        let base = self.atom()?;
+        match self.peek() {
+            Some((_, '^')) => {
+                self.chars.next();
+                let exponent = self.unary()?;
+                power(base, exponent)
+            }
+            _ => Ok(base),
+        }
+    }
+
+    // atom = number | "(" expression ")"
+    fn atom(&mut self) -> Result<Number, ExpressionError> {
+        match self.peek() {
+            Some((_, '(')) => {
+                self.chars.next();
+                let value = self.expression()?;
+                match self.peek() {
+                    Some((_, ')')) => {
+                        self.chars.next();
+                        Ok(value)
+                    }
+                    Some((offset, _)) => Err(self.error(offset, "Expected `)`")),
+                    None => Err(self.error(self.input.len(), "Expected `)`")),
+                }
+            }
+            Some((offset, character)) if character.is_ascii_digit() || character == '.' => {
+                self.number(offset)
+            }
+            Some((offset, character)) => {
+                Err(self.error(offset, format!("Unexpected `{}`", character)))
+            }
+            None => Err(self.error(self.input.len(), "Expected a number")),
+        }
+    }
+
+    fn number(&mut self, start: usize) -> Result<Number, ExpressionError> {
+        let mut end = start;
+        while let Some(&(offset, character)) = self.chars.peek() {
+            if !(character.is_ascii_digit() || character == '.') {
+                break;
+            }
+            end = offset + 1;
+            self.chars.next();
+        }
+        let literal = &self.input[start..end];
+        if literal.contains('.') {
+            literal
+                .parse()
+                .map(Number::Float)
+                .map_err(|_| self.error(start, format!("Invalid number `{}`", literal)))
+        } else {
+            literal
+                .parse()
+                .map(Number::Integer)
+                .map_err(|_| ExpressionError::Overflow)
+        }
+    }
+
+    /// The next character which isn't whitespace
+    fn peek(&mut self) -> Option<(usize, char)> {
+        while let Some(&(_, character)) = self.chars.peek() {
+            if !character.is_whitespace() {
+                break;
+            }
+            self.chars.next();
+        }
+        self.chars.peek().copied()
+    }
+
+    fn error(&self, offset: usize, message: impl Into<String>) -> ExpressionError {
+        ExpressionError::Parse {
+            offset,
+            message: message.into(),
+        }
+    }
+}
+
+fn add(lhs: Number, rhs: Number) -> Result<Number, ExpressionError> {
+    match (lhs, rhs) {
+        (Number::Integer(lhs), Number::Integer(rhs)) => lhs
+            .checked_add(rhs)
+            .map(Number::Integer)
+            .ok_or(ExpressionError::Overflow),
+        _ => finite(lhs.to_float() + rhs.to_float()),
+    }
+}
+
+fn subtract(lhs: Number, rhs: Number) -> Result<Number, ExpressionError> {
+    match (lhs, rhs) {
+        (Number::Integer(lhs), Number::Integer(rhs)) => lhs
+            .checked_sub(rhs)
+            .map(Number::Integer)
+            .ok_or(ExpressionError::Overflow),
+        _ => finite(lhs.to_float() - rhs.to_float()),
+    }
+}
+
+fn multiply(lhs: Number, rhs: Number) -> Result<Number, ExpressionError> {
+    match (lhs, rhs) {
+        (Number::Integer(lhs), Number::Integer(rhs)) => lhs
+            .checked_mul(rhs)
+            .map(Number::Integer)
+            .ok_or(ExpressionError::Overflow),
+        _ => finite(lhs.to_float() * rhs.to_float()),
+    }
+}
+
+fn divide(lhs: Number, rhs: Number) -> Result<Number, ExpressionError> {
+    match (lhs, rhs) {
+        _ if rhs.to_float() == 0.0 => Err(ExpressionError::DivisionByZero),
+        // Integers which divide exactly stay integers
+        (Number::Integer(lhs), Number::Integer(rhs)) if lhs.checked_rem(rhs) == Some(0) => lhs
+            .checked_div(rhs)
+            .map(Number::Integer)
+            .ok_or(ExpressionError::Overflow),
+        _ => finite(lhs.to_float() / rhs.to_float()),
+    }
+}
+
+fn remainder(lhs: Number, rhs: Number) -> Result<Number, ExpressionError> {
+    match (lhs, rhs) {
+        _ if rhs.to_float() == 0.0 => Err(ExpressionError::DivisionByZero),
+        (Number::Integer(lhs), Number::Integer(rhs)) => lhs
+            .checked_rem(rhs)
+            .map(Number::Integer)
+            .ok_or(ExpressionError::Overflow),
+        _ => finite(lhs.to_float() % rhs.to_float()),
+    }
+}
+
+fn power(base: Number, exponent: Number) -> Result<Number, ExpressionError> {
+    match (base, exponent) {
+        (Number::Integer(base), Number::Integer(exponent)) if exponent >= 0 => {
+            u32::try_from(exponent)
+                .ok()
+                .and_then(|exponent| base.checked_pow(exponent))
+                .map(Number::Integer)
+                .ok_or(ExpressionError::Overflow)
+        }
+        (Number::Integer(0), exponent) if exponent.to_float() < 0.0 => {
+            Err(ExpressionError::DivisionByZero)
+        }
+        _ => finite(base.to_float().powf(exponent.to_float())),
+    }
+}
+
+fn finite(value: f64) -> Result<Number, ExpressionError> {
+    if value.is_finite() {
+        Ok(Number::Float(value))
+    } else {
+        Err(ExpressionError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> String {
+        evaluate(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn operators_follow_precedence() {
+        assert_eq!("14", eval("2 + 3 * 4"));
+        assert_eq!("20", eval("(2 + 3) * 4"));
+        assert_eq!("1", eval("7 % 3 - 10 / 5 + 2"));
+        assert_eq!("512", eval("2 ^ 3 ^ 2"));
+        assert_eq!("-4", eval("-2^2"));
+        assert_eq!("-3", eval("-(1 + 2)"));
+        assert_eq!("0.5", eval("2 ^ -1"));
+    }
+
+    #[test]
+    fn integers_and_floats_are_preserved() {
+        assert_eq!(Number::Integer(4), evaluate("8 / 2").unwrap());
+        assert_eq!("3.5", eval("7 / 2"));
+        assert_eq!("3.0", eval("1.5 * 2"));
+        assert_eq!("0.75", eval(".5 + .25"));
+        assert_eq!("2.5", eval("5.5 % 3"));
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        assert_eq!(Err(ExpressionError::DivisionByZero), evaluate("1 / 0"));
+        assert_eq!(
+            Err(ExpressionError::DivisionByZero),
+            evaluate("1 % (2 - 2)")
+        );
+        assert_eq!(Err(ExpressionError::DivisionByZero), evaluate("1.5 / 0.0"));
+        assert_eq!(Err(ExpressionError::Overflow), evaluate("2 ^ 64"));
+        assert_eq!(
+            Err(ExpressionError::Parse {
+                offset: 4,
+                message: "Expected a number".into()
+            }),
+            evaluate("2 + ")
+        );
+        assert_eq!(
+            "Expected `)` at column 7",
+            evaluate("(1 + 2").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Unexpected `x` at column 3",
+            evaluate("2 x 3").unwrap_err().to_string()
+        );
+        assert!(evaluate("1.2.3").is_err());
+        assert!(evaluate("").is_err());
+    }
+
+    #[test]
+    fn expression_around_the_cursor() {
+        let text = Rope::from("total: 12 * 3 + 4 items\nnone here\n");
+        assert_eq!(Some(7..17), expression_at(&text, 10));
+        assert_eq!(Some(7..17), expression_at(&text, 17));
+        assert_eq!(None, expression_at(&text, 26));
+        assert_eq!(None, expression_at(&text, 2));
+    }
+}
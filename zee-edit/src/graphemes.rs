@@ -1,22 +1,101 @@
 use ropey::{iter::Chunks, str_utils, Rope, RopeSlice};
-use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
-use unicode_width::UnicodeWidthStr;
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete, UnicodeSegmentation};
+use unicode_width::UnicodeWidthChar;
 
 pub type ByteIndex = usize;
 pub type CharIndex = usize;
 pub type LineIndex = usize;
 
+/// The number of terminal columns taken up by the text. Each grapheme is
+/// measured as a whole, so emoji sequences, flags and letters with combining
+/// marks count as a single character.
 pub fn width(tab_width: usize, slice: &RopeSlice) -> usize {
     rope_slice_as_str(slice, |text| {
-        if text == "\t" {
-            tab_width
+        if text.is_ascii() {
+            text.bytes()
+                .map(|byte| match byte {
+                    b'\t' => tab_width,
+                    byte if is_control(byte.into()) => CONTROL_WIDTH,
+                    byte if byte.is_ascii_control() => 0,
+                    _ => 1,
+                })
+                .sum()
         } else {
-            text.chars().filter(|character| *character == '\t').count() * tab_width
-                + UnicodeWidthStr::width(text)
+            text.graphemes(true)
+                .map(|grapheme| grapheme_width(tab_width, grapheme))
+                .sum()
         }
     })
 }
 
+fn grapheme_width(tab_width: usize, grapheme: &str) -> usize {
+    let mut characters = grapheme.chars();
+    let first = match characters.next() {
+        Some('\t') => return tab_width,
+        Some(character) if is_control(character) => return CONTROL_WIDTH,
+        Some(character) => character,
+        None => return 0,
+    };
+    let first_width = first.width().unwrap_or(0);
+
+    if grapheme.contains(EMOJI_PRESENTATION_SELECTOR)
+        || (is_regional_indicator(first) && characters.next().is_some())
+    {
+        // Emoji with an explicit emoji presentation, e.g. `❤️`, and flags,
+        // which are pairs of regional indicators
+        2
+    } else if grapheme.contains(TEXT_PRESENTATION_SELECTOR)
+        || grapheme.contains(ZERO_WIDTH_JOINER)
+        || grapheme.chars().any(is_emoji_modifier)
+    {
+        // Sequences of emoji joined together or with a skin tone are shown as
+        // a single emoji
+        first_width
+    } else {
+        // Combining marks have zero width
+        grapheme.chars().filter_map(UnicodeWidthChar::width).sum()
+    }
+}
+
+/// Whether the character is shown as a visible symbol taking up two columns,
+/// e.g. `^L` for a form feed. These are the ASCII control characters, other
+/// than tabs and line breaks, which could otherwise corrupt the terminal.
+pub fn is_control(character: char) -> bool {
+    character.is_ascii_control() && !matches!(character, '\t' | '\n' | '\r')
+}
+
+/// The caret notation of a control character, e.g. `^L` for a form feed or
+/// `^[` for escape
+pub fn caret_notation(character: char) -> Option<[char; 2]> {
+    is_control(character).then(|| ['^', char::from(character as u8 ^ 0x40)])
+}
+
+/// The symbol from the Control Pictures block for a control character, e.g.
+/// `␌` for a form feed
+pub fn control_picture(character: char) -> Option<char> {
+    match character {
+        '\u{7f}' => Some('\u{2421}'),
+        character if is_control(character) => char::from_u32(0x2400 + character as u32),
+        _ => None,
+    }
+}
+
+/// The number of columns a control character takes up, the same as its caret
+/// notation
+const CONTROL_WIDTH: usize = 2;
+
+fn is_regional_indicator(character: char) -> bool {
+    ('\u{1f1e6}'..='\u{1f1ff}').contains(&character)
+}
+
+fn is_emoji_modifier(character: char) -> bool {
+    ('\u{1f3fb}'..='\u{1f3ff}').contains(&character)
+}
+
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+const TEXT_PRESENTATION_SELECTOR: char = '\u{fe0e}';
+const EMOJI_PRESENTATION_SELECTOR: char = '\u{fe0f}';
+
 pub fn rope_slice_as_str<T>(slice: &RopeSlice, closure: impl FnOnce(&str) -> T) -> T {
     if let Some(text) = slice.as_str() {
         closure(text)
@@ -150,13 +229,76 @@ pub fn strip_trailing_whitespace(mut text: Rope) -> Rope {
         }
     }
 
-    if text.len_chars() > 1 && text.char(text.len_chars() - 1) != '\n' {
-        text.insert_char(text.len_chars(), '\n');
+    text
+}
+
+/// Whether `text` ends with a line break. Empty texts don't need one.
+pub fn has_final_newline(text: &Rope) -> bool {
+    text.len_chars() == 0 || text.char(text.len_chars() - 1) == '\n'
+}
+
+/// Ends a non-empty text with exactly one line break, removing the blank lines
+/// at its end. The last line break is kept as `\r\n` if it was one.
+pub fn ensure_final_newline(mut text: Rope) -> Rope {
+    let len_chars = text.len_chars();
+    if len_chars == 0 {
+        return text;
     }
 
+    let mut content_end = len_chars;
+    let mut chars = text.chars_at(len_chars);
+    while let Some('\n' | '\r') = chars.prev() {
+        content_end -= 1;
+    }
+    let line_break = if len_chars - content_end >= 2
+        && text.char(len_chars - 2) == '\r'
+        && text.char(len_chars - 1) == '\n'
+    {
+        "\r\n"
+    } else {
+        "\n"
+    };
+
+    if text.slice(content_end..) != line_break {
+        text.remove(content_end..);
+        text.insert(content_end, line_break);
+    }
     text
 }
 
+/// Returns the number of characters at the end of `line`'s leading whitespace
+/// that make up its last level of indentation, i.e. what unindenting it by one
+/// level removes.
+///
+/// A tab ending the indentation is a level on its own. Otherwise it's the
+/// spaces back to the previous tab stop (a multiple of `tab_width` columns),
+/// counting the columns tabs before them advance to. Lines without leading
+/// whitespace return 0.
+pub fn length_of_last_indent_level(line: &RopeSlice, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    let mut column = 0;
+    let mut trailing_spaces = 0;
+    for character in line.chars() {
+        match character {
+            ' ' => {
+                column += 1;
+                trailing_spaces += 1;
+            }
+            '\t' => {
+                column = (column / tab_width + 1) * tab_width;
+                trailing_spaces = 0;
+            }
+            _ => break,
+        }
+    }
+    if trailing_spaces == 0 {
+        // Either there's no indentation or a tab ends it
+        return column.min(1);
+    }
+    let previous_tab_stop = (column - 1) / tab_width * tab_width;
+    trailing_spaces.min(column - previous_tab_stop)
+}
+
 pub trait RopeExt {
     /// Finds the previous grapheme boundary before the given char position
     fn prev_grapheme_boundary_n(&self, char_index: CharIndex, n: usize) -> CharIndex;
@@ -291,5 +433,149 @@ mod tests {
         assert_eq!(text.len_chars(), grapheme_end);
     }
 
+    #[test]
+    fn ensure_final_newline_adds_newline() {
+        let ensure = |text: &str| ensure_final_newline(Rope::from(text)).to_string();
+        assert_eq!("fn main() {}\n", ensure("fn main() {}"));
+        assert_eq!("a\nb\n", ensure("a\nb"));
+        assert!(!has_final_newline(&Rope::from("a\nb")));
+    }
+
+    #[test]
+    fn ensure_final_newline_strips_extra_newlines() {
+        let ensure = |text: &str| ensure_final_newline(Rope::from(text)).to_string();
+        assert_eq!("fn main() {}\n", ensure("fn main() {}\n\n\n"));
+        assert_eq!("a\r\nb\r\n", ensure("a\r\nb\r\n\r\n"));
+        assert_eq!("\n", ensure("\n\n"));
+    }
+
+    #[test]
+    fn ensure_final_newline_keeps_text() {
+        for text in ["", "\n", "a\nb\n", "a\r\nb\r\n"] {
+            assert!(has_final_newline(&Rope::from(text)));
+            assert_eq!(text, ensure_final_newline(Rope::from(text)).to_string());
+        }
+    }
+
+    #[test]
+    fn last_indent_level() {
+        let level = |line: &str| length_of_last_indent_level(&Rope::from(line).slice(..), 4);
+        assert_eq!(0, level(""));
+        assert_eq!(0, level("\n"));
+        assert_eq!(0, level("fn main() {}"));
+        assert_eq!(1, level("\tfn main() {}"));
+        assert_eq!(1, level("\t\tfn main() {}"));
+        assert_eq!(2, level("  fn main() {}"));
+        assert_eq!(4, level("    fn main() {}"));
+        assert_eq!(2, level("      fn main() {}"));
+        assert_eq!(4, level("        fn main() {}"));
+        assert_eq!(3, level("   "));
+    }
+
+    #[test]
+    fn last_indent_level_mixed_tabs_and_spaces() {
+        let level = |line: &str| length_of_last_indent_level(&Rope::from(line).slice(..), 4);
+        // Spaces after a tab go back to the tab's stop
+        assert_eq!(1, level(" \t fn main() {}"));
+        assert_eq!(2, level("\t  fn main() {}"));
+        assert_eq!(4, level("\t    fn main() {}"));
+        assert_eq!(1, level("\t     fn main() {}"));
+        // A tab after spaces is removed on its own
+        assert_eq!(1, level("  \t\tfn main() {}"));
+        assert_eq!(1, level("   \tfn main() {}"));
+        assert_eq!(1, level("    \tfn main() {}"));
+    }
+
+    #[test]
+    fn width_of_graphemes() {
+        // The text, the number of graphemes the cursor steps over and the
+        // width in columns
+        let cases = [
+            ("hello", 5, 5),
+            ("a\tb", 3, 6),
+            ("日本語", 3, 6),
+            // Combining accents
+            ("e\u{301}", 1, 1),
+            ("a\u{308}\u{304}bc", 3, 3),
+            // Emoji sequences joined with ZWJ
+            (MULTI_CHAR_EMOJI, 1, 2),
+            ("👩\u{200d}💻x", 2, 3),
+            // Flags
+            ("🇬🇧", 1, 2),
+            ("🇬🇧🇫🇷", 2, 4),
+            // Skin tone modifiers
+            ("👍🏽", 1, 2),
+            ("👋🏿👋", 2, 4),
+            // Variation selectors and keycaps
+            ("\u{2764}\u{fe0f}", 1, 2),
+            ("\u{263a}\u{fe0e}", 1, 1),
+            ("1\u{fe0f}\u{20e3}", 1, 2),
+        ];
+
+        for (text, num_graphemes, expected_width) in cases {
+            let text = Rope::from(text);
+            assert_eq!(
+                expected_width,
+                width(4, &text.slice(..)),
+                "width of {:?}",
+                text
+            );
+
+            let mut cursor = crate::Cursor::new();
+            let mut num_steps = 0;
+            while cursor.range().start < text.len_chars() {
+                crate::movement::move_horizontally(
+                    &text,
+                    &mut cursor,
+                    crate::Direction::Forward,
+                    1,
+                );
+                num_steps += 1;
+            }
+            assert_eq!(num_graphemes, num_steps, "graphemes in {:?}", text);
+            assert_eq!(expected_width, cursor.column_offset(4, &text));
+        }
+    }
+
+    #[test]
+    fn control_characters() {
+        // The text, the number of graphemes the cursor steps over and the
+        // width in columns
+        let cases = [
+            ("\u{c}", 1, 2),
+            // A NUL byte doesn't end the text
+            ("a\0b", 3, 4),
+            ("\u{1b}[1mbold", 8, 9),
+            ("é\u{7f}", 2, 3),
+            ("\r\n", 1, 0),
+        ];
+        for (text, num_graphemes, expected_width) in cases {
+            let text = Rope::from(text);
+            assert_eq!(
+                expected_width,
+                width(4, &text.slice(..)),
+                "width of {:?}",
+                text
+            );
+            assert_eq!(
+                num_graphemes,
+                RopeGraphemes::new(&text.slice(..)).count(),
+                "graphemes in {:?}",
+                text
+            );
+        }
+
+        assert_eq!(Some(['^', 'L']), caret_notation('\u{c}'));
+        assert_eq!(Some(['^', '@']), caret_notation('\0'));
+        assert_eq!(Some(['^', '[']), caret_notation('\u{1b}'));
+        assert_eq!(Some(['^', '?']), caret_notation('\u{7f}'));
+        assert_eq!(None, caret_notation('\t'));
+        assert_eq!(None, caret_notation('a'));
+        assert_eq!(Some('␌'), control_picture('\u{c}'));
+        assert_eq!(Some('␀'), control_picture('\0'));
+        assert_eq!(Some('␡'), control_picture('\u{7f}'));
+        assert_eq!(None, control_picture('\n'));
+    }
+
     const MULTI_CHAR_EMOJI: &str = r#"👨‍👨‍👧‍👧"#;
 }
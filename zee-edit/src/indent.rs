@@ -0,0 +1,123 @@
+use ropey::Rope;
+
+/// How a text is indented, as detected by [`detect_indentation`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indentation {
+    Tabs,
+    /// Spaces, with the number of spaces of each level
+    Spaces(usize),
+}
+
+/// The indentation detected in a text and whether lines are indented with
+/// both tabs and spaces
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DetectedIndentation {
+    pub indentation: Indentation,
+    pub mixed: bool,
+}
+
+/// Detects whether the first `MAX_SAMPLED_LINES` lines of `text` are
+/// indented with tabs or spaces, picking the one most lines use. The width
+/// of a level of spaces is the most common change in indentation between
+/// consecutive lines, ignoring single spaces, e.g. the ` * ` of
+/// block comments. Returns `None` if no line is indented.
+pub fn detect_indentation(text: &Rope) -> Option<DetectedIndentation> {
+    let (mut tab_lines, mut space_lines) = (0, 0);
+    let mut width_counts = [0usize; MAX_WIDTH + 1];
+    let mut previous_spaces = 0;
+
+    for line in text.lines().take(MAX_SAMPLED_LINES) {
+        if line.chars().all(char::is_whitespace) {
+            continue;
+        }
+        if line.char(0) == '\t' {
+            tab_lines += 1;
+            continue;
+        }
+        let spaces = line
+            .chars()
+            .take_while(|&character| character == ' ')
+            .count();
+        if spaces > 0 {
+            space_lines += 1;
+        }
+        let width = spaces.max(previous_spaces) - spaces.min(previous_spaces);
+        if (2..=MAX_WIDTH).contains(&width) {
+            width_counts[width] += 1;
+        }
+        previous_spaces = spaces;
+    }
+
+    let indentation = if tab_lines == 0 && space_lines == 0 {
+        return None;
+    } else if tab_lines > space_lines {
+        Indentation::Tabs
+    } else {
+        // The smallest of the most common widths
+        let width = (2..=MAX_WIDTH)
+            .rev()
+            .max_by_key(|&width| width_counts[width])
+            .filter(|&width| width_counts[width] > 0)?;
+        Indentation::Spaces(width)
+    };
+    Some(DetectedIndentation {
+        indentation,
+        mixed: tab_lines > 0 && space_lines > 0,
+    })
+}
+
+const MAX_SAMPLED_LINES: usize = 1000;
+const MAX_WIDTH: usize = 8;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(text: &str) -> Option<DetectedIndentation> {
+        detect_indentation(&Rope::from(text))
+    }
+
+    fn unmixed(indentation: Indentation) -> Option<DetectedIndentation> {
+        Some(DetectedIndentation {
+            indentation,
+            mixed: false,
+        })
+    }
+
+    #[test]
+    fn tab_indented() {
+        let text = "fn main() {\n\tif true {\n\t\tprintln!();\n\t}\n}\n";
+        assert_eq!(unmixed(Indentation::Tabs), detect(text));
+    }
+
+    #[test]
+    fn space_indented() {
+        let text = "a:\n  b:\n    c: 1\n\n    d: 2\n  e: 3\n";
+        assert_eq!(unmixed(Indentation::Spaces(2)), detect(text));
+
+        let text = "def f():\n    if x:\n        return 1\n    return 2\n";
+        assert_eq!(unmixed(Indentation::Spaces(4)), detect(text));
+
+        // Block comments and continuation lines don't count
+        let text = "/**\n * Docs\n */\nint f() {\n    return g(1,\n             2);\n}\n";
+        assert_eq!(unmixed(Indentation::Spaces(4)), detect(text));
+    }
+
+    #[test]
+    fn mixed_indentation_picks_the_majority() {
+        let text = "{\n\ta\n\tb\n  c\n}\n";
+        assert_eq!(
+            Some(DetectedIndentation {
+                indentation: Indentation::Tabs,
+                mixed: true
+            }),
+            detect(text)
+        );
+    }
+
+    #[test]
+    fn no_indentation() {
+        assert_eq!(None, detect(""));
+        assert_eq!(None, detect("one\ntwo\n\n   \nthree"));
+    }
+}
@@ -1,14 +1,27 @@
+pub mod edit_points;
+pub mod expression;
 pub mod graphemes;
+pub mod indent;
 pub mod movement;
+pub mod multi_cursor;
+pub mod number;
+pub mod rectangle;
+pub mod search;
+pub mod snippet;
+pub mod spell;
 pub mod tree;
+pub mod unicode;
+pub mod whitespace;
 
 mod diff;
 
 use ropey::{Rope, RopeSlice};
-use std::{cmp, ops::Range};
+use std::{cmp, fmt, ops::Range};
+
+use self::movement::WordCharacters;
 
 pub use self::{
-    diff::{DeleteOperation, OpaqueDiff},
+    diff::{diff_ropes, line_hunks, replace_text, DeleteOperation, LineHunk, OpaqueDiff},
     graphemes::{ByteIndex, CharIndex, LineIndex, RopeExt, RopeGraphemes},
     movement::Direction,
 };
@@ -96,6 +109,35 @@ impl Cursor {
         }
     }
 
+    /// Whether a selection is currently active, i.e. one was begun and hasn't
+    /// been cleared since.
+    pub fn has_selection(&self) -> bool {
+        self.selection.is_some()
+    }
+
+    /// The range a region-aware command should operate on. This is the
+    /// selection if one is active, otherwise the current line including its
+    /// trailing newline.
+    pub fn region_or_default(&self, text: &Rope) -> Range<CharIndex> {
+        if self.has_selection() {
+            self.selection()
+        } else {
+            let line_index = text.cursor_to_line(self);
+            text.line_to_char(line_index)..text.line_to_char(line_index + 1)
+        }
+    }
+
+    /// The cursor's line as a complete line, ending with a newline even if
+    /// it is the last line of the text and doesn't have one.
+    pub fn whole_line(&self, text: &Rope) -> String {
+        let line_index = text.cursor_to_line(self);
+        let mut line = String::from(text.line(line_index));
+        if !line.ends_with('\n') {
+            line.push('\n');
+        }
+        line
+    }
+
     pub fn column_offset(&self, tab_width: usize, text: &Rope) -> usize {
         let char_line_start = text.line_to_char(text.cursor_to_line(self));
         graphemes::width(tab_width, &text.slice(char_line_start..self.range.start))
@@ -109,14 +151,29 @@ impl Cursor {
             ..
         } = *diff;
 
-        let modified_range = char_index..cmp::max(old_char_length, new_char_length);
+        // Keep the selection anchored to the same text. An anchor inside a
+        // deleted or replaced range is clamped to the start of the edit.
+        self.selection = self.selection.map(|anchor| {
+            let anchor = if anchor <= char_index {
+                anchor
+            } else if anchor >= char_index + old_char_length {
+                anchor - old_char_length + new_char_length
+            } else {
+                char_index
+            };
+            cmp::min(anchor, new_text.len_chars())
+        });
+
+        // The range of the old text that was replaced by the edit
+        let modified_range = char_index..char_index + old_char_length;
 
         // The edit starts after the end of the cursor, nothing to do
         if modified_range.start >= self.range.end {
             return;
         }
 
-        // The edit ends before the start of the cursor
+        // The edit ends before the start of the cursor, shift the cursor by
+        // the change in length, preserving its width
         if modified_range.end <= self.range.start {
             let (start, end) = (self.range.start, self.range.end);
             if old_char_length > new_char_length {
@@ -126,6 +183,7 @@ impl Cursor {
                 let length_change = new_char_length - old_char_length;
                 self.range = start + length_change..end + length_change;
             };
+            return;
         }
 
         // Otherwise, the change overlaps with the cursor
@@ -148,6 +206,12 @@ impl Cursor {
         self.selection = Some(text.len_chars());
     }
 
+    /// Selects `range`, with the cursor at its start
+    pub fn select(&mut self, text: &Rope, range: Range<CharIndex>) {
+        *self = Cursor::with_range(range.start..text.next_grapheme_boundary(range.start));
+        self.selection = Some(range.end);
+    }
+
     // Editing
 
     pub fn insert_char(&mut self, text: &mut Rope, character: char) -> OpaqueDiff {
@@ -189,31 +253,246 @@ impl Cursor {
         )
     }
 
-    pub fn delete_forward(&mut self, text: &mut Rope) -> DeleteOperation {
-        if text.len_chars() == 0 || text.len_chars() == self.range.start {
+    /// Inserts `lines` as complete lines above the cursor's line, adding a
+    /// trailing newline if missing. The cursor stays on the same character.
+    pub fn insert_lines_above(&mut self, text: &mut Rope, lines: &str) -> OpaqueDiff {
+        if lines.is_empty() {
+            return OpaqueDiff::empty();
+        }
+
+        self.clear_selection();
+        let line_start = text.line_to_char(text.cursor_to_line(self));
+        let mut cursor = Cursor::with_range(line_start..line_start);
+        let mut diff = cursor.insert_chars(text, lines.chars());
+        if !lines.ends_with('\n') {
+            text.insert_char(cursor.range.start + diff.new_char_length, '\n');
+            diff = OpaqueDiff::new(
+                diff.byte_index,
+                0,
+                diff.new_byte_length + 1,
+                diff.char_index,
+                0,
+                diff.new_char_length + 1,
+            );
+        }
+        self.range = self.range.start + diff.new_char_length..self.range.end + diff.new_char_length;
+        diff
+    }
+
+    /// Inserts a copy of the selection right after it, or without a selection
+    /// a copy of the cursor's line below it. The cursor moves to the same
+    /// column of the new line, while a selection stays over the original. A
+    /// last line without a newline gets one so that its copy is a line of its
+    /// own.
+    pub fn duplicate(&mut self, text: &mut Rope) -> OpaqueDiff {
+        let (position, copy) = if self.has_selection() {
+            let selection = self.selection();
+            (selection.end, text.slice(selection).to_string())
+        } else {
+            let line_index = text.cursor_to_line(self);
+            let line = String::from(text.line(line_index));
+            if line.ends_with('\n') {
+                (text.line_to_char(line_index + 1), line)
+            } else {
+                (text.len_chars(), format!("\n{}", line))
+            }
+        };
+        if copy.is_empty() {
+            return OpaqueDiff::empty();
+        }
+
+        let byte_index = text.char_to_byte(position);
+        let length = copy.chars().count();
+        text.insert(position, &copy);
+        if !self.has_selection() {
+            let start = self.range.start + length;
+            self.range = start..text.next_grapheme_boundary(start);
+            self.visual_horizontal_offset = None;
+        }
+        OpaqueDiff::new(byte_index, 0, copy.len(), position, 0, length)
+    }
+
+    /// Deletes the characters in `range`, leaving the cursor at the start of
+    /// the deleted range. Returns the deleted text alongside the diff.
+    pub fn delete_range(&mut self, text: &mut Rope, range: Range<CharIndex>) -> DeleteOperation {
+        if range.is_empty() {
             return DeleteOperation::empty();
         }
 
-        let byte_range = text.char_to_byte(self.range.start)..text.char_to_byte(self.range.end);
+        let deleted = text.slice(range.clone()).into();
+        let byte_range = text.char_to_byte(range.start)..text.char_to_byte(range.end);
         let diff = OpaqueDiff::new(
             byte_range.start,
             byte_range.end - byte_range.start,
             0,
-            self.range.start,
-            self.range.end - self.range.start,
+            range.start,
+            range.end - range.start,
             0,
         );
-        text.remove(self.range.clone());
-
-        let grapheme_start = self.range.start;
-        let grapheme_end = text.next_grapheme_boundary(self.range.start);
-        let deleted = text.slice(grapheme_start..grapheme_end).into();
+        text.remove(range.clone());
 
+        let grapheme_start = range.start;
+        let grapheme_end = text.next_grapheme_boundary(grapheme_start);
         *self = Cursor::with_range(grapheme_start..grapheme_end);
 
         DeleteOperation { diff, deleted }
     }
 
+    /// Replaces the characters in `range` with `replacement`, leaving the
+    /// cursor at the end of the inserted text. The returned diff describes the
+    /// whole edit, so an empty range is a pure insertion and an empty
+    /// replacement a pure deletion.
+    pub fn replace_range(
+        &mut self,
+        text: &mut Rope,
+        range: Range<CharIndex>,
+        replacement: &str,
+    ) -> OpaqueDiff {
+        if range.is_empty() && replacement.is_empty() {
+            return OpaqueDiff::empty();
+        }
+
+        let byte_range = text.char_to_byte(range.start)..text.char_to_byte(range.end);
+        let new_char_length = replacement.chars().count();
+        let diff = OpaqueDiff::new(
+            byte_range.start,
+            byte_range.end - byte_range.start,
+            replacement.len(),
+            range.start,
+            range.end - range.start,
+            new_char_length,
+        );
+        text.remove(range.clone());
+        text.insert(range.start, replacement);
+
+        let grapheme_start = range.start + new_char_length;
+        let grapheme_end = text.next_grapheme_boundary(grapheme_start);
+        *self = Cursor::with_range(grapheme_start..grapheme_end);
+
+        diff
+    }
+
+    /// Swaps the text of two ranges, e.g. two arguments of a function call,
+    /// and selects the text of `selected` at its new position. Nothing
+    /// changes if the ranges overlap.
+    pub fn swap_ranges(
+        &mut self,
+        text: &mut Rope,
+        selected: Range<CharIndex>,
+        other: Range<CharIndex>,
+    ) -> OpaqueDiff {
+        let selected_text = text.slice(selected.clone()).to_string();
+        let other_text = text.slice(other.clone()).to_string();
+        let diff = match apply_edits(
+            text,
+            &[
+                (selected.clone(), other_text),
+                (other.clone(), selected_text),
+            ],
+        ) {
+            Ok(diff) => diff,
+            Err(_) => return OpaqueDiff::empty(),
+        };
+
+        let start = if selected.start < other.start {
+            other.end - selected.len()
+        } else {
+            other.start
+        };
+        self.select(text, start..start + selected.len());
+        diff
+    }
+
+    /// Swaps the grapheme before the cursor with the one at the cursor and
+    /// moves past both, like `C-t` in Emacs. At the end of the text the last
+    /// two graphemes are swapped and the cursor stays. Nothing changes at the
+    /// start of the text.
+    pub fn transpose_chars(&mut self, text: &mut Rope) -> OpaqueDiff {
+        let middle = if self.range.start >= text.len_chars() {
+            text.prev_grapheme_boundary(text.len_chars())
+        } else {
+            self.range.start
+        };
+        let start = text.prev_grapheme_boundary(middle);
+        let end = text.next_grapheme_boundary(middle);
+        if start == middle || middle == end {
+            return OpaqueDiff::empty();
+        }
+
+        let transposed = format!("{}{}", text.slice(middle..end), text.slice(start..middle));
+        self.replace_range(text, start..end, &transposed)
+    }
+
+    /// Inserts `lines` as a rectangle, one per line, at the cursor's column
+    /// starting with the cursor's line. Columns count terminal cells, a tab
+    /// taking up `tab_width`. Lines shorter than the column are padded with
+    /// spaces and new lines are added past the end of the text.
+    pub fn insert_rectangle(
+        &mut self,
+        text: &mut Rope,
+        lines: &[&str],
+        tab_width: usize,
+    ) -> OpaqueDiff {
+        if lines.iter().all(|line| line.is_empty()) {
+            return OpaqueDiff::empty();
+        }
+
+        let first_line = text.char_to_line(self.range.start);
+        let line_start = text.line_to_char(first_line);
+        let column = graphemes::width(tab_width, &text.slice(line_start..self.range.start));
+
+        let mut block = String::new();
+        let mut cursor_offset = 0;
+        let mut end = line_start;
+        for (offset, piece) in lines.iter().enumerate() {
+            let line_index = first_line + offset;
+            let (line, line_ending, split, width) = if line_index < text.len_lines() {
+                let line = text.line(line_index).to_string();
+                let content_length = line.trim_end_matches(&['\r', '\n'][..]).len();
+                end = text.line_to_char(line_index) + line[..content_length].chars().count();
+                let split =
+                    rectangle::chars_in_columns(text, line_index, column..column, tab_width).start
+                        - text.line_to_char(line_index);
+                let width = rectangle::line_width(text, line_index, tab_width);
+                let (content, line_ending) = line.split_at(content_length);
+                (content.to_string(), line_ending.to_string(), split, width)
+            } else {
+                (String::new(), String::new(), 0, 0)
+            };
+
+            let split = line
+                .char_indices()
+                .nth(split)
+                .map_or(line.len(), |(index, _)| index);
+            block.push_str(&line[..split]);
+            block.extend(std::iter::repeat(' ').take(column.saturating_sub(width)));
+            block.push_str(piece);
+            cursor_offset = block.chars().count();
+            block.push_str(&line[split..]);
+
+            if offset + 1 < lines.len() {
+                block.push_str(if line_ending.is_empty() {
+                    "\n"
+                } else {
+                    &line_ending
+                });
+            }
+        }
+
+        let diff = self.replace_range(text, line_start..end, &block);
+        let cursor_start = line_start + cursor_offset;
+        *self = Cursor::with_range(cursor_start..text.next_grapheme_boundary(cursor_start));
+        diff
+    }
+
+    pub fn delete_forward(&mut self, text: &mut Rope) -> DeleteOperation {
+        if text.len_chars() == 0 || text.len_chars() == self.range.start {
+            return DeleteOperation::empty();
+        }
+
+        self.delete_range(text, self.range())
+    }
+
     pub fn delete_backward(&mut self, text: &mut Rope) -> DeleteOperation {
         if self.range.start > 0 {
             movement::move_horizontally(text, self, Direction::Backward, 1);
@@ -228,29 +507,43 @@ impl Cursor {
             return DeleteOperation::empty();
         }
 
-        // Delete line
+        // Delete line. If it's the last line in the buffer and it doesn't end
+        // with a newline, remove the newline preceding it instead, otherwise
+        // an empty line would be left behind.
         let line_index = text.char_to_line(self.range.start);
-        let delete_range_start = text.line_to_char(line_index);
+        let mut delete_range_start = text.line_to_char(line_index);
         let delete_range_end = text.line_to_char(line_index + 1);
-        let deleted = text.slice(delete_range_start..delete_range_end).into();
-        let diff = OpaqueDiff::new(
-            text.char_to_byte(delete_range_start),
-            text.char_to_byte(delete_range_end) - text.char_to_byte(delete_range_start),
-            0,
-            delete_range_start,
-            delete_range_end - delete_range_start,
-            0,
-        );
-        text.remove(delete_range_start..delete_range_end);
+        if line_index > 0 && text.line(line_index).chars().last() != Some('\n') {
+            delete_range_start -= 1;
+        }
+        let operation = self.delete_range(text, delete_range_start..delete_range_end);
 
-        // Update cursor position
+        // Update cursor position, placing it at the start of the line which
+        // took the place of the deleted one, or the new last line
         let grapheme_start =
-            text.line_to_char(cmp::min(line_index, text.len_lines().saturating_sub(2)));
+            text.line_to_char(cmp::min(line_index, text.len_lines().saturating_sub(1)));
         let grapheme_end = text.next_grapheme_boundary(grapheme_start);
-
         *self = Cursor::with_range(grapheme_start..grapheme_end);
 
-        DeleteOperation { diff, deleted }
+        operation
+    }
+
+    /// Kills from the cursor to the end of its line, like `C-k` in Emacs,
+    /// leaving the line ending. At the end of a line, e.g. an empty one, the
+    /// line ending itself is killed, joining the next line. The cursor stays
+    /// in place.
+    pub fn kill_to_end_of_line(&mut self, text: &mut Rope) -> DeleteOperation {
+        let start = self.range.start;
+        let line_index = text.char_to_line(start);
+        let line = text.line(line_index);
+        let line_end = text.line_to_char(line_index) + line.len_chars();
+        let content_end = line_end - line_ending_length(&line);
+        let end = if start < content_end {
+            content_end
+        } else {
+            line_end
+        };
+        self.delete_range(text, start..end)
     }
 
     pub fn delete_selection(&mut self, text: &mut Rope) -> DeleteOperation {
@@ -258,29 +551,390 @@ impl Cursor {
             return DeleteOperation::empty();
         }
 
-        // Delete selection
-        let selection = self.selection();
-        let deleted = text.slice(selection.start..selection.end).into();
-        let diff = OpaqueDiff::new(
-            text.char_to_byte(selection.start),
-            text.char_to_byte(selection.end) - text.char_to_byte(selection.start),
-            0,
-            selection.start,
-            selection.end - selection.start,
-            0,
-        );
-        text.remove(selection.start..selection.end);
+        // Delete the selection, or the current line if there's none
+        let region = self.region_or_default(text);
+        let operation = self.delete_range(text, region.clone());
 
         // Update cursor position
-        let grapheme_start = cmp::min(
-            self.range.start,
-            text.prev_grapheme_boundary(text.len_chars()),
-        );
+        let grapheme_start = cmp::min(region.start, text.prev_grapheme_boundary(text.len_chars()));
         let grapheme_end = text.next_grapheme_boundary(grapheme_start);
-
         *self = Cursor::with_range(grapheme_start..grapheme_end);
 
-        DeleteOperation { diff, deleted }
+        operation
+    }
+
+    /// Joins the next line onto the cursor's line, replacing the line break
+    /// and the indentation of the next line with a single space. No space is
+    /// added after whitespace or an empty line, or before an empty line. With
+    /// a selection, all the lines it touches are joined. The cursor is left
+    /// at the last join.
+    pub fn join_lines(&mut self, text: &mut Rope) -> OpaqueDiff {
+        let (first_line, last_line) = if self.has_selection() {
+            let selection = self.selection();
+            let last_char = cmp::max(selection.start, selection.end.saturating_sub(1));
+            (
+                text.char_to_line(selection.start),
+                text.char_to_line(last_char),
+            )
+        } else {
+            let line_index = text.cursor_to_line(self);
+            (line_index, line_index)
+        };
+        let last_line = cmp::min(
+            cmp::max(last_line, first_line + 1),
+            text.len_lines().saturating_sub(1),
+        );
+
+        // The text of a line without its line break, and without its
+        // indentation unless it's the first line
+        let content = |line_index: LineIndex| {
+            let line = text.line(line_index);
+            let start = if line_index == first_line {
+                0
+            } else {
+                indentation_length(&line)
+            };
+            let end = cmp::max(start, line.len_chars() - line_ending_length(&line));
+            (
+                text.line_to_char(line_index) + start,
+                line.slice(start..end),
+            )
+        };
+        let last_char = |slice: RopeSlice| slice.chars_at(slice.len_chars()).reversed().next();
+
+        // Whether to add a space depends on the end of the line joined so far
+        let mut joined_end = last_char(content(first_line).1);
+        let edits: Vec<_> = (first_line..last_line)
+            .map(|line_index| {
+                let (start, current) = content(line_index);
+                let (next_start, next) = content(line_index + 1);
+                let separator = match joined_end {
+                    Some(character) if !character.is_whitespace() && next.len_chars() > 0 => " ",
+                    _ => "",
+                };
+                joined_end = last_char(next).or(joined_end);
+                (
+                    start + current.len_chars()..next_start,
+                    separator.to_owned(),
+                )
+            })
+            .collect();
+        let (last_edit, preceding) = match edits.split_last() {
+            Some(split) => split,
+            None => return OpaqueDiff::empty(),
+        };
+        // The characters removed by the joins before the last one
+        let removed: usize = preceding
+            .iter()
+            .map(|(range, separator)| range.len() - separator.len())
+            .sum();
+        let last_join = last_edit.0.start - removed;
+        let diff = apply_edits(text, &edits).expect("edits of different lines don't overlap");
+        *self = Cursor::with_range(last_join..text.next_grapheme_boundary(last_join));
+        diff
+    }
+
+    /// Converts the selection to uppercase, or the word at the cursor, or up
+    /// to the end of the next word if the cursor isn't on one
+    pub fn upcase_selection(
+        &mut self,
+        text: &mut Rope,
+        word_characters: WordCharacters,
+    ) -> OpaqueDiff {
+        self.convert_case(text, word_characters, |_, selected| selected.to_uppercase())
+    }
+
+    /// Converts the selection to lowercase, or the word at the cursor, or up
+    /// to the end of the next word if the cursor isn't on one
+    pub fn downcase_selection(
+        &mut self,
+        text: &mut Rope,
+        word_characters: WordCharacters,
+    ) -> OpaqueDiff {
+        self.convert_case(text, word_characters, |_, selected| selected.to_lowercase())
+    }
+
+    /// Uppercases the first letter of each word in the selection and
+    /// lowercases the rest, or of the word at the cursor, or up to the end
+    /// of the next word if the cursor isn't on one
+    pub fn capitalize_selection(
+        &mut self,
+        text: &mut Rope,
+        word_characters: WordCharacters,
+    ) -> OpaqueDiff {
+        self.convert_case(text, word_characters, |in_word, selected| {
+            capitalize(selected, word_characters, in_word)
+        })
+    }
+
+    /// Replaces the text of the selection, or the word at the cursor, with
+    /// `convert(in_word, text)`, where `in_word` is whether the text starts
+    /// in the middle of a word. A selection stays over the converted text,
+    /// otherwise the cursor moves past it.
+    fn convert_case(
+        &mut self,
+        text: &mut Rope,
+        word_characters: WordCharacters,
+        convert: impl Fn(bool, &str) -> String,
+    ) -> OpaqueDiff {
+        let range = if self.has_selection() {
+            self.selection()
+        } else {
+            search::word_at(text, self.range.start, word_characters).unwrap_or_else(|| {
+                let mut end = self.clone();
+                movement::move_forward_word(text, &mut end, word_characters, false);
+                self.range.start..end.range.start
+            })
+        };
+        let in_word = range.start > 0 && word_characters.contains(text.char(range.start - 1));
+        let selected = text.slice(range.clone()).to_string();
+        let converted = convert(in_word, &selected);
+        if converted == selected {
+            return OpaqueDiff::empty();
+        }
+
+        let selection = self.has_selection();
+        let diff = self.replace_range(text, range.clone(), &converted);
+        if selection {
+            self.select(text, range.start..range.start + converted.chars().count());
+        }
+        diff
+    }
+
+    /// Adds a level of `indentation` to the start of every line the selection
+    /// touches, empty lines included, or of the cursor's line without a
+    /// selection. The selection stays over the same text and grows to cover
+    /// the new indentation when it starts at the start of a line.
+    pub fn indent(&mut self, text: &mut Rope, indentation: &str) -> OpaqueDiff {
+        if indentation.is_empty() {
+            return OpaqueDiff::empty();
+        }
+
+        let lines = if self.has_selection() {
+            let selection = self.selection();
+            let last_char = cmp::max(selection.start, selection.end.saturating_sub(1));
+            text.char_to_line(selection.start)..text.char_to_line(last_char) + 1
+        } else {
+            let line_index = text.cursor_to_line(self);
+            line_index..line_index + 1
+        };
+        let line_starts: Vec<_> = lines
+            .map(|line_index| text.line_to_char(line_index))
+            .collect();
+        let edits: Vec<_> = line_starts
+            .iter()
+            .map(|&line_start| (line_start..line_start, indentation.to_owned()))
+            .collect();
+        let diff = apply_edits(text, &edits).expect("edits of different lines don't overlap");
+
+        // Positions move past the indentation inserted before them. The start
+        // of a selection at the start of a line stays there.
+        let length = indentation.chars().count();
+        let shifted = |position: CharIndex, keep_line_start: bool| {
+            position
+                + length
+                    * line_starts
+                        .iter()
+                        .filter(|&&line_start| {
+                            line_start < position || (line_start == position && !keep_line_start)
+                        })
+                        .count()
+        };
+        let start = self.range.start;
+        let (start, anchor) = match self.selection {
+            Some(anchor) => (
+                shifted(start, start < anchor),
+                Some(shifted(anchor, anchor < start)),
+            ),
+            None => (shifted(start, false), None),
+        };
+        self.range = start..text.next_grapheme_boundary(start);
+        self.selection = anchor;
+        self.visual_horizontal_offset = None;
+        diff
+    }
+
+    /// Comments out every line the selection touches, or the cursor's line
+    /// without a selection, by inserting `token` after each line's
+    /// indentation. If all of them are already commented the token is removed
+    /// instead. Blank lines are left alone and the selection stays over the
+    /// same text.
+    pub fn toggle_comment_lines(&mut self, text: &mut Rope, token: &str) -> OpaqueDiff {
+        // A token like `// ` also matches a comment without the space after it
+        let marker = token.trim_end();
+        if marker.is_empty() {
+            return OpaqueDiff::empty();
+        }
+
+        let comments: Vec<_> = self
+            .edited_lines(text)
+            .filter_map(|line_index| {
+                let line = text.line(line_index);
+                let indentation = indentation_length(&line);
+                let rest = line.slice(indentation..).to_string();
+                if rest.trim().is_empty() {
+                    return None;
+                }
+                let start = text.line_to_char(line_index) + indentation;
+                let length = if rest.starts_with(token) {
+                    Some(token.chars().count())
+                } else if rest.starts_with(marker) {
+                    Some(marker.chars().count())
+                } else {
+                    None
+                };
+                Some((start, length))
+            })
+            .collect();
+        if comments.is_empty() {
+            return OpaqueDiff::empty();
+        }
+
+        let uncomment = comments.iter().all(|(_, length)| length.is_some());
+        let edits: Vec<_> = comments
+            .iter()
+            .map(|&(start, length)| match length {
+                Some(length) if uncomment => (start..start + length, String::new()),
+                _ => (start..start, token.to_owned()),
+            })
+            .collect();
+        let diff = apply_edits(text, &edits).expect("edits of different lines don't overlap");
+        self.move_past_edits(text, &edits);
+        diff
+    }
+
+    /// Removes the last level of indentation from every line the selection
+    /// touches, or from the cursor's line without a selection: a tab, or the
+    /// spaces back to the previous tab stop. The selection stays over the
+    /// same text.
+    pub fn unindent(&mut self, text: &mut Rope, tab_width: usize) -> OpaqueDiff {
+        let edits: Vec<_> = self
+            .edited_lines(text)
+            .filter_map(|line_index| {
+                let line = text.line(line_index);
+                let end = text.line_to_char(line_index) + indentation_length(&line);
+                let length = graphemes::length_of_last_indent_level(&line, tab_width);
+                (length > 0).then(|| (end - length..end, String::new()))
+            })
+            .collect();
+        if edits.is_empty() {
+            return OpaqueDiff::empty();
+        }
+
+        let diff = apply_edits(text, &edits).expect("edits of different lines don't overlap");
+        self.move_past_edits(text, &edits);
+        diff
+    }
+
+    /// The lines a multi-line edit applies to: the ones the selection
+    /// touches, or the cursor's line without a selection
+    fn edited_lines(&self, text: &Rope) -> Range<LineIndex> {
+        if self.has_selection() {
+            let selection = self.selection();
+            let last_char = cmp::max(selection.start, selection.end.saturating_sub(1));
+            text.char_to_line(selection.start)..text.char_to_line(last_char) + 1
+        } else {
+            let line_index = text.cursor_to_line(self);
+            line_index..line_index + 1
+        }
+    }
+
+    /// Moves the cursor and the selection's anchor with the text after
+    /// `edits` made before them. Positions inside a removed range move to
+    /// where it was.
+    fn move_past_edits(&mut self, text: &Rope, edits: &[(Range<CharIndex>, String)]) {
+        let shifted = |position: CharIndex| {
+            edits
+                .iter()
+                .fold(position, |shifted, (range, replacement)| {
+                    if range.end <= position {
+                        shifted + replacement.chars().count() - range.len()
+                    } else if range.start < position {
+                        shifted - (position - range.start)
+                    } else {
+                        shifted
+                    }
+                })
+        };
+        let start = shifted(self.range.start);
+        self.range = start..text.next_grapheme_boundary(start);
+        self.selection = self.selection.map(shifted);
+        self.visual_horizontal_offset = None;
+    }
+
+    /// Converts the indentation of the selected lines, or of every line
+    /// without a selection, to tabs. Indentation which isn't a whole number
+    /// of tabs keeps spaces for the rest. Only the whitespace at the start of
+    /// lines is changed and the cursor stays in the same column.
+    pub fn tabify(&mut self, text: &mut Rope, tab_width: usize) -> OpaqueDiff {
+        let tab_width = cmp::max(tab_width, 1);
+        self.convert_indentation(text, tab_width, |width| {
+            let mut indentation = "\t".repeat(width / tab_width);
+            indentation.push_str(&" ".repeat(width % tab_width));
+            indentation
+        })
+    }
+
+    /// Converts the indentation of the selected lines, or of every line
+    /// without a selection, to spaces. Only the whitespace at the start of
+    /// lines is changed and the cursor stays in the same column.
+    pub fn untabify(&mut self, text: &mut Rope, tab_width: usize) -> OpaqueDiff {
+        self.convert_indentation(text, tab_width, |width| " ".repeat(width))
+    }
+
+    /// Replaces the indentation of lines with `indentation(width)`, where
+    /// `width` is the number of columns it takes up
+    fn convert_indentation(
+        &mut self,
+        text: &mut Rope,
+        tab_width: usize,
+        indentation: impl Fn(usize) -> String,
+    ) -> OpaqueDiff {
+        let lines = if self.has_selection() {
+            let selection = self.selection();
+            let last_char = cmp::max(selection.start, selection.end.saturating_sub(1));
+            text.char_to_line(selection.start)..text.char_to_line(last_char) + 1
+        } else {
+            0..text.len_lines()
+        };
+        let edits: Vec<_> = lines
+            .filter_map(|line_index| {
+                let line_start = text.line_to_char(line_index);
+                let line = text.line(line_index);
+                let length = indentation_length(&line);
+                let width = graphemes::width(tab_width, &line.slice(..length));
+                let replacement = indentation(width);
+                (line.slice(..length) != replacement.as_str())
+                    .then(|| (line_start..line_start + length, replacement))
+            })
+            .collect();
+
+        let line_index = text.cursor_to_line(self);
+        let line_start = text.line_to_char(line_index);
+        let old_length = indentation_length(&text.line(line_index));
+        let column = self.column_offset(tab_width, text);
+        let diff = apply_edits(text, &edits).expect("edits of different lines don't overlap");
+
+        // Keep the cursor in the same column, or at the start of the tab
+        // which covers it
+        let line = text.line(line_index);
+        let new_length = indentation_length(&line);
+        let offset = self.range.start - line_start;
+        let offset = if offset >= old_length {
+            offset - old_length + new_length
+        } else {
+            let mut width = 0;
+            line.chars()
+                .take(new_length)
+                .position(|character| {
+                    width += if character == '\t' { tab_width } else { 1 };
+                    width > column
+                })
+                .unwrap_or(new_length)
+        };
+        let position = text.line_to_char(line_index) + offset;
+        *self = Cursor::with_range(position..text.next_grapheme_boundary(position));
+        diff
     }
 
     pub fn sync(&mut self, current_text: &Rope, new_text: &Rope) {
@@ -300,6 +954,127 @@ impl Cursor {
     }
 }
 
+/// Uppercases the first letter of each word and lowercases the rest, where
+/// `in_word` is whether `text` starts in the middle of a word
+fn capitalize(text: &str, word_characters: WordCharacters, in_word: bool) -> String {
+    let mut capitalized = String::with_capacity(text.len());
+    let mut word_start = None;
+    let mut in_word = in_word;
+    for (index, character) in text.char_indices() {
+        let is_word_character = word_characters.contains(character);
+        match (in_word, is_word_character) {
+            (false, true) => {
+                capitalized.extend(character.to_uppercase());
+                word_start = Some(index + character.len_utf8());
+            }
+            (true, false) => {
+                if let Some(start) = word_start.take() {
+                    capitalized.push_str(&text[start..index].to_lowercase());
+                }
+                capitalized.push(character);
+            }
+            (true, true) => {
+                word_start.get_or_insert(index);
+            }
+            (false, false) => capitalized.push(character),
+        }
+        in_word = is_word_character;
+    }
+    if let Some(start) = word_start {
+        capitalized.push_str(&text[start..].to_lowercase());
+    }
+    capitalized
+}
+
+/// The number of characters of the line break at the end of a line, `\r\n`
+/// or `\n`
+fn line_ending_length(line: &RopeSlice) -> usize {
+    line.chars_at(line.len_chars())
+        .reversed()
+        .take(2)
+        .take_while(|&character| character == '\n' || character == '\r')
+        .count()
+}
+
+/// The number of spaces and tabs at the start of a line
+fn indentation_length(line: &RopeSlice) -> usize {
+    line.chars()
+        .take_while(|&character| character == ' ' || character == '\t')
+        .count()
+}
+
+/// The error returned by [`apply_edits`] when two edits in a batch overlap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OverlappingEdits {
+    pub first: Range<CharIndex>,
+    pub second: Range<CharIndex>,
+}
+
+impl fmt::Display for OverlappingEdits {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "edits at {:?} and {:?} overlap",
+            self.first, self.second
+        )
+    }
+}
+
+impl std::error::Error for OverlappingEdits {}
+
+/// Applies a batch of non-overlapping edits atomically, each replacing a range
+/// of the original text with a new string.
+///
+/// The edits are applied in descending order of position, so the offsets of
+/// the ones yet to be applied stay valid. Returns a single diff spanning all
+/// the edits. If any two edits overlap, the text is left untouched.
+pub fn apply_edits(
+    text: &mut Rope,
+    edits: &[(Range<CharIndex>, String)],
+) -> Result<OpaqueDiff, OverlappingEdits> {
+    let mut edits: Vec<_> = edits
+        .iter()
+        .filter(|(range, replacement)| !range.is_empty() || !replacement.is_empty())
+        .collect();
+    edits.sort_by_key(|(range, _)| (range.start, range.end));
+
+    for pair in edits.windows(2) {
+        let (previous, next) = (&pair[0].0, &pair[1].0);
+        if previous.end > next.start || (previous.is_empty() && previous == next) {
+            return Err(OverlappingEdits {
+                first: previous.clone(),
+                second: next.clone(),
+            });
+        }
+    }
+
+    let (start, end) = match (
+        edits.first(),
+        edits.iter().map(|(range, _)| range.end).max(),
+    ) {
+        (Some((first, _)), Some(end)) => (first.start, end),
+        _ => return Ok(OpaqueDiff::empty()),
+    };
+    let byte_start = text.char_to_byte(start);
+    let old_byte_length = text.char_to_byte(end) - byte_start;
+
+    let mut new_end = end;
+    for (range, replacement) in edits.iter().rev() {
+        text.remove(range.clone());
+        text.insert(range.start, replacement);
+        new_end = new_end + replacement.chars().count() - range.len();
+    }
+
+    Ok(OpaqueDiff::new(
+        byte_start,
+        old_byte_length,
+        text.char_to_byte(new_end) - byte_start,
+        start,
+        end - start,
+        new_end - start,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use ropey::Rope;
@@ -323,6 +1098,311 @@ mod tests {
         assert_eq!(Cursor::new(), cursor);
     }
 
+    // Reconcile
+    #[test]
+    fn reconcile_with_insert_before_cursor() {
+        let mut text = Rope::from("The flowers 👨‍👨‍👧‍👧 were blooming.\n");
+        let mut cursor = Cursor::with_range(12..19);
+        let diff = Cursor::new().insert_chars(&mut text, "Oh! ".chars());
+        cursor.reconcile(&text, &diff);
+        assert_eq!(Cursor::with_range(16..23), cursor);
+        assert_eq!("👨‍👨‍👧‍👧", text.slice(cursor.range()));
+    }
+
+    #[test]
+    fn reconcile_with_delete_before_cursor() {
+        let mut text = Rope::from("Oh! The flowers 👨‍👨‍👧‍👧 were blooming.\n");
+        let mut cursor = Cursor::with_range(16..23);
+        let mut editing_cursor = Cursor::new();
+        editing_cursor.begin_selection();
+        movement::move_horizontally(&text, &mut editing_cursor, Direction::Forward, 4);
+        let diff = editing_cursor.delete_selection(&mut text).diff;
+        cursor.reconcile(&text, &diff);
+        assert_eq!(Cursor::with_range(12..19), cursor);
+        assert_eq!("👨‍👨‍👧‍👧", text.slice(cursor.range()));
+    }
+
+    fn text_with_selection(text: impl Into<Rope>, selection: Range<CharIndex>) -> (Rope, Cursor) {
+        let (text, mut cursor) = text_with_cursor(text);
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, selection.start);
+        cursor.begin_selection();
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, selection.len());
+        (text, cursor)
+    }
+
+    fn insert_at(text: &mut Rope, char_index: CharIndex, insertion: &str) -> OpaqueDiff {
+        Cursor::with_range(char_index..char_index).insert_chars(text, insertion.chars())
+    }
+
+    fn delete_at(text: &mut Rope, range: Range<CharIndex>) -> OpaqueDiff {
+        let (_, mut cursor) = text_with_selection(text.clone(), range);
+        cursor.delete_selection(text).diff
+    }
+
+    #[test]
+    fn reconcile_selection_with_edit_before() {
+        let (mut text, mut cursor) = text_with_selection("The flowers were blooming.\n", 4..11);
+        let diff = insert_at(&mut text, 0, "Oh! ");
+        cursor.reconcile(&text, &diff);
+        assert_eq!(8..15, cursor.selection());
+        assert_eq!("flowers", text.slice(cursor.selection()));
+
+        let diff = delete_at(&mut text, 0..4);
+        cursor.reconcile(&text, &diff);
+        assert_eq!(4..11, cursor.selection());
+        assert_eq!("flowers", text.slice(cursor.selection()));
+    }
+
+    #[test]
+    fn reconcile_selection_with_edit_inside() {
+        let (mut text, mut cursor) = text_with_selection("The flowers were blooming.\n", 4..16);
+        let diff = insert_at(&mut text, 11, " truly");
+        cursor.reconcile(&text, &diff);
+        assert_eq!(4..22, cursor.selection());
+        assert_eq!("flowers truly were", text.slice(cursor.selection()));
+
+        let diff = delete_at(&mut text, 4..12);
+        cursor.reconcile(&text, &diff);
+        assert_eq!(4..14, cursor.selection());
+        assert_eq!("truly were", text.slice(cursor.selection()));
+    }
+
+    #[test]
+    fn reconcile_selection_with_edit_across_anchor() {
+        // Selecting backwards, so the anchor is at the end of the selection
+        let text = Rope::from("The flowers were blooming.\n");
+        let mut cursor = Cursor::new();
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 11);
+        cursor.begin_selection();
+        movement::move_horizontally(&text, &mut cursor, Direction::Backward, 7);
+        assert_eq!(4..11, cursor.selection());
+
+        let mut text = text;
+        let diff = delete_at(&mut text, 8..16);
+        cursor.reconcile(&text, &diff);
+        assert_eq!(4..8, cursor.selection());
+        assert_eq!("flow", text.slice(cursor.selection()));
+    }
+
+    #[test]
+    fn reconcile_with_edit_after_cursor() {
+        let mut text = Rope::from("The flowers were blooming.\n");
+        let mut cursor = Cursor::with_range(2..3);
+        let mut editing_cursor = Cursor::new();
+        movement::move_to_end_of_line(&text, &mut editing_cursor);
+        let diff = editing_cursor.insert_chars(&mut text, " Indeed.".chars());
+        cursor.reconcile(&text, &diff);
+        assert_eq!(Cursor::with_range(2..3), cursor);
+    }
+
+    // Delete range
+    #[test]
+    fn delete_range_multibyte() {
+        let (mut text, mut cursor) = text_with_cursor("CJK 豈 更 車 Ⅷ\n");
+        let operation = cursor.delete_range(&mut text, 4..8);
+        assert_eq!(Rope::from("CJK 車 Ⅷ\n"), text);
+        assert_eq!(Rope::from("豈 更 "), operation.deleted);
+        assert_eq!(OpaqueDiff::new(4, 8, 0, 4, 4, 0), operation.diff);
+        assert_eq!(Cursor::with_range(4..5), cursor);
+    }
+
+    #[test]
+    fn delete_range_empty() {
+        let (mut text, mut cursor) = text_with_cursor("CJK 豈 更 車 Ⅷ\n");
+        let operation = cursor.delete_range(&mut text, 4..4);
+        assert_eq!(Rope::from("CJK 豈 更 車 Ⅷ\n"), text);
+        assert!(operation.diff.is_empty());
+    }
+
+    // Replace range
+    #[test]
+    fn replace_range_multibyte() {
+        let (mut text, mut cursor) = text_with_cursor("CJK 豈 更 車 Ⅷ\n");
+        let diff = cursor.replace_range(&mut text, 4..5, "👨‍👨‍👧‍👧");
+        assert_eq!(Rope::from("CJK 👨‍👨‍👧‍👧 更 車 Ⅷ\n"), text);
+        assert_eq!(OpaqueDiff::new(4, 3, 25, 4, 1, 7), diff);
+        assert_eq!(Cursor::with_range(11..12), cursor);
+    }
+
+    #[test]
+    fn replace_empty_range_inserts() {
+        let (mut text, mut cursor) = text_with_cursor("The flowers were blooming.\n");
+        let diff = cursor.replace_range(&mut text, 4..4, "red ");
+        assert_eq!(Rope::from("The red flowers were blooming.\n"), text);
+        assert_eq!(OpaqueDiff::new(4, 0, 4, 4, 0, 4), diff);
+        assert_eq!(Cursor::with_range(8..9), cursor);
+    }
+
+    #[test]
+    fn replace_range_with_empty_string_deletes() {
+        let (mut text, mut cursor) = text_with_cursor("The flowers were blooming.\n");
+        let diff = cursor.replace_range(&mut text, 3..11, "");
+        assert_eq!(Rope::from("The were blooming.\n"), text);
+        assert_eq!(OpaqueDiff::new(3, 8, 0, 3, 8, 0), diff);
+        assert_eq!(Cursor::with_range(3..4), cursor);
+
+        let diff = cursor.replace_range(&mut text, 3..3, "");
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn insert_rectangle_at_column() {
+        let mut text = Rope::from("The flowers\nwere\nblooming.\n");
+        let mut cursor = Cursor::with_range(2..3);
+        let diff = cursor.insert_rectangle(&mut text, &["ab", "cd", "ef"], 4);
+        assert_eq!(Rope::from("Thabe flowers\nwecdre\nblefooming.\n"), text);
+        assert_eq!(OpaqueDiff::new(0, 26, 32, 0, 26, 32), diff);
+        assert_eq!(Cursor::with_range(25..26), cursor);
+    }
+
+    #[test]
+    fn insert_rectangle_at_the_display_column() {
+        // The cursor after the tab is in column 5. The wide character covering
+        // it is pushed along whole and the short line is padded
+        let mut text = Rope::from("a\tb\n日本語\nxy\n");
+        let mut cursor = Cursor::with_range(2..3);
+        cursor.insert_rectangle(&mut text, &["|", "|", "|"], 4);
+        assert_eq!(Rope::from("a\t|b\n日本|語\nxy   |\n"), text);
+    }
+
+    #[test]
+    fn insert_rectangle_pads_short_lines() {
+        let mut text = Rope::from("The flowers\r\nwe\r\n\r\nblooming.");
+        let mut cursor = Cursor::with_range(4..5);
+        cursor.insert_rectangle(&mut text, &["ab", "cd", "ef", "gh", "ij"], 4);
+        assert_eq!(
+            Rope::from("The abflowers\r\nwe  cd\r\n    ef\r\nblooghming.\n    ij"),
+            text
+        );
+        assert_eq!(Cursor::with_range(49..49), cursor);
+    }
+
+    #[test]
+    fn whole_line_adds_missing_newline() {
+        let text = Rope::from("The flowers\nwere blooming.");
+        assert_eq!("The flowers\n", Cursor::with_range(4..5).whole_line(&text));
+        assert_eq!(
+            "were blooming.\n",
+            Cursor::with_range(16..17).whole_line(&text)
+        );
+        assert_eq!("\n", Cursor::new().whole_line(&Rope::new()));
+    }
+
+    #[test]
+    fn insert_lines_above_cursor_line() {
+        let mut text = Rope::from("The flowers\nwere blooming.");
+        let mut cursor = Cursor::with_range(16..17);
+        let diff = cursor.insert_lines_above(&mut text, "Buy a milk goat\n");
+        assert_eq!(
+            Rope::from("The flowers\nBuy a milk goat\nwere blooming."),
+            text
+        );
+        assert_eq!(OpaqueDiff::new(12, 0, 16, 12, 0, 16), diff);
+        assert_eq!(Cursor::with_range(32..33), cursor);
+
+        let mut cursor = Cursor::with_range(2..3);
+        let diff = cursor.insert_lines_above(&mut text, "車 Ⅷ");
+        assert_eq!(
+            Rope::from("車 Ⅷ\nThe flowers\nBuy a milk goat\nwere blooming."),
+            text
+        );
+        assert_eq!(OpaqueDiff::new(0, 0, 8, 0, 0, 4), diff);
+        assert_eq!(Cursor::with_range(6..7), cursor);
+    }
+
+    // Batch edits
+    #[test]
+    fn apply_several_edits() {
+        let mut text = Rope::from("The flowers were blooming.\nAt the market 豈 更\n");
+        let edits = [
+            (27..29, "Down at".to_string()),
+            (0..0, "Oh! ".to_string()),
+            (41..42, "車".to_string()),
+            (12..16, "are".to_string()),
+        ];
+
+        // Applying the edits one by one, from the last to the first
+        let mut expected = text.clone();
+        for (range, replacement) in [&edits[2], &edits[0], &edits[3], &edits[1]] {
+            Cursor::new().replace_range(&mut expected, range.clone(), replacement);
+        }
+
+        let diff = apply_edits(&mut text, &edits).unwrap();
+        assert_eq!(expected, text);
+        assert_eq!(
+            Rope::from("Oh! The flowers are blooming.\nDown at the market 車 更\n"),
+            text
+        );
+        assert_eq!(OpaqueDiff::new(0, 44, 52, 0, 42, 50), diff);
+    }
+
+    #[test]
+    fn apply_no_edits() {
+        let mut text = Rope::from("The flowers were blooming.\n");
+        assert_eq!(Ok(OpaqueDiff::empty()), apply_edits(&mut text, &[]));
+        assert_eq!(Rope::from("The flowers were blooming.\n"), text);
+    }
+
+    #[test]
+    fn apply_overlapping_edits() {
+        let mut text = Rope::from("The flowers were blooming.\n");
+        let edits = [(4..11, "roses".to_string()), (8..16, "are".to_string())];
+        assert_eq!(
+            Err(OverlappingEdits {
+                first: 4..11,
+                second: 8..16
+            }),
+            apply_edits(&mut text, &edits)
+        );
+
+        let edits = [(4..4, "red ".to_string()), (4..4, "blue ".to_string())];
+        assert!(apply_edits(&mut text, &edits).is_err());
+        assert_eq!(Rope::from("The flowers were blooming.\n"), text);
+    }
+
+    // Delete line
+    #[test]
+    fn delete_middle_line() {
+        let (mut text, mut cursor) = text_with_cursor("Buy a milk goat\nAt the market\nToday\n");
+        movement::move_vertically(&text, &mut cursor, 4, Direction::Forward, 1);
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 3);
+        let operation = cursor.delete_line(&mut text);
+        assert_eq!(Rope::from("Buy a milk goat\nToday\n"), text);
+        assert_eq!(Rope::from("At the market\n"), operation.deleted);
+        assert_eq!(Cursor::with_range(16..17), cursor);
+    }
+
+    #[test]
+    fn delete_last_line() {
+        let (mut text, mut cursor) = text_with_cursor("Buy a milk goat\nAt the market\n");
+        movement::move_vertically(&text, &mut cursor, 4, Direction::Forward, 1);
+        cursor.delete_line(&mut text);
+        assert_eq!(Rope::from("Buy a milk goat\n"), text);
+        assert_eq!(Cursor::with_range(16..16), cursor);
+
+        // Without a trailing newline, the previous line's newline is removed
+        let (mut text, mut cursor) = text_with_cursor("Buy a milk goat\nAt the market");
+        movement::move_to_end_of_buffer(&text, &mut cursor);
+        let operation = cursor.delete_line(&mut text);
+        assert_eq!(Rope::from("Buy a milk goat"), text);
+        assert_eq!(Rope::from("\nAt the market"), operation.deleted);
+        assert_eq!(Cursor::with_range(0..1), cursor);
+    }
+
+    #[test]
+    fn delete_sole_line() {
+        let (mut text, mut cursor) = text_with_cursor("Buy a milk goat\n");
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 5);
+        cursor.delete_line(&mut text);
+        assert_eq!(Rope::from(""), text);
+        assert_eq!(Cursor::new(), cursor);
+
+        let (mut text, mut cursor) = text_with_cursor("Buy a milk goat");
+        cursor.delete_line(&mut text);
+        assert_eq!(Rope::from(""), text);
+        assert_eq!(Cursor::new(), cursor);
+    }
+
     // Delete forward
     #[test]
     fn delete_forward_at_the_end() {
@@ -376,6 +1456,388 @@ mod tests {
         assert_eq!(expected, text);
     }
 
+    // Region
+    #[test]
+    fn region_without_selection_is_current_line() {
+        let text = Rope::from("Buy a milk goat\nAt the market\n");
+        let mut cursor = Cursor::new();
+        movement::move_vertically(&text, &mut cursor, 4, Direction::Forward, 1);
+        assert!(!cursor.has_selection());
+        assert_eq!(16..30, cursor.region_or_default(&text));
+
+        // The last line of the buffer has no trailing newline
+        let text = Rope::from("Buy a milk goat\nAt the market");
+        movement::move_to_end_of_buffer(&text, &mut cursor);
+        assert_eq!(16..29, cursor.region_or_default(&text));
+    }
+
+    #[test]
+    fn region_with_selection_is_selection() {
+        let text = Rope::from("Buy a milk goat\nAt the market\n");
+        let mut cursor = Cursor::new();
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 4);
+        cursor.begin_selection();
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 6);
+        assert!(cursor.has_selection());
+        assert_eq!(4..10, cursor.region_or_default(&text));
+    }
+
+    #[test]
+    fn delete_selection_falls_back_to_current_line() {
+        let (mut text, mut cursor) = text_with_cursor("Buy a milk goat\nAt the market\n");
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 3);
+        let operation = cursor.delete_selection(&mut text);
+        assert_eq!(Rope::from("At the market\n"), text);
+        assert_eq!(Rope::from("Buy a milk goat\n"), operation.deleted);
+        assert_eq!(Cursor::with_range(0..1), cursor);
+    }
+
+    #[test]
+    fn delete_selection_moves_cursor_to_region_start() {
+        let (mut text, mut cursor) = text_with_cursor("Buy a milk goat\nAt the market\n");
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 4);
+        cursor.begin_selection();
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 6);
+        let operation = cursor.delete_selection(&mut text);
+        assert_eq!(Rope::from("Buy  goat\nAt the market\n"), text);
+        assert_eq!(Rope::from("a milk"), operation.deleted);
+        assert_eq!(Cursor::with_range(4..5), cursor);
+    }
+
+    #[test]
+    fn tabify_and_untabify_mixed_indentation() {
+        let mixed = "fn f() {\n    if x {\n\t\ty();\n  \t}\n  z; // \tz\n}\n";
+        let spaces = "fn f() {\n    if x {\n        y();\n      }\n  z; // \tz\n}\n";
+        let tabs = "fn f() {\n\tif x {\n\t\ty();\n\t  }\n  z; // \tz\n}\n";
+
+        // The cursor stays on `y`
+        let mut text = Rope::from(mixed);
+        let mut cursor = Cursor::with_range(22..23);
+        cursor.untabify(&mut text, 4);
+        assert_eq!(Rope::from(spaces), text);
+        assert_eq!(Cursor::with_range(28..29), cursor);
+
+        let mut text = Rope::from(mixed);
+        let mut cursor = Cursor::with_range(22..23);
+        cursor.tabify(&mut text, 4);
+        assert_eq!(Rope::from(tabs), text);
+        assert_eq!(Cursor::with_range(19..20), cursor);
+
+        // In the indentation, the cursor stays in the same column
+        let mut text = Rope::from(spaces);
+        let mut cursor = Cursor::with_range(26..27);
+        cursor.tabify(&mut text, 4);
+        assert_eq!(Rope::from(tabs), text);
+        assert_eq!(Cursor::with_range(18..19), cursor);
+
+        // Only the selected lines change
+        let mut text = Rope::from(mixed);
+        let mut cursor = Cursor::with_range(9..10);
+        cursor.begin_selection();
+        cursor.range = 22..23;
+        cursor.tabify(&mut text, 4);
+        assert_eq!(
+            Rope::from("fn f() {\n\tif x {\n\t\ty();\n  \t}\n  z; // \tz\n}\n"),
+            text
+        );
+        assert_eq!(Cursor::with_range(19..20), cursor);
+    }
+
+    #[test]
+    fn swap_ranges_keeps_the_selected_text_selected() {
+        let mut text = Rope::from("f(first, second, x)");
+        let mut cursor = Cursor::with_range(2..3);
+        cursor.swap_ranges(&mut text, 2..7, 9..15);
+        assert_eq!(Rope::from("f(second, first, x)"), text);
+        assert_eq!(10..15, cursor.selection());
+
+        cursor.swap_ranges(&mut text, 10..15, 2..8);
+        assert_eq!(Rope::from("f(first, second, x)"), text);
+        assert_eq!(2..7, cursor.selection());
+
+        // Overlapping ranges are left alone
+        let diff = cursor.swap_ranges(&mut text, 2..7, 5..15);
+        assert!(diff.is_empty());
+        assert_eq!(Rope::from("f(first, second, x)"), text);
+    }
+
+    #[test]
+    fn indent_the_selected_lines() {
+        let mut text = Rope::from("fn f() {\n\nx\n}\n");
+        let mut cursor = Cursor::with_range(9..10);
+        cursor.begin_selection();
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 2);
+        let diff = cursor.indent(&mut text, "    ");
+        assert_eq!(Rope::from("fn f() {\n    \n    x\n}\n"), text);
+        assert_eq!(9..19, cursor.selection());
+        assert_eq!(19..20, cursor.range());
+        assert_eq!(
+            (9, 1, 9),
+            (diff.char_index, diff.old_char_length, diff.new_char_length)
+        );
+
+        // A reversed selection stays over the same text
+        let mut text = Rope::from("a\nbc\n");
+        let mut cursor = Cursor::with_range(3..4);
+        cursor.begin_selection();
+        movement::move_horizontally(&text, &mut cursor, Direction::Backward, 3);
+        cursor.indent(&mut text, "\t");
+        assert_eq!(Rope::from("\ta\n\tbc\n"), text);
+        assert_eq!(0..5, cursor.selection());
+        assert_eq!(0..1, cursor.range());
+
+        // Without a selection the cursor's line is indented
+        let mut cursor = Cursor::with_range(5..6);
+        cursor.indent(&mut text, "  ");
+        assert_eq!(Rope::from("\ta\n  \tbc\n"), text);
+        assert_eq!(7..8, cursor.range());
+    }
+
+    #[test]
+    fn duplicate_the_line_or_the_selection() {
+        let mut text = Rope::from("one\ntwo");
+        let mut cursor = Cursor::with_range(1..2);
+        let diff = cursor.duplicate(&mut text);
+        assert_eq!(Rope::from("one\none\ntwo"), text);
+        assert_eq!(5..6, cursor.range());
+        assert_eq!(
+            (4, 0, 4),
+            (diff.char_index, diff.old_char_length, diff.new_char_length)
+        );
+
+        // The last line gets the newline it's missing
+        let mut cursor = Cursor::with_range(10..11);
+        let diff = cursor.duplicate(&mut text);
+        assert_eq!(Rope::from("one\none\ntwo\ntwo"), text);
+        assert_eq!(14..15, cursor.range());
+        assert_eq!(
+            (11, 0, 4),
+            (diff.char_index, diff.old_char_length, diff.new_char_length)
+        );
+
+        let mut cursor = Cursor::with_range(5..6);
+        cursor.begin_selection();
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 4);
+        cursor.duplicate(&mut text);
+        assert_eq!(Rope::from("one\none\ntne\ntwo\ntwo"), text);
+        assert_eq!(5..9, cursor.selection());
+    }
+
+    #[test]
+    fn unindent_the_selected_lines() {
+        let mut text = Rope::from("\t  a\n\n      b\n\tc\n");
+        let mut cursor = Cursor::new();
+        cursor.select(&text, 3..16);
+        let diff = cursor.unindent(&mut text, 4);
+        assert_eq!(Rope::from("\ta\n\n    b\nc\n"), text);
+        assert_eq!(1..11, cursor.selection());
+        assert_eq!(
+            (1, 14, 9),
+            (diff.char_index, diff.old_char_length, diff.new_char_length)
+        );
+
+        // Without a selection the cursor's line is unindented, and a cursor
+        // in the removed indentation moves to its start
+        let mut cursor = Cursor::with_range(6..7);
+        cursor.unindent(&mut text, 4);
+        assert_eq!(Rope::from("\ta\n\nb\nc\n"), text);
+        assert_eq!(4..5, cursor.range());
+    }
+
+    #[test]
+    fn indent_and_unindent_a_selection_within_one_line() {
+        let mut text = Rope::from("    let x = 1;\nnext\n");
+        let mut cursor = Cursor::new();
+        cursor.select(&text, 8..9);
+        let diff = cursor.indent(&mut text, "    ");
+        assert_eq!(Rope::from("        let x = 1;\nnext\n"), text);
+        assert_eq!(12..13, cursor.selection());
+        assert_eq!(
+            (0, 0, 4),
+            (diff.char_index, diff.old_char_length, diff.new_char_length)
+        );
+        let diff = cursor.unindent(&mut text, 4);
+        assert_eq!(Rope::from("    let x = 1;\nnext\n"), text);
+        assert_eq!(8..9, cursor.selection());
+        assert_eq!(
+            (4, 4, 0),
+            (diff.char_index, diff.old_char_length, diff.new_char_length)
+        );
+
+        // A selection made forwards, and one ending with the line break, only
+        // change their own line
+        let mut cursor = Cursor::with_range(8..9);
+        cursor.begin_selection();
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 1);
+        cursor.indent(&mut text, "\t");
+        assert_eq!(Rope::from("\t    let x = 1;\nnext\n"), text);
+        assert_eq!(9..10, cursor.selection());
+        assert_eq!(10..11, cursor.range());
+        cursor.select(&text, 0..16);
+        cursor.unindent(&mut text, 4);
+        cursor.unindent(&mut text, 4);
+        assert_eq!(Rope::from("let x = 1;\nnext\n"), text);
+        assert_eq!(0..11, cursor.selection());
+    }
+
+    #[test]
+    fn toggle_comment_after_the_indentation() {
+        let mut text = Rope::from("fn f() {\n    x\n\n    //y\n}\n");
+        let mut cursor = Cursor::with_range(13..14);
+        cursor.begin_selection();
+        movement::move_vertically(&text, &mut cursor, 4, Direction::Forward, 2);
+        cursor.toggle_comment_lines(&mut text, "// ");
+        assert_eq!(Rope::from("fn f() {\n    // x\n\n    // //y\n}\n"), text);
+        assert_eq!((16, 26), (cursor.selection().start, cursor.range().start));
+
+        // Once every line is commented the tokens are removed, with or without
+        // the space after them
+        let diff = cursor.toggle_comment_lines(&mut text, "// ");
+        assert_eq!(Rope::from("fn f() {\n    x\n\n    //y\n}\n"), text);
+        assert_eq!((13, 20), (cursor.selection().start, cursor.range().start));
+        assert_eq!(
+            (13, 13, 7),
+            (diff.char_index, diff.old_char_length, diff.new_char_length)
+        );
+
+        let mut cursor = Cursor::with_range(21..22);
+        cursor.toggle_comment_lines(&mut text, "// ");
+        assert_eq!(Rope::from("fn f() {\n    x\n\n    y\n}\n"), text);
+        assert_eq!(20..21, cursor.range());
+    }
+
+    #[test]
+    fn kill_to_the_end_of_the_line() {
+        let mut text = Rope::from("let x = 1;\n\r\nend");
+        let mut cursor = Cursor::with_range(4..5);
+        let operation = cursor.kill_to_end_of_line(&mut text);
+        assert_eq!("x = 1;", operation.deleted.to_string());
+        assert_eq!(Rope::from("let \n\r\nend"), text);
+        assert_eq!(4..5, cursor.range());
+
+        // At the end of a line the line ending is killed, CRLF included
+        let operation = cursor.kill_to_end_of_line(&mut text);
+        assert_eq!("\n", operation.deleted.to_string());
+        let operation = cursor.kill_to_end_of_line(&mut text);
+        assert_eq!("\r\n", operation.deleted.to_string());
+        assert_eq!(Rope::from("let end"), text);
+
+        // The last line without a newline is killed up to the end of the text
+        let operation = cursor.kill_to_end_of_line(&mut text);
+        assert_eq!("end", operation.deleted.to_string());
+        let operation = cursor.kill_to_end_of_line(&mut text);
+        assert!(operation.diff.is_empty());
+        assert_eq!(Rope::from("let "), text);
+        assert_eq!(4..4, cursor.range());
+    }
+
+    #[test]
+    fn convert_the_case_of_the_selection() {
+        let word_characters = WordCharacters::default();
+        let mut text = Rope::from("let max_size = größe;");
+        let mut cursor = Cursor::with_range(4..5);
+        cursor.begin_selection();
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 8);
+        let diff = cursor.upcase_selection(&mut text, word_characters);
+        assert_eq!(Rope::from("let MAX_SIZE = größe;"), text);
+        assert_eq!(4..12, cursor.selection());
+        assert_eq!(
+            (4, 8, 8),
+            (diff.char_index, diff.old_char_length, diff.new_char_length)
+        );
+
+        // Without a selection the word at the cursor is converted, `ß`
+        // becoming `SS`, and the cursor moves past it
+        let mut cursor = Cursor::with_range(16..17);
+        cursor.upcase_selection(&mut text, word_characters);
+        assert_eq!(Rope::from("let MAX_SIZE = GRÖSSE;"), text);
+        assert_eq!(21..22, cursor.range());
+        cursor.downcase_selection(&mut text, word_characters);
+        assert_eq!(Rope::from("let MAX_SIZE = GRÖSSE;"), text);
+
+        // Off a word, up to the end of the next one
+        let mut cursor = Cursor::with_range(12..13);
+        cursor.downcase_selection(&mut text, word_characters);
+        assert_eq!(Rope::from("let MAX_SIZE = grösse;"), text);
+
+        let mut text = Rope::from("hELLO wIDE wORLD");
+        let mut cursor = Cursor::with_range(0..1);
+        cursor.begin_selection();
+        movement::move_to_end_of_buffer(&text, &mut cursor);
+        cursor.capitalize_selection(&mut text, word_characters);
+        assert_eq!(Rope::from("Hello Wide World"), text);
+        assert_eq!(0..16, cursor.selection());
+
+        // A selection starting in the middle of a word doesn't capitalize it
+        let mut text = Rope::from("ΣΊΣΥΦΟΣ sisyphus");
+        let mut cursor = Cursor::with_range(2..3);
+        cursor.begin_selection();
+        movement::move_to_end_of_buffer(&text, &mut cursor);
+        cursor.capitalize_selection(&mut text, word_characters);
+        assert_eq!(Rope::from("ΣΊσυφος Sisyphus"), text);
+    }
+
+    #[test]
+    fn join_lines_with_a_single_space() {
+        let mut text = Rope::from("let x =\n    1 +\r\n\t2;\n");
+        let mut cursor = Cursor::with_range(2..3);
+        let diff = cursor.join_lines(&mut text);
+        assert_eq!(Rope::from("let x = 1 +\r\n\t2;\n"), text);
+        assert_eq!(7..8, cursor.range());
+        assert_eq!(
+            (7, 5, 1),
+            (diff.char_index, diff.old_char_length, diff.new_char_length)
+        );
+
+        // Every line the selection touches is joined
+        let mut text = Rope::from("a \n  b\n\n  c\nd\n");
+        let mut cursor = Cursor::with_range(0..1);
+        cursor.begin_selection();
+        movement::move_vertically(&text, &mut cursor, 4, Direction::Forward, 3);
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 1);
+        cursor.join_lines(&mut text);
+        assert_eq!(Rope::from("a b c\nd\n"), text);
+        assert!(!cursor.has_selection());
+        assert_eq!(3..4, cursor.range());
+
+        // The last line has nothing to join
+        let mut text = Rope::from("a\nb");
+        let mut cursor = Cursor::with_range(2..3);
+        assert!(cursor.join_lines(&mut text).is_empty());
+        assert_eq!(Rope::from("a\nb"), text);
+    }
+
+    #[test]
+    fn transpose_chars_around_the_cursor() {
+        let mut text = Rope::from("abcd");
+        let mut cursor = Cursor::with_range(1..2);
+        cursor.transpose_chars(&mut text);
+        assert_eq!(Rope::from("bacd"), text);
+        assert_eq!(2..3, cursor.range());
+        cursor.transpose_chars(&mut text);
+        assert_eq!(Rope::from("bcad"), text);
+        assert_eq!(3..4, cursor.range());
+
+        // The last two graphemes are swapped at the end of the text
+        let mut cursor = Cursor::new();
+        movement::move_to_end_of_buffer(&text, &mut cursor);
+        cursor.transpose_chars(&mut text);
+        assert_eq!(Rope::from("bcda"), text);
+        assert_eq!(4..4, cursor.range());
+
+        // Nothing to swap at the start
+        let diff = Cursor::new().transpose_chars(&mut text);
+        assert!(diff.is_empty());
+        assert_eq!(Rope::from("bcda"), text);
+
+        // Clusters of several code points aren't split
+        let mut text = Rope::from("e\u{301}x");
+        let mut cursor = Cursor::with_range(2..3);
+        cursor.transpose_chars(&mut text);
+        assert_eq!(Rope::from("xe\u{301}"), text);
+        assert_eq!(3..3, cursor.range());
+    }
+
     const TEXT: &str = r#"
 Basic Latin
     ! " # $ % & ' ( ) *+,-./012ABCDEFGHI` a m  t u v z { | } ~
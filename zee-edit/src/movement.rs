@@ -1,8 +1,9 @@
 use ropey::Rope;
+use std::ops::Range;
 
 use crate::{
     graphemes::{RopeExt, RopeGraphemes},
-    Cursor,
+    CharIndex, Cursor, LineIndex,
 };
 
 /// The movement direction
@@ -12,6 +13,25 @@ pub enum Direction {
     Backward,
 }
 
+/// The characters words are made of. Letters, digits, `_` and any other
+/// character that isn't whitespace or ASCII punctuation always are, a mode
+/// can add more, e.g. `-` in Lisp and CSS or `$` in shell scripts.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct WordCharacters<'a> {
+    extra: &'a str,
+}
+
+impl<'a> WordCharacters<'a> {
+    pub fn new(extra: &'a str) -> Self {
+        Self { extra }
+    }
+
+    #[inline]
+    pub fn contains(&self, character: char) -> bool {
+        is_word_character(character) || self.extra.contains(character)
+    }
+}
+
 /// Move the cursor horizontally in the specified direction by `count` positions
 /// (grapheme clusters)
 #[inline]
@@ -61,11 +81,7 @@ pub fn move_vertically(
         }
     };
 
-    let current_visual_x = cursor.visual_horizontal_offset.get_or_insert_with(|| {
-        let current_line_start = text.line_to_char(current_line_index);
-        let line_to_cursor = text.slice(current_line_start..cursor.range.start);
-        crate::graphemes::width(tab_width, &line_to_cursor)
-    });
+    let current_visual_x = goal_column(text, cursor, tab_width);
 
     let new_line = text.line(new_line_index);
     let mut graphemes = RopeGraphemes::new(&new_line);
@@ -73,7 +89,7 @@ pub fn move_vertically(
     let mut char_offset = text.line_to_char(new_line_index);
     for grapheme in &mut graphemes {
         let width = crate::graphemes::width(tab_width, &grapheme);
-        if new_visual_x + width > *current_visual_x || grapheme.slice == "\n" {
+        if new_visual_x + width > current_visual_x || grapheme.slice == "\n" {
             break;
         }
         char_offset += grapheme.slice.len_chars();
@@ -83,18 +99,41 @@ pub fn move_vertically(
     cursor.range = char_offset..text.next_grapheme_boundary(char_offset);
 }
 
-/// Move the cursor in the specified direction by `count` words
+/// The visual column vertical movements aim for. It's the column of the
+/// cursor when the first of a run of vertical movements started, kept until
+/// the cursor moves some other way, so moving through shorter lines doesn't
+/// lose it.
+#[inline]
+fn goal_column(text: &Rope, cursor: &mut Cursor, tab_width: usize) -> usize {
+    *cursor.visual_horizontal_offset.get_or_insert_with(|| {
+        let line_start = text.line_to_char(text.char_to_line(cursor.range.start));
+        let line_to_cursor = text.slice(line_start..cursor.range.start);
+        crate::graphemes::width(tab_width, &line_to_cursor)
+    })
+}
+
+/// Move the cursor in the specified direction by `count` words. Moving
+/// forward lands at the end of a word, moving backward at its start. If
+/// `stop_at_eol` is set, the cursor also stops at the start and end of lines
+/// rather than moving past them to the next word.
 #[inline]
-pub fn move_word(text: &Rope, cursor: &mut Cursor, direction: Direction, count: usize) {
+pub fn move_word(
+    text: &Rope,
+    cursor: &mut Cursor,
+    word_characters: WordCharacters,
+    direction: Direction,
+    count: usize,
+    stop_at_eol: bool,
+) {
     match direction {
         Direction::Forward => {
             for _ in 0..count {
-                move_forward_word(text, cursor);
+                move_forward_word(text, cursor, word_characters, stop_at_eol);
             }
         }
         Direction::Backward => {
             for _ in 0..count {
-                move_backward_word(text, cursor);
+                move_backward_word(text, cursor, word_characters, stop_at_eol);
             }
         }
     }
@@ -102,30 +141,151 @@ pub fn move_word(text: &Rope, cursor: &mut Cursor, direction: Direction, count:
 
 /// Move the cursor forward by one word
 #[inline]
-pub fn move_forward_word(text: &Rope, cursor: &mut Cursor) {
+pub fn move_forward_word(
+    text: &Rope,
+    cursor: &mut Cursor,
+    word_characters: WordCharacters,
+    stop_at_eol: bool,
+) {
+    let is_word_character = |c| word_characters.contains(c);
+    let mut position = cursor.range.start;
+    if stop_at_eol && text.get_char(position).map_or(false, is_line_break) {
+        position = text.next_grapheme_boundary(position);
+    }
+    let is_stop = |c| is_word_character(c) || (stop_at_eol && is_line_break(c));
     let first_word_character =
-        skip_while_forward(text, cursor.range.start, |c| !is_word_character(c))
-            .unwrap_or_else(|| text.len_chars());
-    let grapheme_start = skip_while_forward(text, first_word_character, is_word_character)
-        .unwrap_or_else(|| text.len_chars());
+        skip_while_forward(text, position, |c| !is_stop(c)).unwrap_or_else(|| text.len_chars());
+    let grapheme_start = if stop_at_eol
+        && text
+            .get_char(first_word_character)
+            .map_or(false, is_line_break)
+    {
+        first_word_character
+    } else {
+        skip_while_forward(text, first_word_character, is_word_character)
+            .unwrap_or_else(|| text.len_chars())
+    };
     cursor.range = grapheme_start..text.next_grapheme_boundary(grapheme_start);
     cursor.visual_horizontal_offset = None;
 }
 
 /// Move the cursor backward by one word
 #[inline]
-pub fn move_backward_word(text: &Rope, cursor: &mut Cursor) {
-    let first_word_character =
-        skip_while_backward(text, cursor.range.start, |c| !is_word_character(c)).unwrap_or(0);
-    let grapheme_start =
-        skip_while_backward(text, first_word_character, is_word_character).unwrap_or(0);
+pub fn move_backward_word(
+    text: &Rope,
+    cursor: &mut Cursor,
+    word_characters: WordCharacters,
+    stop_at_eol: bool,
+) {
+    let is_word_character = |c| word_characters.contains(c);
+    let precedes_line_break =
+        |position: usize| position > 0 && is_line_break(text.char(position - 1));
+    let mut position = cursor.range.start;
+    if stop_at_eol && precedes_line_break(position) {
+        position = text.prev_grapheme_boundary(position);
+    }
+    let is_stop = |c| is_word_character(c) || (stop_at_eol && is_line_break(c));
+    let first_word_character = skip_while_backward(text, position, |c| !is_stop(c)).unwrap_or(0);
+    let grapheme_start = if stop_at_eol && precedes_line_break(first_word_character) {
+        first_word_character
+    } else {
+        skip_while_backward(text, first_word_character, is_word_character).unwrap_or(0)
+    };
+    cursor.range = grapheme_start..text.next_grapheme_boundary(grapheme_start);
+    cursor.visual_horizontal_offset = None;
+}
+
+/// Move the cursor in the specified direction by `count` subwords, the parts
+/// of an identifier delimited by case changes and `_` or `-` separators. For
+/// example, `getHTTPResponse` consists of `get`, `HTTP` and `Response`.
+#[inline]
+pub fn move_subword(text: &Rope, cursor: &mut Cursor, direction: Direction, count: usize) {
+    match direction {
+        Direction::Forward => {
+            for _ in 0..count {
+                move_forward_subword(text, cursor);
+            }
+        }
+        Direction::Backward => {
+            for _ in 0..count {
+                move_backward_subword(text, cursor);
+            }
+        }
+    }
+}
+
+/// Move the cursor forward to the end of the next subword
+#[inline]
+pub fn move_forward_subword(text: &Rope, cursor: &mut Cursor) {
+    let start = skip_while_forward(text, cursor.range.start, |c| !is_subword_character(c))
+        .unwrap_or_else(|| text.len_chars());
+    let grapheme_start = match text.get_char(start) {
+        Some(character) if character.is_uppercase() => {
+            let end_of_uppercase = skip_while_forward(text, start, char::is_uppercase)
+                .unwrap_or_else(|| text.len_chars());
+            let followed_by_lowercase = text
+                .get_char(end_of_uppercase)
+                .map_or(false, char::is_lowercase);
+            if end_of_uppercase - start == 1 && followed_by_lowercase {
+                // A capitalised subword, e.g. `Response`
+                skip_while_forward(text, end_of_uppercase, is_lowercase)
+                    .unwrap_or_else(|| text.len_chars())
+            } else if followed_by_lowercase {
+                // An acronym followed by a capitalised subword, e.g. the
+                // `HTTP` in `HTTPResponse`
+                end_of_uppercase - 1
+            } else {
+                end_of_uppercase
+            }
+        }
+        Some(_) => {
+            skip_while_forward(text, start, is_lowercase).unwrap_or_else(|| text.len_chars())
+        }
+        None => start,
+    };
+    cursor.range = grapheme_start..text.next_grapheme_boundary(grapheme_start);
+    cursor.visual_horizontal_offset = None;
+}
+
+/// Move the cursor backward to the start of the previous subword
+#[inline]
+pub fn move_backward_subword(text: &Rope, cursor: &mut Cursor) {
+    let end =
+        skip_while_backward(text, cursor.range.start, |c| !is_subword_character(c)).unwrap_or(0);
+    let grapheme_start = match end.checked_sub(1).map(|index| text.char(index)) {
+        Some(character) if character.is_uppercase() => {
+            skip_while_backward(text, end, char::is_uppercase).unwrap_or(0)
+        }
+        Some(_) => {
+            let start_of_lowercase = skip_while_backward(text, end, is_lowercase).unwrap_or(0);
+            // Include the capital letter of a capitalised subword
+            let capitalised = start_of_lowercase > 0
+                && text.char(start_of_lowercase - 1).is_uppercase()
+                && text.char(start_of_lowercase).is_lowercase();
+            if capitalised {
+                start_of_lowercase - 1
+            } else {
+                start_of_lowercase
+            }
+        }
+        None => 0,
+    };
     cursor.range = grapheme_start..text.next_grapheme_boundary(grapheme_start);
     cursor.visual_horizontal_offset = None;
 }
 
 /// Move the cursor in the specified direction by `count` paragraphs
 #[inline]
-pub fn move_paragraph(text: &Rope, cursor: &mut Cursor, direction: Direction, count: usize) {
+pub fn move_paragraph(
+    text: &Rope,
+    cursor: &mut Cursor,
+    tab_width: usize,
+    direction: Direction,
+    count: usize,
+) {
+    // Paragraphs are a vertical movement too, a line movement after them
+    // returns to the column the cursor started from
+    goal_column(text, cursor, tab_width);
     match direction {
         Direction::Forward => {
             for _ in 0..count {
@@ -140,43 +300,119 @@ pub fn move_paragraph(text: &Rope, cursor: &mut Cursor, direction: Direction, co
     }
 }
 
-/// Move the cursor forward by one paragraph
+/// Move the cursor forward by one paragraph, to the blank line following it
+/// or to the end of the text if it's the last paragraph
 #[inline]
 pub fn move_forward_paragraph(text: &Rope, cursor: &mut Cursor) {
-    let current_line = text.char_to_line(cursor.range.start);
-    let lines = text.lines_at(current_line + 1);
+    let last_line = text.len_lines() - 1;
+    let mut line_index = text.char_to_line(cursor.range.start);
+    // Consecutive blank lines form a single separator, skip them first
+    while line_index <= last_line && is_blank_line(text, line_index) {
+        line_index += 1;
+    }
+    while line_index <= last_line && !is_blank_line(text, line_index) {
+        line_index += 1;
+    }
 
-    let start = lines
-        .enumerate()
-        .find_map(|(index, line)| {
-            line.chars()
-                .all(char::is_whitespace)
-                .then(|| text.line_to_char(current_line + index + 1))
-        })
-        .unwrap_or_else(|| text.len_chars());
+    let start = if line_index > last_line {
+        text.len_chars()
+    } else {
+        text.line_to_char(line_index)
+    };
     cursor.range = start..text.next_grapheme_boundary(start);
-    cursor.visual_horizontal_offset = None;
 }
 
-/// Move the cursor backward by one paragraph
+/// Move the cursor backward by one paragraph, to the blank line preceding it
+/// or to the start of the text if it's the first paragraph
 #[inline]
 pub fn move_backward_paragraph(text: &Rope, cursor: &mut Cursor) {
-    let current_line = text.char_to_line(cursor.range.start);
-    let mut lines = text.lines_at(current_line.saturating_sub(1));
-    lines.reverse();
+    let mut line_index = text.char_to_line(cursor.range.start);
+    while line_index > 0 && is_blank_line(text, line_index) {
+        line_index -= 1;
+    }
+    while line_index > 0 && !is_blank_line(text, line_index) {
+        line_index -= 1;
+    }
 
-    let start = lines
-        .enumerate()
-        .find_map(|(index, line)| {
-            line.chars()
-                .all(char::is_whitespace)
-                .then(|| text.line_to_char(current_line.saturating_sub(index + 1)))
-        })
-        .unwrap_or(0);
+    let start = text.line_to_char(line_index);
     cursor.range = start..text.next_grapheme_boundary(start);
+}
+
+/// Move the cursor by `count` pages, which are separated by form feeds
+/// (`^L`). The cursor lands on the line after a form feed, or at the start or
+/// end of the text if there are no more page breaks.
+#[inline]
+pub fn move_page_break(text: &Rope, cursor: &mut Cursor, direction: Direction, count: usize) {
+    let mut position = cursor.range.start;
+    for _ in 0..count {
+        position = match direction {
+            Direction::Forward => text
+                .chars_at(position)
+                .position(|character| character == FORM_FEED)
+                .map(|offset| page_break_end(text, position + offset))
+                .unwrap_or_else(|| text.len_chars()),
+            // Skip the page break if the cursor is already right after it
+            Direction::Backward => (0..position)
+                .rev()
+                .zip(text.chars_at(position).reversed())
+                .find_map(|(index, character)| {
+                    (character == FORM_FEED)
+                        .then(|| page_break_end(text, index))
+                        .filter(|&end| end < position)
+                })
+                .unwrap_or(0),
+        };
+    }
+    cursor.range = position..text.next_grapheme_boundary(position);
     cursor.visual_horizontal_offset = None;
 }
 
+/// Moves the cursor to the start of the `count`th of `matches` after it, or
+/// before it going backward, e.g. to step through occurrences of a keyword.
+/// The matches are ordered by position. Returns `false`, leaving the cursor
+/// where it is, if there are fewer matches in that direction.
+pub fn move_to_match(
+    text: &Rope,
+    cursor: &mut Cursor,
+    matches: &[Range<CharIndex>],
+    direction: Direction,
+    count: usize,
+) -> bool {
+    let position = cursor.range.start;
+    let target = match direction {
+        Direction::Forward => matches
+            .iter()
+            .filter(|range| range.start > position)
+            .nth(count.saturating_sub(1)),
+        Direction::Backward => matches
+            .iter()
+            .rev()
+            .filter(|range| range.start < position)
+            .nth(count.saturating_sub(1)),
+    };
+    match target {
+        Some(range) => {
+            cursor.range = range.start..text.next_grapheme_boundary(range.start);
+            cursor.visual_horizontal_offset = None;
+            true
+        }
+        None => false,
+    }
+}
+
+/// The start of the line after a form feed. A form feed is usually on a line
+/// of its own, the line break following it is part of the page break.
+fn page_break_end(text: &Rope, form_feed: CharIndex) -> CharIndex {
+    let end = form_feed + 1;
+    match (text.get_char(end), text.get_char(end + 1)) {
+        (Some('\n'), _) => end + 1,
+        (Some('\r'), Some('\n')) => end + 2,
+        _ => end,
+    }
+}
+
+const FORM_FEED: char = '\u{c}';
+
 /// Move the cursor to the beginning of the current line
 #[inline]
 pub fn move_to_start_of_line(text: &Rope, cursor: &mut Cursor) {
@@ -222,6 +458,106 @@ pub fn move_to_end_of_buffer(text: &Rope, cursor: &mut Cursor) {
     cursor.visual_horizontal_offset = None;
 }
 
+/// Move the cursor to the line `percent` of the way through the text, e.g. 50
+/// goes to the middle line. The cursor lands on the first non-whitespace
+/// character of the line. A final empty line after a trailing newline isn't
+/// counted, so 100 goes to the last line with content.
+pub fn move_to_percent(text: &Rope, cursor: &mut Cursor, percent: u8) {
+    let ends_with_newline = text.len_chars() > 0 && text.char(text.len_chars() - 1) == '\n';
+    let last_line_index = text
+        .len_lines()
+        .saturating_sub(if ends_with_newline { 2 } else { 1 });
+    let line_index = last_line_index * usize::from(std::cmp::min(percent, 100)) / 100;
+    move_to_line(text, cursor, line_index);
+}
+
+/// Move the cursor to the first non-whitespace character of a line, or of
+/// the last line if `line_index` is past the end of the text
+pub fn move_to_line(text: &Rope, cursor: &mut Cursor, line_index: LineIndex) {
+    let line_index = std::cmp::min(line_index, text.len_lines() - 1);
+    let line_start = text.line_to_char(line_index);
+    let line_end = line_start + text.line(line_index).len_chars();
+    let position = skip_while_forward(text, line_start, |character| {
+        character.is_whitespace() && !is_line_break(character)
+    })
+    .map_or(line_end, |position| std::cmp::min(position, line_end));
+    cursor.range = position..text.next_grapheme_boundary(position);
+    cursor.visual_horizontal_offset = None;
+}
+
+/// The pairs of brackets matched by [`matching_bracket`], opening first
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// The position of the bracket matching the one at `position`, skipping
+/// nested pairs of the same kind. Only the brackets in `within` for which
+/// `is_code` holds are counted, e.g. to skip the ones in strings and comments.
+/// Returns `None` if there's no bracket at `position` or it's unbalanced.
+pub fn matching_bracket(
+    text: &Rope,
+    position: CharIndex,
+    within: Range<CharIndex>,
+    is_code: impl Fn(CharIndex) -> bool,
+) -> Option<CharIndex> {
+    if position >= text.len_chars() || !is_code(position) {
+        return None;
+    }
+    let character = text.char(position);
+    let (opening, closing, direction) = BRACKETS.iter().find_map(|&(opening, closing)| {
+        if character == opening {
+            Some((opening, closing, Direction::Forward))
+        } else if character == closing {
+            Some((opening, closing, Direction::Backward))
+        } else {
+            None
+        }
+    })?;
+
+    let mut depth = 0usize;
+    let mut visit = |index: CharIndex, character: char| {
+        if !is_code(index) {
+            return false;
+        }
+        if character == opening || character == closing {
+            let deeper = (character == opening) == (direction == Direction::Forward);
+            if deeper {
+                depth += 1;
+            } else if depth == 0 {
+                return true;
+            } else {
+                depth -= 1;
+            }
+        }
+        false
+    };
+    match direction {
+        Direction::Forward => {
+            let end = std::cmp::min(within.end, text.len_chars());
+            (position + 1..end)
+                .zip(text.chars_at(position + 1))
+                .find(|&(index, character)| visit(index, character))
+        }
+        Direction::Backward => (within.start..position)
+            .rev()
+            .zip(text.chars_at(position).reversed())
+            .find(|&(index, character)| visit(index, character)),
+    }
+    .map(|(index, _)| index)
+}
+
+/// Moves the cursor to the bracket matching the one under it, one of `()`,
+/// `[]` or `{}`. Returns whether there was one.
+pub fn move_to_matching_bracket(text: &Rope, cursor: &mut Cursor) -> bool {
+    let position = cursor.range.start;
+    match matching_bracket(text, position, 0..text.len_chars(), |_| true) {
+        Some(position) => {
+            cursor.range = position..text.next_grapheme_boundary(position);
+            cursor.visual_horizontal_offset = None;
+            true
+        }
+        None => false,
+    }
+}
+
 #[inline]
 fn skip_while_forward(
     text: &Rope,
@@ -247,7 +583,27 @@ fn skip_while_backward(
 }
 
 #[inline]
-fn is_word_character(character: char) -> bool {
+fn is_blank_line(text: &Rope, line_index: usize) -> bool {
+    text.line(line_index).chars().all(char::is_whitespace)
+}
+
+#[inline]
+fn is_line_break(character: char) -> bool {
+    character == '\n' || character == '\r'
+}
+
+#[inline]
+fn is_subword_character(character: char) -> bool {
+    character != '_' && is_word_character(character)
+}
+
+#[inline]
+fn is_lowercase(character: char) -> bool {
+    is_subword_character(character) && !character.is_uppercase()
+}
+
+#[inline]
+pub(crate) fn is_word_character(character: char) -> bool {
     character == '_' || (!character.is_whitespace() && !character.is_ascii_punctuation())
 }
 
@@ -341,6 +697,455 @@ mod tests {
         assert_eq!(text.slice_cursor(&cursor), "T");
     }
 
+    fn word_stops(
+        text: &Rope,
+        start: usize,
+        direction: Direction,
+        stop_at_eol: bool,
+        count: usize,
+    ) -> Vec<usize> {
+        let mut cursor = Cursor::with_range(start..text.next_grapheme_boundary(start));
+        (0..count)
+            .map(|_| {
+                move_word(
+                    text,
+                    &mut cursor,
+                    WordCharacters::default(),
+                    direction,
+                    1,
+                    stop_at_eol,
+                );
+                cursor.range.start
+            })
+            .collect()
+    }
+
+    #[test]
+    fn move_forward_word_across_lines() {
+        let text = Rope::from("The flowers\n  were blooming.\n");
+        assert_eq!(
+            vec![3, 11, 18, 27, 29, 29],
+            word_stops(&text, 0, Direction::Forward, false, 6)
+        );
+    }
+
+    #[test]
+    fn move_forward_word_stopping_at_eol() {
+        let text = Rope::from("The flowers\n  were blooming.\n");
+        assert_eq!(
+            vec![3, 11, 18, 27, 28, 29],
+            word_stops(&text, 0, Direction::Forward, true, 6)
+        );
+
+        let text = Rope::from("The\r\nflowers");
+        assert_eq!(
+            vec![3, 12],
+            word_stops(&text, 0, Direction::Forward, true, 2)
+        );
+    }
+
+    #[test]
+    fn move_backward_word_across_lines() {
+        let text = Rope::from("The flowers\n  were blooming.\n");
+        assert_eq!(
+            vec![19, 14, 4, 0, 0],
+            word_stops(&text, 29, Direction::Backward, false, 5)
+        );
+    }
+
+    #[test]
+    fn move_backward_word_stopping_at_eol() {
+        let text = Rope::from("The flowers\n  were blooming.\n");
+        assert_eq!(
+            vec![19, 14, 12, 4, 0, 0],
+            word_stops(&text, 29, Direction::Backward, true, 6)
+        );
+
+        let text = Rope::from("The\r\n flowers");
+        assert_eq!(
+            vec![6, 5, 0],
+            word_stops(&text, 13, Direction::Backward, true, 3)
+        );
+    }
+
+    #[test]
+    fn move_word_with_extra_word_characters() {
+        let text = Rope::from("(foo-bar $baz)");
+        let stops = |word_characters, direction, start| {
+            let mut cursor = Cursor::with_range(start..start + 1);
+            (0..3)
+                .map(|_| {
+                    move_word(&text, &mut cursor, word_characters, direction, 1, false);
+                    cursor.range.start
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let lisp = WordCharacters::new("-");
+        assert_eq!(vec![8, 13, 14], stops(lisp, Direction::Forward, 0));
+        assert_eq!(vec![10, 1, 0], stops(lisp, Direction::Backward, 14));
+        assert_eq!(
+            vec![4, 8, 13],
+            stops(WordCharacters::default(), Direction::Forward, 0)
+        );
+        assert_eq!(
+            vec![9, 5, 1],
+            stops(WordCharacters::new("$"), Direction::Backward, 14)
+        );
+    }
+
+    fn subword_stops(text: &Rope, start: usize, direction: Direction, count: usize) -> Vec<usize> {
+        let mut cursor = Cursor::with_range(start..text.next_grapheme_boundary(start));
+        (0..count)
+            .map(|_| {
+                move_subword(text, &mut cursor, direction, 1);
+                cursor.range.start
+            })
+            .collect()
+    }
+
+    #[test]
+    fn move_forward_subword() {
+        let text = Rope::from("getHTTPResponse");
+        assert_eq!(
+            vec![3, 7, 15, 15],
+            subword_stops(&text, 0, Direction::Forward, 4)
+        );
+
+        let text = Rope::from("snake_case-name ParseXML2json");
+        assert_eq!(
+            vec![5, 10, 15, 21, 24, 29],
+            subword_stops(&text, 0, Direction::Forward, 6)
+        );
+
+        let text = Rope::from("IO URL a");
+        assert_eq!(
+            vec![2, 6, 8],
+            subword_stops(&text, 0, Direction::Forward, 3)
+        );
+    }
+
+    #[test]
+    fn move_backward_subword() {
+        let text = Rope::from("getHTTPResponse");
+        assert_eq!(
+            vec![7, 3, 0, 0],
+            subword_stops(&text, 15, Direction::Backward, 4)
+        );
+
+        let text = Rope::from("snake_case-name ParseXML2json");
+        assert_eq!(
+            vec![24, 21, 16, 11, 6, 0],
+            subword_stops(&text, 29, Direction::Backward, 6)
+        );
+
+        let text = Rope::from("IO URL a");
+        assert_eq!(
+            vec![7, 3, 0],
+            subword_stops(&text, 8, Direction::Backward, 3)
+        );
+    }
+
+    fn paragraph_stops(
+        text: &Rope,
+        start: usize,
+        direction: Direction,
+        count: usize,
+    ) -> Vec<usize> {
+        let mut cursor = Cursor::with_range(start..text.next_grapheme_boundary(start));
+        (0..count)
+            .map(|_| {
+                move_paragraph(text, &mut cursor, 4, direction, 1);
+                cursor.range.start
+            })
+            .collect()
+    }
+
+    #[test]
+    fn move_paragraph_without_trailing_newline() {
+        let text = Rope::from("The flowers\nwere\n\nblooming.");
+        assert_eq!(
+            vec![17, 27, 27],
+            paragraph_stops(&text, 0, Direction::Forward, 3)
+        );
+        assert_eq!(
+            vec![17, 0, 0],
+            paragraph_stops(&text, 27, Direction::Backward, 3)
+        );
+    }
+
+    #[test]
+    fn move_paragraph_with_trailing_newline() {
+        let text = Rope::from("The flowers\n\nwere blooming.\n");
+        assert_eq!(
+            vec![12, 28, 28],
+            paragraph_stops(&text, 0, Direction::Forward, 3)
+        );
+        assert_eq!(
+            vec![12, 0],
+            paragraph_stops(&text, 28, Direction::Backward, 2)
+        );
+    }
+
+    #[test]
+    fn move_paragraph_over_multiple_blank_lines() {
+        let text = Rope::from("A\nB\n\n \n\t\nC\nD");
+        assert_eq!(
+            vec![4, 12, 12],
+            paragraph_stops(&text, 0, Direction::Forward, 3)
+        );
+        assert_eq!(
+            vec![7, 0, 0],
+            paragraph_stops(&text, 12, Direction::Backward, 3)
+        );
+    }
+
+    #[test]
+    fn move_paragraph_in_blank_text() {
+        let text = Rope::from("\n\n\n");
+        assert_eq!(vec![3], paragraph_stops(&text, 0, Direction::Forward, 1));
+        assert_eq!(vec![0], paragraph_stops(&text, 3, Direction::Backward, 1));
+
+        let text = Rope::new();
+        assert_eq!(vec![0], paragraph_stops(&text, 0, Direction::Forward, 1));
+        assert_eq!(vec![0], paragraph_stops(&text, 0, Direction::Backward, 1));
+    }
+
+    /// The visual column of the cursor after each of `moves`, each a
+    /// direction and a number of lines, starting from `start`
+    fn vertical_columns(text: &Rope, start: usize, moves: &[(Direction, usize)]) -> Vec<usize> {
+        let mut cursor = Cursor::with_range(start..text.next_grapheme_boundary(start));
+        moves
+            .iter()
+            .map(|&(direction, count)| {
+                move_vertically(text, &mut cursor, 4, direction, count);
+                let line_start = text.line_to_char(text.char_to_line(cursor.range.start));
+                crate::graphemes::width(4, &text.slice(line_start..cursor.range.start))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn move_by_pages_keeps_the_goal_column() {
+        let text = Rope::from("let value = 1;\nx\n\tif value {\n\nlet other = 2;\n");
+        // The short and blank lines don't change the column the cursor
+        // returns to on longer lines, even with a tab before it
+        assert_eq!(
+            vec![8, 0, 8, 1, 8],
+            vertical_columns(
+                &text,
+                8,
+                &[
+                    (Direction::Forward, 2),
+                    (Direction::Forward, 1),
+                    (Direction::Forward, 1),
+                    (Direction::Backward, 3),
+                    (Direction::Backward, 1),
+                ]
+            )
+        );
+
+        // Moving a whole page at once lands in the same place
+        assert_eq!(
+            vec![8, 1, 8],
+            vertical_columns(
+                &text,
+                8,
+                &[
+                    (Direction::Forward, 4),
+                    (Direction::Backward, 3),
+                    (Direction::Backward, 1)
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn move_paragraph_keeps_the_goal_column() {
+        let text = Rope::from("let value = 1;\nx\n\nlet other = 2;\n");
+        let mut cursor = Cursor::with_range(8..9);
+        move_paragraph(&text, &mut cursor, 4, Direction::Forward, 1);
+        assert_eq!(17..18, cursor.range);
+        move_vertically(&text, &mut cursor, 4, Direction::Forward, 1);
+        assert_eq!(26..27, cursor.range);
+
+        // A horizontal movement sets a new goal column
+        move_horizontally(&text, &mut cursor, Direction::Backward, 2);
+        move_paragraph(&text, &mut cursor, 4, Direction::Backward, 1);
+        assert_eq!(17..18, cursor.range);
+        move_vertically(&text, &mut cursor, 4, Direction::Backward, 2);
+        assert_eq!(6..7, cursor.range);
+    }
+
+    fn percent_stop(text: &Rope, percent: u8) -> usize {
+        let mut cursor = Cursor::new();
+        move_to_percent(text, &mut cursor, percent);
+        assert_eq!(None, cursor.visual_horizontal_offset);
+        text.char_to_line(cursor.range.start)
+    }
+
+    #[test]
+    fn move_to_percent_of_known_size() {
+        let text: Rope = (0..11)
+            .map(|index| format!("line {}\n", index))
+            .collect::<String>()
+            .into();
+        assert_eq!(0, percent_stop(&text, 0));
+        assert_eq!(5, percent_stop(&text, 50));
+        assert_eq!(10, percent_stop(&text, 100));
+        assert_eq!(10, percent_stop(&text, 255));
+    }
+
+    #[test]
+    fn move_to_percent_lands_on_first_non_whitespace() {
+        let text = Rope::from("fn main() {\n    todo!()\n}");
+        let mut cursor = Cursor::new();
+        cursor.visual_horizontal_offset = Some(8);
+        move_to_percent(&text, &mut cursor, 50);
+        assert_eq!(Cursor::with_range(16..17), cursor);
+
+        move_to_percent(&text, &mut cursor, 100);
+        assert_eq!(Cursor::with_range(24..25), cursor);
+
+        let text = Rope::from("A\n  \nB\n");
+        move_to_percent(&text, &mut cursor, 50);
+        assert_eq!(Cursor::with_range(4..5), cursor);
+
+        let text = Rope::new();
+        move_to_percent(&text, &mut cursor, 50);
+        assert_eq!(Cursor::with_range(0..0), cursor);
+    }
+
+    #[test]
+    fn move_to_line_clamps_to_the_last_line() {
+        let text = Rope::from("one\n  two\nthree");
+        let mut cursor = Cursor::new();
+        move_to_line(&text, &mut cursor, 1);
+        assert_eq!(Cursor::with_range(6..7), cursor);
+
+        move_to_line(&text, &mut cursor, 10);
+        assert_eq!(Cursor::with_range(10..11), cursor);
+    }
+
+    #[test]
+    fn move_between_matching_brackets() {
+        let text = Rope::from("f(a[0], (b)) { g() }");
+        let mut cursor = Cursor::with_range(1..2);
+        assert!(move_to_matching_bracket(&text, &mut cursor));
+        assert_eq!(Cursor::with_range(11..12), cursor);
+        assert!(move_to_matching_bracket(&text, &mut cursor));
+        assert_eq!(Cursor::with_range(1..2), cursor);
+
+        let mut cursor = Cursor::with_range(13..14);
+        assert!(move_to_matching_bracket(&text, &mut cursor));
+        assert_eq!(Cursor::with_range(19..20), cursor);
+
+        // Not on a bracket, or an unbalanced one
+        let mut cursor = Cursor::with_range(2..3);
+        assert!(!move_to_matching_bracket(&text, &mut cursor));
+        assert_eq!(Cursor::with_range(2..3), cursor);
+        let text = Rope::from("(()");
+        let mut cursor = Cursor::new();
+        assert!(!move_to_matching_bracket(&text, &mut cursor));
+
+        // Brackets that aren't code are skipped
+        let text = Rope::from("(\")\")");
+        assert_eq!(
+            Some(4),
+            matching_bracket(&text, 0, 0..5, |index| index != 2)
+        );
+        assert_eq!(None, matching_bracket(&text, 0, 0..4, |index| index != 2));
+    }
+
+    #[test]
+    fn move_between_page_breaks() {
+        let text = Rope::from("one\n\u{c}\ntwo\n\u{c}\nthree\n");
+        let stops = |start: usize, direction: Direction| {
+            let mut cursor = Cursor::with_range(start..start + 1);
+            (0..3)
+                .map(|_| {
+                    move_page_break(&text, &mut cursor, direction, 1);
+                    cursor.range.start
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![6, 12, 18], stops(0, Direction::Forward));
+        assert_eq!(vec![12, 6, 0], stops(18, Direction::Backward));
+        assert_eq!(vec![6, 0, 0], stops(12, Direction::Backward));
+
+        let mut cursor = Cursor::new();
+        move_page_break(&text, &mut cursor, Direction::Forward, 2);
+        assert_eq!(12, cursor.range.start);
+    }
+
+    #[test]
+    fn move_page_break_without_line_breaks() {
+        // A form feed in the middle of a line
+        let text = Rope::from("a\u{c}b\r\n\u{c}\r\nc");
+        let mut cursor = Cursor::new();
+        move_page_break(&text, &mut cursor, Direction::Forward, 1);
+        assert_eq!(2, cursor.range.start);
+        move_page_break(&text, &mut cursor, Direction::Forward, 1);
+        assert_eq!(8, cursor.range.start);
+
+        // No page breaks at all
+        let text = Rope::from("one\ntwo\n");
+        let mut cursor = Cursor::with_range(5..6);
+        move_page_break(&text, &mut cursor, Direction::Forward, 1);
+        assert_eq!(8, cursor.range.start);
+        move_page_break(&text, &mut cursor, Direction::Backward, 1);
+        assert_eq!(0, cursor.range.start);
+    }
+
+    #[test]
+    fn move_to_matches_in_either_direction() {
+        let text = Rope::from("a TODO b TODO c TODO");
+        let matches = [2..6, 9..13, 16..20];
+        let mut cursor = Cursor::new();
+        assert!(move_to_match(
+            &text,
+            &mut cursor,
+            &matches,
+            Direction::Forward,
+            1
+        ));
+        assert_eq!(2, cursor.range.start);
+        assert!(move_to_match(
+            &text,
+            &mut cursor,
+            &matches,
+            Direction::Forward,
+            2
+        ));
+        assert_eq!(16, cursor.range.start);
+        assert!(!move_to_match(
+            &text,
+            &mut cursor,
+            &matches,
+            Direction::Forward,
+            1
+        ));
+        assert_eq!(16, cursor.range.start);
+
+        // Inside a match, the previous one is its own start
+        let mut cursor = Cursor::with_range(11..12);
+        assert!(move_to_match(
+            &text,
+            &mut cursor,
+            &matches,
+            Direction::Backward,
+            1
+        ));
+        assert_eq!(9, cursor.range.start);
+        assert!(!move_to_match(
+            &text,
+            &mut cursor,
+            &matches,
+            Direction::Backward,
+            2
+        ));
+    }
+
     const TEXT: &str = r#"
 Basic Latin
     ! " # $ % & ' ( ) *+,-./012ABCDEFGHI` a m  t u v z { | } ~
@@ -0,0 +1,374 @@
+use ropey::Rope;
+use std::{cmp, ops::Range};
+
+use crate::{
+    apply_edits, graphemes::RopeExt, rectangle::line_length, CharIndex, Cursor, Direction,
+    LineIndex, OpaqueDiff,
+};
+
+/// Insertion points which are typed into together, e.g. to add a comma at
+/// the end of every selected line. Edits are applied from the end of the
+/// text backwards, so the offsets of the points before an edit stay valid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultiCursor {
+    // The insertion points, in order and distinct
+    positions: Vec<CharIndex>,
+    // The index of the insertion point followed by the cursor
+    primary: usize,
+    // The number of characters before each point which typing replaces, the
+    // occurrences the points were added at
+    selected: usize,
+}
+
+impl MultiCursor {
+    /// Places an insertion point at the end of each of `lines`, before the
+    /// line break. Lines of different lengths each get their own end.
+    pub fn at_line_ends(text: &Rope, lines: Range<LineIndex>, primary_line: LineIndex) -> Self {
+        Self::new(text, lines, primary_line, |line_index| {
+            text.line_to_char(line_index) + line_length(text, line_index)
+        })
+    }
+
+    /// Places an insertion point at the start of each of `lines`
+    pub fn at_line_starts(text: &Rope, lines: Range<LineIndex>, primary_line: LineIndex) -> Self {
+        Self::new(text, lines, primary_line, |line_index| {
+            text.line_to_char(line_index)
+        })
+    }
+
+    /// A single insertion point, for more to be added to
+    pub fn at(position: CharIndex) -> Self {
+        Self {
+            positions: vec![position],
+            primary: 0,
+            selected: 0,
+        }
+    }
+
+    /// A single insertion point at the end of `selection`, which typing
+    /// replaces. Points added after it select as many characters.
+    pub fn at_selection(selection: Range<CharIndex>) -> Self {
+        Self {
+            selected: selection.len(),
+            ..Self::at(selection.end)
+        }
+    }
+
+    fn new(
+        text: &Rope,
+        lines: Range<LineIndex>,
+        primary_line: LineIndex,
+        position: impl Fn(LineIndex) -> CharIndex,
+    ) -> Self {
+        let positions: Vec<_> = (lines.start..cmp::min(lines.end, text.len_lines()))
+            .map(position)
+            .collect();
+        let primary = cmp::min(
+            primary_line.saturating_sub(lines.start),
+            positions.len().saturating_sub(1),
+        );
+        Self {
+            positions,
+            primary,
+            selected: 0,
+        }
+    }
+
+    /// The insertion points, in order
+    pub fn positions(&self) -> &[CharIndex] {
+        &self.positions
+    }
+
+    /// The insertion point followed by the cursor
+    pub fn primary(&self) -> CharIndex {
+        self.positions[self.primary]
+    }
+
+    /// The text before each insertion point which typing replaces, empty
+    /// ranges if there's none
+    pub fn selections(&self) -> impl Iterator<Item = Range<CharIndex>> + '_ {
+        self.positions
+            .iter()
+            .map(move |&position| position - self.selected..position)
+    }
+
+    /// Adds an insertion point and makes it the primary one. Returns `false`
+    /// if there already is one at `position`.
+    pub fn add(&mut self, position: CharIndex) -> bool {
+        match self.positions.binary_search(&position) {
+            Ok(_) => false,
+            Err(index) => {
+                self.positions.insert(index, position);
+                self.primary = index;
+                true
+            }
+        }
+    }
+
+    /// Adds an insertion point on the line after the last point, or before
+    /// the first one, in the same column or at the end of a shorter line.
+    /// Returns `false` past the first or last line.
+    pub fn add_on_adjacent_line(&mut self, text: &Rope, direction: Direction) -> bool {
+        self.selected = 0;
+        let reference = match direction {
+            Direction::Forward => self.positions[self.positions.len() - 1],
+            Direction::Backward => self.positions[0],
+        };
+        let line_index = text.char_to_line(reference);
+        let column = reference - text.line_to_char(line_index);
+        let line_index = match direction {
+            Direction::Forward if line_index + 1 < text.len_lines() => line_index + 1,
+            Direction::Backward if line_index > 0 => line_index - 1,
+            _ => return false,
+        };
+        let position =
+            text.line_to_char(line_index) + cmp::min(column, line_length(text, line_index));
+        self.add(position)
+    }
+
+    /// Moves the insertion points to keep them in place after an edit made
+    /// elsewhere, the way a cursor is. The occurrences they selected may have
+    /// changed, so typing no longer replaces them.
+    pub fn reconcile(&mut self, new_text: &Rope, diff: &OpaqueDiff) {
+        self.move_points(new_text, |new_text, cursor| {
+            cursor.reconcile(new_text, diff)
+        });
+    }
+
+    /// Moves every insertion point as a cursor there would be by `movement`,
+    /// e.g. a character to the left. Selections end and points which meet
+    /// are merged.
+    pub fn move_points(&mut self, text: &Rope, movement: impl Fn(&Rope, &mut Cursor)) {
+        let primary = self.primary;
+        for position in self.positions.iter_mut() {
+            let mut cursor = Cursor::with_range(*position..*position);
+            movement(text, &mut cursor);
+            *position = cursor.range().start;
+        }
+        let primary = self.positions[primary];
+        self.positions.sort_unstable();
+        self.selected = 0;
+        self.merge(primary);
+    }
+
+    /// Deletes the grapheme before every insertion point as a single edit, or
+    /// the selected occurrences. Points which meet are merged.
+    pub fn delete_backward(&mut self, text: &mut Rope) -> OpaqueDiff {
+        let selected = self.selected;
+        self.delete(text, |text, position| match selected {
+            0 => text.prev_grapheme_boundary(position)..position,
+            selected => position - selected..position,
+        })
+    }
+
+    /// Deletes the grapheme after every insertion point as a single edit, or
+    /// the selected occurrences. Points which meet are merged.
+    pub fn delete_forward(&mut self, text: &mut Rope) -> OpaqueDiff {
+        let selected = self.selected;
+        self.delete(text, |text, position| match selected {
+            0 => position..text.next_grapheme_boundary(position),
+            selected => position - selected..position,
+        })
+    }
+
+    fn delete(
+        &mut self,
+        text: &mut Rope,
+        range: impl Fn(&Rope, CharIndex) -> Range<CharIndex>,
+    ) -> OpaqueDiff {
+        let ranges: Vec<_> = self
+            .positions
+            .iter()
+            .map(|&position| range(text, position))
+            .collect();
+        let edits: Vec<_> = ranges
+            .iter()
+            .map(|range| (range.clone(), String::new()))
+            .collect();
+        let diff = apply_edits(text, &edits).expect("insertion points are distinct");
+
+        let mut removed = 0;
+        for (position, range) in self.positions.iter_mut().zip(ranges) {
+            *position = range.start - removed;
+            removed += range.len();
+        }
+        let primary = self.positions[self.primary];
+        self.selected = 0;
+        self.merge(primary);
+        diff
+    }
+
+    /// Merges insertion points at the same position, keeping the primary one
+    fn merge(&mut self, primary: CharIndex) {
+        self.positions.dedup();
+        self.primary = self
+            .positions
+            .iter()
+            .position(|&position| position == primary)
+            .unwrap_or(0);
+    }
+
+    /// Inserts `insertion` at every insertion point as a single edit,
+    /// replacing the selected occurrences, and moves each point after its
+    /// copy of the inserted text
+    pub fn insert(&mut self, text: &mut Rope, insertion: &str) -> OpaqueDiff {
+        let edits: Vec<_> = self
+            .selections()
+            .map(|selection| (selection, insertion.to_owned()))
+            .collect();
+        let diff = apply_edits(text, &edits).expect("insertion points are distinct");
+
+        let length = insertion.chars().count();
+        for (index, position) in self.positions.iter_mut().enumerate() {
+            *position = *position + (index + 1) * length - (index + 1) * self.selected;
+        }
+        self.selected = 0;
+        diff
+    }
+}
+
+/// The lines of a selection which are edited by a multi-line command. A line
+/// break at the end of the selection doesn't select the line after it.
+pub fn selected_lines(text: &Rope, selection: Range<CharIndex>) -> Range<LineIndex> {
+    let start = text.char_to_line(selection.start);
+    let last = text.char_to_line(cmp::max(selection.start, selection.end.saturating_sub(1)));
+    start..last + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{movement, tree::EditTree};
+
+    #[test]
+    fn line_ends_differ_in_length() {
+        let text = Rope::from("one\nthree\n\nfour\r\nfive");
+        let cursors = MultiCursor::at_line_ends(&text, 0..5, 4);
+        assert_eq!(&[3, 9, 10, 15, 21], cursors.positions());
+        assert_eq!(21, cursors.primary());
+
+        let cursors = MultiCursor::at_line_starts(&text, 1..3, 1);
+        assert_eq!(&[4, 10], cursors.positions());
+        assert_eq!(4, cursors.primary());
+    }
+
+    #[test]
+    fn selected_lines_exclude_the_line_after_a_final_line_break() {
+        let text = Rope::from("one\ntwo\nthree\n");
+        assert_eq!(0..2, selected_lines(&text, 1..6));
+        assert_eq!(0..2, selected_lines(&text, 0..8));
+        assert_eq!(0..3, selected_lines(&text, 0..9));
+        assert_eq!(1..2, selected_lines(&text, 5..5));
+        assert_eq!(0..3, selected_lines(&text, 0..14));
+    }
+
+    #[test]
+    fn typing_appends_to_every_line_in_one_undo_step() {
+        let mut tree = EditTree::new(Rope::from("one\nthree\n\nfour\n"));
+        let lines = selected_lines(&tree, 0..tree.len_chars());
+        let mut cursors = MultiCursor::at_line_ends(&tree, lines, 0);
+
+        let diff = cursors.insert(&mut tree, ",");
+        tree.create_revision(diff, Cursor::new());
+        assert_eq!("one,\nthree,\n,\nfour,\n", tree.to_string());
+        assert_eq!(&[4, 11, 13, 19], cursors.positions());
+
+        tree.undo();
+        assert_eq!("one\nthree\n\nfour\n", tree.to_string());
+    }
+
+    #[test]
+    fn add_insertion_points_on_adjacent_lines() {
+        let text = Rope::from("first\nab\nthird\n");
+        let mut cursors = MultiCursor::at(4);
+        assert!(cursors.add_on_adjacent_line(&text, Direction::Forward));
+        assert!(cursors.add_on_adjacent_line(&text, Direction::Forward));
+        assert_eq!(&[4, 8, 11], cursors.positions());
+        assert_eq!(11, cursors.primary());
+
+        assert!(!cursors.add_on_adjacent_line(&text, Direction::Backward));
+        assert!(!cursors.add(8));
+        assert!(cursors.add(0));
+        assert_eq!(&[0, 4, 8, 11], cursors.positions());
+        assert_eq!(0, cursors.primary());
+    }
+
+    #[test]
+    fn delete_at_every_insertion_point() {
+        let mut text = Rope::from("one, two, three");
+        let mut cursors = MultiCursor::at(4);
+        cursors.add(9);
+        cursors.add(15);
+        cursors.delete_backward(&mut text);
+        assert_eq!("one two thre", text.to_string());
+        assert_eq!(&[3, 7, 12], cursors.positions());
+
+        // Points which meet are merged
+        let mut text = Rope::from("abc");
+        let mut cursors = MultiCursor::at(1);
+        cursors.add(2);
+        cursors.delete_forward(&mut text);
+        assert_eq!("a", text.to_string());
+        assert_eq!(&[1], cursors.positions());
+        assert_eq!(1, cursors.primary());
+
+        // Edits elsewhere move the points
+        let mut cursors = MultiCursor::at(2);
+        cursors.add(6);
+        let text = Rope::from("abcdefghijk");
+        cursors.reconcile(&text, &OpaqueDiff::new(0, 0, 3, 0, 0, 3));
+        cursors.reconcile(&text, &OpaqueDiff::new(8, 2, 0, 8, 2, 0));
+        assert_eq!(&[5, 8], cursors.positions());
+        assert_eq!(8, cursors.primary());
+    }
+
+    #[test]
+    fn typing_replaces_the_selected_occurrences() {
+        let mut text = Rope::from("let x = x + 1; x");
+        let mut cursors = MultiCursor::at_selection(4..5);
+        cursors.add(9);
+        cursors.add(16);
+        assert_eq!(
+            vec![4..5, 8..9, 15..16],
+            cursors.selections().collect::<Vec<_>>()
+        );
+        cursors.insert(&mut text, "y");
+        cursors.insert(&mut text, "z");
+        assert_eq!("let yz = yz + 1; yz", text.to_string());
+        assert_eq!(&[6, 11, 19], cursors.positions());
+
+        // Deleting removes the occurrences as well
+        let mut text = Rope::from("a-b-c");
+        let mut cursors = MultiCursor::at_selection(1..2);
+        cursors.add(4);
+        cursors.delete_backward(&mut text);
+        assert_eq!("abc", text.to_string());
+        assert_eq!(&[1, 2], cursors.positions());
+    }
+
+    #[test]
+    fn move_every_insertion_point() {
+        let text = Rope::from("one\ntwo\n");
+        let mut cursors = MultiCursor::at_line_starts(&text, 0..2, 1);
+        cursors.move_points(&text, |text, cursor| {
+            movement::move_to_end_of_line(text, cursor)
+        });
+        assert_eq!(&[3, 7], cursors.positions());
+        assert_eq!(7, cursors.primary());
+
+        // Points which meet are merged
+        cursors.move_points(&text, |_, cursor| *cursor = Cursor::new());
+        assert_eq!(&[0], cursors.positions());
+    }
+
+    #[test]
+    fn typing_continues_at_line_starts() {
+        let mut text = Rope::from("one\ntwo\n");
+        let mut cursors = MultiCursor::at_line_starts(&text, 0..2, 1);
+        cursors.insert(&mut text, "/");
+        cursors.insert(&mut text, "/");
+        cursors.insert(&mut text, " ");
+        assert_eq!("// one\n// two\n", text.to_string());
+        assert_eq!(10, cursors.primary());
+    }
+}
@@ -0,0 +1,250 @@
+use ropey::Rope;
+use std::ops::Range;
+
+use crate::{expression::Number, CharIndex};
+
+/// The decimal or hexadecimal (`0x1f`) number under or after `char_index`
+/// on its line. A `-` right before a decimal number is its sign, unless the
+/// `-` follows a letter or digit, e.g. in `item-2`.
+pub fn number_at(text: &Rope, char_index: CharIndex) -> Option<Range<CharIndex>> {
+    let line_index = text.char_to_line(char_index);
+    let line_start = text.line_to_char(line_index);
+    let line: Vec<char> = text.line(line_index).chars().collect();
+    let column = char_index - line_start;
+    let line_length = line
+        .iter()
+        .position(|&character| character == '\n' || character == '\r')
+        .unwrap_or(line.len());
+
+    let mut start = 0;
+    while start < line_length {
+        let end = match number_end(&line[..line_length], start) {
+            Some(end) => end,
+            None => {
+                start += 1;
+                continue;
+            }
+        };
+        // A cursor just past a number at the end of the line still finds it
+        if end > column || (end == column && column == line_length) {
+            return Some(line_start + start..line_start + end);
+        }
+        start = end;
+    }
+    None
+}
+
+/// Adds `step` to a number found by [`number_at`], keeping its format:
+/// zero padded numbers keep their width (`007` becomes `008`) and hex
+/// numbers keep the case of their letters. Returns `None` if the result is
+/// out of range, including negative hex numbers.
+pub fn increment(number: &str, step: i64) -> Option<String> {
+    let (prefix, digits) = match number.get(..2) {
+        Some(prefix @ ("0x" | "0X")) => (prefix, &number[2..]),
+        _ => ("", number),
+    };
+
+    if !prefix.is_empty() {
+        let value = i128::from(u64::from_str_radix(digits, 16).ok()?) + i128::from(step);
+        let value = u64::try_from(value).ok()?;
+        let width = digits.len();
+        return Some(if digits.chars().any(|digit| digit.is_ascii_uppercase()) {
+            format!("{}{:0width$X}", prefix, value, width = width)
+        } else {
+            format!("{}{:0width$x}", prefix, value, width = width)
+        });
+    }
+
+    let (negative, digits) = match digits.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, digits),
+    };
+    let magnitude = i128::from(digits.parse::<u64>().ok()?);
+    let value = if negative { -magnitude } else { magnitude };
+    let value = i64::try_from(value + i128::from(step)).ok()?;
+    let width = if digits.len() > 1 && digits.starts_with('0') {
+        digits.len()
+    } else {
+        0
+    };
+    Some(format!(
+        "{}{:0width$}",
+        if value < 0 { "-" } else { "" },
+        value.unsigned_abs(),
+        width = width
+    ))
+}
+
+/// Adds up the decimal numbers in `text`, e.g. `-3` and `4.5` in
+/// `-3 apples, 4.5 kg`, returning how many there are and their sum. Numbers
+/// inside words, like `2` in `v2`, aren't counted. The sum is an integer
+/// unless one of the numbers is a float or it's too large for one.
+pub fn sum(text: &str) -> (usize, Number) {
+    let (mut count, mut integers, mut floats, mut has_float) = (0, 0i128, 0.0, false);
+    for number in scan(text) {
+        count += 1;
+        match number {
+            Number::Integer(value) => integers += i128::from(value),
+            Number::Float(value) => {
+                floats += value;
+                has_float = true;
+            }
+        }
+    }
+    let total = match i64::try_from(integers) {
+        Ok(integers) if !has_float => Number::Integer(integers),
+        _ => Number::Float(integers as f64 + floats),
+    };
+    (count, total)
+}
+
+/// The decimal numbers in `text` which aren't part of a word
+fn scan(text: &str) -> Vec<Number> {
+    let chars: Vec<char> = text.chars().collect();
+    let is_word_char = |index: usize| {
+        chars.get(index).map_or(false, |&character| {
+            character.is_alphanumeric() || character == '_'
+        })
+    };
+    let is_digit = |index: usize| chars.get(index).map_or(false, char::is_ascii_digit);
+
+    let mut numbers = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let starts_number =
+            is_digit(index) || (matches!(chars[index], '-' | '.') && is_digit(index + 1));
+        if !starts_number || (index > 0 && is_word_char(index - 1)) {
+            index += 1;
+            continue;
+        }
+
+        let start = index;
+        if chars[index] == '-' {
+            index += 1;
+        }
+        while is_digit(index) {
+            index += 1;
+        }
+        if chars.get(index) == Some(&'.') && is_digit(index + 1) {
+            index += 1;
+            while is_digit(index) {
+                index += 1;
+            }
+        }
+        if is_word_char(index) {
+            // Skip the rest of the word, e.g. `2nd`
+            while is_word_char(index) {
+                index += 1;
+            }
+            continue;
+        }
+
+        let literal: String = chars[start..index].iter().collect();
+        let number = match literal.parse() {
+            Ok(value) if !literal.contains('.') => Some(Number::Integer(value)),
+            _ => literal.parse().ok().map(Number::Float),
+        };
+        numbers.extend(number);
+    }
+    numbers
+}
+
+/// The end of the number starting at `start`, if there's one
+fn number_end(line: &[char], start: usize) -> Option<usize> {
+    let digits_end = |from: usize, is_digit: fn(&char) -> bool| {
+        from + line[from..]
+            .iter()
+            .take_while(|&character| is_digit(character))
+            .count()
+    };
+
+    let is_hex = line[start] == '0'
+        && matches!(line.get(start + 1), Some('x' | 'X'))
+        && line.get(start + 2).map_or(false, char::is_ascii_hexdigit);
+    if is_hex {
+        return Some(digits_end(start + 2, char::is_ascii_hexdigit));
+    }
+
+    let is_sign = line[start] == '-'
+        && line.get(start + 1).map_or(false, char::is_ascii_digit)
+        && (start == 0 || !line[start - 1].is_alphanumeric());
+    if is_sign {
+        return Some(digits_end(start + 1, char::is_ascii_digit));
+    }
+
+    line[start]
+        .is_ascii_digit()
+        .then(|| digits_end(start, char::is_ascii_digit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Increments the number at `char_index` in `text`
+    fn increment_at(text: &str, char_index: CharIndex, step: i64) -> Option<String> {
+        let mut text = Rope::from(text);
+        let range = number_at(&text, char_index)?;
+        let number = increment(&text.slice(range.clone()).to_string(), step)?;
+        text.remove(range.clone());
+        text.insert(range.start, &number);
+        Some(text.to_string())
+    }
+
+    #[test]
+    fn decimal_numbers() {
+        assert_eq!(Some("x = 43;".into()), increment_at("x = 42;", 4, 1));
+        assert_eq!(Some("x = 41;".into()), increment_at("x = 42;", 4, -1));
+        // The first number after the cursor on the line
+        assert_eq!(Some("x = 52;\n1".into()), increment_at("x = 42;\n1", 0, 10));
+        assert_eq!(None, increment_at("x = 42;\n1", 6, 1));
+    }
+
+    #[test]
+    fn zero_padded_numbers_keep_their_width() {
+        assert_eq!(Some("v008".into()), increment_at("v007", 0, 1));
+        assert_eq!(Some("v006".into()), increment_at("v007", 0, -1));
+        assert_eq!(Some("v100".into()), increment_at("v099", 0, 1));
+        assert_eq!(Some("v000".into()), increment_at("v001", 0, -1));
+    }
+
+    #[test]
+    fn hex_numbers_keep_their_case() {
+        assert_eq!(Some("0x20".into()), increment_at("0x1f", 0, 1));
+        assert_eq!(Some("0x1e".into()), increment_at("0x1f", 3, -1));
+        assert_eq!(Some("0X00FF".into()), increment_at("0X00FE", 0, 1));
+        assert_eq!(Some("0x0A".into()), increment_at("0x0B", 0, -1));
+        assert_eq!(None, increment_at("0x0", 0, -1));
+    }
+
+    #[test]
+    fn negative_numbers() {
+        assert_eq!(Some("-4".into()), increment_at("-5", 0, 1));
+        assert_eq!(Some("-6".into()), increment_at("-5", 1, -1));
+        assert_eq!(Some("0".into()), increment_at("-1", 0, 1));
+        assert_eq!(Some("-1".into()), increment_at("0", 0, -1));
+        assert_eq!(Some("a = -009".into()), increment_at("a = -010", 0, 1));
+        // A dash after a word isn't a sign
+        assert_eq!(Some("item-3".into()), increment_at("item-2", 0, 1));
+    }
+
+    #[test]
+    fn number_at_the_end_of_the_buffer() {
+        assert_eq!(Some("count 10".into()), increment_at("count 9", 7, 1));
+        assert_eq!(Some(6..7), number_at(&Rope::from("count 9"), 7));
+        assert_eq!(None, number_at(&Rope::from("count\n"), 6));
+    }
+
+    #[test]
+    fn sum_numbers_over_several_lines() {
+        let text = "apples 3\npears -2.5\nitem-4 v2 2nd x\n10,5 .5\n";
+        assert_eq!((6, Number::Float(20.0)), sum(text));
+        assert_eq!((3, Number::Integer(-30)), sum("| 10 |\n| -50 |\n| 10 |"));
+        assert_eq!((0, Number::Integer(0)), sum("no numbers here"));
+        assert_eq!((0, Number::Integer(0)), sum(""));
+        assert_eq!(
+            (2, Number::Float(1.8446744073709552e19)),
+            sum("9223372036854775807 9223372036854775809")
+        );
+    }
+}
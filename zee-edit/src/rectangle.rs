@@ -0,0 +1,393 @@
+use ropey::{Rope, RopeSlice};
+use std::{cmp, ops::Range};
+
+use crate::{
+    apply_edits,
+    graphemes::{self, RopeGraphemes},
+    CharIndex, Direction, LineIndex, OpaqueDiff,
+};
+
+/// A position in the text as a line and a column, the number of terminal
+/// cells from the start of the line. A tab takes up the tab width and a wide
+/// character two cells. The column may be past the end of the line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub line: LineIndex,
+    pub column: usize,
+}
+
+/// How the corner of a rectangle is moved, one line or column at a time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RectangleMove {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// A rectangular selection spanning the same columns on consecutive lines,
+/// between the cell where it was started and a corner moved with the arrow
+/// keys.
+///
+/// The corner can move past the end of short lines. The cells there are
+/// virtual, the lines are padded with spaces when the rectangle is edited.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rectangle {
+    anchor: Cell,
+    corner: Cell,
+    // Whether the rectangle has spanned more than one line since it started.
+    // Collapsing back to a single line ends the selection.
+    spanned_lines: bool,
+    tab_width: usize,
+}
+
+impl Rectangle {
+    /// Starts an empty rectangle at `char_index`
+    pub fn new(text: &Rope, char_index: CharIndex, tab_width: usize) -> Self {
+        let line = text.char_to_line(char_index);
+        let cell = Cell {
+            line,
+            column: graphemes::width(tab_width, &text.slice(text.line_to_char(line)..char_index)),
+        };
+        Self {
+            anchor: cell,
+            corner: cell,
+            spanned_lines: false,
+            tab_width,
+        }
+    }
+
+    pub fn anchor(&self) -> Cell {
+        self.anchor
+    }
+
+    pub fn corner(&self) -> Cell {
+        self.corner
+    }
+
+    /// The lines spanned by the rectangle
+    pub fn lines(&self) -> Range<LineIndex> {
+        cmp::min(self.anchor.line, self.corner.line)
+            ..cmp::max(self.anchor.line, self.corner.line) + 1
+    }
+
+    /// The columns selected on each line, empty if the rectangle is only an
+    /// insertion point on each line
+    pub fn columns(&self) -> Range<usize> {
+        cmp::min(self.anchor.column, self.corner.column)
+            ..cmp::max(self.anchor.column, self.corner.column)
+    }
+
+    pub fn contains(&self, cell: Cell) -> bool {
+        self.lines().contains(&cell.line) && self.columns().contains(&cell.column)
+    }
+
+    /// The characters of a line in the selected columns. A character only
+    /// partly in the columns, like a tab, is selected whole. If the rectangle
+    /// is only an insertion point, the range is empty and starts where typing
+    /// inserts text.
+    pub fn char_range(&self, text: &Rope, line_index: LineIndex) -> Range<CharIndex> {
+        chars_in_columns(text, line_index, self.columns(), self.tab_width)
+    }
+
+    /// Moves the corner of the rectangle. Returns `false` if the rectangle
+    /// collapsed back to a single line after spanning several, which ends
+    /// the selection.
+    pub fn extend(&mut self, text: &Rope, movement: RectangleMove) -> bool {
+        let last_line = text.len_lines().saturating_sub(1);
+        match movement {
+            RectangleMove::Up => self.corner.line = self.corner.line.saturating_sub(1),
+            RectangleMove::Down => self.corner.line = cmp::min(self.corner.line + 1, last_line),
+            RectangleMove::Left => self.corner.column = self.corner.column.saturating_sub(1),
+            RectangleMove::Right => self.corner.column += 1,
+        }
+
+        if self.anchor.line != self.corner.line {
+            self.spanned_lines = true;
+            true
+        } else {
+            !self.spanned_lines
+        }
+    }
+
+    /// The char index of the corner, or the end of its line if the corner is
+    /// in a virtual cell
+    pub fn corner_char_index(&self, text: &Rope) -> CharIndex {
+        let column = self.corner.column;
+        chars_in_columns(text, self.corner.line, column..column, self.tab_width).start
+    }
+
+    /// Replaces the selected columns with `insertion` on every line, as a
+    /// single edit. Lines ending before the rectangle are padded with spaces
+    /// first. The rectangle becomes an insertion point after the inserted
+    /// text, so typing continues on every line.
+    pub fn insert(&mut self, text: &mut Rope, insertion: &str) -> OpaqueDiff {
+        let columns = self.columns();
+        let edits: Vec<_> = self
+            .lines()
+            .map(|line_index| {
+                let width = line_width(text, line_index, self.tab_width);
+                let padding = " ".repeat(columns.start.saturating_sub(width));
+                (
+                    chars_in_columns(text, line_index, columns.clone(), self.tab_width),
+                    padding + insertion,
+                )
+            })
+            .collect();
+        let diff = apply_edits(text, &edits).expect("edits on different lines don't overlap");
+
+        let column = columns.start + graphemes::width(self.tab_width, &RopeSlice::from(insertion));
+        self.anchor.column = column;
+        self.corner.column = column;
+        diff
+    }
+
+    /// The text in the selected columns of every line, padded with spaces
+    /// where the rectangle is past the end of a line so the columns stay
+    /// aligned when pasted
+    pub fn contents(&self, text: &Rope) -> Vec<String> {
+        let columns = self.columns();
+        self.lines()
+            .map(|line_index| {
+                let width = line_width(text, line_index, self.tab_width);
+                let selected = chars_in_columns(text, line_index, columns.clone(), self.tab_width);
+                let mut contents = text.slice(selected).to_string();
+                let padding = columns.end.saturating_sub(cmp::max(columns.start, width));
+                contents.extend(std::iter::repeat(' ').take(padding));
+                contents
+            })
+            .collect()
+    }
+
+    /// Deletes the selected columns on every line as a single edit. If the
+    /// rectangle is only an insertion point, the column before or after it
+    /// is deleted instead. The rectangle becomes an insertion point where
+    /// the deleted columns were.
+    pub fn delete(&mut self, text: &mut Rope, direction: Direction) -> OpaqueDiff {
+        let columns = self.columns();
+        let columns = match direction {
+            _ if !columns.is_empty() => columns,
+            Direction::Backward if columns.start > 0 => columns.start - 1..columns.start,
+            Direction::Backward => return OpaqueDiff::empty(),
+            Direction::Forward => columns.start..columns.start + 1,
+        };
+        let edits: Vec<_> = self
+            .lines()
+            .map(|line_index| {
+                (
+                    chars_in_columns(text, line_index, columns.clone(), self.tab_width),
+                    String::new(),
+                )
+            })
+            .collect();
+        let diff = apply_edits(text, &edits).expect("edits on different lines don't overlap");
+
+        self.anchor.column = columns.start;
+        self.corner.column = columns.start;
+        diff
+    }
+}
+
+/// The number of terminal cells taken by a line, not counting the line ending
+pub fn line_width(text: &Rope, line_index: LineIndex, tab_width: usize) -> usize {
+    let line_start = text.line_to_char(line_index);
+    graphemes::width(
+        tab_width,
+        &text.slice(line_start..line_start + line_length(text, line_index)),
+    )
+}
+
+/// The characters of a line in `columns`, counting the terminal cells taken by
+/// each grapheme. A grapheme partly in the columns is included whole. Empty
+/// columns give an empty range before the grapheme covering the column.
+pub(crate) fn chars_in_columns(
+    text: &Rope,
+    line_index: LineIndex,
+    columns: Range<usize>,
+    tab_width: usize,
+) -> Range<CharIndex> {
+    let line_start = text.line_to_char(line_index);
+    let line = text.slice(line_start..line_start + line_length(text, line_index));
+    let mut start = None;
+    let mut column = 0;
+    let mut char_index = line_start;
+    for grapheme in RopeGraphemes::new(&line) {
+        let next_column = column + graphemes::width(tab_width, &grapheme);
+        if start.is_none() && next_column > columns.start {
+            start = Some(char_index);
+            if columns.is_empty() {
+                break;
+            }
+        }
+        if column >= columns.end {
+            break;
+        }
+        column = next_column;
+        char_index += grapheme.len_chars();
+    }
+    start.unwrap_or(char_index)..char_index
+}
+
+/// The number of characters on a line, not counting the line ending
+pub fn line_length(text: &Rope, line_index: LineIndex) -> usize {
+    let line = text.line(line_index);
+    let mut length = line.len_chars();
+    if length > 0 && line.char(length - 1) == '\n' {
+        length -= 1;
+    }
+    if length > 0 && line.char(length - 1) == '\r' {
+        length -= 1;
+    }
+    length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(line: LineIndex, column: usize) -> Cell {
+        Cell { line, column }
+    }
+
+    #[test]
+    fn extend_moves_the_corner() {
+        let text = Rope::from("one\ntwo\nthree\n");
+        let mut rectangle = Rectangle::new(&text, 5, 4);
+        assert_eq!(cell(1, 1), rectangle.anchor());
+        assert_eq!(1..2, rectangle.lines());
+        assert!(rectangle.columns().is_empty());
+
+        assert!(rectangle.extend(&text, RectangleMove::Right));
+        assert!(rectangle.extend(&text, RectangleMove::Down));
+        assert_eq!(cell(2, 2), rectangle.corner());
+        assert_eq!(1..3, rectangle.lines());
+        assert_eq!(1..2, rectangle.columns());
+        assert!(rectangle.contains(cell(2, 1)));
+        assert!(!rectangle.contains(cell(2, 2)));
+        assert!(!rectangle.contains(cell(0, 1)));
+
+        // The corner can be on either side of the anchor
+        assert!(rectangle.extend(&text, RectangleMove::Left));
+        assert!(rectangle.extend(&text, RectangleMove::Left));
+        assert_eq!(cell(2, 0), rectangle.corner());
+        assert_eq!(0..1, rectangle.columns());
+        assert_eq!(8, rectangle.corner_char_index(&text));
+    }
+
+    #[test]
+    fn extend_stops_at_text_edges() {
+        let text = Rope::from("one\ntwo");
+        let mut rectangle = Rectangle::new(&text, 0, 4);
+        assert!(rectangle.extend(&text, RectangleMove::Up));
+        assert!(rectangle.extend(&text, RectangleMove::Left));
+        assert_eq!(cell(0, 0), rectangle.corner());
+        assert!(rectangle.extend(&text, RectangleMove::Down));
+        assert!(rectangle.extend(&text, RectangleMove::Down));
+        assert_eq!(cell(1, 0), rectangle.corner());
+    }
+
+    #[test]
+    fn collapsing_to_one_line_ends_the_selection() {
+        let text = Rope::from("one\ntwo\nthree\n");
+        let mut rectangle = Rectangle::new(&text, 0, 4);
+        // Moving along the starting line keeps the selection
+        assert!(rectangle.extend(&text, RectangleMove::Right));
+        assert!(rectangle.extend(&text, RectangleMove::Down));
+        assert!(rectangle.extend(&text, RectangleMove::Down));
+        assert!(rectangle.extend(&text, RectangleMove::Up));
+        assert!(!rectangle.extend(&text, RectangleMove::Up));
+        assert_eq!(0..1, rectangle.lines());
+    }
+
+    #[test]
+    fn corner_past_the_end_of_a_line_is_virtual() {
+        let text = Rope::from("long line\nab\r\nlonger line\n");
+        let mut rectangle = Rectangle::new(&text, 5, 4);
+        rectangle.extend(&text, RectangleMove::Down);
+        assert_eq!(cell(1, 5), rectangle.corner());
+        // The cursor goes to the end of the short line
+        assert_eq!(12, rectangle.corner_char_index(&text));
+    }
+
+    #[test]
+    fn insert_on_every_line() {
+        let mut text = Rope::from("one\ntwo\nthree\n");
+        let mut rectangle = Rectangle::new(&text, 1, 4);
+        rectangle.extend(&text, RectangleMove::Down);
+        rectangle.extend(&text, RectangleMove::Down);
+        let diff = rectangle.insert(&mut text, "-");
+        assert_eq!("o-ne\nt-wo\nt-hree\n", text.to_string());
+        assert_eq!(OpaqueDiff::new(1, 8, 11, 1, 8, 11), diff);
+
+        // Typing continues after the inserted text
+        assert_eq!(2..2, rectangle.columns());
+        rectangle.insert(&mut text, "+");
+        assert_eq!("o-+ne\nt-+wo\nt-+hree\n", text.to_string());
+    }
+
+    #[test]
+    fn insert_replaces_the_selected_columns() {
+        let mut text = Rope::from("one\ntwo\nthree\n");
+        let mut rectangle = Rectangle::new(&text, 0, 4);
+        rectangle.extend(&text, RectangleMove::Down);
+        rectangle.extend(&text, RectangleMove::Right);
+        rectangle.extend(&text, RectangleMove::Right);
+        rectangle.insert(&mut text, "X");
+        assert_eq!("Xe\nXo\nthree\n", text.to_string());
+        assert_eq!(1..1, rectangle.columns());
+    }
+
+    #[test]
+    fn copy_and_delete_the_selected_columns() {
+        let mut text = Rope::from("one two\nab\nthree four\n");
+        let mut rectangle = Rectangle::new(&text, 1, 4);
+        rectangle.extend(&text, RectangleMove::Down);
+        rectangle.extend(&text, RectangleMove::Down);
+        for _ in 0..3 {
+            rectangle.extend(&text, RectangleMove::Right);
+        }
+        assert_eq!(vec!["ne ", "b  ", "hre"], rectangle.contents(&text));
+
+        let diff = rectangle.delete(&mut text, Direction::Backward);
+        assert_eq!("otwo\na\nte four\n", text.to_string());
+        assert_eq!(OpaqueDiff::new(1, 14, 7, 1, 14, 7), diff);
+        assert_eq!(1..1, rectangle.columns());
+
+        // An insertion point deletes the column before or after it
+        rectangle.delete(&mut text, Direction::Forward);
+        assert_eq!("owo\na\nt four\n", text.to_string());
+        rectangle.delete(&mut text, Direction::Backward);
+        assert_eq!("wo\n\n four\n", text.to_string());
+        assert!(rectangle.delete(&mut text, Direction::Backward).is_empty());
+    }
+
+    #[test]
+    fn insert_pads_short_lines() {
+        let mut text = Rope::from("long line\nab\n\nlonger\n");
+        let mut rectangle = Rectangle::new(&text, 4, 4);
+        for _ in 0..3 {
+            rectangle.extend(&text, RectangleMove::Down);
+        }
+        rectangle.extend(&text, RectangleMove::Right);
+        rectangle.insert(&mut text, "|");
+        assert_eq!("long|line\nab  |\n    |\nlong|r\n", text.to_string());
+    }
+
+    #[test]
+    fn columns_count_terminal_cells() {
+        let mut text = Rope::from("a\tb\n日本語\nabcdefg\n");
+        let mut rectangle = Rectangle::new(&text, 2, 4);
+        assert_eq!(cell(0, 5), rectangle.anchor());
+        rectangle.extend(&text, RectangleMove::Down);
+        rectangle.extend(&text, RectangleMove::Down);
+        rectangle.extend(&text, RectangleMove::Left);
+        rectangle.extend(&text, RectangleMove::Left);
+        assert_eq!(3..5, rectangle.columns());
+        assert_eq!(11, rectangle.corner_char_index(&text));
+        // Characters partly in the columns, like the tab, are selected whole
+        assert_eq!(vec!["\t", "本語", "de"], rectangle.contents(&text));
+
+        rectangle.delete(&mut text, Direction::Forward);
+        assert_eq!("ab\n日\nabcfg\n", text.to_string());
+    }
+}
@@ -0,0 +1,679 @@
+use regex::Regex;
+use ropey::Rope;
+use std::{borrow::Cow, ops::Range, rc::Rc};
+
+use crate::{
+    graphemes::{CharIndex, RopeExt},
+    line_ending_length,
+    movement::{Direction, WordCharacters},
+    LineIndex, OpaqueDiff,
+};
+
+/// How letter case is taken into account when matching a query
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaseMode {
+    /// Characters must match exactly
+    Sensitive,
+    /// Characters match if they are equal after case folding
+    Insensitive,
+    /// Case sensitive if the query contains an uppercase letter, insensitive
+    /// otherwise
+    Smart,
+}
+
+impl CaseMode {
+    /// Resolves `Smart` to either `Sensitive` or `Insensitive` for `query`
+    pub fn resolve(self, query: &str) -> Self {
+        match self {
+            Self::Smart if query.chars().any(char::is_uppercase) => Self::Sensitive,
+            Self::Smart => Self::Insensitive,
+            case_mode => case_mode,
+        }
+    }
+}
+
+impl Default for CaseMode {
+    fn default() -> Self {
+        Self::Smart
+    }
+}
+
+/// Finds all non-overlapping occurrences of `query` in `text`. The matches
+/// are returned in order as char ranges aligned to extended grapheme clusters,
+/// occurrences that start or end inside a grapheme are skipped. With
+/// `whole_word` set, occurrences that are part of a longer word, as made of
+/// the given word characters, are skipped too.
+pub fn find_all(
+    text: &Rope,
+    query: &str,
+    case_mode: CaseMode,
+    whole_word: Option<WordCharacters>,
+) -> Vec<Range<CharIndex>> {
+    let fold: fn(char) -> char = match case_mode.resolve(query) {
+        CaseMode::Insensitive => fold_case,
+        _ => |character| character,
+    };
+    let query: Vec<char> = query.chars().map(fold).collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    // Knuth-Morris-Pratt, streaming over the characters of the rope
+    let failure = failure_function(&query);
+    let mut matches = Vec::new();
+    let mut matched = 0;
+    for (index, character) in text.chars().map(fold).enumerate() {
+        while matched > 0 && query[matched] != character {
+            matched = failure[matched - 1];
+        }
+        if query[matched] == character {
+            matched += 1;
+        }
+        if matched == query.len() {
+            let start = index + 1 - query.len();
+            let overlaps_previous = matches
+                .last()
+                .map_or(false, |previous: &Range<CharIndex>| previous.end > start);
+            if !overlaps_previous
+                && is_grapheme_boundary(text, start)
+                && is_grapheme_boundary(text, index + 1)
+                && whole_word.map_or(true, |word_characters| {
+                    is_whole_word(text, start..index + 1, word_characters)
+                })
+            {
+                matches.push(start..index + 1);
+            }
+            matched = failure[matched - 1];
+        }
+    }
+    matches
+}
+
+/// Finds the next occurrence of `query` from `position` in the given
+/// direction, wrapping around the ends of the text. See `next_match_index`
+/// for how a match at `position` is treated.
+pub fn find_next(
+    text: &Rope,
+    query: &str,
+    case_mode: CaseMode,
+    whole_word: Option<WordCharacters>,
+    position: CharIndex,
+    direction: Direction,
+    skip_current: bool,
+) -> Option<Range<CharIndex>> {
+    let matches = find_all(text, query, case_mode, whole_word);
+    next_match_index(&matches, position, direction, skip_current)
+        .map(|index| matches[index].clone())
+}
+
+/// The index of the match to move to from `position`, wrapping around the
+/// ends of the text. A match starting at `position` is the current one, it
+/// is returned unless `skip_current` is set.
+pub fn next_match_index(
+    matches: &[Range<CharIndex>],
+    position: CharIndex,
+    direction: Direction,
+    skip_current: bool,
+) -> Option<usize> {
+    if matches.is_empty() {
+        return None;
+    }
+
+    let index = match direction {
+        Direction::Forward => matches
+            .iter()
+            .position(|range| range.start > position || (!skip_current && range.start == position))
+            .unwrap_or(0),
+        Direction::Backward => matches
+            .iter()
+            .rposition(|range| range.start < position || (!skip_current && range.start == position))
+            .unwrap_or(matches.len() - 1),
+    };
+    Some(index)
+}
+
+/// The range of the word under `char_index`, e.g. the identifier the cursor
+/// is on. Returns `None` if the character there isn't part of a word, i.e.
+/// it's whitespace or punctuation.
+pub fn word_at(
+    text: &Rope,
+    char_index: CharIndex,
+    word_characters: WordCharacters,
+) -> Option<Range<CharIndex>> {
+    if char_index >= text.len_chars() || !word_characters.contains(text.char(char_index)) {
+        return None;
+    }
+    let mut start = char_index;
+    while start > 0 && word_characters.contains(text.char(start - 1)) {
+        start -= 1;
+    }
+    let mut end = char_index + 1;
+    while end < text.len_chars() && word_characters.contains(text.char(end)) {
+        end += 1;
+    }
+    Some(start..end)
+}
+
+/// Finds the whole word occurrences of any of `keywords` in `text`, e.g.
+/// `TODO` and `FIXME`, ordered by position
+pub fn find_keywords(
+    text: &Rope,
+    keywords: &[String],
+    case_mode: CaseMode,
+) -> Vec<Range<CharIndex>> {
+    let mut matches: Vec<_> = keywords
+        .iter()
+        .flat_map(|keyword| find_all(text, keyword, case_mode, Some(WordCharacters::default())))
+        .collect();
+    matches.sort_by_key(|range| range.start);
+    // The same keyword listed twice, or in different cases, matches twice
+    matches.dedup();
+    matches
+}
+
+/// Replaces each of the `matches` with `replacement`. The matches have to be
+/// ordered and non-overlapping, like the ones returned by `find_all`. The
+/// returned diff spans all the replacements, so they can be undone together.
+pub fn replace_all(text: &mut Rope, matches: &[Range<CharIndex>], replacement: &str) -> OpaqueDiff {
+    let (start, old_end) = match (matches.first(), matches.last()) {
+        (Some(first), Some(last)) => (first.start, last.end),
+        _ => return OpaqueDiff::empty(),
+    };
+    let byte_start = text.char_to_byte(start);
+    let old_byte_end = text.char_to_byte(old_end);
+
+    // Replace from the end, so the ranges of the remaining matches stay valid
+    for range in matches.iter().rev() {
+        text.remove(range.clone());
+        text.insert(range.start, replacement);
+    }
+
+    let matched_chars: usize = matches.iter().map(|range| range.end - range.start).sum();
+    let new_end = old_end + matches.len() * replacement.chars().count() - matched_chars;
+    OpaqueDiff::new(
+        byte_start,
+        old_byte_end - byte_start,
+        text.char_to_byte(new_end) - byte_start,
+        start,
+        old_end - start,
+        new_end - start,
+    )
+}
+
+/// A match of a regular expression, found by [`find_regex`]
+#[derive(Clone, Debug)]
+pub struct RegexMatch {
+    pub range: Range<CharIndex>,
+    // The line of the match, to look up its capture groups again
+    line: Rc<str>,
+    line_byte_start: usize,
+}
+
+impl RegexMatch {
+    /// The replacement for the match, with `$1` or `${name}` in `template`
+    /// replaced by the text of the capture group
+    pub fn expand(&self, regex: &Regex, template: &str) -> String {
+        let mut replacement = String::new();
+        let captures = regex.captures_iter(&self.line).find(|captures| {
+            captures.get(0).map(|whole| whole.start()) == Some(self.line_byte_start)
+        });
+        if let Some(captures) = captures {
+            captures.expand(template, &mut replacement);
+        }
+        replacement
+    }
+}
+
+/// The matches of a regular expression in order, found a line at a time so
+/// the text is never copied as a whole. Matches don't span lines.
+pub struct RegexMatches<'a> {
+    text: &'a Rope,
+    regex: &'a Regex,
+    start: CharIndex,
+    line_index: LineIndex,
+    pending: std::vec::IntoIter<RegexMatch>,
+}
+
+/// Finds the matches of `regex` in `text` which start at or after `start`
+pub fn find_regex<'a>(text: &'a Rope, regex: &'a Regex, start: CharIndex) -> RegexMatches<'a> {
+    RegexMatches {
+        text,
+        regex,
+        start,
+        line_index: text.char_to_line(start),
+        pending: Vec::new().into_iter(),
+    }
+}
+
+impl<'a> Iterator for RegexMatches<'a> {
+    type Item = RegexMatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(next) = self.pending.next() {
+                return Some(next);
+            }
+            if self.line_index >= self.text.len_lines() {
+                return None;
+            }
+
+            let line = self.text.line(self.line_index);
+            let line = line.slice(..line.len_chars() - line_ending_length(&line));
+            let byte_start = self.text.line_to_byte(self.line_index);
+            self.line_index += 1;
+
+            // Borrowed unless the line is split across chunks of the rope, and
+            // only shared with the matches if there are any
+            let contents = Cow::from(line);
+            let mut shared: Option<Rc<str>> = None;
+            let mut matches = Vec::new();
+            for found in self.regex.find_iter(&contents) {
+                let range = self.text.byte_to_char(byte_start + found.start())
+                    ..self.text.byte_to_char(byte_start + found.end());
+                if range.start < self.start {
+                    continue;
+                }
+                matches.push(RegexMatch {
+                    range,
+                    line: shared.get_or_insert_with(|| Rc::from(&*contents)).clone(),
+                    line_byte_start: found.start(),
+                });
+            }
+            self.pending = matches.into_iter();
+        }
+    }
+}
+
+fn failure_function(query: &[char]) -> Vec<usize> {
+    let mut failure = vec![0; query.len()];
+    let mut matched = 0;
+    for index in 1..query.len() {
+        while matched > 0 && query[matched] != query[index] {
+            matched = failure[matched - 1];
+        }
+        if query[matched] == query[index] {
+            matched += 1;
+        }
+        failure[index] = matched;
+    }
+    failure
+}
+
+/// Simple case folding of a single character. Folding maps characters one to
+/// one, so match ranges in the folded text are also valid in the original.
+/// Going through the uppercase form folds variants like the final sigma or the
+/// Kelvin sign together with their usual lowercase letter.
+fn fold_case(character: char) -> char {
+    let lowercase = single_char(character.to_lowercase()).unwrap_or(character);
+    single_char(lowercase.to_uppercase())
+        .and_then(|uppercase| single_char(uppercase.to_lowercase()))
+        .unwrap_or(lowercase)
+}
+
+fn single_char(mut characters: impl Iterator<Item = char>) -> Option<char> {
+    let character = characters.next()?;
+    characters.next().is_none().then(|| character)
+}
+
+/// Whether the match isn't preceded or followed by word characters it would
+/// join with. Matches starting or ending with punctuation are delimited by it.
+fn is_whole_word(text: &Rope, range: Range<CharIndex>, word_characters: WordCharacters) -> bool {
+    let joins = |left: CharIndex, right: CharIndex| {
+        word_characters.contains(text.char(left)) && word_characters.contains(text.char(right))
+    };
+    (range.start == 0 || !joins(range.start - 1, range.start))
+        && (range.end == text.len_chars() || !joins(range.end - 1, range.end))
+}
+
+fn is_grapheme_boundary(text: &Rope, char_index: CharIndex) -> bool {
+    char_index == 0
+        || char_index == text.len_chars()
+        || text.next_grapheme_boundary(text.prev_grapheme_boundary(char_index)) == char_index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_all_occurrences() {
+        let text = Rope::from("The flowers were blooming.\nThe flowers\n");
+        assert_eq!(
+            vec![4..11, 31..38],
+            find_all(&text, "flowers", CaseMode::Sensitive, None)
+        );
+        assert_eq!(
+            vec![0..3, 27..30],
+            find_all(&text, "The", CaseMode::Sensitive, None)
+        );
+        assert_eq!(
+            vec![25..28],
+            find_all(&text, ".\nT", CaseMode::Sensitive, None)
+        );
+        assert!(find_all(&text, "roses", CaseMode::Sensitive, None).is_empty());
+        assert!(find_all(&text, "", CaseMode::Sensitive, None).is_empty());
+        assert!(find_all(&Rope::new(), "The", CaseMode::Sensitive, None).is_empty());
+    }
+
+    #[test]
+    fn find_all_does_not_overlap() {
+        let text = Rope::from("aaaaa abab");
+        assert_eq!(
+            vec![0..2, 2..4],
+            find_all(&text, "aa", CaseMode::Sensitive, None)
+        );
+        assert_eq!(
+            vec![6..10],
+            find_all(&text, "abab", CaseMode::Sensitive, None)
+        );
+        assert_eq!(
+            vec![6..8, 8..10],
+            find_all(&text, "ab", CaseMode::Sensitive, None)
+        );
+    }
+
+    #[test]
+    fn find_all_is_grapheme_aligned() {
+        let text = Rope::from("CJK 豈 👨‍👨‍👧‍👧 e\u{301} e");
+        assert_eq!(vec![4..5], find_all(&text, "豈", CaseMode::Sensitive, None));
+        assert_eq!(
+            vec![6..13],
+            find_all(&text, "👨‍👨‍👧‍👧", CaseMode::Sensitive, None)
+        );
+        // The man is only part of the family grapheme
+        assert!(find_all(&text, "👨", CaseMode::Sensitive, None).is_empty());
+        // An `e` followed by a combining acute accent is a different grapheme
+        assert_eq!(
+            vec![17..18],
+            find_all(&text, "e", CaseMode::Sensitive, None)
+        );
+    }
+
+    #[test]
+    fn next_match_wraps_around() {
+        let matches = [4..11, 31..38];
+        let next = |position, direction, skip_current| {
+            next_match_index(&matches, position, direction, skip_current)
+        };
+        assert_eq!(Some(0), next(0, Direction::Forward, false));
+        assert_eq!(Some(0), next(4, Direction::Forward, false));
+        assert_eq!(Some(1), next(4, Direction::Forward, true));
+        assert_eq!(Some(0), next(31, Direction::Forward, true));
+        assert_eq!(Some(1), next(0, Direction::Backward, false));
+        assert_eq!(Some(1), next(31, Direction::Backward, false));
+        assert_eq!(Some(0), next(31, Direction::Backward, true));
+        assert_eq!(None, next_match_index(&[], 0, Direction::Forward, false));
+    }
+
+    #[test]
+    fn find_next_from_position() {
+        let text = Rope::from("The flowers were blooming.\nThe flowers\n");
+        assert_eq!(
+            Some(31..38),
+            find_next(
+                &text,
+                "flowers",
+                CaseMode::Sensitive,
+                None,
+                5,
+                Direction::Forward,
+                false
+            )
+        );
+        assert_eq!(
+            Some(4..11),
+            find_next(
+                &text,
+                "flowers",
+                CaseMode::Sensitive,
+                None,
+                5,
+                Direction::Backward,
+                false
+            )
+        );
+        assert_eq!(
+            Some(4..11),
+            find_next(
+                &text,
+                "flowers",
+                CaseMode::Sensitive,
+                None,
+                31,
+                Direction::Forward,
+                true
+            )
+        );
+        assert_eq!(
+            None,
+            find_next(
+                &text,
+                "roses",
+                CaseMode::Sensitive,
+                None,
+                0,
+                Direction::Forward,
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn smart_case_depends_on_query() {
+        let text = Rope::from("Flowers, flowers and FLOWERS");
+        assert_eq!(
+            vec![0..7, 9..16, 21..28],
+            find_all(&text, "flowers", CaseMode::Smart, None)
+        );
+        assert_eq!(
+            vec![0..7],
+            find_all(&text, "Flowers", CaseMode::Smart, None)
+        );
+        assert_eq!(
+            vec![21..28],
+            find_all(&text, "FLOWERS", CaseMode::Smart, None)
+        );
+        assert_eq!(
+            vec![9..16],
+            find_all(&text, "flowers", CaseMode::Sensitive, None)
+        );
+        assert_eq!(
+            vec![0..7, 9..16, 21..28],
+            find_all(&text, "Flowers", CaseMode::Insensitive, None)
+        );
+    }
+
+    #[test]
+    fn insensitive_search_folds_unicode_case() {
+        let text = Rope::from("ΣΊΣΥΦΟΣ σίσυφος Straße STRASSE");
+        assert_eq!(
+            vec![0..7, 8..15],
+            find_all(&text, "σίσυφος", CaseMode::Insensitive, None)
+        );
+        assert_eq!(
+            vec![16..22],
+            find_all(&text, "straße", CaseMode::Smart, None)
+        );
+        // The Kelvin sign folds to a Latin `k`
+        let text = Rope::from("300 \u{212a} and 300 k");
+        assert_eq!(
+            vec![4..5, 14..15],
+            find_all(&text, "k", CaseMode::Smart, None)
+        );
+    }
+
+    #[test]
+    fn insensitive_matches_are_grapheme_aligned() {
+        let text = Rope::from("E\u{301} e");
+        assert_eq!(vec![3..4], find_all(&text, "e", CaseMode::Smart, None));
+        assert_eq!(
+            vec![0..2],
+            find_all(&text, "e\u{301}", CaseMode::Smart, None)
+        );
+    }
+
+    #[test]
+    fn whole_word_skips_partial_words() {
+        let text = Rope::from("log login catalog log_file log.");
+        let whole_words = |query| {
+            find_all(
+                &text,
+                query,
+                CaseMode::Smart,
+                Some(WordCharacters::default()),
+            )
+        };
+        assert_eq!(vec![0..3, 27..30], whole_words("log"));
+        assert_eq!(vec![4..9], whole_words("login"));
+        assert_eq!(vec![18..26], whole_words("log_file"));
+        assert!(whole_words("log_").is_empty());
+        assert!(whole_words("ogin").is_empty());
+        // Punctuation at the edge of the query is its own boundary
+        assert_eq!(vec![30..31], whole_words("."));
+        assert_eq!(vec![27..31], whole_words("log."));
+        assert_eq!(
+            vec![0..3, 4..7, 14..17, 18..21, 27..30],
+            find_all(&text, "log", CaseMode::Smart, None)
+        );
+    }
+
+    #[test]
+    fn whole_word_with_extra_word_characters() {
+        let text = Rope::from("(foo foo-bar)");
+        let lisp = Some(WordCharacters::new("-"));
+        assert_eq!(vec![1..4], find_all(&text, "foo", CaseMode::Smart, lisp));
+        assert_eq!(
+            vec![5..12],
+            find_all(&text, "foo-bar", CaseMode::Smart, lisp)
+        );
+        assert!(find_all(&text, "bar", CaseMode::Smart, lisp).is_empty());
+        assert_eq!(
+            vec![9..12],
+            find_all(
+                &text,
+                "bar",
+                CaseMode::Smart,
+                Some(WordCharacters::default())
+            )
+        );
+    }
+
+    #[test]
+    fn whole_word_at_text_edges() {
+        let text = Rope::from("word");
+        assert_eq!(
+            vec![0..4],
+            find_all(
+                &text,
+                "word",
+                CaseMode::Smart,
+                Some(WordCharacters::default())
+            )
+        );
+        let text = Rope::from("words\nsword\nword");
+        assert_eq!(
+            vec![12..16],
+            find_all(
+                &text,
+                "word",
+                CaseMode::Smart,
+                Some(WordCharacters::default())
+            )
+        );
+        assert_eq!(
+            Some(12..16),
+            find_next(
+                &text,
+                "word",
+                CaseMode::Smart,
+                Some(WordCharacters::default()),
+                0,
+                Direction::Forward,
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn find_keywords_as_whole_words() {
+        let text = Rope::from("// TODO: fix\n// FIXME TODOS todo\nXXX\n");
+        let keywords: Vec<String> = ["TODO", "FIXME", "XXX"].map(String::from).into();
+        assert_eq!(
+            vec![3..7, 16..21, 33..36],
+            find_keywords(&text, &keywords, CaseMode::Sensitive)
+        );
+        assert_eq!(
+            vec![3..7, 16..21, 28..32, 33..36],
+            find_keywords(&text, &keywords, CaseMode::Insensitive)
+        );
+        assert!(find_keywords(&text, &[], CaseMode::Sensitive).is_empty());
+    }
+
+    #[test]
+    fn word_at_cursor() {
+        let text = Rope::from("let log_file = log(x);\n");
+        let default = WordCharacters::default();
+        assert_eq!(Some(4..12), word_at(&text, 4, default));
+        assert_eq!(Some(4..12), word_at(&text, 11, default));
+        assert_eq!(Some(15..18), word_at(&text, 17, default));
+        assert_eq!(Some(0..3), word_at(&text, 0, default));
+        assert_eq!(Some(19..20), word_at(&text, 19, default));
+        // Whitespace and punctuation aren't part of a word
+        assert_eq!(None, word_at(&text, 3, default));
+        assert_eq!(None, word_at(&text, 13, default));
+        assert_eq!(None, word_at(&text, 18, default));
+        assert_eq!(None, word_at(&text, 22, default));
+        assert_eq!(None, word_at(&text, 23, default));
+
+        let text = Rope::from("(foo-bar)");
+        assert_eq!(Some(1..4), word_at(&text, 2, default));
+        assert_eq!(Some(1..8), word_at(&text, 2, WordCharacters::new("-")));
+    }
+
+    #[test]
+    fn replace_all_matches_in_one_diff() {
+        let mut text = Rope::from("log login\nlog\n");
+        let matches = find_all(
+            &text,
+            "log",
+            CaseMode::Smart,
+            Some(WordCharacters::default()),
+        );
+        let diff = replace_all(&mut text, &matches, "träce");
+        assert_eq!("träce login\nträce\n", text.to_string());
+        assert_eq!(OpaqueDiff::new(0, 13, 19, 0, 13, 17), diff);
+
+        let mut text = Rope::from("a-a-a");
+        let matches = find_all(&text, "a", CaseMode::Smart, None);
+        let diff = replace_all(&mut text, &matches, "");
+        assert_eq!("--", text.to_string());
+        assert_eq!(OpaqueDiff::new(0, 5, 2, 0, 5, 2), diff);
+
+        let mut text = Rope::from("unchanged");
+        assert!(replace_all(&mut text, &[], "replacement").is_empty());
+        assert_eq!("unchanged", text.to_string());
+    }
+
+    #[test]
+    fn find_regex_matches_a_line_at_a_time() {
+        let text = Rope::from("let x = 1;\r\nlet öy = 22;\n");
+        let regex = Regex::new(r"let (?P<name>\w+) = (\d+)").unwrap();
+        let matches: Vec<_> = find_regex(&text, &regex, 0).collect();
+        assert_eq!(
+            vec![0..9, 12..23],
+            matches
+                .iter()
+                .map(|found| found.range.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("x: 1", matches[0].expand(&regex, "$name: $2"));
+        assert_eq!("öy: 22", matches[1].expand(&regex, "${name}: $2"));
+
+        // Matches starting before `start` are skipped, `$` is the end of a line
+        let regex = Regex::new(r"\d+;$").unwrap();
+        let ranges: Vec<_> = find_regex(&text, &regex, 9)
+            .map(|found| found.range)
+            .collect();
+        assert_eq!(vec![21..24], ranges);
+    }
+}
@@ -0,0 +1,321 @@
+use ropey::Rope;
+use std::ops::Range;
+
+use crate::{graphemes::RopeExt, CharIndex, Cursor, OpaqueDiff};
+
+/// A template expanded into the text, e.g. `for ${1:item} in $2 {\n    $0\n}`.
+///
+/// `$1`, `$2`, ... are tab stops the cursor visits in order, `${1:default}`
+/// fills in a default value and `$0` is where the cursor ends up. Repeating a
+/// number mirrors the first occurrence, the copies are updated while typing.
+/// `$$` inserts a literal `$`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snippet {
+    text: String,
+    // The tab stop number and the range it occupies, relative to the start of
+    // the snippet
+    stops: Vec<(usize, Range<CharIndex>)>,
+}
+
+impl Snippet {
+    pub fn parse(template: &str) -> Self {
+        let mut text = String::new();
+        let mut stops: Vec<(usize, Range<CharIndex>)> = Vec::new();
+        let mut num_chars = 0;
+        let mut chars = template.chars().peekable();
+        while let Some(character) = chars.next() {
+            if character != '$' {
+                text.push(character);
+                num_chars += 1;
+                continue;
+            }
+
+            let braced = chars.next_if_eq(&'{').is_some();
+            let mut number = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                number.push(digit);
+            }
+            let number: usize = match number.parse() {
+                Ok(number) => number,
+                // Not a tab stop, keep the text as is
+                Err(_) if braced => {
+                    text.push_str("${");
+                    num_chars += 2;
+                    continue;
+                }
+                Err(_) => {
+                    chars.next_if_eq(&'$');
+                    text.push('$');
+                    num_chars += 1;
+                    continue;
+                }
+            };
+
+            let start = num_chars;
+            if braced && chars.next_if_eq(&':').is_some() {
+                for character in chars.by_ref().take_while(|&character| character != '}') {
+                    text.push(character);
+                    num_chars += 1;
+                }
+            } else {
+                if braced {
+                    chars.next_if_eq(&'}');
+                }
+                // A mirror starts out with the default of the field it copies
+                if let Some((_, range)) = stops.iter().find(|(other, _)| *other == number) {
+                    let default: String =
+                        text.chars().skip(range.start).take(range.len()).collect();
+                    text.push_str(&default);
+                    num_chars += range.len();
+                }
+            }
+            stops.push((number, start..num_chars));
+        }
+        Self { text, stops }
+    }
+
+    /// A snippet without tab stops, inserted as is
+    pub fn literal(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            stops: Vec::new(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The ranges of each field relative to `offset`, in the order they're
+    /// visited. The first range of a field is the one typed into, the rest
+    /// mirror it. The final position `$0` comes last.
+    fn fields(&self, offset: CharIndex) -> Vec<Vec<Range<CharIndex>>> {
+        let mut numbers: Vec<_> = self.stops.iter().map(|(number, _)| *number).collect();
+        numbers.sort_unstable_by_key(|&number| (number == 0, number));
+        numbers.dedup();
+        numbers
+            .into_iter()
+            .map(|number| {
+                self.stops
+                    .iter()
+                    .filter(|(stop_number, _)| *stop_number == number)
+                    .map(|(_, range)| offset + range.start..offset + range.end)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// The fields of an expanded snippet, tracked as the text changes until the
+/// cursor moves past the last one
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnippetSession {
+    fields: Vec<Vec<Range<CharIndex>>>,
+    current: usize,
+}
+
+impl SnippetSession {
+    /// The range of the field the cursor is in
+    pub fn current_field(&self) -> Range<CharIndex> {
+        self.fields[self.current][0].clone()
+    }
+
+    /// Whether the cursor is in the last field, the session ends when moving
+    /// on from it
+    pub fn is_last_field(&self) -> bool {
+        self.current + 1 >= self.fields.len()
+    }
+
+    /// Moves on to the next field, selecting its text. Returns `false` if
+    /// there are no more fields.
+    pub fn next_field(&mut self, cursor: &mut Cursor, text: &Rope) -> bool {
+        if self.is_last_field() {
+            return false;
+        }
+        self.current += 1;
+        select_field(cursor, text, self.current_field());
+        true
+    }
+
+    /// Updates the fields after an edit. The current field grows when typing
+    /// at its boundaries.
+    pub fn reconcile(&mut self, diff: &OpaqueDiff) {
+        let current = self.current;
+        for (index, ranges) in self.fields.iter_mut().enumerate() {
+            for range in ranges.iter_mut() {
+                *range = reconcile_range(range, diff, index == current);
+            }
+        }
+    }
+
+    /// Copies the text of the current field to its mirrors, returning a diff
+    /// spanning the changes
+    pub fn update_mirrors(&mut self, text: &mut Rope) -> OpaqueDiff {
+        let field = &self.fields[self.current];
+        let primary = text.slice(field[0].clone()).to_string();
+        let num_chars = primary.chars().count();
+        let mut merged = OpaqueDiff::empty();
+        for mirror_index in 1..self.fields[self.current].len() {
+            let range = self.fields[self.current][mirror_index].clone();
+            if text.slice(range.clone()) == primary.as_str() {
+                continue;
+            }
+            let diff = Cursor::with_range(range.clone()).replace_range(text, range, &primary);
+            for (index, ranges) in self.fields.iter_mut().enumerate() {
+                for (range_index, range) in ranges.iter_mut().enumerate() {
+                    *range = if index == self.current && range_index == mirror_index {
+                        diff.char_index..diff.char_index + num_chars
+                    } else {
+                        reconcile_range(range, &diff, false)
+                    };
+                }
+            }
+            merged = merged.merge(&diff);
+        }
+        merged
+    }
+}
+
+impl Cursor {
+    /// Inserts a snippet at the cursor, selecting the first field. Returns
+    /// the session tracking the fields, unless there's nothing left to visit.
+    pub fn insert_snippet(
+        &mut self,
+        text: &mut Rope,
+        snippet: &Snippet,
+    ) -> (OpaqueDiff, Option<SnippetSession>) {
+        let start = self.range.start;
+        let diff = self.insert_chars(text, snippet.text.chars());
+        let end = start + diff.new_char_length;
+
+        let mut fields = snippet.fields(start);
+        if snippet.stops.iter().all(|(number, _)| *number != 0) {
+            // Without `$0` the cursor ends up after the snippet
+            fields.push(std::iter::once(end..end).collect());
+        }
+        select_field(self, text, fields[0][0].clone());
+
+        let session = (fields.len() > 1).then(|| SnippetSession { fields, current: 0 });
+        (diff, session)
+    }
+}
+
+/// Selects the text of a field, or places the cursor in an empty one
+fn select_field(cursor: &mut Cursor, text: &Rope, field: Range<CharIndex>) {
+    *cursor = Cursor::with_range(field.start..text.next_grapheme_boundary(field.start));
+    if !field.is_empty() {
+        cursor.selection = Some(field.end);
+    }
+}
+
+fn reconcile_range(range: &Range<CharIndex>, diff: &OpaqueDiff, sticky: bool) -> Range<CharIndex> {
+    let OpaqueDiff {
+        char_index,
+        old_char_length,
+        new_char_length,
+        ..
+    } = *diff;
+    let edit_end = char_index + old_char_length;
+    let shift = |index: CharIndex| index + new_char_length - old_char_length;
+    if edit_end < range.start || (!sticky && edit_end == range.start) {
+        shift(range.start)..shift(range.end)
+    } else if char_index > range.end || (!sticky && char_index == range.end) {
+        range.clone()
+    } else {
+        let start = if char_index < range.start {
+            char_index
+        } else {
+            range.start
+        };
+        let end = if edit_end > range.end {
+            char_index + new_char_length
+        } else {
+            shift(range.end)
+        };
+        start..end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_snippet() {
+        let snippet = Snippet::parse("for ${1:item} in $2 {\n    $0\n}");
+        assert_eq!("for item in  {\n    \n}", snippet.text());
+        assert_eq!(vec![(1, 4..8), (2, 12..12), (0, 19..19)], snippet.stops);
+
+        let snippet = Snippet::parse("$$5 and ${NAME}");
+        assert_eq!("$5 and ${NAME}", snippet.text());
+        assert!(snippet.stops.is_empty());
+    }
+
+    #[test]
+    fn insert_literal_snippet() {
+        let mut text = Rope::from("Today is \n");
+        let mut cursor = Cursor::with_range(9..10);
+        let (diff, session) = cursor.insert_snippet(&mut text, &Snippet::literal("2022-07-30"));
+        assert_eq!("Today is 2022-07-30\n", text);
+        assert_eq!(OpaqueDiff::new(9, 0, 10, 9, 0, 10), diff);
+        assert_eq!(Cursor::with_range(19..20), cursor);
+        assert!(session.is_none());
+    }
+
+    #[test]
+    fn insert_snippet_with_single_stop() {
+        let mut text = Rope::from("\n");
+        let mut cursor = Cursor::new();
+        let snippet = Snippet::parse("println!(\"$1\");");
+        let (_, session) = cursor.insert_snippet(&mut text, &snippet);
+        assert_eq!("println!(\"\");\n", text);
+        assert_eq!(10, cursor.range().start);
+        assert!(!cursor.has_selection());
+
+        // Typing in the field grows it, then Tab moves past the snippet
+        let mut session = session.unwrap();
+        for character in "zee".chars() {
+            let diff = cursor.insert_char(&mut text, character);
+            crate::movement::move_horizontally(&text, &mut cursor, crate::Direction::Forward, 1);
+            session.reconcile(&diff);
+        }
+        assert_eq!("println!(\"zee\");\n", text);
+        assert_eq!(10..13, session.current_field());
+        assert!(session.next_field(&mut cursor, &text));
+        assert_eq!(16, cursor.range().start);
+        assert!(!session.next_field(&mut cursor, &text));
+    }
+
+    #[test]
+    fn mirrored_fields_update_together() {
+        let mut text = Rope::new();
+        let mut cursor = Cursor::new();
+        let snippet = Snippet::parse("<${1:div}>$0</$1>");
+        let (_, session) = cursor.insert_snippet(&mut text, &snippet);
+        let mut session = session.unwrap();
+        assert_eq!("<div></div>", text);
+        assert_eq!(1..4, cursor.selection());
+
+        let diff = Cursor::new().replace_range(&mut text, 1..4, "span");
+        session.reconcile(&diff);
+        let mirror_diff = session.update_mirrors(&mut text);
+        assert_eq!("<span></span>", text);
+        assert_eq!(OpaqueDiff::new(8, 3, 4, 8, 3, 4), mirror_diff);
+
+        assert!(session.next_field(&mut cursor, &text));
+        assert_eq!(6, cursor.range().start);
+    }
+
+    #[test]
+    fn nested_snippet_grows_outer_field() {
+        let mut text = Rope::new();
+        let mut cursor = Cursor::new();
+        let (_, outer) = cursor.insert_snippet(&mut text, &Snippet::parse("f($1)$0"));
+        let mut outer = outer.unwrap();
+        let (diff, inner) = cursor.insert_snippet(&mut text, &Snippet::parse("g($1)"));
+        outer.reconcile(&diff);
+        assert_eq!("f(g())", text);
+        assert_eq!(2..5, outer.current_field());
+        assert!(inner.is_some());
+    }
+}
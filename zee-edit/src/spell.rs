@@ -0,0 +1,232 @@
+use std::{cmp, collections::HashSet, ops::Range};
+
+use crate::CharIndex;
+
+/// The words known to be spelled correctly, e.g. from a system word list
+/// and a personal dictionary
+#[derive(Clone, Debug, Default)]
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Adds the words of a word list, one per line
+    pub fn add_word_list(&mut self, word_list: &str) {
+        self.words.extend(
+            word_list
+                .lines()
+                .map(str::trim)
+                .filter(|word| !word.is_empty())
+                .map(String::from),
+        );
+    }
+
+    pub fn insert(&mut self, word: &str) {
+        self.words.insert(word.to_owned());
+    }
+
+    /// Whether `word` is spelled correctly. A capitalised word, e.g. at the
+    /// start of a sentence, is correct if its lowercase form is.
+    pub fn check(&self, word: &str) -> bool {
+        self.words.contains(word) || self.words.contains(&word.to_lowercase())
+    }
+
+    /// Up to `limit` known words closest to `word`, at most two edits away,
+    /// the closest first and then in alphabetical order. Suggestions for a
+    /// capitalised word are capitalised.
+    pub fn suggestions(&self, word: &str, limit: usize) -> Vec<String> {
+        let lowercase: Vec<char> = word.to_lowercase().chars().collect();
+        let mut candidates: Vec<_> = self
+            .words
+            .iter()
+            .filter_map(|candidate| {
+                let candidate_chars: Vec<char> = candidate.to_lowercase().chars().collect();
+                let length_difference = cmp::max(candidate_chars.len(), lowercase.len())
+                    - cmp::min(candidate_chars.len(), lowercase.len());
+                if length_difference > MAX_EDITS {
+                    return None;
+                }
+                let distance = edit_distance(&lowercase, &candidate_chars);
+                (distance <= MAX_EDITS).then(|| (distance, candidate))
+            })
+            .collect();
+        candidates.sort();
+
+        let capitalised = word.chars().next().map_or(false, char::is_uppercase);
+        let mut suggestions: Vec<String> = Vec::new();
+        for (_, candidate) in candidates {
+            let suggestion = if capitalised {
+                capitalise(candidate)
+            } else {
+                candidate.clone()
+            };
+            if suggestion != word && !suggestions.contains(&suggestion) {
+                suggestions.push(suggestion);
+            }
+            if suggestions.len() == limit {
+                break;
+            }
+        }
+        suggestions
+    }
+}
+
+/// The words of `text` to spell check, as char ranges. Identifiers aren't
+/// words: camelCase and ALLCAPS words and words joined to digits or
+/// underscores are skipped, as are single letters.
+pub fn words(text: &str) -> Vec<Range<CharIndex>> {
+    let chars: Vec<char> = text.chars().collect();
+    let is_identifier_char = |index: usize| {
+        chars
+            .get(index)
+            .map_or(false, |&character| is_identifier_char(character))
+    };
+
+    let mut words = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        if !is_identifier_char(index) {
+            index += 1;
+            continue;
+        }
+
+        // An apostrophe between letters is part of a word, e.g. `don't`
+        let start = index;
+        while is_identifier_char(index)
+            || (chars.get(index) == Some(&'\'')
+                && chars
+                    .get(index + 1)
+                    .map_or(false, |next| next.is_alphabetic())
+                && index > start)
+        {
+            index += 1;
+        }
+        let word = &chars[start..index];
+        let is_word = word.len() > 1
+            && word
+                .iter()
+                .all(|&character| character.is_alphabetic() || character == '\'')
+            && !word[1..].iter().any(|character| character.is_uppercase());
+        if is_word {
+            words.push(start..index);
+        }
+    }
+    words
+}
+
+/// The misspelled words of `text`, as char ranges
+pub fn misspelled(dictionary: &Dictionary, text: &str) -> Vec<Range<CharIndex>> {
+    let chars: Vec<char> = text.chars().collect();
+    words(text)
+        .into_iter()
+        .filter(|range| {
+            let word: String = chars[range.clone()].iter().collect();
+            !dictionary.check(&word)
+        })
+        .collect()
+}
+
+fn is_identifier_char(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+fn capitalise(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// The number of insertions, deletions, substitutions and transpositions of
+/// adjacent characters between two words
+fn edit_distance(lhs: &[char], rhs: &[char]) -> usize {
+    let mut rows = vec![(0..=rhs.len()).collect::<Vec<_>>()];
+    for (i, &lhs_char) in lhs.iter().enumerate() {
+        let mut row = vec![i + 1; rhs.len() + 1];
+        for (j, &rhs_char) in rhs.iter().enumerate() {
+            let substitution = rows[i][j] + usize::from(lhs_char != rhs_char);
+            row[j + 1] = cmp::min(cmp::min(rows[i][j + 1] + 1, row[j] + 1), substitution);
+            if i > 0 && j > 0 && lhs_char == rhs[j - 1] && lhs[i - 1] == rhs_char {
+                row[j + 1] = cmp::min(row[j + 1], rows[i - 1][j - 1] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[lhs.len()][rhs.len()]
+}
+
+const MAX_EDITS: usize = 2;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary() -> Dictionary {
+        let mut dictionary = Dictionary::new();
+        dictionary.add_word_list("the\nthen\nten\ntea\nParis\ndon't\nhello\n\n");
+        dictionary
+    }
+
+    fn words_of(text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        words(text)
+            .into_iter()
+            .map(|range| chars[range].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn identifiers_are_not_words() {
+        assert_eq!(
+            vec!["Parse", "the", "don't", "files"],
+            words_of("// Parse the camelCase HTTP snake_case x2 don't 'files' a")
+        );
+    }
+
+    #[test]
+    fn check_words() {
+        let dictionary = dictionary();
+        assert!(dictionary.check("the"));
+        assert!(dictionary.check("The"));
+        assert!(dictionary.check("Paris"));
+        assert!(!dictionary.check("paris"));
+        assert!(!dictionary.check("teh"));
+
+        let text = "Teh tea, then hllo";
+        assert_eq!(vec![0..3, 14..18], misspelled(&dictionary, text));
+    }
+
+    #[test]
+    fn suggestions_are_closest_first() {
+        let dictionary = dictionary();
+        assert_eq!(vec!["tea", "ten", "the"], dictionary.suggestions("teh", 3));
+        assert_eq!(vec!["Tea", "Ten"], dictionary.suggestions("Teh", 2));
+        assert_eq!(vec!["hello"], dictionary.suggestions("hllo", 5));
+        assert!(dictionary.suggestions("xylophone", 5).is_empty());
+    }
+
+    #[test]
+    fn edit_distances() {
+        let distance = |lhs: &str, rhs: &str| {
+            edit_distance(
+                &lhs.chars().collect::<Vec<_>>(),
+                &rhs.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(0, distance("word", "word"));
+        assert_eq!(1, distance("teh", "the"));
+        assert_eq!(1, distance("hllo", "hello"));
+        assert_eq!(1, distance("teh", "ten"));
+        assert_eq!(2, distance("teh", "then"));
+        assert_eq!(3, distance("", "abc"));
+    }
+}
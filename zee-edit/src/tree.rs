@@ -1,9 +1,15 @@
 use euclid::default::Vector2D;
 use ropey::Rope;
 use smallvec::SmallVec;
-use std::ops::{Deref, DerefMut};
+use std::{
+    cmp::{self, Reverse},
+    collections::{BinaryHeap, HashSet},
+    iter, mem,
+    ops::{Deref, DerefMut, Range},
+    time::Instant,
+};
 
-use crate::{movement, Cursor, OpaqueDiff};
+use crate::{diff_ropes, movement, CharIndex, Cursor, LineIndex, OpaqueDiff};
 
 #[derive(Debug, Clone)]
 pub struct Revision {
@@ -12,6 +18,10 @@ pub struct Revision {
     pub parent: Option<Reference>,
     pub children: SmallVec<[Reference; 1]>,
     pub redo_index: usize,
+    /// When the revision was created
+    pub timestamp: Instant,
+    // When the head was last at the revision, counting visits to revisions
+    last_visit: usize,
 }
 
 impl Revision {
@@ -24,8 +34,35 @@ impl Revision {
             parent: None,
             children: SmallVec::new(),
             redo_index: 0,
+            timestamp: Instant::now(),
+            last_visit: 0,
         }
     }
+
+    /// The bytes of text the edit that created this revision inserted and
+    /// deleted, zero for the root revision. Revisions share the text an edit
+    /// leaves alone, so this is roughly the memory the revision takes up.
+    fn edit_bytes(&self) -> usize {
+        self.parent.as_ref().map_or(0, |parent| {
+            parent.diff.old_byte_length + parent.diff.new_byte_length
+        })
+    }
+
+    /// The size of the edit that created this revision from its parent, `None`
+    /// for the root revision
+    pub fn diff_stats(&self) -> Option<DiffStats> {
+        self.parent.as_ref().map(|parent| DiffStats {
+            inserted_chars: parent.diff.old_char_length,
+            deleted_chars: parent.diff.new_char_length,
+        })
+    }
+}
+
+/// The number of characters inserted and deleted by an edit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiffStats {
+    pub inserted_chars: usize,
+    pub deleted_chars: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +77,17 @@ pub struct EditTree {
     pub head_index: usize,
     staged: Rope,
     has_staged_changes: bool,
+    // The revision last saved to disk, `None` if it was pruned
+    saved_index: Option<usize>,
+    // The text last saved to disk, `None` if it never was
+    saved_text: Option<Rope>,
+    // The number of revisions kept, zero for no limit
+    max_revisions: usize,
+    // The bytes of edits kept, zero for no limit
+    max_edit_bytes: usize,
+    // The bytes inserted and deleted by the edits of all the revisions
+    edit_bytes: usize,
+    num_visits: usize,
 }
 
 impl EditTree {
@@ -47,11 +95,44 @@ impl EditTree {
         Self {
             revisions: vec![Revision::root(text.clone())],
             head_index: 0,
-            staged: text,
+            staged: text.clone(),
             has_staged_changes: false,
+            saved_index: Some(0),
+            saved_text: Some(text),
+            max_revisions: 0,
+            max_edit_bytes: 0,
+            edit_bytes: 0,
+            num_visits: 0,
         }
     }
 
+    /// Limits the number of revisions kept to `max_revisions`, zero for no
+    /// limit. Creating a revision past the limit prunes the revisions off the
+    /// current branch least recently visited, then the oldest revisions of
+    /// the branch, always keeping at least `MIN_UNDOS` undos from the head.
+    pub fn with_max_revisions(mut self, max_revisions: usize) -> Self {
+        self.max_revisions = max_revisions;
+        self.prune();
+        self
+    }
+
+    /// Limits the bytes of text inserted and deleted by the edits kept to
+    /// `max_edit_bytes`, zero for no limit, which bounds the memory taken up
+    /// by the revisions. Revisions are pruned as for `with_max_revisions`.
+    pub fn with_max_edit_bytes(mut self, max_edit_bytes: usize) -> Self {
+        self.max_edit_bytes = max_edit_bytes;
+        self.prune();
+        self
+    }
+
+    /// Starts without a saved text, e.g. for a new file, so the text is
+    /// modified until it's first saved
+    pub fn unsaved(mut self) -> Self {
+        self.saved_index = None;
+        self.saved_text = None;
+        self
+    }
+
     pub fn next_child(&mut self) {
         let current_revision = &mut self.revisions[self.head_index];
         if current_revision.redo_index < current_revision.children.len().saturating_sub(1) {
@@ -82,6 +163,8 @@ impl EditTree {
             }),
             children: SmallVec::new(),
             redo_index: 0,
+            timestamp: Instant::now(),
+            last_visit: 0,
         });
         {
             let head = &mut self.revisions[self.head_index];
@@ -93,6 +176,45 @@ impl EditTree {
         }
         self.head_index = new_revision_index;
         self.has_staged_changes = false;
+        self.edit_bytes += self.revisions[new_revision_index].edit_bytes();
+        self.visit_head();
+        self.prune();
+    }
+
+    /// Adds an edit of the staged text to the head revision rather than
+    /// creating another one, e.g. for the rest of a paste. A revision is
+    /// created instead if the head is the root, the saved revision or has
+    /// children, as changing it would change more than the last edit.
+    pub fn amend_revision(&mut self, diff: OpaqueDiff, cursor: Cursor) {
+        let head = &self.revisions[self.head_index];
+        let parent_index = match head.parent {
+            Some(ref parent)
+                if head.children.is_empty() && self.saved_index != Some(self.head_index) =>
+            {
+                parent.index
+            }
+            _ => return self.create_revision(diff, cursor),
+        };
+
+        let head_index = self.head_index;
+        let parent_to_child = self.revisions[parent_index]
+            .children
+            .iter_mut()
+            .find(|child| child.index == head_index)
+            .expect("the head is a child of its parent");
+        parent_to_child.diff = parent_to_child.diff.merge(&diff);
+        let child_to_parent_diff = parent_to_child.diff.reverse();
+
+        let head = &mut self.revisions[head_index];
+        self.edit_bytes -= head.edit_bytes();
+        if let Some(ref mut parent) = head.parent {
+            parent.diff = child_to_parent_diff;
+        }
+        self.edit_bytes += head.edit_bytes();
+        head.text = self.staged.clone();
+        head.cursor = cursor;
+        self.has_staged_changes = false;
+        self.prune();
     }
 
     pub fn undo(&mut self) -> Option<(OpaqueDiff, Cursor)> {
@@ -106,7 +228,9 @@ impl EditTree {
             self.head_index = previous_index;
 
             self.has_staged_changes = false;
-            Some((diff.clone(), previous_revision.cursor.clone()))
+            let undone = (diff.clone(), previous_revision.cursor.clone());
+            self.visit_head();
+            Some(undone)
         } else {
             None
         }
@@ -125,7 +249,7 @@ impl EditTree {
             redo_index,
             ..
         } = revisions[*head_index];
-        children
+        let redone = children
             .get(redo_index)
             .map(|Reference { ref diff, index }| {
                 let Revision {
@@ -137,7 +261,379 @@ impl EditTree {
                 *has_staged_changes = false;
                 *head_index = *index;
                 (diff.clone(), cursor.clone())
+            });
+        if redone.is_some() {
+            self.visit_head();
+        }
+        redone
+    }
+
+    /// Moves the head to the revision at `index`, making its text the staged
+    /// text. The revisions leading to it are set to redo towards it, so it
+    /// becomes part of the current branch. Returns `false`, leaving the tree
+    /// unchanged, if there's no such revision.
+    pub fn checkout(&mut self, index: usize) -> bool {
+        if index >= self.revisions.len() {
+            return false;
+        }
+
+        let mut child_index = index;
+        while let Some(parent_index) = self.revisions[child_index]
+            .parent
+            .as_ref()
+            .map(|parent| parent.index)
+        {
+            let parent = &mut self.revisions[parent_index];
+            if let Some(redo_index) = parent
+                .children
+                .iter()
+                .position(|child| child.index == child_index)
+            {
+                parent.redo_index = redo_index;
+            }
+            child_index = parent_index;
+        }
+
+        self.staged = self.revisions[index].text.clone();
+        self.head_index = index;
+        self.has_staged_changes = false;
+        self.visit_head();
+        true
+    }
+
+    /// The number of revisions that can be undone from the head
+    pub fn num_undos(&self) -> usize {
+        let mut num_undos = 0;
+        let mut index = self.head_index;
+        while let Some(ref parent) = self.revisions[index].parent {
+            index = parent.index;
+            num_undos += 1;
+        }
+        num_undos
+    }
+
+    /// The number of revisions that can be redone from the head, following
+    /// the selected child of each revision
+    pub fn num_redos(&self) -> usize {
+        let mut num_redos = 0;
+        let mut revision = &self.revisions[self.head_index];
+        while let Some(child) = revision.children.get(revision.redo_index) {
+            revision = &self.revisions[child.index];
+            num_redos += 1;
+        }
+        num_redos
+    }
+
+    /// Reverts the most recent edit overlapping `region`, keeping the edits
+    /// made since then elsewhere in the text. Unlike `undo`, this doesn't move
+    /// the head, the returned diff is a new edit that should be committed with
+    /// `create_revision`.
+    ///
+    /// Only the edits on the path from the root to the head are considered.
+    /// An edit that was itself modified by a later edit can't be reverted on
+    /// its own and is skipped, the later edit is the one overlapping the
+    /// region in that case.
+    pub fn undo_in_region(&mut self, region: Range<CharIndex>) -> Option<OpaqueDiff> {
+        // The edits made after the one being considered, newest first
+        let mut later_diffs: Vec<OpaqueDiff> = Vec::new();
+        let mut child_index = self.head_index;
+        while let Some(Reference {
+            index: parent_index,
+            ref diff,
+        }) = self.revisions[child_index].parent
+        {
+            let parent_text = &self.revisions[parent_index].text;
+            let child_text = &self.revisions[child_index].text;
+            if diff.is_empty() {
+                // Some revisions, e.g. saving with whitespace trimmed, don't
+                // record a diff even though the text changed
+                if parent_text != child_text {
+                    later_diffs.extend(diff_ropes(parent_text, child_text).into_iter().rev());
+                }
+            } else {
+                let edit = diff.reverse();
+                let edited = edit.char_index..edit.char_index + edit.new_char_length;
+                let current = later_diffs.iter().rev().try_fold(edited, map_range);
+                if let Some(current) = current.filter(|current| overlaps(current, &region)) {
+                    let original: String = parent_text
+                        .slice(edit.char_index..edit.char_index + edit.old_char_length)
+                        .into();
+                    return Some(self.replace_staged(current, &original));
+                }
+                later_diffs.push(edit);
+            }
+            child_index = parent_index;
+        }
+        None
+    }
+
+    fn visit_head(&mut self) {
+        self.num_visits += 1;
+        self.revisions[self.head_index].last_visit = self.num_visits;
+    }
+
+    /// Removes revisions until there are at most `max_revisions` and their
+    /// edits take up at most `max_edit_bytes`. The current branch, from the
+    /// root through the head to the revisions redo follows, is kept, the
+    /// other revisions go first, least recently visited first. If that's not
+    /// enough, the root moves down the branch while there are more than
+    /// `MIN_UNDOS` undos from the head.
+    fn prune(&mut self) {
+        let (max_revisions, max_edit_bytes) = (self.max_revisions, self.max_edit_bytes);
+        let over_limit = |num_revisions: usize, edit_bytes: usize| {
+            (max_revisions != 0 && num_revisions > max_revisions)
+                || (max_edit_bytes != 0 && edit_bytes > max_edit_bytes)
+        };
+        let num_revisions = self.revisions.len();
+        let mut edit_bytes = self.edit_bytes;
+        if !over_limit(num_revisions, edit_bytes) {
+            return;
+        }
+
+        let mut on_branch = vec![false; num_revisions];
+        let mut index = self.head_index;
+        on_branch[index] = true;
+        while let Some(ref parent) = self.revisions[index].parent {
+            index = parent.index;
+            on_branch[index] = true;
+        }
+        let mut revision = &self.revisions[self.head_index];
+        while let Some(child) = revision.children.get(revision.redo_index) {
+            on_branch[child.index] = true;
+            revision = &self.revisions[child.index];
+        }
+
+        // Remove the leaves off the branch, a revision becomes a leaf once
+        // its children are removed
+        let mut removed = vec![false; num_revisions];
+        let mut num_removed = 0;
+        let mut num_children: Vec<_> = self
+            .revisions
+            .iter()
+            .map(|revision| revision.children.len())
+            .collect();
+        let mut leaves: BinaryHeap<_> = (0..num_revisions)
+            .filter(|&index| !on_branch[index] && num_children[index] == 0)
+            .map(|index| Reverse((self.revisions[index].last_visit, index)))
+            .collect();
+        while over_limit(num_revisions - num_removed, edit_bytes) {
+            let index = match leaves.pop() {
+                Some(Reverse((_, index))) => index,
+                None => break,
+            };
+            removed[index] = true;
+            num_removed += 1;
+            edit_bytes -= self.revisions[index].edit_bytes();
+            if let Some(ref parent) = self.revisions[index].parent {
+                num_children[parent.index] -= 1;
+                if num_children[parent.index] == 0 && !on_branch[parent.index] {
+                    leaves.push(Reverse((
+                        self.revisions[parent.index].last_visit,
+                        parent.index,
+                    )));
+                }
+            }
+        }
+
+        // Only the branch is left by now, drop its oldest revisions
+        let mut root_index = 0;
+        let mut num_undos = self.num_undos();
+        while over_limit(num_revisions - num_removed, edit_bytes) && num_undos > MIN_UNDOS {
+            removed[root_index] = true;
+            num_removed += 1;
+            num_undos -= 1;
+            root_index = self.revisions[root_index]
+                .children
+                .iter()
+                .find(|child| !removed[child.index])
+                .expect("the root is an ancestor of the head")
+                .index;
+            // The new root's edit goes with its parent
+            edit_bytes -= self.revisions[root_index].edit_bytes();
+        }
+
+        // Children are created after their parents, so keeping the order of
+        // the revisions keeps the root first
+        let mut new_indices = vec![0; num_revisions];
+        let mut num_kept = 0;
+        for (index, new_index) in new_indices.iter_mut().enumerate() {
+            if !removed[index] {
+                *new_index = num_kept;
+                num_kept += 1;
+            }
+        }
+        self.revisions = mem::take(&mut self.revisions)
+            .into_iter()
+            .enumerate()
+            .filter(|&(index, _)| !removed[index])
+            .map(|(_, mut revision)| {
+                revision.parent =
+                    revision
+                        .parent
+                        .filter(|parent| !removed[parent.index])
+                        .map(|parent| Reference {
+                            index: new_indices[parent.index],
+                            ..parent
+                        });
+                let redo_child = revision
+                    .children
+                    .get(revision.redo_index)
+                    .map(|child| child.index);
+                revision.children.retain(|child| !removed[child.index]);
+                revision.redo_index = redo_child
+                    .and_then(|redo_child| {
+                        revision
+                            .children
+                            .iter()
+                            .position(|child| child.index == redo_child)
+                    })
+                    .unwrap_or(0);
+                for child in revision.children.iter_mut() {
+                    child.index = new_indices[child.index];
+                }
+                revision
             })
+            .collect();
+        self.head_index = new_indices[self.head_index];
+        self.saved_index = self
+            .saved_index
+            .filter(|&saved_index| !removed[saved_index])
+            .map(|saved_index| new_indices[saved_index]);
+        self.edit_bytes = edit_bytes;
+    }
+
+    fn replace_staged(&mut self, range: Range<CharIndex>, replacement: &str) -> OpaqueDiff {
+        let text = self.staged_mut();
+        let byte_index = text.char_to_byte(range.start);
+        let old_byte_length = text.char_to_byte(range.end) - byte_index;
+        text.remove(range.clone());
+        text.insert(range.start, replacement);
+        OpaqueDiff::new(
+            byte_index,
+            old_byte_length,
+            replacement.len(),
+            range.start,
+            range.end - range.start,
+            replacement.chars().count(),
+        )
+    }
+
+    /// Marks the head as the revision saved to disk, with the staged text.
+    /// Changes made to the staged text by saving, e.g. trimming whitespace,
+    /// become part of the head revision.
+    pub fn mark_saved(&mut self) {
+        self.revisions[self.head_index].text = self.staged.clone();
+        self.has_staged_changes = false;
+        self.saved_index = Some(self.head_index);
+        self.saved_text = Some(self.staged.clone());
+    }
+
+    /// Whether the staged text is the text last saved, e.g. after undoing
+    /// back to it or deleting what was just typed. Texts are only compared
+    /// when their lengths are the same, away from the saved revision.
+    pub fn is_saved(&self) -> bool {
+        match self.saved_text {
+            Some(ref saved_text) => {
+                (self.saved_index == Some(self.head_index) && !self.has_staged_changes)
+                    || (saved_text.len_bytes() == self.staged.len_bytes()
+                        && *saved_text == self.staged)
+            }
+            None => false,
+        }
+    }
+
+    /// The ranges of lines of the head revision changed since the saved one,
+    /// ordered and without overlaps. It's worked out from the edits between
+    /// the two revisions rather than comparing their texts, every line is
+    /// changed if the text was never saved or the saved revision was pruned.
+    pub fn modified_line_ranges(&self) -> Vec<Range<LineIndex>> {
+        let head_text = &self.revisions[self.head_index].text;
+        if self.is_saved() {
+            return Vec::new();
+        }
+        let saved_index = match self.saved_index {
+            Some(saved_index) => saved_index,
+            None => return iter::once(0..head_text.len_lines()).collect(),
+        };
+
+        // The edits from the saved revision up to the closest ancestor it has
+        // in common with the head, then down to the head
+        let ancestors = |mut index: usize| {
+            let mut ancestors = vec![index];
+            while let Some(ref parent) = self.revisions[index].parent {
+                index = parent.index;
+                ancestors.push(index);
+            }
+            ancestors
+        };
+        let (saved_ancestors, head_ancestors) =
+            (ancestors(saved_index), ancestors(self.head_index));
+        let head_ancestor_set: HashSet<usize> = head_ancestors.iter().copied().collect();
+        let common = saved_ancestors
+            .iter()
+            .position(|index| head_ancestor_set.contains(index))
+            .expect("revisions share the root");
+        let common_index = saved_ancestors[common];
+        let down = head_ancestors
+            .iter()
+            .position(|&index| index == common_index)
+            .unwrap();
+        let steps = saved_ancestors[..common]
+            .iter()
+            .map(|&index| (index, true))
+            .chain(
+                head_ancestors[..down]
+                    .iter()
+                    .rev()
+                    .map(|&index| (index, false)),
+            );
+
+        let mut modified: Vec<Range<CharIndex>> = Vec::new();
+        for (index, undo) in steps {
+            let Reference {
+                index: parent_index,
+                ref diff,
+            } = *self.revisions[index].parent.as_ref().unwrap();
+            let (old_text, new_text, diff) = if undo {
+                (
+                    &self.revisions[index].text,
+                    &self.revisions[parent_index].text,
+                    diff.clone(),
+                )
+            } else {
+                (
+                    &self.revisions[parent_index].text,
+                    &self.revisions[index].text,
+                    diff.reverse(),
+                )
+            };
+            if diff.is_empty() {
+                // Some revisions, e.g. saving with whitespace trimmed, don't
+                // record a diff even though the text changed
+                if old_text != new_text {
+                    for diff in diff_ropes(old_text, new_text) {
+                        add_modified_range(&mut modified, &diff);
+                    }
+                }
+            } else {
+                add_modified_range(&mut modified, &diff);
+            }
+        }
+
+        let mut line_ranges: Vec<Range<LineIndex>> = Vec::new();
+        for range in modified {
+            let start = head_text.char_to_line(range.start);
+            let end = if range.is_empty() {
+                start + 1
+            } else {
+                head_text.char_to_line(range.end - 1) + 1
+            };
+            match line_ranges.last_mut() {
+                Some(last) if start <= last.end => last.end = cmp::max(last.end, end),
+                _ => line_ranges.push(start..end),
+            }
+        }
+        line_ranges
     }
 
     pub fn staged(&self) -> &Rope {
@@ -164,6 +660,57 @@ impl DerefMut for EditTree {
     }
 }
 
+/// Adds the text changed by an edit to the ordered `modified` ranges, moving
+/// the ranges after it and merging the ones it overlaps or touches
+fn add_modified_range(modified: &mut Vec<Range<CharIndex>>, diff: &OpaqueDiff) {
+    let edit_end = diff.char_index + diff.old_char_length;
+    let shift = |index: CharIndex| index - diff.old_char_length + diff.new_char_length;
+    let mut edited = diff.char_index..diff.char_index + diff.new_char_length;
+    let mut ranges = Vec::with_capacity(modified.len() + 1);
+    for range in modified.drain(..) {
+        if range.end < diff.char_index {
+            ranges.push(range);
+        } else if range.start > edit_end {
+            ranges.push(shift(range.start)..shift(range.end));
+        } else {
+            edited.start = cmp::min(edited.start, range.start);
+            if range.end > edit_end {
+                edited.end = cmp::max(edited.end, shift(range.end));
+            }
+        }
+    }
+    let index = ranges.partition_point(|range| range.start < edited.start);
+    ranges.insert(index, edited);
+    *modified = ranges;
+}
+
+/// The number of undos from the head pruning keeps, however few revisions
+/// are kept otherwise
+const MIN_UNDOS: usize = 100;
+
+/// Maps a range of text through a later edit, `None` if the edit changed it
+fn map_range(range: Range<CharIndex>, diff: &OpaqueDiff) -> Option<Range<CharIndex>> {
+    let edit_end = diff.char_index + diff.old_char_length;
+    if range.end <= diff.char_index {
+        Some(range)
+    } else if range.start >= edit_end {
+        let shift = |index: CharIndex| index + diff.new_char_length - diff.old_char_length;
+        Some(shift(range.start)..shift(range.end))
+    } else {
+        None
+    }
+}
+
+/// Whether an edited range overlaps the region. Deletions leave an empty
+/// range, which overlaps the region if it's inside or at its ends.
+fn overlaps(range: &Range<CharIndex>, region: &Range<CharIndex>) -> bool {
+    if range.is_empty() {
+        region.start <= range.start && range.start <= region.end
+    } else {
+        range.start < region.end && region.start < range.end
+    }
+}
+
 pub struct FormattedRevision {
     pub transform: Vector2D<isize>,
     pub current_branch: bool,
@@ -229,6 +776,33 @@ mod tests {
         assert_eq!("The flowers are... so...\ndunno.", &tree.to_string());
     }
 
+    #[test]
+    fn amended_edits_are_undone_together() {
+        let mut tree = EditTree::new("end\n".into());
+        let mut cursor = Cursor::new();
+        let diff = cursor.insert_char(&mut tree, 'a');
+        tree.create_revision(diff, cursor.clone());
+        for character in "b\n".chars().rev() {
+            let diff = cursor.insert_char(&mut tree, character);
+            tree.amend_revision(diff, cursor.clone());
+        }
+        assert_eq!("b\naend\n", &tree.to_string());
+        assert_eq!(2, tree.revisions.len());
+
+        tree.undo();
+        assert_eq!("end\n", &tree.to_string());
+        tree.redo();
+        assert_eq!("b\naend\n", &tree.to_string());
+
+        // The saved revision is left as it was saved
+        tree.mark_saved();
+        let diff = cursor.insert_char(&mut tree, 'c');
+        tree.amend_revision(diff, cursor.clone());
+        assert_eq!(3, tree.revisions.len());
+        tree.undo();
+        assert_eq!("b\naend\n", &tree.to_string());
+    }
+
     #[test]
     fn undo_at_root_has_no_effect() {
         let mut tree = EditTree::new("The flowers are violet.\n".into());
@@ -283,4 +857,289 @@ mod tests {
 
     #[test]
     fn render_undo_tree() {}
+
+    fn edit(tree: &mut EditTree, range: Range<CharIndex>, replacement: &str) {
+        let mut cursor = Cursor::new();
+        let diff = tree.replace_staged(range, replacement);
+        cursor.reconcile(tree, &diff);
+        tree.create_revision(diff, cursor);
+    }
+
+    #[test]
+    fn undo_in_region_keeps_edits_elsewhere() {
+        let mut tree = EditTree::new("The flowers are violet.\nThe sky is grey.\n".into());
+        edit(&mut tree, 16..22, "red");
+        edit(&mut tree, 32..36, "blue");
+        assert_eq!(
+            "The flowers are red.\nThe sky is blue.\n",
+            &tree.to_string()
+        );
+
+        // The latest edit is outside of the region, only the first one is undone
+        let diff = tree.undo_in_region(0..20).unwrap();
+        assert_eq!(OpaqueDiff::new(16, 3, 6, 16, 3, 6), diff);
+        assert_eq!(
+            "The flowers are violet.\nThe sky is blue.\n",
+            &tree.to_string()
+        );
+        tree.create_revision(diff, Cursor::new());
+
+        // Undoing in the region is itself an edit that can be undone
+        tree.undo();
+        assert_eq!(
+            "The flowers are red.\nThe sky is blue.\n",
+            &tree.to_string()
+        );
+    }
+
+    #[test]
+    fn undo_in_region_picks_the_latest_overlapping_edit() {
+        let mut tree = EditTree::new("one two three\n".into());
+        edit(&mut tree, 0..3, "1");
+        edit(&mut tree, 2..5, "2");
+        edit(&mut tree, 4..9, "");
+        assert_eq!("1 2 \n", &tree.to_string());
+
+        // The deletion is at the end of the region
+        let diff = tree.undo_in_region(0..4).unwrap();
+        tree.create_revision(diff, Cursor::new());
+        assert_eq!("1 2 three\n", &tree.to_string());
+
+        let diff = tree.undo_in_region(2..3).unwrap();
+        tree.create_revision(diff, Cursor::new());
+        assert_eq!("1 two three\n", &tree.to_string());
+    }
+
+    #[test]
+    fn undo_in_region_skips_edits_changed_later() {
+        let mut tree = EditTree::new("abc\n".into());
+        edit(&mut tree, 1..2, "xyz");
+        edit(&mut tree, 2..3, "Y");
+        assert_eq!("axYzc\n", &tree.to_string());
+
+        // The first edit was changed by the second one, which is undone
+        assert_eq!(
+            Some(OpaqueDiff::new(2, 1, 1, 2, 1, 1)),
+            tree.undo_in_region(1..4)
+        );
+        assert_eq!("axyzc\n", &tree.to_string());
+
+        // Nothing changed in the region
+        let mut tree = EditTree::new("abc\n".into());
+        edit(&mut tree, 0..1, "A");
+        assert_eq!(None, tree.undo_in_region(2..3));
+        assert_eq!(None, EditTree::new("abc\n".into()).undo_in_region(0..3));
+    }
+
+    #[test]
+    fn undo_and_redo_counts() {
+        let mut tree = EditTree::new("abc\n".into());
+        assert_eq!((0, 0), (tree.num_undos(), tree.num_redos()));
+        edit(&mut tree, 0..1, "A");
+        edit(&mut tree, 1..2, "B");
+        edit(&mut tree, 2..3, "C");
+        assert_eq!((3, 0), (tree.num_undos(), tree.num_redos()));
+
+        tree.undo();
+        tree.undo();
+        assert_eq!((1, 2), (tree.num_undos(), tree.num_redos()));
+
+        // A new branch becomes the one followed when redoing
+        edit(&mut tree, 3..4, "\n\n");
+        tree.undo();
+        assert_eq!((1, 1), (tree.num_undos(), tree.num_redos()));
+        tree.previous_child();
+        assert_eq!((1, 2), (tree.num_undos(), tree.num_redos()));
+    }
+
+    #[test]
+    fn diff_stats_of_revisions() {
+        let mut tree = EditTree::new("The flowers are violet.\n".into());
+        edit(&mut tree, 16..22, "red");
+        edit(&mut tree, 19..19, "dish");
+        let stats: Vec<_> = tree.revisions.iter().map(Revision::diff_stats).collect();
+        assert_eq!(
+            vec![
+                None,
+                Some(DiffStats {
+                    inserted_chars: 3,
+                    deleted_chars: 6
+                }),
+                Some(DiffStats {
+                    inserted_chars: 4,
+                    deleted_chars: 0
+                })
+            ],
+            stats
+        );
+        assert!(tree.revisions[0].timestamp <= tree.revisions[2].timestamp);
+    }
+
+    #[test]
+    fn checkout_revisions() {
+        let mut tree = EditTree::new("abc\n".into());
+        edit(&mut tree, 0..1, "A");
+        edit(&mut tree, 1..2, "B");
+        tree.undo();
+        tree.undo();
+        edit(&mut tree, 2..3, "C");
+        assert_eq!("abC\n", &tree.to_string());
+
+        // Jump to the end of the other branch, which is redone from the root
+        assert!(tree.checkout(2));
+        assert_eq!("ABc\n", &tree.to_string());
+        assert_eq!((2, 0), (tree.num_undos(), tree.num_redos()));
+        tree.undo();
+        tree.undo();
+        assert_eq!((0, 2), (tree.num_undos(), tree.num_redos()));
+        tree.redo();
+        assert_eq!("Abc\n", &tree.to_string());
+
+        assert!(tree.checkout(0));
+        assert_eq!("abc\n", &tree.to_string());
+        assert!(!tree.checkout(4));
+        assert_eq!("abc\n", &tree.to_string());
+        assert_eq!(0, tree.head_index);
+    }
+
+    #[test]
+    fn modified_line_ranges_since_saved() {
+        let mut tree = EditTree::new("one\ntwo\nthree\nfour\nfive\n".into());
+        assert!(tree.is_saved());
+        assert!(tree.modified_line_ranges().is_empty());
+
+        edit(&mut tree, 4..7, "TWO");
+        edit(&mut tree, 19..19, "4\n");
+        assert!(!tree.is_saved());
+        assert_eq!(vec![1..2, 4..5], tree.modified_line_ranges());
+
+        // Joining the lines in between coalesces the ranges
+        edit(&mut tree, 7..19, "");
+        assert_eq!("one\nTWO4\nfive\n", &tree.to_string());
+        assert_eq!(vec![1..2], tree.modified_line_ranges());
+
+        // Undoing back to the saved revision leaves nothing modified
+        tree.undo();
+        assert_eq!(vec![1..2, 4..5], tree.modified_line_ranges());
+        tree.undo();
+        tree.undo();
+        assert!(tree.is_saved());
+        assert!(tree.modified_line_ranges().is_empty());
+
+        // Undoing past a save, then editing on another branch
+        tree.redo();
+        tree.mark_saved();
+        assert!(tree.modified_line_ranges().is_empty());
+        tree.undo();
+        assert_eq!(vec![1..2], tree.modified_line_ranges());
+        edit(&mut tree, 0..0, "zero\n");
+        assert_eq!(vec![0..1, 2..3], tree.modified_line_ranges());
+    }
+
+    #[test]
+    fn saved_when_the_text_is_the_same() {
+        let mut tree = EditTree::new("one\n".into());
+        edit(&mut tree, 3..3, " two");
+        assert!(!tree.is_saved());
+        tree.undo();
+        assert!(tree.is_saved());
+
+        // Deleting what was typed is a new revision with the saved text
+        edit(&mut tree, 3..3, "x");
+        edit(&mut tree, 3..4, "");
+        assert_eq!(2, tree.num_undos());
+        assert!(tree.is_saved());
+        assert!(tree.modified_line_ranges().is_empty());
+        edit(&mut tree, 0..1, "O");
+        assert!(!tree.is_saved());
+
+        // A new text is modified until it's saved
+        let mut tree = EditTree::new("".into()).unsaved();
+        assert!(!tree.is_saved());
+        assert_eq!(vec![0..1], tree.modified_line_ranges());
+        edit(&mut tree, 0..0, "a");
+        tree.undo();
+        assert!(!tree.is_saved());
+        tree.mark_saved();
+        assert!(tree.is_saved());
+    }
+
+    fn revision_texts(tree: &EditTree) -> Vec<String> {
+        tree.revisions
+            .iter()
+            .map(|revision| revision.text.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn prune_least_recently_visited_branches() {
+        let mut tree = EditTree::new("".into()).with_max_revisions(4);
+        edit(&mut tree, 0..0, "a");
+        tree.undo();
+        edit(&mut tree, 0..0, "b");
+        tree.undo();
+        edit(&mut tree, 0..0, "c");
+        assert!(tree.checkout(1));
+        assert!(tree.checkout(3));
+
+        // `b` is the branch visited least recently
+        edit(&mut tree, 1..1, "d");
+        assert_eq!(vec!["", "a", "c", "cd"], revision_texts(&tree));
+        assert_eq!((2, 0), (tree.num_undos(), tree.num_redos()));
+        tree.undo();
+        tree.undo();
+        assert_eq!("", &tree.to_string());
+        tree.redo();
+        assert_eq!("c", &tree.to_string());
+
+        // Then `a`, as `c` and `cd` were visited since
+        tree.undo();
+        edit(&mut tree, 0..0, "e");
+        assert_eq!(vec!["", "c", "cd", "e"], revision_texts(&tree));
+        tree.undo();
+        tree.redo();
+        assert_eq!("e", &tree.to_string());
+    }
+
+    #[test]
+    fn prune_old_history_keeping_recent_undos() {
+        let mut tree = EditTree::new("".into()).with_max_revisions(10);
+        for index in 0..MIN_UNDOS + 20 {
+            edit(&mut tree, index..index, "x");
+        }
+        assert_eq!(MIN_UNDOS + 1, tree.revisions.len());
+        assert_eq!(MIN_UNDOS, tree.num_undos());
+        assert!(tree.revisions[0].parent.is_none());
+        for _ in 0..MIN_UNDOS {
+            assert!(tree.undo().is_some());
+        }
+        assert!(tree.undo().is_none());
+        assert_eq!("x".repeat(20), tree.to_string());
+        assert!(tree.redo().is_some());
+        assert_eq!("x".repeat(21), tree.to_string());
+    }
+
+    #[test]
+    fn prune_branches_past_the_edit_bytes() {
+        let mut tree = EditTree::new("".into()).with_max_edit_bytes(1000);
+        edit(&mut tree, 0..0, &"a".repeat(600));
+        tree.undo();
+        edit(&mut tree, 0..0, "b");
+        assert_eq!(3, tree.revisions.len());
+
+        // The branch of `a`s is dropped to make room, the current one is kept
+        edit(&mut tree, 1..1, &"c".repeat(500));
+        assert_eq!(
+            vec!["".to_string(), "b".into(), format!("b{}", "c".repeat(500))],
+            revision_texts(&tree)
+        );
+
+        // Recent undos are kept past the limit
+        for index in 0..5 {
+            edit(&mut tree, 0..0, &index.to_string().repeat(400));
+        }
+        assert_eq!(8, tree.revisions.len());
+        while tree.undo().is_some() {}
+        assert_eq!("", &tree.to_string());
+    }
 }
@@ -0,0 +1,174 @@
+mod tables;
+
+use std::fmt;
+
+/// Why a character couldn't be looked up
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CharLookupError {
+    /// A surrogate or a value beyond `U+10FFFF`
+    InvalidCodePoint(u32),
+    /// A valid code point without a character assigned to it
+    Unassigned(char),
+    UnknownName(String),
+}
+
+impl fmt::Display for CharLookupError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidCodePoint(code_point) => {
+                write!(formatter, "U+{:04X} is not a valid code point", code_point)
+            }
+            Self::Unassigned(character) => {
+                write!(formatter, "U+{:04X} is not assigned", *character as u32)
+            }
+            Self::UnknownName(name) => write!(formatter, "Unknown character name `{}`", name),
+        }
+    }
+}
+
+impl std::error::Error for CharLookupError {}
+
+/// Looks up a character by code point, e.g. `U+1F600`, `0x1F600` or just
+/// `1F600`, or by its Unicode name, e.g. `grinning face`. Names are case
+/// insensitive and only common characters are known by name.
+pub fn lookup_char(input: &str) -> Result<char, CharLookupError> {
+    let input = input.trim();
+    let prefixed_hex = ["U+", "u+", "0x", "0X"]
+        .iter()
+        .find_map(|prefix| input.strip_prefix(prefix));
+    if let Some(hex) = prefixed_hex {
+        return char_by_code_point(hex)
+            .unwrap_or_else(|| Err(CharLookupError::UnknownName(input.into())));
+    }
+
+    if let Some(character) = char_by_name(input) {
+        return Ok(character);
+    }
+    char_by_code_point(input).unwrap_or_else(|| Err(CharLookupError::UnknownName(input.into())))
+}
+
+/// Parses a hexadecimal code point. Returns `None` if `hex` isn't a number.
+fn char_by_code_point(hex: &str) -> Option<Result<char, CharLookupError>> {
+    if hex.is_empty() || !hex.chars().all(|character| character.is_ascii_hexdigit()) {
+        return None;
+    }
+    let code_point = u32::from_str_radix(hex, 16).unwrap_or(u32::MAX);
+    Some(match char::from_u32(code_point) {
+        Some(character) if is_assigned(character) => Ok(character),
+        Some(character) => Err(CharLookupError::Unassigned(character)),
+        None => Err(CharLookupError::InvalidCodePoint(code_point)),
+    })
+}
+
+/// Looks up a character by its Unicode name, ignoring case and repeated
+/// whitespace
+pub fn char_by_name(name: &str) -> Option<char> {
+    let name = name
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase();
+    tables::NAMES
+        .binary_search_by(|(other, _)| (*other).cmp(name.as_str()))
+        .ok()
+        .map(|index| tables::NAMES[index].1)
+}
+
+/// Whether the Unicode standard assigns a character to this code point,
+/// including private use characters
+pub fn is_assigned(character: char) -> bool {
+    let code_point = character as u32;
+    tables::ASSIGNED
+        .binary_search_by(|&(start, end)| {
+            if end < code_point {
+                std::cmp::Ordering::Less
+            } else if start > code_point {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Combines an accent typed before a letter into a single character, e.g.
+/// `'` and `e` into `é`. The accents are the ones used by Emacs' `C-x 8`.
+pub fn compose_digraph(accent: char, base: char) -> Option<char> {
+    let (_, bases, composed) = DIGRAPHS.iter().find(|(other, _, _)| *other == accent)?;
+    let index = bases.chars().position(|other| other == base)?;
+    composed.chars().nth(index)
+}
+
+/// For each accent, the letters it combines with and the resulting characters
+pub const DIGRAPHS: &[(char, &str, &str)] = &[
+    ('\'', "aAeEiIoOuUyYnNcC", "áÁéÉíÍóÓúÚýÝńŃćĆ"),
+    ('`', "aAeEiIoOuU", "àÀèÈìÌòÒùÙ"),
+    ('^', "aAeEiIoOuUyYcC", "âÂêÊîÎôÔûÛŷŶĉĈ"),
+    ('"', "aAeEiIoOuUyYs", "äÄëËïÏöÖüÜÿŸß"),
+    ('~', "aAiIoOuUnN", "ãÃĩĨõÕũŨñÑ"),
+    (',', "nNcC", "ņŅçÇ"),
+    ('/', "oOaAeE", "øØåÅæÆ"),
+];
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+
+    use super::*;
+    use crate::{movement, Cursor, Direction};
+
+    #[test]
+    fn lookup_char_by_code_point() {
+        assert_eq!(Ok('😀'), lookup_char("U+1F600"));
+        assert_eq!(Ok('é'), lookup_char("u+e9"));
+        assert_eq!(Ok('λ'), lookup_char("0x3bb"));
+        assert_eq!(Ok('€'), lookup_char(" 20AC "));
+        assert_eq!(
+            Err(CharLookupError::InvalidCodePoint(0xD800)),
+            lookup_char("U+D800")
+        );
+        assert_eq!(
+            Err(CharLookupError::InvalidCodePoint(0x110000)),
+            lookup_char("U+110000")
+        );
+        assert_eq!(
+            Err(CharLookupError::Unassigned('\u{378}')),
+            lookup_char("U+0378")
+        );
+        assert_eq!(
+            Err(CharLookupError::UnknownName("U+12G".into())),
+            lookup_char("U+12G")
+        );
+    }
+
+    #[test]
+    fn lookup_char_by_name() {
+        assert_eq!(Ok('😀'), lookup_char("GRINNING FACE"));
+        assert_eq!(Ok('é'), lookup_char("latin small letter  e with acute"));
+        assert_eq!(Ok('→'), lookup_char("Rightwards Arrow"));
+        assert_eq!(Ok('\u{301}'), lookup_char("combining acute accent"));
+        assert_eq!(
+            Err(CharLookupError::UnknownName("no such character".into())),
+            lookup_char("no such character")
+        );
+    }
+
+    #[test]
+    fn compose_digraphs() {
+        assert_eq!(Some('é'), compose_digraph('\'', 'e'));
+        assert_eq!(Some('Ñ'), compose_digraph('~', 'N'));
+        assert_eq!(Some('ß'), compose_digraph('"', 's'));
+        assert_eq!(None, compose_digraph('\'', 'x'));
+        assert_eq!(None, compose_digraph('!', 'e'));
+    }
+
+    #[test]
+    fn combining_character_joins_previous_grapheme() {
+        let mut text = Rope::from("e\n");
+        let mut cursor = Cursor::with_range(1..2);
+        cursor.insert_char(&mut text, lookup_char("U+0301").unwrap());
+        movement::move_horizontally(&text, &mut cursor, Direction::Forward, 1);
+        assert_eq!("e\u{301}\n", text);
+        assert_eq!(2..3, cursor.range());
+    }
+}
@@ -0,0 +1,598 @@
+// Generated from the Unicode 14.0 character database
+
+/// The ranges of assigned code points, sorted and non-overlapping
+#[rustfmt::skip]
+pub(super) const ASSIGNED: &[(u32, u32)] = &[
+    (0x0, 0x377), (0x37A, 0x37F), (0x384, 0x38A), (0x38C, 0x38C),
+    (0x38E, 0x3A1), (0x3A3, 0x52F), (0x531, 0x556), (0x559, 0x58A),
+    (0x58D, 0x58F), (0x591, 0x5C7), (0x5D0, 0x5EA), (0x5EF, 0x5F4),
+    (0x600, 0x70D), (0x70F, 0x74A), (0x74D, 0x7B1), (0x7C0, 0x7FA),
+    (0x7FD, 0x82D), (0x830, 0x83E), (0x840, 0x85B), (0x85E, 0x85E),
+    (0x860, 0x86A), (0x870, 0x88E), (0x890, 0x891), (0x898, 0x983),
+    (0x985, 0x98C), (0x98F, 0x990), (0x993, 0x9A8), (0x9AA, 0x9B0),
+    (0x9B2, 0x9B2), (0x9B6, 0x9B9), (0x9BC, 0x9C4), (0x9C7, 0x9C8),
+    (0x9CB, 0x9CE), (0x9D7, 0x9D7), (0x9DC, 0x9DD), (0x9DF, 0x9E3),
+    (0x9E6, 0x9FE), (0xA01, 0xA03), (0xA05, 0xA0A), (0xA0F, 0xA10),
+    (0xA13, 0xA28), (0xA2A, 0xA30), (0xA32, 0xA33), (0xA35, 0xA36),
+    (0xA38, 0xA39), (0xA3C, 0xA3C), (0xA3E, 0xA42), (0xA47, 0xA48),
+    (0xA4B, 0xA4D), (0xA51, 0xA51), (0xA59, 0xA5C), (0xA5E, 0xA5E),
+    (0xA66, 0xA76), (0xA81, 0xA83), (0xA85, 0xA8D), (0xA8F, 0xA91),
+    (0xA93, 0xAA8), (0xAAA, 0xAB0), (0xAB2, 0xAB3), (0xAB5, 0xAB9),
+    (0xABC, 0xAC5), (0xAC7, 0xAC9), (0xACB, 0xACD), (0xAD0, 0xAD0),
+    (0xAE0, 0xAE3), (0xAE6, 0xAF1), (0xAF9, 0xAFF), (0xB01, 0xB03),
+    (0xB05, 0xB0C), (0xB0F, 0xB10), (0xB13, 0xB28), (0xB2A, 0xB30),
+    (0xB32, 0xB33), (0xB35, 0xB39), (0xB3C, 0xB44), (0xB47, 0xB48),
+    (0xB4B, 0xB4D), (0xB55, 0xB57), (0xB5C, 0xB5D), (0xB5F, 0xB63),
+    (0xB66, 0xB77), (0xB82, 0xB83), (0xB85, 0xB8A), (0xB8E, 0xB90),
+    (0xB92, 0xB95), (0xB99, 0xB9A), (0xB9C, 0xB9C), (0xB9E, 0xB9F),
+    (0xBA3, 0xBA4), (0xBA8, 0xBAA), (0xBAE, 0xBB9), (0xBBE, 0xBC2),
+    (0xBC6, 0xBC8), (0xBCA, 0xBCD), (0xBD0, 0xBD0), (0xBD7, 0xBD7),
+    (0xBE6, 0xBFA), (0xC00, 0xC0C), (0xC0E, 0xC10), (0xC12, 0xC28),
+    (0xC2A, 0xC39), (0xC3C, 0xC44), (0xC46, 0xC48), (0xC4A, 0xC4D),
+    (0xC55, 0xC56), (0xC58, 0xC5A), (0xC5D, 0xC5D), (0xC60, 0xC63),
+    (0xC66, 0xC6F), (0xC77, 0xC8C), (0xC8E, 0xC90), (0xC92, 0xCA8),
+    (0xCAA, 0xCB3), (0xCB5, 0xCB9), (0xCBC, 0xCC4), (0xCC6, 0xCC8),
+    (0xCCA, 0xCCD), (0xCD5, 0xCD6), (0xCDD, 0xCDE), (0xCE0, 0xCE3),
+    (0xCE6, 0xCEF), (0xCF1, 0xCF2), (0xD00, 0xD0C), (0xD0E, 0xD10),
+    (0xD12, 0xD44), (0xD46, 0xD48), (0xD4A, 0xD4F), (0xD54, 0xD63),
+    (0xD66, 0xD7F), (0xD81, 0xD83), (0xD85, 0xD96), (0xD9A, 0xDB1),
+    (0xDB3, 0xDBB), (0xDBD, 0xDBD), (0xDC0, 0xDC6), (0xDCA, 0xDCA),
+    (0xDCF, 0xDD4), (0xDD6, 0xDD6), (0xDD8, 0xDDF), (0xDE6, 0xDEF),
+    (0xDF2, 0xDF4), (0xE01, 0xE3A), (0xE3F, 0xE5B), (0xE81, 0xE82),
+    (0xE84, 0xE84), (0xE86, 0xE8A), (0xE8C, 0xEA3), (0xEA5, 0xEA5),
+    (0xEA7, 0xEBD), (0xEC0, 0xEC4), (0xEC6, 0xEC6), (0xEC8, 0xECD),
+    (0xED0, 0xED9), (0xEDC, 0xEDF), (0xF00, 0xF47), (0xF49, 0xF6C),
+    (0xF71, 0xF97), (0xF99, 0xFBC), (0xFBE, 0xFCC), (0xFCE, 0xFDA),
+    (0x1000, 0x10C5), (0x10C7, 0x10C7), (0x10CD, 0x10CD), (0x10D0, 0x1248),
+    (0x124A, 0x124D), (0x1250, 0x1256), (0x1258, 0x1258), (0x125A, 0x125D),
+    (0x1260, 0x1288), (0x128A, 0x128D), (0x1290, 0x12B0), (0x12B2, 0x12B5),
+    (0x12B8, 0x12BE), (0x12C0, 0x12C0), (0x12C2, 0x12C5), (0x12C8, 0x12D6),
+    (0x12D8, 0x1310), (0x1312, 0x1315), (0x1318, 0x135A), (0x135D, 0x137C),
+    (0x1380, 0x1399), (0x13A0, 0x13F5), (0x13F8, 0x13FD), (0x1400, 0x169C),
+    (0x16A0, 0x16F8), (0x1700, 0x1715), (0x171F, 0x1736), (0x1740, 0x1753),
+    (0x1760, 0x176C), (0x176E, 0x1770), (0x1772, 0x1773), (0x1780, 0x17DD),
+    (0x17E0, 0x17E9), (0x17F0, 0x17F9), (0x1800, 0x1819), (0x1820, 0x1878),
+    (0x1880, 0x18AA), (0x18B0, 0x18F5), (0x1900, 0x191E), (0x1920, 0x192B),
+    (0x1930, 0x193B), (0x1940, 0x1940), (0x1944, 0x196D), (0x1970, 0x1974),
+    (0x1980, 0x19AB), (0x19B0, 0x19C9), (0x19D0, 0x19DA), (0x19DE, 0x1A1B),
+    (0x1A1E, 0x1A5E), (0x1A60, 0x1A7C), (0x1A7F, 0x1A89), (0x1A90, 0x1A99),
+    (0x1AA0, 0x1AAD), (0x1AB0, 0x1ACE), (0x1B00, 0x1B4C), (0x1B50, 0x1B7E),
+    (0x1B80, 0x1BF3), (0x1BFC, 0x1C37), (0x1C3B, 0x1C49), (0x1C4D, 0x1C88),
+    (0x1C90, 0x1CBA), (0x1CBD, 0x1CC7), (0x1CD0, 0x1CFA), (0x1D00, 0x1F15),
+    (0x1F18, 0x1F1D), (0x1F20, 0x1F45), (0x1F48, 0x1F4D), (0x1F50, 0x1F57),
+    (0x1F59, 0x1F59), (0x1F5B, 0x1F5B), (0x1F5D, 0x1F5D), (0x1F5F, 0x1F7D),
+    (0x1F80, 0x1FB4), (0x1FB6, 0x1FC4), (0x1FC6, 0x1FD3), (0x1FD6, 0x1FDB),
+    (0x1FDD, 0x1FEF), (0x1FF2, 0x1FF4), (0x1FF6, 0x1FFE), (0x2000, 0x2064),
+    (0x2066, 0x2071), (0x2074, 0x208E), (0x2090, 0x209C), (0x20A0, 0x20C0),
+    (0x20D0, 0x20F0), (0x2100, 0x218B), (0x2190, 0x2426), (0x2440, 0x244A),
+    (0x2460, 0x2B73), (0x2B76, 0x2B95), (0x2B97, 0x2CF3), (0x2CF9, 0x2D25),
+    (0x2D27, 0x2D27), (0x2D2D, 0x2D2D), (0x2D30, 0x2D67), (0x2D6F, 0x2D70),
+    (0x2D7F, 0x2D96), (0x2DA0, 0x2DA6), (0x2DA8, 0x2DAE), (0x2DB0, 0x2DB6),
+    (0x2DB8, 0x2DBE), (0x2DC0, 0x2DC6), (0x2DC8, 0x2DCE), (0x2DD0, 0x2DD6),
+    (0x2DD8, 0x2DDE), (0x2DE0, 0x2E5D), (0x2E80, 0x2E99), (0x2E9B, 0x2EF3),
+    (0x2F00, 0x2FD5), (0x2FF0, 0x2FFB), (0x3000, 0x303F), (0x3041, 0x3096),
+    (0x3099, 0x30FF), (0x3105, 0x312F), (0x3131, 0x318E), (0x3190, 0x31E3),
+    (0x31F0, 0x321E), (0x3220, 0xA48C), (0xA490, 0xA4C6), (0xA4D0, 0xA62B),
+    (0xA640, 0xA6F7), (0xA700, 0xA7CA), (0xA7D0, 0xA7D1), (0xA7D3, 0xA7D3),
+    (0xA7D5, 0xA7D9), (0xA7F2, 0xA82C), (0xA830, 0xA839), (0xA840, 0xA877),
+    (0xA880, 0xA8C5), (0xA8CE, 0xA8D9), (0xA8E0, 0xA953), (0xA95F, 0xA97C),
+    (0xA980, 0xA9CD), (0xA9CF, 0xA9D9), (0xA9DE, 0xA9FE), (0xAA00, 0xAA36),
+    (0xAA40, 0xAA4D), (0xAA50, 0xAA59), (0xAA5C, 0xAAC2), (0xAADB, 0xAAF6),
+    (0xAB01, 0xAB06), (0xAB09, 0xAB0E), (0xAB11, 0xAB16), (0xAB20, 0xAB26),
+    (0xAB28, 0xAB2E), (0xAB30, 0xAB6B), (0xAB70, 0xABED), (0xABF0, 0xABF9),
+    (0xAC00, 0xD7A3), (0xD7B0, 0xD7C6), (0xD7CB, 0xD7FB), (0xD800, 0xFA6D),
+    (0xFA70, 0xFAD9), (0xFB00, 0xFB06), (0xFB13, 0xFB17), (0xFB1D, 0xFB36),
+    (0xFB38, 0xFB3C), (0xFB3E, 0xFB3E), (0xFB40, 0xFB41), (0xFB43, 0xFB44),
+    (0xFB46, 0xFBC2), (0xFBD3, 0xFD8F), (0xFD92, 0xFDC7), (0xFDCF, 0xFDCF),
+    (0xFDF0, 0xFE19), (0xFE20, 0xFE52), (0xFE54, 0xFE66), (0xFE68, 0xFE6B),
+    (0xFE70, 0xFE74), (0xFE76, 0xFEFC), (0xFEFF, 0xFEFF), (0xFF01, 0xFFBE),
+    (0xFFC2, 0xFFC7), (0xFFCA, 0xFFCF), (0xFFD2, 0xFFD7), (0xFFDA, 0xFFDC),
+    (0xFFE0, 0xFFE6), (0xFFE8, 0xFFEE), (0xFFF9, 0xFFFD), (0x10000, 0x1000B),
+    (0x1000D, 0x10026), (0x10028, 0x1003A), (0x1003C, 0x1003D), (0x1003F, 0x1004D),
+    (0x10050, 0x1005D), (0x10080, 0x100FA), (0x10100, 0x10102), (0x10107, 0x10133),
+    (0x10137, 0x1018E), (0x10190, 0x1019C), (0x101A0, 0x101A0), (0x101D0, 0x101FD),
+    (0x10280, 0x1029C), (0x102A0, 0x102D0), (0x102E0, 0x102FB), (0x10300, 0x10323),
+    (0x1032D, 0x1034A), (0x10350, 0x1037A), (0x10380, 0x1039D), (0x1039F, 0x103C3),
+    (0x103C8, 0x103D5), (0x10400, 0x1049D), (0x104A0, 0x104A9), (0x104B0, 0x104D3),
+    (0x104D8, 0x104FB), (0x10500, 0x10527), (0x10530, 0x10563), (0x1056F, 0x1057A),
+    (0x1057C, 0x1058A), (0x1058C, 0x10592), (0x10594, 0x10595), (0x10597, 0x105A1),
+    (0x105A3, 0x105B1), (0x105B3, 0x105B9), (0x105BB, 0x105BC), (0x10600, 0x10736),
+    (0x10740, 0x10755), (0x10760, 0x10767), (0x10780, 0x10785), (0x10787, 0x107B0),
+    (0x107B2, 0x107BA), (0x10800, 0x10805), (0x10808, 0x10808), (0x1080A, 0x10835),
+    (0x10837, 0x10838), (0x1083C, 0x1083C), (0x1083F, 0x10855), (0x10857, 0x1089E),
+    (0x108A7, 0x108AF), (0x108E0, 0x108F2), (0x108F4, 0x108F5), (0x108FB, 0x1091B),
+    (0x1091F, 0x10939), (0x1093F, 0x1093F), (0x10980, 0x109B7), (0x109BC, 0x109CF),
+    (0x109D2, 0x10A03), (0x10A05, 0x10A06), (0x10A0C, 0x10A13), (0x10A15, 0x10A17),
+    (0x10A19, 0x10A35), (0x10A38, 0x10A3A), (0x10A3F, 0x10A48), (0x10A50, 0x10A58),
+    (0x10A60, 0x10A9F), (0x10AC0, 0x10AE6), (0x10AEB, 0x10AF6), (0x10B00, 0x10B35),
+    (0x10B39, 0x10B55), (0x10B58, 0x10B72), (0x10B78, 0x10B91), (0x10B99, 0x10B9C),
+    (0x10BA9, 0x10BAF), (0x10C00, 0x10C48), (0x10C80, 0x10CB2), (0x10CC0, 0x10CF2),
+    (0x10CFA, 0x10D27), (0x10D30, 0x10D39), (0x10E60, 0x10E7E), (0x10E80, 0x10EA9),
+    (0x10EAB, 0x10EAD), (0x10EB0, 0x10EB1), (0x10F00, 0x10F27), (0x10F30, 0x10F59),
+    (0x10F70, 0x10F89), (0x10FB0, 0x10FCB), (0x10FE0, 0x10FF6), (0x11000, 0x1104D),
+    (0x11052, 0x11075), (0x1107F, 0x110C2), (0x110CD, 0x110CD), (0x110D0, 0x110E8),
+    (0x110F0, 0x110F9), (0x11100, 0x11134), (0x11136, 0x11147), (0x11150, 0x11176),
+    (0x11180, 0x111DF), (0x111E1, 0x111F4), (0x11200, 0x11211), (0x11213, 0x1123E),
+    (0x11280, 0x11286), (0x11288, 0x11288), (0x1128A, 0x1128D), (0x1128F, 0x1129D),
+    (0x1129F, 0x112A9), (0x112B0, 0x112EA), (0x112F0, 0x112F9), (0x11300, 0x11303),
+    (0x11305, 0x1130C), (0x1130F, 0x11310), (0x11313, 0x11328), (0x1132A, 0x11330),
+    (0x11332, 0x11333), (0x11335, 0x11339), (0x1133B, 0x11344), (0x11347, 0x11348),
+    (0x1134B, 0x1134D), (0x11350, 0x11350), (0x11357, 0x11357), (0x1135D, 0x11363),
+    (0x11366, 0x1136C), (0x11370, 0x11374), (0x11400, 0x1145B), (0x1145D, 0x11461),
+    (0x11480, 0x114C7), (0x114D0, 0x114D9), (0x11580, 0x115B5), (0x115B8, 0x115DD),
+    (0x11600, 0x11644), (0x11650, 0x11659), (0x11660, 0x1166C), (0x11680, 0x116B9),
+    (0x116C0, 0x116C9), (0x11700, 0x1171A), (0x1171D, 0x1172B), (0x11730, 0x11746),
+    (0x11800, 0x1183B), (0x118A0, 0x118F2), (0x118FF, 0x11906), (0x11909, 0x11909),
+    (0x1190C, 0x11913), (0x11915, 0x11916), (0x11918, 0x11935), (0x11937, 0x11938),
+    (0x1193B, 0x11946), (0x11950, 0x11959), (0x119A0, 0x119A7), (0x119AA, 0x119D7),
+    (0x119DA, 0x119E4), (0x11A00, 0x11A47), (0x11A50, 0x11AA2), (0x11AB0, 0x11AF8),
+    (0x11C00, 0x11C08), (0x11C0A, 0x11C36), (0x11C38, 0x11C45), (0x11C50, 0x11C6C),
+    (0x11C70, 0x11C8F), (0x11C92, 0x11CA7), (0x11CA9, 0x11CB6), (0x11D00, 0x11D06),
+    (0x11D08, 0x11D09), (0x11D0B, 0x11D36), (0x11D3A, 0x11D3A), (0x11D3C, 0x11D3D),
+    (0x11D3F, 0x11D47), (0x11D50, 0x11D59), (0x11D60, 0x11D65), (0x11D67, 0x11D68),
+    (0x11D6A, 0x11D8E), (0x11D90, 0x11D91), (0x11D93, 0x11D98), (0x11DA0, 0x11DA9),
+    (0x11EE0, 0x11EF8), (0x11FB0, 0x11FB0), (0x11FC0, 0x11FF1), (0x11FFF, 0x12399),
+    (0x12400, 0x1246E), (0x12470, 0x12474), (0x12480, 0x12543), (0x12F90, 0x12FF2),
+    (0x13000, 0x1342E), (0x13430, 0x13438), (0x14400, 0x14646), (0x16800, 0x16A38),
+    (0x16A40, 0x16A5E), (0x16A60, 0x16A69), (0x16A6E, 0x16ABE), (0x16AC0, 0x16AC9),
+    (0x16AD0, 0x16AED), (0x16AF0, 0x16AF5), (0x16B00, 0x16B45), (0x16B50, 0x16B59),
+    (0x16B5B, 0x16B61), (0x16B63, 0x16B77), (0x16B7D, 0x16B8F), (0x16E40, 0x16E9A),
+    (0x16F00, 0x16F4A), (0x16F4F, 0x16F87), (0x16F8F, 0x16F9F), (0x16FE0, 0x16FE4),
+    (0x16FF0, 0x16FF1), (0x17000, 0x187F7), (0x18800, 0x18CD5), (0x18D00, 0x18D08),
+    (0x1AFF0, 0x1AFF3), (0x1AFF5, 0x1AFFB), (0x1AFFD, 0x1AFFE), (0x1B000, 0x1B122),
+    (0x1B150, 0x1B152), (0x1B164, 0x1B167), (0x1B170, 0x1B2FB), (0x1BC00, 0x1BC6A),
+    (0x1BC70, 0x1BC7C), (0x1BC80, 0x1BC88), (0x1BC90, 0x1BC99), (0x1BC9C, 0x1BCA3),
+    (0x1CF00, 0x1CF2D), (0x1CF30, 0x1CF46), (0x1CF50, 0x1CFC3), (0x1D000, 0x1D0F5),
+    (0x1D100, 0x1D126), (0x1D129, 0x1D1EA), (0x1D200, 0x1D245), (0x1D2E0, 0x1D2F3),
+    (0x1D300, 0x1D356), (0x1D360, 0x1D378), (0x1D400, 0x1D454), (0x1D456, 0x1D49C),
+    (0x1D49E, 0x1D49F), (0x1D4A2, 0x1D4A2), (0x1D4A5, 0x1D4A6), (0x1D4A9, 0x1D4AC),
+    (0x1D4AE, 0x1D4B9), (0x1D4BB, 0x1D4BB), (0x1D4BD, 0x1D4C3), (0x1D4C5, 0x1D505),
+    (0x1D507, 0x1D50A), (0x1D50D, 0x1D514), (0x1D516, 0x1D51C), (0x1D51E, 0x1D539),
+    (0x1D53B, 0x1D53E), (0x1D540, 0x1D544), (0x1D546, 0x1D546), (0x1D54A, 0x1D550),
+    (0x1D552, 0x1D6A5), (0x1D6A8, 0x1D7CB), (0x1D7CE, 0x1DA8B), (0x1DA9B, 0x1DA9F),
+    (0x1DAA1, 0x1DAAF), (0x1DF00, 0x1DF1E), (0x1E000, 0x1E006), (0x1E008, 0x1E018),
+    (0x1E01B, 0x1E021), (0x1E023, 0x1E024), (0x1E026, 0x1E02A), (0x1E100, 0x1E12C),
+    (0x1E130, 0x1E13D), (0x1E140, 0x1E149), (0x1E14E, 0x1E14F), (0x1E290, 0x1E2AE),
+    (0x1E2C0, 0x1E2F9), (0x1E2FF, 0x1E2FF), (0x1E7E0, 0x1E7E6), (0x1E7E8, 0x1E7EB),
+    (0x1E7ED, 0x1E7EE), (0x1E7F0, 0x1E7FE), (0x1E800, 0x1E8C4), (0x1E8C7, 0x1E8D6),
+    (0x1E900, 0x1E94B), (0x1E950, 0x1E959), (0x1E95E, 0x1E95F), (0x1EC71, 0x1ECB4),
+    (0x1ED01, 0x1ED3D), (0x1EE00, 0x1EE03), (0x1EE05, 0x1EE1F), (0x1EE21, 0x1EE22),
+    (0x1EE24, 0x1EE24), (0x1EE27, 0x1EE27), (0x1EE29, 0x1EE32), (0x1EE34, 0x1EE37),
+    (0x1EE39, 0x1EE39), (0x1EE3B, 0x1EE3B), (0x1EE42, 0x1EE42), (0x1EE47, 0x1EE47),
+    (0x1EE49, 0x1EE49), (0x1EE4B, 0x1EE4B), (0x1EE4D, 0x1EE4F), (0x1EE51, 0x1EE52),
+    (0x1EE54, 0x1EE54), (0x1EE57, 0x1EE57), (0x1EE59, 0x1EE59), (0x1EE5B, 0x1EE5B),
+    (0x1EE5D, 0x1EE5D), (0x1EE5F, 0x1EE5F), (0x1EE61, 0x1EE62), (0x1EE64, 0x1EE64),
+    (0x1EE67, 0x1EE6A), (0x1EE6C, 0x1EE72), (0x1EE74, 0x1EE77), (0x1EE79, 0x1EE7C),
+    (0x1EE7E, 0x1EE7E), (0x1EE80, 0x1EE89), (0x1EE8B, 0x1EE9B), (0x1EEA1, 0x1EEA3),
+    (0x1EEA5, 0x1EEA9), (0x1EEAB, 0x1EEBB), (0x1EEF0, 0x1EEF1), (0x1F000, 0x1F02B),
+    (0x1F030, 0x1F093), (0x1F0A0, 0x1F0AE), (0x1F0B1, 0x1F0BF), (0x1F0C1, 0x1F0CF),
+    (0x1F0D1, 0x1F0F5), (0x1F100, 0x1F1AD), (0x1F1E6, 0x1F202), (0x1F210, 0x1F23B),
+    (0x1F240, 0x1F248), (0x1F250, 0x1F251), (0x1F260, 0x1F265), (0x1F300, 0x1F6D7),
+    (0x1F6DD, 0x1F6EC), (0x1F6F0, 0x1F6FC), (0x1F700, 0x1F773), (0x1F780, 0x1F7D8),
+    (0x1F7E0, 0x1F7EB), (0x1F7F0, 0x1F7F0), (0x1F800, 0x1F80B), (0x1F810, 0x1F847),
+    (0x1F850, 0x1F859), (0x1F860, 0x1F887), (0x1F890, 0x1F8AD), (0x1F8B0, 0x1F8B1),
+    (0x1F900, 0x1FA53), (0x1FA60, 0x1FA6D), (0x1FA70, 0x1FA74), (0x1FA78, 0x1FA7C),
+    (0x1FA80, 0x1FA86), (0x1FA90, 0x1FAAC), (0x1FAB0, 0x1FABA), (0x1FAC0, 0x1FAC5),
+    (0x1FAD0, 0x1FAD9), (0x1FAE0, 0x1FAE7), (0x1FAF0, 0x1FAF6), (0x1FB00, 0x1FB92),
+    (0x1FB94, 0x1FBCA), (0x1FBF0, 0x1FBF9), (0x20000, 0x2A6DF), (0x2A700, 0x2B738),
+    (0x2B740, 0x2B81D), (0x2B820, 0x2CEA1), (0x2CEB0, 0x2EBE0), (0x2F800, 0x2FA1D),
+    (0x30000, 0x3134A), (0xE0001, 0xE0001), (0xE0020, 0xE007F), (0xE0100, 0xE01EF),
+    (0xF0000, 0xFFFFD), (0x100000, 0x10FFFD),
+];
+
+/// The names of common characters, sorted by name
+#[rustfmt::skip]
+pub(super) const NAMES: &[(&str, char)] = &[
+    ("ACUTE ACCENT", '\u{B4}'),
+    ("ALMOST EQUAL TO", '\u{2248}'),
+    ("BALLOT BOX", '\u{2610}'),
+    ("BALLOT BOX WITH CHECK", '\u{2611}'),
+    ("BALLOT BOX WITH X", '\u{2612}'),
+    ("BALLOT X", '\u{2717}'),
+    ("BLACK STAR", '\u{2605}'),
+    ("BOX DRAWINGS LIGHT HORIZONTAL", '\u{2500}'),
+    ("BOX DRAWINGS LIGHT VERTICAL", '\u{2502}'),
+    ("BROKEN BAR", '\u{A6}'),
+    ("BULLET", '\u{2022}'),
+    ("CARET", '\u{2038}'),
+    ("CEDILLA", '\u{B8}'),
+    ("CENT SIGN", '\u{A2}'),
+    ("CHECK MARK", '\u{2713}'),
+    ("CLAPPING HANDS SIGN", '\u{1F44F}'),
+    ("COMBINING ACUTE ACCENT", '\u{301}'),
+    ("COMBINING BREVE", '\u{306}'),
+    ("COMBINING CARON", '\u{30C}'),
+    ("COMBINING CEDILLA", '\u{327}'),
+    ("COMBINING CIRCUMFLEX ACCENT", '\u{302}'),
+    ("COMBINING DIAERESIS", '\u{308}'),
+    ("COMBINING DOT ABOVE", '\u{307}'),
+    ("COMBINING DOUBLE ACUTE ACCENT", '\u{30B}'),
+    ("COMBINING DOUBLE GRAVE ACCENT", '\u{30F}'),
+    ("COMBINING DOUBLE VERTICAL LINE ABOVE", '\u{30E}'),
+    ("COMBINING GRAVE ACCENT", '\u{300}'),
+    ("COMBINING HOOK ABOVE", '\u{309}'),
+    ("COMBINING MACRON", '\u{304}'),
+    ("COMBINING OGONEK", '\u{328}'),
+    ("COMBINING OVERLINE", '\u{305}'),
+    ("COMBINING RING ABOVE", '\u{30A}'),
+    ("COMBINING TILDE", '\u{303}'),
+    ("COMBINING VERTICAL LINE ABOVE", '\u{30D}'),
+    ("COPYRIGHT SIGN", '\u{A9}'),
+    ("CURRENCY SIGN", '\u{A4}'),
+    ("DAGGER", '\u{2020}'),
+    ("DEGREE SIGN", '\u{B0}'),
+    ("DIAERESIS", '\u{A8}'),
+    ("DIVISION SIGN", '\u{F7}'),
+    ("DOT OPERATOR", '\u{22C5}'),
+    ("DOUBLE DAGGER", '\u{2021}'),
+    ("DOUBLE HIGH-REVERSED-9 QUOTATION MARK", '\u{201F}'),
+    ("DOUBLE LOW LINE", '\u{2017}'),
+    ("DOUBLE LOW-9 QUOTATION MARK", '\u{201E}'),
+    ("DOUBLE PRIME", '\u{2033}'),
+    ("DOUBLE VERTICAL LINE", '\u{2016}'),
+    ("DOWNWARDS ARROW", '\u{2193}'),
+    ("ELEMENT OF", '\u{2208}'),
+    ("EM DASH", '\u{2014}'),
+    ("EMPTY SET", '\u{2205}'),
+    ("EN DASH", '\u{2013}'),
+    ("EURO SIGN", '\u{20AC}'),
+    ("FACE SAVOURING DELICIOUS FOOD", '\u{1F60B}'),
+    ("FACE WITH TEARS OF JOY", '\u{1F602}'),
+    ("FEMININE ORDINAL INDICATOR", '\u{AA}'),
+    ("FIGURE DASH", '\u{2012}'),
+    ("FIRE", '\u{1F525}'),
+    ("FOR ALL", '\u{2200}'),
+    ("GREATER-THAN OR EQUAL TO", '\u{2265}'),
+    ("GREEK CAPITAL LETTER ALPHA", '\u{391}'),
+    ("GREEK CAPITAL LETTER BETA", '\u{392}'),
+    ("GREEK CAPITAL LETTER CHI", '\u{3A7}'),
+    ("GREEK CAPITAL LETTER DELTA", '\u{394}'),
+    ("GREEK CAPITAL LETTER EPSILON", '\u{395}'),
+    ("GREEK CAPITAL LETTER ETA", '\u{397}'),
+    ("GREEK CAPITAL LETTER GAMMA", '\u{393}'),
+    ("GREEK CAPITAL LETTER IOTA", '\u{399}'),
+    ("GREEK CAPITAL LETTER IOTA WITH DIALYTIKA", '\u{3AA}'),
+    ("GREEK CAPITAL LETTER KAPPA", '\u{39A}'),
+    ("GREEK CAPITAL LETTER LAMDA", '\u{39B}'),
+    ("GREEK CAPITAL LETTER MU", '\u{39C}'),
+    ("GREEK CAPITAL LETTER NU", '\u{39D}'),
+    ("GREEK CAPITAL LETTER OMEGA", '\u{3A9}'),
+    ("GREEK CAPITAL LETTER OMICRON", '\u{39F}'),
+    ("GREEK CAPITAL LETTER PHI", '\u{3A6}'),
+    ("GREEK CAPITAL LETTER PI", '\u{3A0}'),
+    ("GREEK CAPITAL LETTER PSI", '\u{3A8}'),
+    ("GREEK CAPITAL LETTER RHO", '\u{3A1}'),
+    ("GREEK CAPITAL LETTER SIGMA", '\u{3A3}'),
+    ("GREEK CAPITAL LETTER TAU", '\u{3A4}'),
+    ("GREEK CAPITAL LETTER THETA", '\u{398}'),
+    ("GREEK CAPITAL LETTER UPSILON", '\u{3A5}'),
+    ("GREEK CAPITAL LETTER UPSILON WITH DIALYTIKA", '\u{3AB}'),
+    ("GREEK CAPITAL LETTER XI", '\u{39E}'),
+    ("GREEK CAPITAL LETTER ZETA", '\u{396}'),
+    ("GREEK SMALL LETTER ALPHA", '\u{3B1}'),
+    ("GREEK SMALL LETTER ALPHA WITH TONOS", '\u{3AC}'),
+    ("GREEK SMALL LETTER BETA", '\u{3B2}'),
+    ("GREEK SMALL LETTER CHI", '\u{3C7}'),
+    ("GREEK SMALL LETTER DELTA", '\u{3B4}'),
+    ("GREEK SMALL LETTER EPSILON", '\u{3B5}'),
+    ("GREEK SMALL LETTER EPSILON WITH TONOS", '\u{3AD}'),
+    ("GREEK SMALL LETTER ETA", '\u{3B7}'),
+    ("GREEK SMALL LETTER ETA WITH TONOS", '\u{3AE}'),
+    ("GREEK SMALL LETTER FINAL SIGMA", '\u{3C2}'),
+    ("GREEK SMALL LETTER GAMMA", '\u{3B3}'),
+    ("GREEK SMALL LETTER IOTA", '\u{3B9}'),
+    ("GREEK SMALL LETTER IOTA WITH TONOS", '\u{3AF}'),
+    ("GREEK SMALL LETTER KAPPA", '\u{3BA}'),
+    ("GREEK SMALL LETTER LAMDA", '\u{3BB}'),
+    ("GREEK SMALL LETTER MU", '\u{3BC}'),
+    ("GREEK SMALL LETTER NU", '\u{3BD}'),
+    ("GREEK SMALL LETTER OMEGA", '\u{3C9}'),
+    ("GREEK SMALL LETTER OMICRON", '\u{3BF}'),
+    ("GREEK SMALL LETTER PHI", '\u{3C6}'),
+    ("GREEK SMALL LETTER PI", '\u{3C0}'),
+    ("GREEK SMALL LETTER PSI", '\u{3C8}'),
+    ("GREEK SMALL LETTER RHO", '\u{3C1}'),
+    ("GREEK SMALL LETTER SIGMA", '\u{3C3}'),
+    ("GREEK SMALL LETTER TAU", '\u{3C4}'),
+    ("GREEK SMALL LETTER THETA", '\u{3B8}'),
+    ("GREEK SMALL LETTER UPSILON", '\u{3C5}'),
+    ("GREEK SMALL LETTER UPSILON WITH DIALYTIKA AND TONOS", '\u{3B0}'),
+    ("GREEK SMALL LETTER XI", '\u{3BE}'),
+    ("GREEK SMALL LETTER ZETA", '\u{3B6}'),
+    ("GRINNING FACE", '\u{1F600}'),
+    ("GRINNING FACE WITH SMILING EYES", '\u{1F601}'),
+    ("HEAVY BALLOT X", '\u{2718}'),
+    ("HEAVY BLACK HEART", '\u{2764}'),
+    ("HEAVY CHECK MARK", '\u{2714}'),
+    ("HORIZONTAL BAR", '\u{2015}'),
+    ("HORIZONTAL ELLIPSIS", '\u{2026}'),
+    ("HYPHEN", '\u{2010}'),
+    ("HYPHENATION POINT", '\u{2027}'),
+    ("IDENTICAL TO", '\u{2261}'),
+    ("INDIAN RUPEE SIGN", '\u{20B9}'),
+    ("INFINITY", '\u{221E}'),
+    ("INTEGRAL", '\u{222B}'),
+    ("INTERSECTION", '\u{2229}'),
+    ("INVERTED EXCLAMATION MARK", '\u{A1}'),
+    ("INVERTED QUESTION MARK", '\u{BF}'),
+    ("LATIN CAPITAL LETTER A WITH ACUTE", '\u{C1}'),
+    ("LATIN CAPITAL LETTER A WITH BREVE", '\u{102}'),
+    ("LATIN CAPITAL LETTER A WITH CIRCUMFLEX", '\u{C2}'),
+    ("LATIN CAPITAL LETTER A WITH DIAERESIS", '\u{C4}'),
+    ("LATIN CAPITAL LETTER A WITH GRAVE", '\u{C0}'),
+    ("LATIN CAPITAL LETTER A WITH MACRON", '\u{100}'),
+    ("LATIN CAPITAL LETTER A WITH OGONEK", '\u{104}'),
+    ("LATIN CAPITAL LETTER A WITH RING ABOVE", '\u{C5}'),
+    ("LATIN CAPITAL LETTER A WITH TILDE", '\u{C3}'),
+    ("LATIN CAPITAL LETTER AE", '\u{C6}'),
+    ("LATIN CAPITAL LETTER C WITH ACUTE", '\u{106}'),
+    ("LATIN CAPITAL LETTER C WITH CARON", '\u{10C}'),
+    ("LATIN CAPITAL LETTER C WITH CEDILLA", '\u{C7}'),
+    ("LATIN CAPITAL LETTER C WITH CIRCUMFLEX", '\u{108}'),
+    ("LATIN CAPITAL LETTER C WITH DOT ABOVE", '\u{10A}'),
+    ("LATIN CAPITAL LETTER D WITH CARON", '\u{10E}'),
+    ("LATIN CAPITAL LETTER D WITH STROKE", '\u{110}'),
+    ("LATIN CAPITAL LETTER E WITH ACUTE", '\u{C9}'),
+    ("LATIN CAPITAL LETTER E WITH BREVE", '\u{114}'),
+    ("LATIN CAPITAL LETTER E WITH CARON", '\u{11A}'),
+    ("LATIN CAPITAL LETTER E WITH CIRCUMFLEX", '\u{CA}'),
+    ("LATIN CAPITAL LETTER E WITH DIAERESIS", '\u{CB}'),
+    ("LATIN CAPITAL LETTER E WITH DOT ABOVE", '\u{116}'),
+    ("LATIN CAPITAL LETTER E WITH GRAVE", '\u{C8}'),
+    ("LATIN CAPITAL LETTER E WITH MACRON", '\u{112}'),
+    ("LATIN CAPITAL LETTER E WITH OGONEK", '\u{118}'),
+    ("LATIN CAPITAL LETTER ENG", '\u{14A}'),
+    ("LATIN CAPITAL LETTER ETH", '\u{D0}'),
+    ("LATIN CAPITAL LETTER G WITH BREVE", '\u{11E}'),
+    ("LATIN CAPITAL LETTER G WITH CEDILLA", '\u{122}'),
+    ("LATIN CAPITAL LETTER G WITH CIRCUMFLEX", '\u{11C}'),
+    ("LATIN CAPITAL LETTER G WITH DOT ABOVE", '\u{120}'),
+    ("LATIN CAPITAL LETTER H WITH CIRCUMFLEX", '\u{124}'),
+    ("LATIN CAPITAL LETTER H WITH STROKE", '\u{126}'),
+    ("LATIN CAPITAL LETTER I WITH ACUTE", '\u{CD}'),
+    ("LATIN CAPITAL LETTER I WITH BREVE", '\u{12C}'),
+    ("LATIN CAPITAL LETTER I WITH CIRCUMFLEX", '\u{CE}'),
+    ("LATIN CAPITAL LETTER I WITH DIAERESIS", '\u{CF}'),
+    ("LATIN CAPITAL LETTER I WITH DOT ABOVE", '\u{130}'),
+    ("LATIN CAPITAL LETTER I WITH GRAVE", '\u{CC}'),
+    ("LATIN CAPITAL LETTER I WITH MACRON", '\u{12A}'),
+    ("LATIN CAPITAL LETTER I WITH OGONEK", '\u{12E}'),
+    ("LATIN CAPITAL LETTER I WITH TILDE", '\u{128}'),
+    ("LATIN CAPITAL LETTER J WITH CIRCUMFLEX", '\u{134}'),
+    ("LATIN CAPITAL LETTER K WITH CEDILLA", '\u{136}'),
+    ("LATIN CAPITAL LETTER L WITH ACUTE", '\u{139}'),
+    ("LATIN CAPITAL LETTER L WITH CARON", '\u{13D}'),
+    ("LATIN CAPITAL LETTER L WITH CEDILLA", '\u{13B}'),
+    ("LATIN CAPITAL LETTER L WITH MIDDLE DOT", '\u{13F}'),
+    ("LATIN CAPITAL LETTER L WITH STROKE", '\u{141}'),
+    ("LATIN CAPITAL LETTER N WITH ACUTE", '\u{143}'),
+    ("LATIN CAPITAL LETTER N WITH CARON", '\u{147}'),
+    ("LATIN CAPITAL LETTER N WITH CEDILLA", '\u{145}'),
+    ("LATIN CAPITAL LETTER N WITH TILDE", '\u{D1}'),
+    ("LATIN CAPITAL LETTER O WITH ACUTE", '\u{D3}'),
+    ("LATIN CAPITAL LETTER O WITH BREVE", '\u{14E}'),
+    ("LATIN CAPITAL LETTER O WITH CIRCUMFLEX", '\u{D4}'),
+    ("LATIN CAPITAL LETTER O WITH DIAERESIS", '\u{D6}'),
+    ("LATIN CAPITAL LETTER O WITH DOUBLE ACUTE", '\u{150}'),
+    ("LATIN CAPITAL LETTER O WITH GRAVE", '\u{D2}'),
+    ("LATIN CAPITAL LETTER O WITH MACRON", '\u{14C}'),
+    ("LATIN CAPITAL LETTER O WITH STROKE", '\u{D8}'),
+    ("LATIN CAPITAL LETTER O WITH TILDE", '\u{D5}'),
+    ("LATIN CAPITAL LETTER R WITH ACUTE", '\u{154}'),
+    ("LATIN CAPITAL LETTER R WITH CARON", '\u{158}'),
+    ("LATIN CAPITAL LETTER R WITH CEDILLA", '\u{156}'),
+    ("LATIN CAPITAL LETTER S WITH ACUTE", '\u{15A}'),
+    ("LATIN CAPITAL LETTER S WITH CARON", '\u{160}'),
+    ("LATIN CAPITAL LETTER S WITH CEDILLA", '\u{15E}'),
+    ("LATIN CAPITAL LETTER S WITH CIRCUMFLEX", '\u{15C}'),
+    ("LATIN CAPITAL LETTER T WITH CARON", '\u{164}'),
+    ("LATIN CAPITAL LETTER T WITH CEDILLA", '\u{162}'),
+    ("LATIN CAPITAL LETTER T WITH STROKE", '\u{166}'),
+    ("LATIN CAPITAL LETTER THORN", '\u{DE}'),
+    ("LATIN CAPITAL LETTER U WITH ACUTE", '\u{DA}'),
+    ("LATIN CAPITAL LETTER U WITH BREVE", '\u{16C}'),
+    ("LATIN CAPITAL LETTER U WITH CIRCUMFLEX", '\u{DB}'),
+    ("LATIN CAPITAL LETTER U WITH DIAERESIS", '\u{DC}'),
+    ("LATIN CAPITAL LETTER U WITH DOUBLE ACUTE", '\u{170}'),
+    ("LATIN CAPITAL LETTER U WITH GRAVE", '\u{D9}'),
+    ("LATIN CAPITAL LETTER U WITH MACRON", '\u{16A}'),
+    ("LATIN CAPITAL LETTER U WITH OGONEK", '\u{172}'),
+    ("LATIN CAPITAL LETTER U WITH RING ABOVE", '\u{16E}'),
+    ("LATIN CAPITAL LETTER U WITH TILDE", '\u{168}'),
+    ("LATIN CAPITAL LETTER W WITH CIRCUMFLEX", '\u{174}'),
+    ("LATIN CAPITAL LETTER Y WITH ACUTE", '\u{DD}'),
+    ("LATIN CAPITAL LETTER Y WITH CIRCUMFLEX", '\u{176}'),
+    ("LATIN CAPITAL LETTER Y WITH DIAERESIS", '\u{178}'),
+    ("LATIN CAPITAL LETTER Z WITH ACUTE", '\u{179}'),
+    ("LATIN CAPITAL LETTER Z WITH CARON", '\u{17D}'),
+    ("LATIN CAPITAL LETTER Z WITH DOT ABOVE", '\u{17B}'),
+    ("LATIN CAPITAL LIGATURE IJ", '\u{132}'),
+    ("LATIN CAPITAL LIGATURE OE", '\u{152}'),
+    ("LATIN SMALL LETTER A WITH ACUTE", '\u{E1}'),
+    ("LATIN SMALL LETTER A WITH BREVE", '\u{103}'),
+    ("LATIN SMALL LETTER A WITH CIRCUMFLEX", '\u{E2}'),
+    ("LATIN SMALL LETTER A WITH DIAERESIS", '\u{E4}'),
+    ("LATIN SMALL LETTER A WITH GRAVE", '\u{E0}'),
+    ("LATIN SMALL LETTER A WITH MACRON", '\u{101}'),
+    ("LATIN SMALL LETTER A WITH OGONEK", '\u{105}'),
+    ("LATIN SMALL LETTER A WITH RING ABOVE", '\u{E5}'),
+    ("LATIN SMALL LETTER A WITH TILDE", '\u{E3}'),
+    ("LATIN SMALL LETTER AE", '\u{E6}'),
+    ("LATIN SMALL LETTER C WITH ACUTE", '\u{107}'),
+    ("LATIN SMALL LETTER C WITH CARON", '\u{10D}'),
+    ("LATIN SMALL LETTER C WITH CEDILLA", '\u{E7}'),
+    ("LATIN SMALL LETTER C WITH CIRCUMFLEX", '\u{109}'),
+    ("LATIN SMALL LETTER C WITH DOT ABOVE", '\u{10B}'),
+    ("LATIN SMALL LETTER D WITH CARON", '\u{10F}'),
+    ("LATIN SMALL LETTER D WITH STROKE", '\u{111}'),
+    ("LATIN SMALL LETTER DOTLESS I", '\u{131}'),
+    ("LATIN SMALL LETTER E WITH ACUTE", '\u{E9}'),
+    ("LATIN SMALL LETTER E WITH BREVE", '\u{115}'),
+    ("LATIN SMALL LETTER E WITH CARON", '\u{11B}'),
+    ("LATIN SMALL LETTER E WITH CIRCUMFLEX", '\u{EA}'),
+    ("LATIN SMALL LETTER E WITH DIAERESIS", '\u{EB}'),
+    ("LATIN SMALL LETTER E WITH DOT ABOVE", '\u{117}'),
+    ("LATIN SMALL LETTER E WITH GRAVE", '\u{E8}'),
+    ("LATIN SMALL LETTER E WITH MACRON", '\u{113}'),
+    ("LATIN SMALL LETTER E WITH OGONEK", '\u{119}'),
+    ("LATIN SMALL LETTER ENG", '\u{14B}'),
+    ("LATIN SMALL LETTER ETH", '\u{F0}'),
+    ("LATIN SMALL LETTER G WITH BREVE", '\u{11F}'),
+    ("LATIN SMALL LETTER G WITH CEDILLA", '\u{123}'),
+    ("LATIN SMALL LETTER G WITH CIRCUMFLEX", '\u{11D}'),
+    ("LATIN SMALL LETTER G WITH DOT ABOVE", '\u{121}'),
+    ("LATIN SMALL LETTER H WITH CIRCUMFLEX", '\u{125}'),
+    ("LATIN SMALL LETTER H WITH STROKE", '\u{127}'),
+    ("LATIN SMALL LETTER I WITH ACUTE", '\u{ED}'),
+    ("LATIN SMALL LETTER I WITH BREVE", '\u{12D}'),
+    ("LATIN SMALL LETTER I WITH CIRCUMFLEX", '\u{EE}'),
+    ("LATIN SMALL LETTER I WITH DIAERESIS", '\u{EF}'),
+    ("LATIN SMALL LETTER I WITH GRAVE", '\u{EC}'),
+    ("LATIN SMALL LETTER I WITH MACRON", '\u{12B}'),
+    ("LATIN SMALL LETTER I WITH OGONEK", '\u{12F}'),
+    ("LATIN SMALL LETTER I WITH TILDE", '\u{129}'),
+    ("LATIN SMALL LETTER J WITH CIRCUMFLEX", '\u{135}'),
+    ("LATIN SMALL LETTER K WITH CEDILLA", '\u{137}'),
+    ("LATIN SMALL LETTER KRA", '\u{138}'),
+    ("LATIN SMALL LETTER L WITH ACUTE", '\u{13A}'),
+    ("LATIN SMALL LETTER L WITH CARON", '\u{13E}'),
+    ("LATIN SMALL LETTER L WITH CEDILLA", '\u{13C}'),
+    ("LATIN SMALL LETTER L WITH MIDDLE DOT", '\u{140}'),
+    ("LATIN SMALL LETTER L WITH STROKE", '\u{142}'),
+    ("LATIN SMALL LETTER LONG S", '\u{17F}'),
+    ("LATIN SMALL LETTER N PRECEDED BY APOSTROPHE", '\u{149}'),
+    ("LATIN SMALL LETTER N WITH ACUTE", '\u{144}'),
+    ("LATIN SMALL LETTER N WITH CARON", '\u{148}'),
+    ("LATIN SMALL LETTER N WITH CEDILLA", '\u{146}'),
+    ("LATIN SMALL LETTER N WITH TILDE", '\u{F1}'),
+    ("LATIN SMALL LETTER O WITH ACUTE", '\u{F3}'),
+    ("LATIN SMALL LETTER O WITH BREVE", '\u{14F}'),
+    ("LATIN SMALL LETTER O WITH CIRCUMFLEX", '\u{F4}'),
+    ("LATIN SMALL LETTER O WITH DIAERESIS", '\u{F6}'),
+    ("LATIN SMALL LETTER O WITH DOUBLE ACUTE", '\u{151}'),
+    ("LATIN SMALL LETTER O WITH GRAVE", '\u{F2}'),
+    ("LATIN SMALL LETTER O WITH MACRON", '\u{14D}'),
+    ("LATIN SMALL LETTER O WITH STROKE", '\u{F8}'),
+    ("LATIN SMALL LETTER O WITH TILDE", '\u{F5}'),
+    ("LATIN SMALL LETTER R WITH ACUTE", '\u{155}'),
+    ("LATIN SMALL LETTER R WITH CARON", '\u{159}'),
+    ("LATIN SMALL LETTER R WITH CEDILLA", '\u{157}'),
+    ("LATIN SMALL LETTER S WITH ACUTE", '\u{15B}'),
+    ("LATIN SMALL LETTER S WITH CARON", '\u{161}'),
+    ("LATIN SMALL LETTER S WITH CEDILLA", '\u{15F}'),
+    ("LATIN SMALL LETTER S WITH CIRCUMFLEX", '\u{15D}'),
+    ("LATIN SMALL LETTER SHARP S", '\u{DF}'),
+    ("LATIN SMALL LETTER T WITH CARON", '\u{165}'),
+    ("LATIN SMALL LETTER T WITH CEDILLA", '\u{163}'),
+    ("LATIN SMALL LETTER T WITH STROKE", '\u{167}'),
+    ("LATIN SMALL LETTER THORN", '\u{FE}'),
+    ("LATIN SMALL LETTER U WITH ACUTE", '\u{FA}'),
+    ("LATIN SMALL LETTER U WITH BREVE", '\u{16D}'),
+    ("LATIN SMALL LETTER U WITH CIRCUMFLEX", '\u{FB}'),
+    ("LATIN SMALL LETTER U WITH DIAERESIS", '\u{FC}'),
+    ("LATIN SMALL LETTER U WITH DOUBLE ACUTE", '\u{171}'),
+    ("LATIN SMALL LETTER U WITH GRAVE", '\u{F9}'),
+    ("LATIN SMALL LETTER U WITH MACRON", '\u{16B}'),
+    ("LATIN SMALL LETTER U WITH OGONEK", '\u{173}'),
+    ("LATIN SMALL LETTER U WITH RING ABOVE", '\u{16F}'),
+    ("LATIN SMALL LETTER U WITH TILDE", '\u{169}'),
+    ("LATIN SMALL LETTER W WITH CIRCUMFLEX", '\u{175}'),
+    ("LATIN SMALL LETTER Y WITH ACUTE", '\u{FD}'),
+    ("LATIN SMALL LETTER Y WITH CIRCUMFLEX", '\u{177}'),
+    ("LATIN SMALL LETTER Y WITH DIAERESIS", '\u{FF}'),
+    ("LATIN SMALL LETTER Z WITH ACUTE", '\u{17A}'),
+    ("LATIN SMALL LETTER Z WITH CARON", '\u{17E}'),
+    ("LATIN SMALL LETTER Z WITH DOT ABOVE", '\u{17C}'),
+    ("LATIN SMALL LIGATURE IJ", '\u{133}'),
+    ("LATIN SMALL LIGATURE OE", '\u{153}'),
+    ("LEFT DOUBLE QUOTATION MARK", '\u{201C}'),
+    ("LEFT RIGHT ARROW", '\u{2194}'),
+    ("LEFT RIGHT DOUBLE ARROW", '\u{21D4}'),
+    ("LEFT SINGLE QUOTATION MARK", '\u{2018}'),
+    ("LEFT-POINTING DOUBLE ANGLE QUOTATION MARK", '\u{AB}'),
+    ("LEFTWARDS ARROW", '\u{2190}'),
+    ("LEFTWARDS DOUBLE ARROW", '\u{21D0}'),
+    ("LESS-THAN OR EQUAL TO", '\u{2264}'),
+    ("LOGICAL AND", '\u{2227}'),
+    ("LOGICAL OR", '\u{2228}'),
+    ("MACRON", '\u{AF}'),
+    ("MASCULINE ORDINAL INDICATOR", '\u{BA}'),
+    ("MICRO SIGN", '\u{B5}'),
+    ("MIDDLE DOT", '\u{B7}'),
+    ("MINUS SIGN", '\u{2212}'),
+    ("MULTIPLICATION SIGN", '\u{D7}'),
+    ("N-ARY SUMMATION", '\u{2211}'),
+    ("NABLA", '\u{2207}'),
+    ("NO-BREAK SPACE", '\u{A0}'),
+    ("NON-BREAKING HYPHEN", '\u{2011}'),
+    ("NOT AN ELEMENT OF", '\u{2209}'),
+    ("NOT EQUAL TO", '\u{2260}'),
+    ("NOT SIGN", '\u{AC}'),
+    ("NUMERO SIGN", '\u{2116}'),
+    ("ONE DOT LEADER", '\u{2024}'),
+    ("PARTIAL DIFFERENTIAL", '\u{2202}'),
+    ("PARTY POPPER", '\u{1F389}'),
+    ("PER MILLE SIGN", '\u{2030}'),
+    ("PER TEN THOUSAND SIGN", '\u{2031}'),
+    ("PILCROW SIGN", '\u{B6}'),
+    ("PLUS-MINUS SIGN", '\u{B1}'),
+    ("POUND SIGN", '\u{A3}'),
+    ("PRIME", '\u{2032}'),
+    ("REGISTERED SIGN", '\u{AE}'),
+    ("RELIEVED FACE", '\u{1F60C}'),
+    ("REPLACEMENT CHARACTER", '\u{FFFD}'),
+    ("REVERSED DOUBLE PRIME", '\u{2036}'),
+    ("REVERSED PRIME", '\u{2035}'),
+    ("REVERSED TRIPLE PRIME", '\u{2037}'),
+    ("RIGHT DOUBLE QUOTATION MARK", '\u{201D}'),
+    ("RIGHT SINGLE QUOTATION MARK", '\u{2019}'),
+    ("RIGHT-POINTING DOUBLE ANGLE QUOTATION MARK", '\u{BB}'),
+    ("RIGHTWARDS ARROW", '\u{2192}'),
+    ("RIGHTWARDS DOUBLE ARROW", '\u{21D2}'),
+    ("ROCKET", '\u{1F680}'),
+    ("RUBLE SIGN", '\u{20BD}'),
+    ("SECTION SIGN", '\u{A7}'),
+    ("SINGLE HIGH-REVERSED-9 QUOTATION MARK", '\u{201B}'),
+    ("SINGLE LEFT-POINTING ANGLE QUOTATION MARK", '\u{2039}'),
+    ("SINGLE LOW-9 QUOTATION MARK", '\u{201A}'),
+    ("SINGLE RIGHT-POINTING ANGLE QUOTATION MARK", '\u{203A}'),
+    ("SLIGHTLY SMILING FACE", '\u{1F642}'),
+    ("SMILING FACE WITH HALO", '\u{1F607}'),
+    ("SMILING FACE WITH HEART-SHAPED EYES", '\u{1F60D}'),
+    ("SMILING FACE WITH HORNS", '\u{1F608}'),
+    ("SMILING FACE WITH OPEN MOUTH", '\u{1F603}'),
+    ("SMILING FACE WITH OPEN MOUTH AND COLD SWEAT", '\u{1F605}'),
+    ("SMILING FACE WITH OPEN MOUTH AND SMILING EYES", '\u{1F604}'),
+    ("SMILING FACE WITH OPEN MOUTH AND TIGHTLY-CLOSED EYES", '\u{1F606}'),
+    ("SMILING FACE WITH SMILING EYES", '\u{1F60A}'),
+    ("SMILING FACE WITH SUNGLASSES", '\u{1F60E}'),
+    ("SMIRKING FACE", '\u{1F60F}'),
+    ("SOFT HYPHEN", '\u{AD}'),
+    ("SQUARE ROOT", '\u{221A}'),
+    ("SUBSET OF", '\u{2282}'),
+    ("SUBSET OF OR EQUAL TO", '\u{2286}'),
+    ("SUPERSCRIPT ONE", '\u{B9}'),
+    ("SUPERSCRIPT THREE", '\u{B3}'),
+    ("SUPERSCRIPT TWO", '\u{B2}'),
+    ("SUPERSET OF", '\u{2283}'),
+    ("SUPERSET OF OR EQUAL TO", '\u{2287}'),
+    ("THERE EXISTS", '\u{2203}'),
+    ("THINKING FACE", '\u{1F914}'),
+    ("THUMBS DOWN SIGN", '\u{1F44E}'),
+    ("THUMBS UP SIGN", '\u{1F44D}'),
+    ("TRADE MARK SIGN", '\u{2122}'),
+    ("TRIANGULAR BULLET", '\u{2023}'),
+    ("TRIPLE PRIME", '\u{2034}'),
+    ("TWO DOT LEADER", '\u{2025}'),
+    ("UNION", '\u{222A}'),
+    ("UP DOWN ARROW", '\u{2195}'),
+    ("UPSIDE-DOWN FACE", '\u{1F643}'),
+    ("UPWARDS ARROW", '\u{2191}'),
+    ("VULGAR FRACTION ONE HALF", '\u{BD}'),
+    ("VULGAR FRACTION ONE QUARTER", '\u{BC}'),
+    ("VULGAR FRACTION THREE QUARTERS", '\u{BE}'),
+    ("WHITE SMILING FACE", '\u{263A}'),
+    ("WHITE STAR", '\u{2606}'),
+    ("WINKING FACE", '\u{1F609}'),
+    ("YEN SIGN", '\u{A5}'),
+];
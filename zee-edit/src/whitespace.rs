@@ -0,0 +1,128 @@
+use ropey::Rope;
+
+use crate::{
+    graphemes::has_final_newline,
+    indent::{self, Indentation},
+};
+
+/// The whitespace a text is inconsistent about, found by [`scan_whitespace`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WhitespaceIssues {
+    pub lf_lines: usize,
+    pub crlf_lines: usize,
+    pub trailing_whitespace_lines: usize,
+    /// The indentation most lines use, if they're indented with both tabs and
+    /// spaces
+    pub mixed_indentation: Option<Indentation>,
+    pub missing_final_newline: bool,
+}
+
+impl WhitespaceIssues {
+    /// A one line summary of the issues, e.g. `mixed LF/CRLF line endings;
+    /// trailing whitespace on 12 lines`, or `None` if there are none
+    pub fn summary(&self) -> Option<String> {
+        let mut issues = Vec::new();
+        if self.lf_lines > 0 && self.crlf_lines > 0 {
+            issues.push("mixed LF/CRLF line endings".to_owned());
+        }
+        if let Some(indentation) = self.mixed_indentation {
+            issues.push(format!(
+                "indented with tabs and spaces, mostly {}",
+                match indentation {
+                    Indentation::Tabs => "tabs",
+                    Indentation::Spaces(_) => "spaces",
+                }
+            ));
+        }
+        if self.trailing_whitespace_lines > 0 {
+            issues.push(format!(
+                "trailing whitespace on {} line{}",
+                self.trailing_whitespace_lines,
+                if self.trailing_whitespace_lines == 1 {
+                    ""
+                } else {
+                    "s"
+                }
+            ));
+        }
+        if self.missing_final_newline {
+            issues.push("no final newline".to_owned());
+        }
+        (!issues.is_empty()).then(|| issues.join("; "))
+    }
+}
+
+/// Scans the first `MAX_SCANNED_LINES` lines of `text` for mixed line endings
+/// and indentation, and for trailing whitespace, counting the lines of each.
+/// The final newline is checked whatever the size of the text.
+pub fn scan_whitespace(text: &Rope) -> WhitespaceIssues {
+    let mut issues = WhitespaceIssues {
+        mixed_indentation: indent::detect_indentation(text)
+            .filter(|detected| detected.mixed)
+            .map(|detected| detected.indentation),
+        missing_final_newline: !has_final_newline(text),
+        ..WhitespaceIssues::default()
+    };
+    for line in text.lines().take(MAX_SCANNED_LINES) {
+        let mut chars = line.chars_at(line.len_chars());
+        let mut last = chars.prev();
+        if last == Some('\n') {
+            last = chars.prev();
+            if last == Some('\r') {
+                issues.crlf_lines += 1;
+                last = chars.prev();
+            } else {
+                issues.lf_lines += 1;
+            }
+        }
+        if matches!(last, Some(character) if character.is_whitespace()) {
+            issues.trailing_whitespace_lines += 1;
+        }
+    }
+    issues
+}
+
+const MAX_SCANNED_LINES: usize = 10_000;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(text: &str) -> Option<String> {
+        scan_whitespace(&Rope::from(text)).summary()
+    }
+
+    #[test]
+    fn clean_text_has_no_issues() {
+        assert_eq!(None, summary(""));
+        assert_eq!(None, summary("fn main() {\n    println!();\n}\n"));
+        assert_eq!(None, summary("a\r\n\tb\r\n"));
+    }
+
+    #[test]
+    fn summarize_whitespace_issues() {
+        let text = Rope::from("one \r\n\ttwo\n  three\t\n\tfour\n\nfive");
+        assert_eq!(
+            WhitespaceIssues {
+                lf_lines: 4,
+                crlf_lines: 1,
+                trailing_whitespace_lines: 2,
+                mixed_indentation: Some(Indentation::Tabs),
+                missing_final_newline: true,
+            },
+            scan_whitespace(&text)
+        );
+        assert_eq!(
+            Some(
+                "mixed LF/CRLF line endings; indented with tabs and spaces, mostly tabs; \
+                 trailing whitespace on 2 lines; no final newline"
+                    .to_owned()
+            ),
+            scan_whitespace(&text).summary()
+        );
+        assert_eq!(
+            Some("trailing whitespace on 1 line".to_owned()),
+            summary("a\nb \n")
+        );
+    }
+}
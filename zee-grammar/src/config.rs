@@ -17,6 +17,14 @@ pub struct ModeConfig {
     pub comment: Option<CommentConfig>,
     pub indentation: IndentationConfig,
     pub grammar: Option<GrammarConfig>,
+    #[serde(default)]
+    pub related_files: Vec<RelatedFilePattern>,
+    #[serde(default)]
+    pub test_files: Vec<TestFilePattern>,
+    /// Characters that are part of words in the mode, besides letters,
+    /// digits and `_`, e.g. `-` in CSS
+    #[serde(default)]
+    pub word_characters: String,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -106,6 +114,102 @@ impl FilenamePattern {
     }
 }
 
+/// A heuristic for finding the counterpart of a file, e.g. the header of a C
+/// source file
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum RelatedFilePattern {
+    /// Swaps the suffix of the file name, `Suffix(".c", ".h")` relates
+    /// `foo.c` to `foo.h` in the same directory
+    Suffix(String, String),
+    /// Relates a module file to the file named after its directory, both ways.
+    /// `Module("mod.rs", ".rs")` relates `editor/mod.rs` and `editor.rs`.
+    Module(String, String),
+}
+
+impl RelatedFilePattern {
+    /// The path of the related file, if the pattern applies. The file may not
+    /// exist.
+    pub fn related_path(&self, path: impl AsRef<Path>) -> Option<PathBuf> {
+        let path = path.as_ref();
+        let file_name = path.file_name().and_then(OsStr::to_str)?;
+        match self {
+            Self::Suffix(ref from, ref to) => {
+                let stem = file_name.strip_suffix(from.as_str())?;
+                Some(path.with_file_name(format!("{}{}", stem, to)))
+            }
+            Self::Module(ref module_file, ref suffix) if file_name == module_file => {
+                let directory = path.parent()?;
+                let directory_name = directory.file_name().and_then(OsStr::to_str)?;
+                Some(directory.with_file_name(format!("{}{}", directory_name, suffix)))
+            }
+            Self::Module(ref module_file, ref suffix) => {
+                let stem = file_name.strip_suffix(suffix.as_str())?;
+                Some(path.with_file_name(stem).join(module_file))
+            }
+        }
+    }
+}
+
+/// A convention for where the tests of a file are, tried in order to toggle
+/// between a file and its tests
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum TestFilePattern {
+    /// Swaps the suffix of the file name, `Suffix(".go", "_test.go")` relates
+    /// `foo.go` and `foo_test.go` both ways
+    Suffix(String, String),
+    /// Adds or removes a prefix of the file name, `Prefix("test_")` relates
+    /// `foo.py` and `test_foo.py`
+    Prefix(String),
+    /// Swaps the innermost directory with one of the names,
+    /// `Directory("src", "tests")` relates `src/foo.rs` and `tests/foo.rs`
+    /// both ways
+    Directory(String, String),
+    /// The tests are in the same file, in the first syntax node of a kind
+    /// with a name. `Inline("mod_item", "tests")` is a Rust `mod tests`.
+    Inline(String, String),
+}
+
+impl TestFilePattern {
+    /// The path of the counterpart of a file, its tests or the file they
+    /// test, if the pattern applies. The file may not exist. Inline tests
+    /// have no path.
+    pub fn counterpart_path(&self, path: impl AsRef<Path>) -> Option<PathBuf> {
+        let path = path.as_ref();
+        let file_name = path.file_name().and_then(OsStr::to_str)?;
+        match self {
+            Self::Suffix(ref source, ref test) => {
+                let name = match file_name.strip_suffix(test.as_str()) {
+                    Some(stem) => format!("{}{}", stem, source),
+                    None => format!("{}{}", file_name.strip_suffix(source.as_str())?, test),
+                };
+                Some(path.with_file_name(name))
+            }
+            Self::Prefix(ref prefix) => {
+                let name = match file_name.strip_prefix(prefix.as_str()) {
+                    Some(name) => name.to_owned(),
+                    None => format!("{}{}", prefix, file_name),
+                };
+                Some(path.with_file_name(name))
+            }
+            Self::Directory(ref source, ref test) => {
+                let components: Vec<_> = path.components().collect();
+                let (index, counterpart) = components.iter().enumerate().rev().skip(1).find_map(
+                    |(index, component)| match component.as_os_str().to_str()? {
+                        name if name == source => Some((index, test)),
+                        name if name == test => Some((index, source)),
+                        _ => None,
+                    },
+                )?;
+                let mut counterpart_path: PathBuf = components[..index].iter().collect();
+                counterpart_path.push(counterpart);
+                counterpart_path.extend(&components[index + 1..]);
+                Some(counterpart_path)
+            }
+            Self::Inline(..) => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename = "Grammar")]
 pub struct GrammarConfig {
@@ -160,3 +264,65 @@ pub fn config_dir() -> Result<PathBuf> {
 }
 
 pub static CONFIG_DIR: Lazy<Result<PathBuf>> = Lazy::new(config_dir);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counterpart(pattern: TestFilePattern, path: &str) -> Option<PathBuf> {
+        pattern.counterpart_path(path)
+    }
+
+    #[test]
+    fn test_file_suffix() {
+        let pattern = || TestFilePattern::Suffix(".go".into(), "_test.go".into());
+        assert_eq!(
+            Some(PathBuf::from("cmd/main_test.go")),
+            counterpart(pattern(), "cmd/main.go")
+        );
+        assert_eq!(
+            Some(PathBuf::from("cmd/main.go")),
+            counterpart(pattern(), "cmd/main_test.go")
+        );
+        assert_eq!(None, counterpart(pattern(), "cmd/main.rs"));
+    }
+
+    #[test]
+    fn test_file_prefix() {
+        let pattern = || TestFilePattern::Prefix("test_".into());
+        assert_eq!(
+            Some(PathBuf::from("app/test_views.py")),
+            counterpart(pattern(), "app/views.py")
+        );
+        assert_eq!(
+            Some(PathBuf::from("app/views.py")),
+            counterpart(pattern(), "app/test_views.py")
+        );
+    }
+
+    #[test]
+    fn test_file_directory() {
+        let pattern = || TestFilePattern::Directory("src".into(), "tests".into());
+        assert_eq!(
+            Some(PathBuf::from("/zee/tests/editor/buffer.rs")),
+            counterpart(pattern(), "/zee/src/editor/buffer.rs")
+        );
+        assert_eq!(
+            Some(PathBuf::from("src/lib.rs")),
+            counterpart(pattern(), "tests/lib.rs")
+        );
+        // The innermost directory is swapped, the file name never is
+        assert_eq!(
+            Some(PathBuf::from("src/zee/tests/src")),
+            counterpart(pattern(), "src/zee/src/src")
+        );
+        assert_eq!(None, counterpart(pattern(), "zee/lib/src"));
+        assert_eq!(
+            None,
+            counterpart(
+                TestFilePattern::Inline("mod_item".into(), "tests".into()),
+                "src/lib.rs"
+            )
+        );
+    }
+}
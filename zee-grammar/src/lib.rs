@@ -5,10 +5,13 @@ mod git;
 
 use anyhow::Result;
 use once_cell::sync::Lazy;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tree_sitter::{Language, Query};
 
-use self::config::{CommentConfig, FilenamePattern, IndentationConfig, ModeConfig};
+use self::config::{
+    CommentConfig, FilenamePattern, IndentationConfig, ModeConfig, RelatedFilePattern,
+    TestFilePattern,
+};
 
 #[derive(Debug)]
 pub struct Mode {
@@ -18,6 +21,9 @@ pub struct Mode {
     pub patterns: Vec<FilenamePattern>,
     pub comment: Option<CommentConfig>,
     pub indentation: IndentationConfig,
+    pub related_files: Vec<RelatedFilePattern>,
+    pub test_files: Vec<TestFilePattern>,
+    pub word_characters: String,
     grammar: LazyGrammar,
 }
 
@@ -31,6 +37,9 @@ impl Mode {
             comment,
             indentation,
             grammar: grammar_config,
+            related_files,
+            test_files,
+            word_characters,
         } = config;
         Self {
             name,
@@ -39,6 +48,9 @@ impl Mode {
             patterns,
             comment,
             indentation,
+            related_files,
+            test_files,
+            word_characters,
             grammar: Lazy::new(Box::new(move || {
                 grammar_config
                     .map(|grammar_config| grammar_config.grammar_id)
@@ -53,6 +65,22 @@ impl Mode {
             .any(|pattern| pattern.matches(filename.as_ref()))
     }
 
+    /// The paths of the files related to `path`, e.g. `foo.h` for `foo.c`,
+    /// according to the mode's heuristics. The files may not exist.
+    pub fn related_files(&self, path: impl AsRef<Path>) -> Vec<PathBuf> {
+        let mut related_files = Vec::new();
+        for related_file in self
+            .related_files
+            .iter()
+            .filter_map(|pattern| pattern.related_path(path.as_ref()))
+        {
+            if !related_files.contains(&related_file) {
+                related_files.push(related_file);
+            }
+        }
+        related_files
+    }
+
     pub fn language(&self) -> Option<Result<Language, &anyhow::Error>> {
         Some(self.grammar()?.map(|parser| parser.language))
     }
@@ -73,6 +101,9 @@ impl Default for Mode {
             patterns: vec![],
             comment: None,
             indentation: Default::default(),
+            related_files: vec![],
+            test_files: vec![],
+            word_characters: String::new(),
             grammar: Lazy::new(Box::new(|| None)),
         }
     }
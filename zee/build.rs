@@ -6,14 +6,12 @@ use zee_grammar::config::ModeConfig;
 
 static DEFAULT_CONFIG_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/config");
 
+// Only the modes are needed to build the parsers, the other settings are
+// validated when the editor starts
 #[derive(Clone, Debug, Deserialize)]
-#[serde(deny_unknown_fields, rename = "Zee")]
+#[serde(rename = "Zee")]
 pub struct EditorConfig {
-    #[serde(default)]
-    pub theme: String,
     pub modes: Vec<ModeConfig>,
-    #[serde(default)]
-    pub trim_trailing_whitespace_on_save: bool,
 }
 
 fn main() -> Result<()> {
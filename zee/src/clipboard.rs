@@ -1,18 +1,208 @@
-use std::sync::Arc;
+use parking_lot::RwLock;
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::VecDeque, env, sync::Arc};
 
 use crate::error::Result;
 
-pub trait Clipboard {
-    fn get_contents(&self) -> Result<String>;
+pub trait ClipboardProvider {
+    /// Returns the contents of the clipboard or `None` if the provider is
+    /// write-only.
+    fn get_contents(&self) -> Result<Option<String>>;
     fn set_contents(&self, contents: String) -> Result<()>;
+
+    /// Returns the contents of the primary selection, set on X11 and Wayland
+    /// by selecting text. `None` if the platform doesn't have one.
+    fn get_primary(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Sets the primary selection, a no-op on platforms without one. If it's
+    /// set by another program, waiting for it is returned to run on the task
+    /// pool rather than block the editor.
+    fn set_primary(&self, _contents: String) -> Result<Option<PendingPrimary>> {
+        Ok(None)
+    }
+}
+
+/// Waits for the program setting the primary selection to finish
+pub type PendingPrimary = Box<dyn FnOnce() -> Result<()> + Send>;
+
+/// Which clipboard provider to use, as set in the configuration file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ClipboardSetting {
+    /// OSC 52 when running over SSH, the native clipboard otherwise
+    Auto,
+    /// The system clipboard if enabled at build time, or one local to the
+    /// editor otherwise
+    Native,
+    /// Terminal escape sequences, write-only
+    Osc52,
+}
+
+impl Default for ClipboardSetting {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ProviderKind {
+    Native,
+    Osc52,
+}
+
+fn select_provider(setting: ClipboardSetting, over_ssh: bool) -> ProviderKind {
+    match setting {
+        ClipboardSetting::Auto if over_ssh => ProviderKind::Osc52,
+        ClipboardSetting::Auto | ClipboardSetting::Native => ProviderKind::Native,
+        ClipboardSetting::Osc52 => ProviderKind::Osc52,
+    }
 }
 
-pub fn create() -> Result<Arc<dyn Clipboard>> {
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "system-clipboard")] {
-            system::create()
-        } else {
-            local::create()
+pub fn create(setting: ClipboardSetting) -> Result<Arc<dyn ClipboardProvider>> {
+    match select_provider(setting, env::var_os("SSH_TTY").is_some()) {
+        ProviderKind::Native => {
+            cfg_if::cfg_if! {
+                if #[cfg(feature = "system-clipboard")] {
+                    system::create()
+                } else {
+                    local::create()
+                }
+            }
+        }
+        ProviderKind::Osc52 => osc52::create(),
+    }
+}
+
+/// The shape of text copied from a buffer, which decides how it's pasted back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentKind {
+    /// A contiguous run of characters, pasted at the cursor
+    Charwise,
+    /// Complete lines, pasted above the cursor's line
+    Linewise,
+    /// The lines of a rectangular selection, pasted at the cursor's column on
+    /// consecutive lines
+    Rectangular,
+}
+
+/// Keeps track of the text recently copied or killed in the editor, most
+/// recent first, for `yank-pop` to cycle through. The clipboard only stores
+/// strings, so the kill ring remembers the kind of the copied content.
+#[derive(Default)]
+pub struct KillRing {
+    entries: RwLock<VecDeque<(String, ContentKind)>>,
+}
+
+impl KillRing {
+    pub fn push(&self, contents: String, kind: ContentKind) {
+        let mut entries = self.entries.write();
+        entries.push_front((contents, kind));
+        entries.truncate(KILL_RING_CAPACITY);
+    }
+
+    /// Appends `contents` to the most recent entry, e.g. for consecutive
+    /// kills, and returns the whole entry. Text appended to anything but
+    /// charwise text starts a new entry.
+    pub fn append(&self, contents: &str) -> String {
+        let mut entries = self.entries.write();
+        match entries.front_mut() {
+            Some((last, ContentKind::Charwise)) => {
+                last.push_str(contents);
+                last.clone()
+            }
+            _ => {
+                entries.push_front((contents.to_owned(), ContentKind::Charwise));
+                entries.truncate(KILL_RING_CAPACITY);
+                contents.to_owned()
+            }
+        }
+    }
+
+    /// Returns the kind of `contents` if they were copied from the editor.
+    /// Anything else was copied by another program and is charwise.
+    pub fn kind_of(&self, contents: &str) -> ContentKind {
+        self.entries
+            .read()
+            .iter()
+            .find(|(entry, _)| entry == contents)
+            .map_or(ContentKind::Charwise, |&(_, kind)| kind)
+    }
+
+    /// The content last copied from the editor, used when pasting with a
+    /// write-only clipboard.
+    pub fn last(&self) -> Option<(String, ContentKind)> {
+        self.entries.read().front().cloned()
+    }
+
+    /// The entry `index` places back from the most recent one, wrapping
+    /// around to the most recent after the oldest
+    pub fn get(&self, index: usize) -> Option<(String, ContentKind)> {
+        let entries = self.entries.read();
+        (!entries.is_empty()).then(|| entries[index % entries.len()].clone())
+    }
+}
+
+const KILL_RING_CAPACITY: usize = 60;
+
+mod osc52 {
+    use std::{env, fs::OpenOptions, io::Write, sync::Arc};
+
+    use super::ClipboardProvider;
+    use crate::error::Result;
+
+    /// Terminals cap the size of escape sequences they accept, larger
+    /// selections are refused rather than silently truncated.
+    const MAX_ENCODED_LENGTH: usize = 100_000;
+
+    pub(crate) fn create() -> Result<Arc<dyn ClipboardProvider>> {
+        Ok(Arc::new(Osc52Clipboard {
+            in_tmux: env::var_os("TMUX").is_some(),
+        }))
+    }
+
+    /// Sets the clipboard of the terminal emulator by writing an OSC 52
+    /// escape sequence to the controlling terminal. This works over SSH, but
+    /// reading the clipboard back is rarely supported.
+    struct Osc52Clipboard {
+        in_tmux: bool,
+    }
+
+    impl ClipboardProvider for Osc52Clipboard {
+        fn get_contents(&self) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        fn set_contents(&self, contents: String) -> Result<()> {
+            self.write_selection('c', contents)
+        }
+
+        fn set_primary(&self, contents: String) -> Result<Option<super::PendingPrimary>> {
+            self.write_selection('p', contents)?;
+            Ok(None)
+        }
+    }
+
+    impl Osc52Clipboard {
+        fn write_selection(&self, target: char, contents: String) -> Result<()> {
+            let encoded = base64::encode(contents);
+            if encoded.len() > MAX_ENCODED_LENGTH {
+                anyhow::bail!(
+                    "Selection is too large to copy using OSC 52 ({} bytes encoded, at most {})",
+                    encoded.len(),
+                    MAX_ENCODED_LENGTH
+                );
+            }
+
+            let sequence = if self.in_tmux {
+                format!("\x1bPtmux;\x1b\x1b]52;{};{}\x07\x1b\\", target, encoded)
+            } else {
+                format!("\x1b]52;{};{}\x07", target, encoded)
+            };
+            let mut terminal = OpenOptions::new().write(true).open("/dev/tty")?;
+            terminal.write_all(sequence.as_bytes())?;
+            terminal.flush()?;
+            Ok(())
         }
     }
 }
@@ -25,7 +215,7 @@ mod system {
 
     use crate::error::Result;
 
-    pub(crate) fn create() -> Result<Arc<dyn super::Clipboard>> {
+    pub(crate) fn create() -> Result<Arc<dyn super::ClipboardProvider>> {
         Ok(SystemClipboard::new().map(std::sync::Arc::new)?)
     }
 
@@ -41,15 +231,157 @@ mod system {
         }
     }
 
-    impl super::Clipboard for SystemClipboard {
-        fn get_contents(&self) -> Result<String> {
-            Ok(self.context.write().get_string_contents()?)
+    impl super::ClipboardProvider for SystemClipboard {
+        fn get_contents(&self) -> Result<Option<String>> {
+            Ok(Some(self.context.write().get_string_contents()?))
         }
 
         fn set_contents(&self, contents: String) -> Result<()> {
             self.context.write().set_string_contents(contents)?;
             Ok(())
         }
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        fn get_primary(&self) -> Result<Option<String>> {
+            primary::get()
+        }
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        fn set_primary(&self, contents: String) -> Result<Option<super::PendingPrimary>> {
+            primary::set(contents)
+        }
+    }
+
+    /// The primary selection on X11 and Wayland, read and set by the
+    /// programs which come with their clipboards, `wl-clipboard` and `xclip`.
+    /// Without a display server or the program it's a no-op.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    mod primary {
+        use std::{
+            env,
+            io::{ErrorKind, Write},
+            process::{Command, Stdio},
+            thread,
+            time::Duration,
+        };
+
+        use crate::{
+            clipboard::PendingPrimary, editor::operations::CancelToken, error::Result, process,
+        };
+
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub(super) enum Display {
+            Wayland,
+            X11,
+        }
+
+        impl Display {
+            fn current() -> Option<Self> {
+                select_display(
+                    env::var_os("WAYLAND_DISPLAY").is_some(),
+                    env::var_os("DISPLAY").is_some(),
+                )
+            }
+
+            fn paste_command(self) -> Command {
+                match self {
+                    Self::Wayland => command("wl-paste", &["--primary", "--no-newline"]),
+                    Self::X11 => command("xclip", &["-selection", "primary", "-out"]),
+                }
+            }
+
+            fn copy_command(self) -> Command {
+                match self {
+                    Self::Wayland => command("wl-copy", &["--primary"]),
+                    Self::X11 => command("xclip", &["-selection", "primary", "-in"]),
+                }
+            }
+        }
+
+        /// Wayland sessions usually run X11 programs too, so Wayland comes first
+        pub(super) fn select_display(wayland: bool, x11: bool) -> Option<Display> {
+            match (wayland, x11) {
+                (true, _) => Some(Display::Wayland),
+                (false, true) => Some(Display::X11),
+                (false, false) => None,
+            }
+        }
+
+        pub(super) fn get() -> Result<Option<String>> {
+            let display = match Display::current() {
+                Some(display) => display,
+                None => return Ok(None),
+            };
+            let output = match display.paste_command().stderr(Stdio::null()).output() {
+                Ok(output) => output,
+                Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+                Err(error) => return Err(error.into()),
+            };
+            // Both programs fail when nothing is selected
+            Ok(output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).into_owned()))
+        }
+
+        pub(super) fn set(contents: String) -> Result<Option<PendingPrimary>> {
+            let display = match Display::current() {
+                Some(display) => display,
+                None => return Ok(None),
+            };
+            // The programs fork to serve the selection in the background, the
+            // process started exits once its input is closed. Its output isn't
+            // read, the background process would keep the pipes open.
+            let mut command = display.copy_command();
+            let program = command.get_program().to_string_lossy().into_owned();
+            let mut child = match command
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+            {
+                Ok(child) => child,
+                Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+                Err(error) => return Err(error.into()),
+            };
+            if let Some(mut stdin) = child.stdin.take() {
+                // Written on a thread so a program not reading its input can
+                // still be killed after the timeout
+                thread::spawn(move || stdin.write_all(contents.as_bytes()));
+            }
+            Ok(Some(Box::new(move || {
+                process::wait_with_timeout(
+                    &mut child,
+                    &program,
+                    Some(COPY_TIMEOUT),
+                    &CancelToken::default(),
+                )?;
+                Ok(())
+            })))
+        }
+
+        /// How long setting the primary selection may take before the program
+        /// is killed. It only needs to read the selection and fork.
+        const COPY_TIMEOUT: Duration = Duration::from_secs(5);
+
+        fn command(program: &str, args: &[&str]) -> Command {
+            let mut command = Command::new(program);
+            command.args(args);
+            command
+        }
+    }
+
+    #[cfg(all(test, unix, not(target_os = "macos")))]
+    mod tests {
+        use super::primary::{select_display, Display};
+
+        #[test]
+        fn select_the_display_server_of_the_primary_selection() {
+            assert_eq!(Some(Display::Wayland), select_display(true, true));
+            assert_eq!(Some(Display::Wayland), select_display(true, false));
+            assert_eq!(Some(Display::X11), select_display(false, true));
+            assert_eq!(None, select_display(false, false));
+        }
     }
 }
 
@@ -58,10 +390,10 @@ mod local {
     use parking_lot::RwLock;
     use std::sync::Arc;
 
-    use super::Clipboard;
+    use super::ClipboardProvider;
     use crate::error::Result;
 
-    pub(crate) fn create() -> Result<Arc<dyn Clipboard>> {
+    pub(crate) fn create() -> Result<Arc<dyn ClipboardProvider>> {
         Ok(Arc::new(LocalClipboard::new()))
     }
 
@@ -77,9 +409,9 @@ mod local {
         }
     }
 
-    impl Clipboard for LocalClipboard {
-        fn get_contents(&self) -> Result<String> {
-            Ok(self.contents.read().clone())
+    impl ClipboardProvider for LocalClipboard {
+        fn get_contents(&self) -> Result<Option<String>> {
+            Ok(Some(self.contents.read().clone()))
         }
 
         fn set_contents(&self, contents: String) -> Result<()> {
@@ -88,3 +420,64 @@ mod local {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_a_provider_for_the_setting() {
+        use ClipboardSetting::*;
+
+        for (setting, over_ssh, expected) in [
+            // Over SSH only the terminal can reach the local clipboard
+            (Auto, true, ProviderKind::Osc52),
+            (Auto, false, ProviderKind::Native),
+            // An explicit setting wins
+            (Native, true, ProviderKind::Native),
+            (Native, false, ProviderKind::Native),
+            (Osc52, true, ProviderKind::Osc52),
+            (Osc52, false, ProviderKind::Osc52),
+        ] {
+            assert_eq!(
+                expected,
+                select_provider(setting, over_ssh),
+                "{:?} over ssh: {}",
+                setting,
+                over_ssh
+            );
+        }
+    }
+
+    #[test]
+    fn kill_ring_keeps_the_latest_entries() {
+        let kill_ring = KillRing::default();
+        assert_eq!(None, kill_ring.get(0));
+
+        kill_ring.push("fn".into(), ContentKind::Charwise);
+        kill_ring.push("line\n".into(), ContentKind::Linewise);
+        assert_eq!(
+            Some(("line\n".into(), ContentKind::Linewise)),
+            kill_ring.last()
+        );
+        assert_eq!(Some(("fn".into(), ContentKind::Charwise)), kill_ring.get(1));
+        assert_eq!(kill_ring.last(), kill_ring.get(2));
+        assert_eq!(ContentKind::Linewise, kill_ring.kind_of("line\n"));
+        assert_eq!(ContentKind::Charwise, kill_ring.kind_of("elsewhere"));
+
+        // Consecutive kills join the latest charwise entry
+        assert_eq!("x", kill_ring.append("x"));
+        assert_eq!("xy", kill_ring.append("y"));
+        assert_eq!(
+            Some(("line\n".into(), ContentKind::Linewise)),
+            kill_ring.get(1)
+        );
+
+        for index in 0..KILL_RING_CAPACITY {
+            kill_ring.push(index.to_string(), ContentKind::Charwise);
+        }
+        assert_eq!(Some(("59".into(), ContentKind::Charwise)), kill_ring.last());
+        assert_eq!(Some(("0".into(), ContentKind::Charwise)), kill_ring.get(59));
+        assert_eq!(kill_ring.last(), kill_ring.get(60));
+    }
+}
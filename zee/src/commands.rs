@@ -0,0 +1,275 @@
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use zi::{terminal::Key, AnyCharacter, Bindings, Component, EndsWith};
+
+/// What a command does when it runs. The variants mirror the kinds of
+/// functions zi accepts as commands.
+pub enum Handler<T: Component> {
+    /// Acts on the component, e.g. moves its cursor
+    Run(fn(&T)),
+    /// Acts on the component, maybe sending it a message
+    Update(fn(&T) -> Option<T::Message>),
+    /// Sends a message to the component
+    Send(fn() -> T::Message),
+    /// Acts on the component with the keys that invoked the command, e.g. to
+    /// insert the character typed
+    WithKeys(fn(&T, &[Key]) -> Option<T::Message>),
+}
+
+impl<T: Component> Handler<T> {
+    fn run(self, component: &T, keys: &[Key]) -> Option<T::Message> {
+        match self {
+            Self::Run(run) => {
+                run(component);
+                None
+            }
+            Self::Update(update) => update(component),
+            Self::Send(send) => Some(send()),
+            Self::WithKeys(run) => run(component, keys),
+        }
+    }
+}
+
+impl<T: Component> Clone for Handler<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Component> Copy for Handler<T> {}
+
+/// A command of a component, registered once with a stable name. Key
+/// bindings refer to commands by name, see [`Keymap`].
+pub struct Command<T: Component> {
+    /// The name the command is bound and invoked by, e.g. `move-forward-word`
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Whether a count, e.g. from a prefix argument, repeats the command
+    pub repeatable: bool,
+    /// When the command asks before running, unless overridden in the
+    /// configuration
+    pub confirm: Confirm,
+    pub handler: Handler<T>,
+}
+
+impl<T: Component> Command<T> {
+    pub fn new(name: &'static str, description: &'static str, handler: Handler<T>) -> Self {
+        Self {
+            name,
+            description,
+            repeatable: false,
+            confirm: Confirm::Never,
+            handler,
+        }
+    }
+
+    pub fn repeatable(self) -> Self {
+        Self {
+            repeatable: true,
+            ..self
+        }
+    }
+
+    pub fn confirms(self, confirm: Confirm) -> Self {
+        Self { confirm, ..self }
+    }
+
+    /// Runs the command on a component as if invoked by `keys`
+    pub fn run(&self, component: &T, keys: &[Key]) -> Option<T::Message> {
+        self.handler.run(component, keys)
+    }
+}
+
+impl<T: Component> Clone for Command<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Component> Copy for Command<T> {}
+
+/// When a destructive command asks for confirmation before it runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Confirm {
+    Never,
+    Always,
+    /// Only if it would discard unsaved changes
+    WhenModified,
+}
+
+impl Confirm {
+    /// Whether to ask, given whether there are unsaved changes
+    pub fn asks(self, modified: bool) -> bool {
+        match self {
+            Self::Never => false,
+            Self::Always => true,
+            Self::WhenModified => modified,
+        }
+    }
+}
+
+/// The confirmations configured for destructive commands
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Confirmations {
+    /// Whether any command asks for confirmation
+    pub enabled: bool,
+    /// When a command asks, by name, overriding its default
+    pub commands: HashMap<String, Confirm>,
+}
+
+impl Confirmations {
+    /// When the command named `name` asks, given its default
+    pub fn policy(&self, name: &str, default: Confirm) -> Confirm {
+        if !self.enabled {
+            return Confirm::Never;
+        }
+        self.commands.get(name).copied().unwrap_or(default)
+    }
+}
+
+impl Default for Confirmations {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            commands: HashMap::new(),
+        }
+    }
+}
+
+/// The command named `name`
+pub fn find<'a, T: Component>(commands: &'a [Command<T>], name: &str) -> Option<&'a Command<T>> {
+    commands.iter().find(|command| command.name == name)
+}
+
+/// The keys which invoke a command
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyBinding {
+    /// A sequence of keys, e.g. `C-x C-s`
+    Keys(Vec<Key>),
+    /// Any sequence ending with a key, e.g. `C-g` to cancel a prefix
+    EndsWith(Key),
+    /// Any single character
+    AnyCharacter,
+}
+
+impl<const N: usize> From<[Key; N]> for KeyBinding {
+    fn from(keys: [Key; N]) -> Self {
+        Self::Keys(keys.into())
+    }
+}
+
+impl From<EndsWith> for KeyBinding {
+    fn from(ends_with: EndsWith) -> Self {
+        Self::EndsWith(ends_with.0)
+    }
+}
+
+impl From<AnyCharacter> for KeyBinding {
+    fn from(_: AnyCharacter) -> Self {
+        Self::AnyCharacter
+    }
+}
+
+/// Maps keys to the names of the commands they invoke
+#[derive(Clone, Debug, Default)]
+pub struct Keymap {
+    bindings: Vec<(&'static str, KeyBinding)>,
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds `keys` to the command named `name`. A command may be bound to
+    /// several key sequences. A sequence bound to a command can't also start
+    /// a longer binding: zi waits for the rest of a prefix with no timeout
+    /// after which it could run a command of its own.
+    pub fn bind(&mut self, name: &'static str, keys: impl Into<KeyBinding>) -> &mut Self {
+        self.bindings.push((name, keys.into()));
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(&'static str, KeyBinding)> {
+        self.bindings.iter()
+    }
+
+    fn bindings_of<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a KeyBinding> {
+        self.bindings
+            .iter()
+            .filter(move |(command, _)| *command == name)
+            .map(|(_, keys)| keys)
+    }
+}
+
+/// Takes a command and its keys before it runs, returning a message for the
+/// component if it handles them itself, e.g. to insert them literally
+pub type Interceptor<T> = fn(&T, &Command<T>, &[Key]) -> Option<<T as Component>::Message>;
+
+/// Adds `commands` to a component's bindings, bound to their keys in `keymap`.
+/// `intercept` is given the keys of each command first, only keys bound to a
+/// command of the component are intercepted.
+pub fn register_intercepted<T: Component>(
+    bindings: &mut Bindings<T>,
+    commands: &[Command<T>],
+    keymap: &Keymap,
+    intercept: Interceptor<T>,
+) {
+    for command in commands {
+        let command = *command;
+        let mut binding = bindings.command(command.name, move |component: &T, keys: &[Key]| {
+            intercept(component, &command, keys).or_else(|| command.run(component, keys))
+        });
+        for keys in keymap.bindings_of(command.name) {
+            binding = match *keys {
+                KeyBinding::Keys(ref keys) => binding.with(keys.iter().copied()),
+                KeyBinding::EndsWith(key) => binding.with(EndsWith(key)),
+                KeyBinding::AnyCharacter => binding.with(AnyCharacter),
+            };
+        }
+    }
+    debug_assert!(
+        keymap
+            .iter()
+            .all(|(name, _)| commands.iter().any(|command| command.name == *name)),
+        "a key is bound to an unknown command"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirm_destructive_commands() {
+        let mut confirmations = Confirmations::default();
+        let kill_buffer = confirmations.policy("kill-buffer", Confirm::WhenModified);
+        assert!(kill_buffer.asks(true));
+        assert!(!kill_buffer.asks(false));
+        assert!(!confirmations
+            .policy("delete-line", Confirm::Never)
+            .asks(true));
+
+        // Configured per command, or turned off altogether
+        confirmations
+            .commands
+            .insert("delete-line".into(), Confirm::Always);
+        confirmations
+            .commands
+            .insert("kill-buffer".into(), Confirm::Never);
+        assert!(confirmations
+            .policy("delete-line", Confirm::Never)
+            .asks(false));
+        assert!(!confirmations
+            .policy("kill-buffer", Confirm::WhenModified)
+            .asks(true));
+        confirmations.enabled = false;
+        assert!(!confirmations
+            .policy("delete-line", Confirm::Never)
+            .asks(true));
+        assert!(!confirmations
+            .policy("delete-file", Confirm::Always)
+            .asks(true));
+    }
+}
@@ -0,0 +1,145 @@
+use ropey::RopeSlice;
+use std::{borrow::Cow, ops::Range};
+use unicode_bidi::BidiInfo;
+
+use zee_edit::ByteIndex;
+
+/// A grapheme of a line, in the order it's displayed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VisualGrapheme {
+    /// The index of the grapheme in logical order
+    pub index: usize,
+    /// Whether the grapheme is part of a right-to-left run
+    pub rtl: bool,
+}
+
+/// Reorders the graphemes of a line for display using the Unicode
+/// bidirectional algorithm, so that right-to-left scripts like Arabic and
+/// Hebrew read correctly. The text itself stays in logical order.
+///
+/// `graphemes` are the byte ranges of the line's graphemes in logical order.
+/// Returns `None` if the line is entirely left-to-right. The line break is
+/// always displayed last.
+pub fn visual_order(
+    line: RopeSlice,
+    graphemes: &[Range<ByteIndex>],
+) -> Option<Vec<VisualGrapheme>> {
+    if is_left_to_right(line) {
+        return None;
+    }
+
+    let line: Cow<str> = line.into();
+    let content_end = line.trim_end_matches(&['\r', '\n'][..]).len();
+    let bidi_info = BidiInfo::new(&line[..content_end], None);
+    if !bidi_info.has_rtl() {
+        return None;
+    }
+
+    let mut order = Vec::with_capacity(graphemes.len());
+    for paragraph in &bidi_info.paragraphs {
+        let (levels, runs) = bidi_info.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            // The graphemes starting in the run, a grapheme gets the level of
+            // its first character
+            let start = graphemes.partition_point(|grapheme| grapheme.start < run.start);
+            let end = graphemes.partition_point(|grapheme| grapheme.start < run.end);
+            let rtl = levels[run.start].is_rtl();
+            let run = (start..end).map(|index| VisualGrapheme { index, rtl });
+            if rtl {
+                order.extend(run.rev());
+            } else {
+                order.extend(run);
+            }
+        }
+    }
+
+    // The line break
+    let start = graphemes.partition_point(|grapheme| grapheme.start < content_end);
+    order.extend((start..graphemes.len()).map(|index| VisualGrapheme { index, rtl: false }));
+    Some(order)
+}
+
+/// Whether a line has no characters from the right-to-left blocks, which
+/// start at Hebrew, so the bidi algorithm can be skipped
+pub fn is_left_to_right(line: RopeSlice) -> bool {
+    line.chars().all(|character| character < '\u{590}')
+}
+
+/// The mirrored form of brackets displayed in a right-to-left run, e.g. `)`
+/// for `(`
+pub fn mirror(character: char) -> Option<char> {
+    Some(match character {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ropey::Rope;
+
+    use super::*;
+    use zee_edit::RopeGraphemes;
+
+    fn display(line: &str) -> String {
+        let text = Rope::from(line);
+        let graphemes: Vec<_> = RopeGraphemes::new(&text.slice(..)).collect();
+        let ranges: Vec<_> = graphemes
+            .iter()
+            .map(|grapheme| grapheme.byte_start..grapheme.byte_end)
+            .collect();
+        match visual_order(text.slice(..), &ranges) {
+            Some(order) => order
+                .into_iter()
+                .map(|VisualGrapheme { index, rtl }| {
+                    let grapheme = graphemes[index].slice.to_string();
+                    match grapheme.chars().next().and_then(mirror) {
+                        Some(mirrored) if rtl => mirrored.to_string(),
+                        _ => grapheme,
+                    }
+                })
+                .collect(),
+            None => line.into(),
+        }
+    }
+
+    #[test]
+    fn left_to_right_lines_are_unchanged() {
+        assert_eq!(
+            None,
+            visual_order(Rope::from("hello (world)\n").slice(..), &[])
+        );
+        assert_eq!("hello (world)\n", display("hello (world)\n"));
+    }
+
+    #[test]
+    fn right_to_left_runs_are_reversed() {
+        // A left-to-right paragraph with a Hebrew word
+        assert_eq!("say םולש now\n", display("say שלום now\n"));
+        // A right-to-left paragraph with an English word, the line break
+        // stays at the end
+        assert_eq!("hello םולש\n", display("שלום hello\n"));
+    }
+
+    #[test]
+    fn numbers_and_neutrals_in_right_to_left_context() {
+        // Numbers keep their left-to-right order, brackets are mirrored
+        assert_eq!("(123) אב", display("בא (123)"));
+        assert_eq!("2022-07-30 םוי", display("יום 2022-07-30"));
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_base() {
+        // Hebrew letters with a vowel point, a grapheme each
+        assert_eq!("\u{5d1}\u{5b8}\u{5d0}", display("\u{5d0}\u{5d1}\u{5b8}"));
+    }
+}
@@ -0,0 +1,765 @@
+use zi::{terminal::Key, AnyCharacter};
+
+use zee_edit::{unicode, Direction};
+use zee_grammar::config::IndentationUnit;
+
+use super::{numeric_argument::NumericArgument, Buffer, Message, WindowLine};
+use crate::{
+    commands::{Command, Handler, Keymap},
+    editor::buffer::{CaseChange, CursorMessage, FilePathFormat},
+    utils::ring_bell,
+};
+
+/// The commands which apply in a buffer
+pub fn commands() -> Vec<Command<Buffer>> {
+    vec![
+        // Cursor movement
+        Command::new(
+            "move-backward-line",
+            "Move up a line",
+            Handler::Run(Buffer::move_up),
+        )
+        .repeatable(),
+        Command::new(
+            "move-forward-line",
+            "Move down a line",
+            Handler::Run(Buffer::move_down),
+        )
+        .repeatable(),
+        Command::new(
+            "move-backward",
+            "Move back a character",
+            Handler::Run(Buffer::move_left),
+        )
+        .repeatable(),
+        Command::new(
+            "move-forward",
+            "Move forward a character",
+            Handler::Run(Buffer::move_right),
+        )
+        .repeatable(),
+        Command::new(
+            "move-backward-word",
+            "Move back a word",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::MoveWord(Direction::Backward, 1))
+            }),
+        )
+        .repeatable(),
+        Command::new(
+            "move-forward-word",
+            "Move forward a word",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::MoveWord(Direction::Forward, 1))
+            }),
+        )
+        .repeatable(),
+        Command::new(
+            "move-backward-paragraph",
+            "Move back a paragraph",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::MoveParagraph(Direction::Backward, 1))
+            }),
+        )
+        .repeatable(),
+        Command::new(
+            "move-forward-paragraph",
+            "Move forward a paragraph",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::MoveParagraph(Direction::Forward, 1))
+            }),
+        )
+        .repeatable(),
+        Command::new(
+            "move-backward-page-break",
+            "Move back to the previous form feed",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::MovePageBreak(Direction::Backward, 1))
+            }),
+        )
+        .repeatable(),
+        Command::new(
+            "move-forward-page-break",
+            "Move forward to the next form feed",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::MovePageBreak(Direction::Forward, 1))
+            }),
+        )
+        .repeatable(),
+        Command::new(
+            "move-to-matching-bracket",
+            "Move to the bracket matching the one under the cursor",
+            Handler::Run(|this: &Buffer| this.properties.cursor.move_to_matching_bracket()),
+        ),
+        Command::new(
+            "move-to-last-edit",
+            "Go back to where the text was last edited",
+            Handler::Run(|this: &Buffer| this.properties.cursor.move_to_last_edit()),
+        ),
+        Command::new(
+            "move-forward-todo",
+            "Move to the next keyword like `TODO` in a comment",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::MoveTodo(Direction::Forward, 1))
+            }),
+        )
+        .repeatable(),
+        Command::new(
+            "move-backward-todo",
+            "Move to the previous keyword like `TODO` in a comment",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::MoveTodo(Direction::Backward, 1))
+            }),
+        )
+        .repeatable(),
+        Command::new(
+            "move-page-down",
+            "Scroll down a page",
+            Handler::Send(|| Message::MovePage(Direction::Forward)),
+        ),
+        Command::new(
+            "move-page-up",
+            "Scroll up a page",
+            Handler::Send(|| Message::MovePage(Direction::Backward)),
+        ),
+        Command::new(
+            "move-start-of-line",
+            "Move to the start of the line",
+            Handler::Run(Buffer::move_start_of_line),
+        ),
+        Command::new(
+            "move-end-of-line",
+            "Move to the end of the line",
+            Handler::Run(Buffer::move_end_of_line),
+        ),
+        Command::new(
+            "move-start-of-buffer",
+            "Move to the start of the buffer",
+            Handler::Run(Buffer::move_start_of_buffer),
+        ),
+        Command::new(
+            "move-end-of-buffer",
+            "Move to the end of the buffer",
+            Handler::Run(Buffer::move_end_of_buffer),
+        ),
+        // Editing
+        Command::new(
+            "delete-forward",
+            "Delete the character after the cursor",
+            Handler::Run(Buffer::delete_forward),
+        ),
+        Command::new(
+            "delete-backward",
+            "Delete the character before the cursor",
+            Handler::Update(|this: &Buffer| {
+                if this.viewing_edit_tree {
+                    Some(Message::DeleteRevisionDigit)
+                } else {
+                    this.delete_backward();
+                    None
+                }
+            }),
+        ),
+        Command::new(
+            "delete-line",
+            "Delete the current line",
+            Handler::Run(Buffer::delete_line),
+        ),
+        Command::new(
+            "join-lines",
+            "Join the next line, or the selected lines, onto the current line",
+            Handler::Run(|this: &Buffer| this.properties.cursor.join_lines()),
+        ),
+        Command::new(
+            "duplicate",
+            "Duplicate the selection, or the current line below itself",
+            Handler::Run(|this: &Buffer| this.properties.cursor.duplicate()),
+        ),
+        Command::new(
+            "kill-line",
+            "Kill to the end of the line, or the newline at the end of a line",
+            Handler::Run(|this: &Buffer| this.properties.cursor.kill_line()),
+        ),
+        Command::new(
+            "delete-forward-word",
+            "Delete the word after the cursor",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::DeleteWord(Direction::Forward))
+            }),
+        ),
+        Command::new(
+            "delete-backward-word",
+            "Delete the word before the cursor",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::DeleteWord(Direction::Backward))
+            }),
+        ),
+        Command::new(
+            "transpose-chars",
+            "Swap the characters around the cursor and move past them",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .send_cursor(CursorMessage::TransposeChars)
+            }),
+        ),
+        Command::new(
+            "insert-new-line",
+            "Insert a new line",
+            Handler::Update(|this: &Buffer| {
+                if this.viewing_edit_tree {
+                    Some(Message::CheckoutRevision)
+                } else {
+                    this.insert_new_line();
+                    None
+                }
+            }),
+        ),
+        Command::new(
+            "insert-new-line-after",
+            "Insert a new line after the cursor",
+            Handler::Run(|this: &Buffer| this.properties.cursor.insert_char('\n', false)),
+        ),
+        Command::new(
+            "insert-tab",
+            "Indent with a tab or spaces",
+            Handler::Run(|this: &Buffer| this.properties.cursor.insert_tab()),
+        ),
+        Command::new(
+            "universal-argument",
+            "Give the next command a count, 4 or the digits typed",
+            Handler::Send(|| Message::NumericArgument(NumericArgument::new())),
+        ),
+        Command::new(
+            "insert-date",
+            "Insert the current date",
+            Handler::Run(|this: &Buffer| this.properties.cursor.insert_date()),
+        ),
+        Command::new(
+            "eval-and-replace",
+            "Replace the selection, or the expression at the cursor, with its value",
+            Handler::Run(|this: &Buffer| this.properties.cursor.evaluate_expression()),
+        ),
+        Command::new(
+            "tabify",
+            "Indent the selected lines, or the whole buffer, with tabs",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .convert_indentation(Some(IndentationUnit::Tab))
+            }),
+        ),
+        Command::new(
+            "untabify",
+            "Indent the selected lines, or the whole buffer, with spaces",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .convert_indentation(Some(IndentationUnit::Space))
+            }),
+        ),
+        Command::new(
+            "indent-lines",
+            "Indent the selected lines, or the current line, by one level",
+            Handler::Run(|this: &Buffer| this.properties.cursor.indent_lines()),
+        ),
+        Command::new(
+            "unindent-lines",
+            "Unindent the selected lines, or the current line, by one level",
+            Handler::Run(|this: &Buffer| this.properties.cursor.unindent_lines()),
+        ),
+        Command::new(
+            "toggle-comment",
+            "Comment out the selected lines, or the current line, or uncomment them",
+            Handler::Run(|this: &Buffer| this.properties.cursor.toggle_comment()),
+        ),
+        Command::new(
+            "upcase",
+            "Convert the selection, or the word at the cursor, to uppercase",
+            Handler::Run(|this: &Buffer| this.properties.cursor.change_case(CaseChange::Upcase)),
+        ),
+        Command::new(
+            "downcase",
+            "Convert the selection, or the word at the cursor, to lowercase",
+            Handler::Run(|this: &Buffer| this.properties.cursor.change_case(CaseChange::Downcase)),
+        ),
+        Command::new(
+            "capitalize",
+            "Capitalize the words of the selection, or the word at the cursor",
+            Handler::Run(|this: &Buffer| {
+                this.properties.cursor.change_case(CaseChange::Capitalize)
+            }),
+        ),
+        Command::new(
+            "clean-up-indentation",
+            "Indent the selected lines, or the whole buffer, as the buffer is indented",
+            Handler::Run(|this: &Buffer| this.properties.cursor.convert_indentation(None)),
+        ),
+        Command::new(
+            "increment-number",
+            "Increment the number under or after the cursor",
+            Handler::Run(|this: &Buffer| this.properties.cursor.increment_number(1)),
+        ),
+        Command::new(
+            "decrement-number",
+            "Decrement the number under or after the cursor",
+            Handler::Run(|this: &Buffer| this.properties.cursor.increment_number(-1)),
+        ),
+        Command::new(
+            "sum-numbers",
+            "Show the total of the numbers in the selection",
+            Handler::Run(|this: &Buffer| this.properties.cursor.sum_numbers(false)),
+        ),
+        Command::new(
+            "insert-sum-of-numbers",
+            "Insert the total of the numbers in the selection",
+            Handler::Run(|this: &Buffer| this.properties.cursor.sum_numbers(true)),
+        ),
+        Command::new(
+            "insert-digraph",
+            "Insert an accented character, e.g. `C-x 8 ' e` for `é`",
+            Handler::WithKeys(|this: &Buffer, keys| {
+                if let &[.., Key::Char(accent), Key::Char(base)] = keys {
+                    if let Some(character) = unicode::compose_digraph(accent, base) {
+                        this.properties.cursor.insert_char(character, true);
+                    }
+                }
+                None
+            }),
+        ),
+        Command::new(
+            "insert-character",
+            "Insert the character typed",
+            Handler::WithKeys(|this: &Buffer, keys| match *keys {
+                // Revision numbers are typed while viewing the edit tree
+                [Key::Char(character)] if this.viewing_edit_tree => {
+                    if character.is_ascii_digit() {
+                        Some(Message::TypeRevisionDigit(character))
+                    } else {
+                        ring_bell();
+                        None
+                    }
+                }
+                [Key::Char(character)] if character != '\n' => {
+                    this.properties.cursor.insert_char(character, true);
+                    None
+                }
+                _ => None,
+            }),
+        ),
+        // Selections
+        Command::new(
+            "begin-selection",
+            "Start selecting text at the cursor",
+            Handler::Run(|this: &Buffer| this.properties.cursor.begin_selection()),
+        ),
+        Command::new(
+            "begin-rectangle",
+            "Start a rectangular selection, extended with the arrow keys",
+            Handler::Run(|this: &Buffer| this.properties.cursor.begin_rectangle()),
+        ),
+        Command::new(
+            "insert-at-line-ends",
+            "Type at the end of every selected line",
+            Handler::Run(|this: &Buffer| this.properties.cursor.insert_at_line_ends()),
+        ),
+        Command::new(
+            "insert-at-line-starts",
+            "Type at the start of every selected line",
+            Handler::Run(|this: &Buffer| this.properties.cursor.insert_at_line_starts()),
+        ),
+        Command::new(
+            "add-cursor-below",
+            "Add a cursor on the line below",
+            Handler::Run(|this: &Buffer| this.properties.cursor.add_cursor_below()),
+        ),
+        Command::new(
+            "add-cursor-above",
+            "Add a cursor on the line above",
+            Handler::Run(|this: &Buffer| this.properties.cursor.add_cursor_above()),
+        ),
+        Command::new(
+            "add-cursor-at-next-match",
+            "Add a cursor after the next occurrence of the selection or word",
+            Handler::Run(|this: &Buffer| this.properties.cursor.add_cursor_at_next_match()),
+        ),
+        Command::new(
+            "select-all",
+            "Select the whole buffer",
+            Handler::Run(|this: &Buffer| this.properties.cursor.select_all()),
+        ),
+        Command::new(
+            "copy-selection",
+            "Copy the selection to the clipboard",
+            Handler::Run(|this: &Buffer| this.properties.cursor.copy_selection_to_clipboard()),
+        ),
+        Command::new(
+            "cut-selection",
+            "Cut the selection to the clipboard",
+            Handler::Run(|this: &Buffer| this.properties.cursor.cut_selection_to_clipboard()),
+        ),
+        Command::new(
+            "paste-clipboard",
+            "Paste from the clipboard",
+            Handler::Run(|this: &Buffer| this.properties.cursor.paste_from_clipboard()),
+        ),
+        Command::new(
+            "yank-pop",
+            "Replace the text just pasted with the previous entry of the kill ring",
+            Handler::Run(|this: &Buffer| this.properties.cursor.yank_pop()),
+        ),
+        Command::new(
+            "copy-file-path",
+            "Copy the absolute path of the file",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .copy_file_path(FilePathFormat::Absolute)
+            }),
+        ),
+        Command::new(
+            "copy-relative-file-path",
+            "Copy the path of the file relative to the repository",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .copy_file_path(FilePathFormat::Relative)
+            }),
+        ),
+        Command::new(
+            "copy-file-path-with-line",
+            "Copy the relative path of the file with the cursor's line",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .copy_file_path(FilePathFormat::RelativeWithLine)
+            }),
+        ),
+        Command::new(
+            "paste-primary-selection",
+            "Paste from the primary selection",
+            Handler::Run(|this: &Buffer| this.properties.cursor.paste_from_primary_selection()),
+        ),
+        // Undo / Redo
+        Command::new(
+            "undo",
+            "Undo the last edit",
+            Handler::Run(|this: &Buffer| this.properties.cursor.undo()),
+        ),
+        Command::new(
+            "redo",
+            "Redo the last undone edit",
+            Handler::Run(|this: &Buffer| this.properties.cursor.redo()),
+        ),
+        // Files
+        Command::new(
+            "save-buffer",
+            "Save the buffer to its file",
+            Handler::Run(|this: &Buffer| this.properties.cursor.save()),
+        ),
+        Command::new(
+            "toggle-autosave",
+            "Save the buffer automatically once it's idle after an edit",
+            Handler::Run(|this: &Buffer| this.properties.cursor.toggle_autosave()),
+        ),
+        Command::new(
+            "enable-all-features",
+            "Turn on syntax parsing and editing for a large file",
+            Handler::Run(|this: &Buffer| this.properties.cursor.enable_all_features()),
+        ),
+        // View
+        Command::new(
+            "center-cursor-visually",
+            "Scroll so the cursor line is in the middle of the window",
+            Handler::Send(|| Message::CenterCursorVisually),
+        ),
+        Command::new(
+            "move-to-window-line",
+            "Move the cursor to the middle, top or bottom line of the window",
+            Handler::Send(|| Message::MoveToWindowLine),
+        ),
+        Command::new(
+            "scroll-cursor-to-top",
+            "Scroll so the cursor line is the top line of the window",
+            Handler::Send(|| Message::ScrollCursorLineTo(WindowLine::Top)),
+        ),
+        Command::new(
+            "scroll-cursor-to-bottom",
+            "Scroll so the cursor line is the bottom line of the window",
+            Handler::Send(|| Message::ScrollCursorLineTo(WindowLine::Bottom)),
+        ),
+        Command::new(
+            "toggle-centered-cursor",
+            "Keep the cursor line in the middle of the window while moving",
+            Handler::Send(|| Message::ToggleCenteredCursor),
+        ),
+        Command::new(
+            "toggle-sticky-scopes",
+            "Pin the first lines of the enclosing scopes to the top of the window",
+            Handler::Send(|| Message::ToggleStickyScopes),
+        ),
+        Command::new(
+            "toggle-symbol-highlight",
+            "Highlight the occurrences of the symbol at the cursor",
+            Handler::Send(|| Message::ToggleSymbolHighlight),
+        ),
+        Command::new(
+            "toggle-unsaved-lines",
+            "Mark the lines changed since the last save in the gutter",
+            Handler::Send(|| Message::ToggleUnsavedLines),
+        ),
+        // Spell checking
+        Command::new(
+            "toggle-spell-check",
+            "Underline misspelled words",
+            Handler::Send(|| Message::ToggleSpellCheck),
+        ),
+        Command::new(
+            "spell-suggestions",
+            "Pick a correction of the misspelled word at the cursor",
+            Handler::Send(|| Message::SpellSuggestions),
+        ),
+        Command::new(
+            "add-to-dictionary",
+            "Add the word at the cursor to the personal dictionary",
+            Handler::Send(|| Message::AddToDictionary),
+        ),
+        // Structural editing with the syntax tree
+        Command::new(
+            "select-next-syntax-node",
+            "Select the next sibling of the selected syntax node",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .select_syntax_sibling(Direction::Forward)
+            }),
+        ),
+        Command::new(
+            "select-previous-syntax-node",
+            "Select the previous sibling of the selected syntax node",
+            Handler::Run(|this: &Buffer| {
+                this.properties
+                    .cursor
+                    .select_syntax_sibling(Direction::Backward)
+            }),
+        ),
+        Command::new(
+            "select-parent-syntax-node",
+            "Select the parent of the selected syntax node",
+            Handler::Run(|this: &Buffer| this.properties.cursor.select_syntax_parent()),
+        ),
+        Command::new(
+            "drag-syntax-node-forward",
+            "Swap the selected syntax node with the next one",
+            Handler::Run(|this: &Buffer| {
+                this.properties.cursor.drag_syntax_node(Direction::Forward)
+            }),
+        ),
+        Command::new(
+            "drag-syntax-node-backward",
+            "Swap the selected syntax node with the previous one",
+            Handler::Run(|this: &Buffer| {
+                this.properties.cursor.drag_syntax_node(Direction::Backward)
+            }),
+        ),
+        Command::new(
+            "describe-position",
+            "Describe the character under the cursor and where it is",
+            Handler::Send(|| Message::DescribePosition),
+        ),
+        // View edit tree
+        Command::new(
+            "toggle-edit-tree",
+            "Show or hide the edit tree",
+            Handler::Send(|| Message::ToggleEditTree),
+        ),
+        Command::new(
+            "toggle-diff",
+            "Show or hide the diff against the saved file",
+            Handler::Send(|| Message::ToggleDiff),
+        ),
+        Command::new(
+            "clear-selection",
+            "Clear the selection, or close the edit tree or diff",
+            Handler::Update(|this: &Buffer| {
+                if this.viewing_edit_tree || this.saved_diff.is_some() {
+                    Some(Message::ClearSelection)
+                } else {
+                    this.properties.cursor.clear_selection();
+                    None
+                }
+            }),
+        ),
+    ]
+}
+
+/// The default key bindings of the buffer's commands
+pub fn keymap() -> Keymap {
+    use Key::*;
+
+    let mut keymap = Keymap::new();
+    keymap
+        // Cursor movement
+        //
+        // TODO: Add Alt + Left / Right / Up / Down alternative key bindings
+        //       For this to be possible, zi should support Alt + a key, not just char
+        .bind("move-backward-line", [Ctrl('p')])
+        .bind("move-backward-line", [Up])
+        .bind("move-forward-line", [Ctrl('n')])
+        .bind("move-forward-line", [Down])
+        .bind("move-backward", [Ctrl('b')])
+        .bind("move-backward", [Left])
+        .bind("move-forward", [Ctrl('f')])
+        .bind("move-forward", [Right])
+        .bind("move-backward-word", [Alt('b')])
+        .bind("move-forward-word", [Alt('f')])
+        .bind("move-backward-paragraph", [Alt('p')])
+        .bind("move-forward-paragraph", [Alt('n')])
+        .bind("move-backward-page-break", [Ctrl('x'), Char('[')])
+        .bind("move-forward-page-break", [Ctrl('x'), Char(']')])
+        .bind("move-to-matching-bracket", [Ctrl('c'), Char('%')])
+        .bind("move-to-last-edit", [Ctrl('c'), Char('j')])
+        .bind("move-forward-todo", [Ctrl('c'), Char('t'), Char('n')])
+        .bind("move-backward-todo", [Ctrl('c'), Char('t'), Char('p')])
+        .bind("move-page-down", [Ctrl('v')])
+        .bind("move-page-down", [PageDown])
+        .bind("move-page-up", [Alt('v')])
+        .bind("move-page-up", [PageUp])
+        .bind("move-start-of-line", [Ctrl('a')])
+        .bind("move-start-of-line", [Home])
+        .bind("move-end-of-line", [Ctrl('e')])
+        .bind("move-end-of-line", [End])
+        .bind("move-start-of-buffer", [Alt('<')])
+        .bind("move-end-of-buffer", [Alt('>')])
+        // Editing
+        .bind("delete-forward", [Ctrl('d')])
+        .bind("delete-forward", [Delete])
+        .bind("delete-backward", [Backspace])
+        .bind("kill-line", [Ctrl('k')])
+        .bind("join-lines", [Alt('^')])
+        .bind("duplicate", [Ctrl('c'), Char('d')])
+        .bind("delete-forward-word", [Alt('d')])
+        .bind("delete-backward-word", [Ctrl('x'), Backspace])
+        .bind("transpose-chars", [Ctrl('t')])
+        .bind("insert-new-line", [Char('\n')])
+        .bind("insert-new-line-after", [Ctrl('o')])
+        .bind("insert-tab", [Char('\t')])
+        .bind("insert-date", [Ctrl('c'), Char('i'), Char('d')])
+        .bind("universal-argument", [Ctrl('u')])
+        .bind("eval-and-replace", [Ctrl('c'), Char('e')])
+        .bind("tabify", [Ctrl('c'), Char('x'), Char('t')])
+        .bind("untabify", [Ctrl('c'), Char('x'), Char('u')])
+        .bind("clean-up-indentation", [Ctrl('c'), Char('x'), Char('x')])
+        .bind("indent-lines", [Ctrl('x'), Char('\t')])
+        .bind("unindent-lines", [Ctrl('x'), BackTab])
+        .bind("toggle-comment", [Alt(';')])
+        .bind("upcase", [Alt('u')])
+        .bind("downcase", [Alt('l')])
+        .bind("capitalize", [Alt('c')])
+        .bind("increment-number", [Ctrl('c'), Char('+')])
+        .bind("decrement-number", [Ctrl('c'), Char('-')])
+        .bind("sum-numbers", [Ctrl('c'), Char('n'), Char('s')])
+        .bind("insert-sum-of-numbers", [Ctrl('c'), Char('n'), Char('i')])
+        .bind("insert-character", AnyCharacter)
+        // Selections
+        .bind("begin-selection", [Null])
+        .bind("begin-selection", [Ctrl(' ')])
+        .bind("begin-rectangle", [Ctrl('x'), Char(' ')])
+        .bind("insert-at-line-ends", [Ctrl('c'), Char('l'), Char('e')])
+        .bind("insert-at-line-starts", [Ctrl('c'), Char('l'), Char('a')])
+        .bind("add-cursor-below", [Ctrl('c'), Char('m'), Char('n')])
+        .bind("add-cursor-above", [Ctrl('c'), Char('m'), Char('p')])
+        .bind(
+            "add-cursor-at-next-match",
+            [Ctrl('c'), Char('m'), Char('m')],
+        )
+        .bind("select-all", [Ctrl('x'), Char('h')])
+        .bind("copy-selection", [Alt('w')])
+        .bind("cut-selection", [Ctrl('w')])
+        .bind("paste-clipboard", [Ctrl('y')])
+        .bind("yank-pop", [Alt('y')])
+        .bind("copy-file-path", [Ctrl('c'), Char('f'), Char('p')])
+        .bind("copy-relative-file-path", [Ctrl('c'), Char('f'), Char('r')])
+        .bind(
+            "copy-file-path-with-line",
+            [Ctrl('c'), Char('f'), Char('l')],
+        )
+        .bind("paste-primary-selection", [Ctrl('x'), Ctrl('y')])
+        // Undo / Redo
+        .bind("undo", [Ctrl('_')])
+        .bind("undo", [Ctrl('z')])
+        .bind("undo", [Ctrl('/')])
+        .bind("redo", [Alt('_')])
+        // Files
+        .bind("save-buffer", [Ctrl('x'), Ctrl('s')])
+        .bind("save-buffer", [Ctrl('x'), Char('s')])
+        .bind("toggle-autosave", [Ctrl('x'), Char('a')])
+        .bind("enable-all-features", [Ctrl('x'), Ctrl('q')])
+        // View
+        .bind("center-cursor-visually", [Ctrl('l')])
+        .bind("move-to-window-line", [Alt('r')])
+        .bind("scroll-cursor-to-top", [Ctrl('c'), Char('w'), Char('t')])
+        .bind("scroll-cursor-to-bottom", [Ctrl('c'), Char('w'), Char('b')])
+        .bind("toggle-centered-cursor", [Ctrl('c'), Char('w'), Char('c')])
+        .bind("toggle-sticky-scopes", [Ctrl('c'), Char('w'), Char('s')])
+        .bind("toggle-symbol-highlight", [Ctrl('c'), Char('h')])
+        .bind("toggle-unsaved-lines", [Ctrl('c'), Char('u')])
+        // Spell checking
+        .bind("toggle-spell-check", [Ctrl('c'), Char('s'), Char('s')])
+        .bind("spell-suggestions", [Ctrl('c'), Char('s'), Char('c')])
+        .bind("add-to-dictionary", [Ctrl('c'), Char('s'), Char('a')])
+        // Structural editing with the syntax tree
+        .bind("select-next-syntax-node", [Ctrl('c'), Char('y'), Char('n')])
+        .bind(
+            "select-previous-syntax-node",
+            [Ctrl('c'), Char('y'), Char('p')],
+        )
+        .bind(
+            "select-parent-syntax-node",
+            [Ctrl('c'), Char('y'), Char('u')],
+        )
+        .bind(
+            "drag-syntax-node-forward",
+            [Ctrl('c'), Char('y'), Char('f')],
+        )
+        .bind(
+            "drag-syntax-node-backward",
+            [Ctrl('c'), Char('y'), Char('b')],
+        )
+        .bind("describe-position", [Ctrl('x'), Char('=')])
+        // View edit tree
+        .bind("toggle-edit-tree", [Ctrl('x'), Char('u')])
+        .bind("toggle-diff", [Ctrl('c'), Char('=')])
+        .bind("clear-selection", [Ctrl('g')]);
+
+    // Insert an accented character, e.g. `C-x 8 ' e` for `é`
+    for &(accent, bases, _) in unicode::DIGRAPHS {
+        for base in bases.chars() {
+            keymap.bind(
+                "insert-digraph",
+                [Ctrl('x'), Char('8'), Char(accent), Char(base)],
+            );
+        }
+    }
+    keymap
+}
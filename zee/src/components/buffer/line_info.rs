@@ -1,10 +1,17 @@
+use std::{ops::Range, rc::Rc};
 use zi::{Canvas, Component, ComponentLink, Layout, Rect, ShouldRender, Style};
 
+use zee_edit::LineIndex;
+
 #[derive(Clone, PartialEq)]
 pub struct Properties {
     pub style: Style,
     pub line_offset: usize,
     pub num_lines: usize,
+    /// The lines changed since the buffer was last saved, marked with
+    /// `unsaved_style`
+    pub unsaved_lines: Rc<Vec<Range<LineIndex>>>,
+    pub unsaved_style: Style,
 }
 
 pub struct LineInfo {
@@ -41,22 +48,25 @@ impl Component for LineInfo {
                     style,
                     line_offset,
                     num_lines,
+                    ref unsaved_lines,
+                    unsaved_style,
                 },
             frame,
         } = *self;
 
         let mut canvas = Canvas::new(frame.size);
         for line_index in 0..frame.size.height {
-            canvas.draw_str(
-                0,
-                line_index as usize,
-                style,
-                if line_offset + line_index < num_lines {
-                    " "
-                } else {
-                    "╶"
-                },
-            );
+            let (style, symbol) = if line_offset + line_index >= num_lines {
+                (style, "╶")
+            } else if unsaved_lines
+                .iter()
+                .any(|range| range.contains(&(line_offset + line_index)))
+            {
+                (unsaved_style, "▎")
+            } else {
+                (style, " ")
+            };
+            canvas.draw_str(0, line_index as usize, style, symbol);
         }
         canvas.into()
     }
@@ -1,36 +1,75 @@
+mod bidi;
+mod bindings;
 pub mod line_info;
+mod numeric_argument;
+pub mod overlay;
 pub mod status_bar;
 pub mod textarea;
 
-use std::{borrow::Cow, iter, path::PathBuf};
+use ropey::Rope;
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    cmp,
+    fs::File,
+    io::BufReader,
+    iter,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant},
+};
 use zi::{
     components::text::{Text, TextAlign, TextProperties},
     prelude::*,
 };
 
-use zee_edit::{tree::EditTree, Direction};
+use zee_edit::{
+    graphemes::has_final_newline,
+    line_hunks,
+    movement::{self, WordCharacters},
+    multi_cursor::MultiCursor,
+    rectangle::{self, Rectangle},
+    search::{self, CaseMode},
+    tree::EditTree,
+    CharIndex, Cursor, Direction, LineHunk, LineIndex,
+};
 use zee_grammar::Mode;
 
 use self::{
     line_info::{LineInfo, Properties as LineInfoProperties},
-    status_bar::{Properties as StatusBarProperties, StatusBar, Theme as StatusBarTheme},
-    textarea::{Properties as TextAreaProperties, TextArea},
+    numeric_argument::{ArgumentKey, NumericArgument},
+    overlay::{priority, OverlayLayer, OverlayStyle, Overlays},
+    status_bar::{
+        Properties as StatusBarProperties, ScrollPosition, SearchMatches, Segment, SelectionSize,
+        StatusBar, Theme as StatusBarTheme,
+    },
+    textarea::{CursorShape, Properties as TextAreaProperties, TextArea},
 };
-use super::edit_tree_viewer::{
-    EditTreeViewer, Properties as EditTreeViewerProperties, Theme as EditTreeViewerTheme,
+use super::{
+    diff_viewer::{DiffViewer, Properties as DiffViewerProperties, Theme as DiffViewerTheme},
+    edit_tree_viewer::{
+        EditTreeViewer, Properties as EditTreeViewerProperties, Theme as EditTreeViewerTheme,
+    },
 };
 use crate::{
+    commands::{self, Command},
     editor::{
-        buffer::{BufferCursor, CursorMessage, ModifiedStatus, RepositoryRc},
-        ContextHandle,
+        buffer::{BufferCursor, DiskStatus, ModifiedStatus, RepositoryRc, SearchStatus},
+        ContextHandle, Message as EditorMessage,
     },
-    syntax::{highlight::Theme as SyntaxTheme, parse::ParseTree},
+    syntax::{highlight::Theme as SyntaxTheme, parse::ParseTree, spelling, structure, todo},
+    utils::ring_bell,
     versioned::WeakHandle,
 };
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Theme {
     pub border: Style,
+    /// The gutter marker of lines changed since the last save
+    pub unsaved_line: Style,
+    pub diff_viewer: DiffViewerTheme,
     pub edit_tree_viewer: EditTreeViewerTheme,
     pub status_bar: StatusBarTheme,
     pub syntax: SyntaxTheme,
@@ -48,6 +87,21 @@ pub struct Properties {
     pub cursor: BufferCursor,
     pub parse_tree: Option<ParseTree>,
     pub modified_status: ModifiedStatus,
+    pub disk_status: DiskStatus,
+    pub large_file: bool,
+    pub loading_progress: Option<usize>,
+    pub read_only: bool,
+    pub search: Option<SearchStatus>,
+    /// The number of jumps to recent edits, the view is centred after each
+    pub edit_point_jumps: usize,
+    /// How letter case is matched when highlighting the symbol at point
+    pub case_mode: CaseMode,
+    pub rectangle: Option<Rectangle>,
+    pub multi_cursor: Option<MultiCursor>,
+    /// The prefix keys typed so far, e.g. `C-x-`
+    pub pending_keys: Option<String>,
+    /// A quoted insert is waiting for a key, the editor inserts it
+    pub quoted_insert: bool,
 }
 
 impl PartialEq for Properties {
@@ -57,45 +111,209 @@ impl PartialEq for Properties {
             && self.parse_tree.as_ref().map(|tree| tree.version)
                 == other.parse_tree.as_ref().map(|tree| tree.version)
             && self.modified_status == other.modified_status
+            && self.disk_status == other.disk_status
+            && self.large_file == other.large_file
+            && self.loading_progress == other.loading_progress
+            && self.read_only == other.read_only
             && self.focused == other.focused
             && self.frame_id == other.frame_id
             && *self.theme == *other.theme
             && self.mode == other.mode
             && self.repo == other.repo
             && self.file_path == other.file_path
+            && self.search == other.search
+            && self.edit_point_jumps == other.edit_point_jumps
+            && self.case_mode == other.case_mode
+            && self.rectangle == other.rectangle
+            && self.multi_cursor == other.multi_cursor
+            && self.pending_keys == other.pending_keys
+            && self.quoted_insert == other.quoted_insert
     }
 }
 
 #[derive(Debug)]
 pub enum Message {
     CenterCursorVisually,
+    MovePage(Direction),
+    MoveToWindowLine,
+    ScrollCursorLineTo(WindowLine),
     ClearSelection,
     ToggleEditTree,
+    ToggleDiff,
+    TypeRevisionDigit(char),
+    DeleteRevisionDigit,
+    CheckoutRevision,
+    ToggleSymbolHighlight,
+    ToggleUnsavedLines,
+    ToggleCenteredCursor,
+    ToggleStickyScopes,
+    HighlightSymbol,
+    UpdateBreadcrumb,
+    UpdateUnsavedLines,
+    BlinkCursor,
+    ToggleSpellCheck,
+    SpellSuggestions,
+    AddToDictionary,
+    DescribePosition,
+    /// Inserts a character of a paste after its first
+    Paste(char),
+    /// A key typed during a quoted insert, which the editor inserts
+    QuotedKey(Key),
+    /// Starts or continues a numeric argument
+    NumericArgument(NumericArgument),
+    /// Ends a numeric argument, with the message of the command it ran
+    EndNumericArgument(Option<Box<Message>>),
+    /// Asks before running a command configured to confirm
+    ConfirmCommand(&'static str, Vec<Key>),
+    /// Runs a command once confirmed
+    RunConfirmed(&'static str, Vec<Key>),
 }
 
 pub struct Buffer {
     properties: Properties,
     frame: Rect,
+    link: ComponentLink<Self>,
     line_offset: usize,
     viewing_edit_tree: bool,
+    // The number of the revision to jump to, typed in the edit tree viewer
+    revision_input: String,
+    // The diff against the file on disk, while it's being viewed
+    saved_diff: Option<SavedDiff>,
+    symbol_highlight: SymbolHighlight,
+    breadcrumb: Breadcrumb,
+    spell_check: bool,
+    unsaved_lines: UnsavedLines,
+    scroll_policy: ScrollPolicy,
+    // Pin the first lines of the scopes enclosing the text in view
+    sticky_scopes: bool,
+    cursor_blink: CursorBlink,
+    // Set after `universal-argument`, the count of the next command
+    numeric_argument: Option<NumericArgument>,
+    // When the last key was pressed, to tell a paste apart from typing
+    last_key_at: Cell<Option<Instant>>,
+}
+
+/// The occurrences of the word under the cursor in view. They are found once
+/// the cursor, text and view stop changing for `SYMBOL_HIGHLIGHT_DELAY`.
+struct SymbolHighlight {
+    enabled: bool,
+    matches: Rc<Vec<Range<CharIndex>>>,
+    due: Option<Instant>,
+}
+
+/// The lines changed since the last save marked in the gutter. Working them
+/// out walks the edit tree, so they're only found again on a tick once the
+/// text or its saved revision changed.
+struct UnsavedLines {
+    enabled: bool,
+    ranges: Rc<Vec<Range<LineIndex>>>,
+    // The text version and modified status the ranges were found for
+    found_for: Option<(usize, ModifiedStatus)>,
+}
+
+/// The scopes enclosing the cursor shown in the status bar, found again once
+/// the cursor stops moving
+struct Breadcrumb {
+    path: Option<String>,
+    due: Option<Instant>,
+}
+
+/// Whether a blinking cursor is currently shown and when it's next toggled.
+/// The cursor is shown again whenever it moves or the text is edited.
+struct CursorBlink {
+    visible: bool,
+    toggle_at: Instant,
+}
+
+impl CursorBlink {
+    fn new() -> Self {
+        Self {
+            visible: true,
+            toggle_at: Instant::now() + CURSOR_BLINK_INTERVAL,
+        }
+    }
+}
+
+struct SavedDiff {
+    saved: Rope,
+    version: usize,
+    hunks: Rc<Vec<LineHunk>>,
+}
+
+impl SavedDiff {
+    fn new(saved: Rope, content: &WeakHandle<EditTree>) -> Self {
+        let hunks = line_hunks(&saved, content.upgrade().staged());
+        Self {
+            saved,
+            version: content.version(),
+            hunks: Rc::new(hunks),
+        }
+    }
+}
+
+/// Reads the saved version of a file. Buffers without a file, or whose file
+/// can't be read, are compared against an empty text.
+fn read_saved(file_path: Option<&Path>) -> Rope {
+    file_path
+        .and_then(|file_path| File::open(file_path).ok())
+        .and_then(|file| Rope::from_reader(BufReader::new(file)).ok())
+        .unwrap_or_default()
 }
 
 impl Buffer {
     fn ensure_cursor_in_view(&mut self) -> ShouldRender {
         let content = self.properties.content.upgrade();
         let current_line = content.char_to_line(self.properties.cursor.inner().range().start);
-        let num_lines = self.frame.size.height.saturating_sub(1);
-        if current_line < self.line_offset {
-            self.line_offset = current_line;
-            ShouldRender::Yes
-        } else if current_line - self.line_offset > num_lines.saturating_sub(1) {
-            self.line_offset = current_line + 1 - num_lines;
+        let line_offset = line_offset_following(
+            self.line_offset,
+            current_line,
+            self.frame.size.height.saturating_sub(1),
+            content.len_lines(),
+            self.scroll_policy,
+        );
+        if line_offset != self.line_offset {
+            self.line_offset = line_offset;
             ShouldRender::Yes
         } else {
             ShouldRender::No
         }
     }
 
+    fn center_cursor_line(&mut self) {
+        let content = self.properties.content.upgrade();
+        let line_index = content.char_to_line(self.properties.cursor.inner().range().start);
+        self.line_offset = line_index.saturating_sub(self.frame.size.height / 2);
+    }
+
+    /// Moves the cursor to the middle line of the window, then to the top and
+    /// bottom lines when repeated
+    fn move_to_window_line(&self) {
+        let content = self.properties.content.upgrade();
+        let current_line = content.char_to_line(self.properties.cursor.inner().range().start);
+        let height = self.frame.size.height.saturating_sub(1);
+        let window_line =
+            |position| line_at(self.line_offset, position, height, content.len_lines());
+        let position = if current_line == window_line(WindowLine::Middle) {
+            WindowLine::Top
+        } else if current_line == window_line(WindowLine::Top) {
+            WindowLine::Bottom
+        } else {
+            WindowLine::Middle
+        };
+        self.properties.cursor.move_to_line(window_line(position));
+    }
+
+    /// Scrolls so the cursor line is at `position` in the window
+    fn scroll_cursor_line_to(&mut self, position: WindowLine) {
+        let content = self.properties.content.upgrade();
+        let current_line = content.char_to_line(self.properties.cursor.inner().range().start);
+        self.line_offset = line_offset_showing(
+            current_line,
+            position,
+            self.frame.size.height.saturating_sub(1),
+        );
+    }
+
     fn center_visual_cursor(&mut self) {
         let content = self.properties.content.upgrade();
         let line_index = content.char_to_line(self.properties.cursor.inner().range().start);
@@ -110,6 +328,398 @@ impl Buffer {
         }
     }
 
+    /// Looks for the occurrences of the symbol at point again after a delay.
+    /// The current ones are cleared straight away if the text was edited, as
+    /// their ranges are out of date.
+    fn schedule_symbol_highlight(&mut self, edited: bool) {
+        if !self.symbol_highlight.enabled {
+            return;
+        }
+        if edited {
+            self.symbol_highlight.matches = Rc::new(Vec::new());
+        }
+        self.symbol_highlight.due = Some(Instant::now() + SYMBOL_HIGHLIGHT_DELAY);
+    }
+
+    /// Finds the scopes enclosing the cursor again after a delay, if the
+    /// status bar shows them
+    fn schedule_breadcrumb(&mut self) {
+        if self
+            .properties
+            .context
+            .config
+            .get()
+            .status_bar
+            .contains(&Segment::Breadcrumb)
+        {
+            self.breadcrumb.due = Some(Instant::now() + BREADCRUMB_DELAY);
+        }
+    }
+
+    /// The scopes enclosing the cursor, elided to fit half of the window's
+    /// width. `None` without an up to date parse tree.
+    fn find_breadcrumb(&self) -> Option<String> {
+        let parse_tree = self
+            .properties
+            .parse_tree
+            .as_ref()
+            .filter(|tree| tree.version == self.properties.content.version())?;
+        let content = self.properties.content.upgrade();
+        let path = structure::syntax_path(
+            parse_tree,
+            content.staged(),
+            self.properties.cursor.inner().range().start,
+        );
+        Some(structure::elide_path(&path, self.frame.size.width / 2))
+    }
+
+    /// Finds the whole word occurrences of the word under the cursor in the
+    /// lines in view
+    fn find_symbol_matches(&self) -> Vec<Range<CharIndex>> {
+        let content = self.properties.content.upgrade();
+        let text = content.staged();
+        let word_characters = WordCharacters::new(&self.properties.mode.word_characters);
+        let symbol = match search::word_at(
+            text,
+            self.properties.cursor.inner().range().start,
+            word_characters,
+        ) {
+            Some(symbol) => symbol,
+            None => return Vec::new(),
+        };
+        let query = text.slice(symbol).to_string();
+
+        let in_view = self.chars_in_view(text);
+        search::find_all(
+            &Rope::from(text.slice(in_view.clone())),
+            &query,
+            self.properties.case_mode,
+            Some(word_characters),
+        )
+        .into_iter()
+        .map(|range| in_view.start + range.start..in_view.start + range.end)
+        .collect()
+    }
+
+    /// Loads the dictionary, returning whether it's ready to spell check
+    fn load_dictionary(&self) -> bool {
+        self.properties
+            .context
+            .spell_checker
+            .load()
+            .map_err(|error| self.properties.context.log(format!("{:#}", error)))
+            .is_ok()
+    }
+
+    /// Takes the keys of a command during a quoted insert or a numeric
+    /// argument. A quoted insert passes the first key of the binding to the
+    /// editor to insert literally rather than running the command, dropping
+    /// the rest, e.g. the `C-s` of `C-x C-s`. The characters of a paste are
+    /// inserted literally too, a new line or tab included, as part of a
+    /// single edit.
+    fn intercept_keys(&self, command: &Command<Self>, keys: &[Key]) -> Option<Message> {
+        let now = Instant::now();
+        let pasted = self
+            .last_key_at
+            .replace(Some(now))
+            .map_or(false, |last_key_at| now - last_key_at < PASTE_BURST_GAP);
+        let key = *keys.first()?;
+        if self.properties.quoted_insert {
+            return Some(Message::QuotedKey(key));
+        }
+        match (self.numeric_argument, keys) {
+            (None, &[Key::Char(character)]) if pasted && !self.viewing_edit_tree => {
+                Some(Message::Paste(character))
+            }
+            (Some(numeric_argument), _) => Some(match numeric_argument.key(key) {
+                ArgumentKey::Argument(argument) => Message::NumericArgument(argument),
+                ArgumentKey::Command(count) => Message::EndNumericArgument(
+                    self.run_counted(command, keys, count).map(Box::new),
+                ),
+            }),
+            (None, _) => self.confirm_command(command, keys),
+        }
+    }
+
+    /// Asks before running a command if the configuration says so, with
+    /// `WhenModified` meaning unsaved changes in this buffer
+    fn confirm_command(&self, command: &Command<Self>, keys: &[Key]) -> Option<Message> {
+        let modified = self.properties.modified_status != ModifiedStatus::Unchanged;
+        self.properties
+            .context
+            .config
+            .get()
+            .confirmations
+            .policy(command.name, command.confirm)
+            .asks(modified)
+            .then(|| Message::ConfirmCommand(command.name, keys.to_vec()))
+    }
+
+    /// Runs a command given a numeric argument. A typed character is
+    /// inserted `count` times as a single edit and repeatable commands run
+    /// `count` times, neither doing anything for a count below one. The
+    /// number at the cursor changes by `count`, a negative count reversing
+    /// the direction. Other commands run once.
+    fn run_counted(&self, command: &Command<Self>, keys: &[Key], count: isize) -> Option<Message> {
+        match command.name {
+            "increment-number" => {
+                self.properties.cursor.increment_number(count as i64);
+                return None;
+            }
+            "decrement-number" => {
+                self.properties.cursor.increment_number(-(count as i64));
+                return None;
+            }
+            _ => {}
+        }
+        let count = usize::try_from(count).unwrap_or(0);
+        match *keys {
+            [Key::Char(character)]
+                if command.name == "insert-character"
+                    && character != '\n'
+                    && !self.viewing_edit_tree =>
+            {
+                self.properties.cursor.insert_repeated(character, count);
+                None
+            }
+            _ if command.repeatable => {
+                for _ in 0..count {
+                    command.run(self, keys);
+                }
+                None
+            }
+            _ => command.run(self, keys),
+        }
+    }
+
+    /// The word under the cursor
+    fn word_at_cursor(&self) -> Option<String> {
+        let content = self.properties.content.upgrade();
+        let text = content.staged();
+        search::word_at(
+            text,
+            self.properties.cursor.inner().range().start,
+            WordCharacters::default(),
+        )
+        .map(|word| text.slice(word).to_string())
+    }
+
+    /// Finds the misspelled words in the lines in view
+    fn find_misspellings(&self, text: &Rope) -> Vec<Range<CharIndex>> {
+        if !self.spell_check {
+            return Vec::new();
+        }
+        self.properties
+            .context
+            .spell_checker
+            .with_dictionary(|dictionary| {
+                spelling::find_misspellings(
+                    dictionary,
+                    self.properties.mode,
+                    text,
+                    self.chars_in_view(text),
+                    self.properties.parse_tree.as_ref(),
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    /// The first lines of the scopes enclosing the first line in view, pinned
+    /// to the top of the window. Outer scopes are dropped before the header
+    /// would cover the cursor.
+    fn sticky_lines(&self, text: &Rope) -> Vec<LineIndex> {
+        let parse_tree = match self.properties.parse_tree {
+            Some(ref parse_tree)
+                if self.sticky_scopes
+                    && parse_tree.version == self.properties.content.version() =>
+            {
+                parse_tree
+            }
+            _ => return Vec::new(),
+        };
+        let mut lines =
+            structure::enclosing_scope_lines(parse_tree, text, self.line_offset, MAX_STICKY_SCOPES);
+        let cursor_row = text
+            .char_to_line(self.properties.cursor.inner().range().start)
+            .saturating_sub(self.line_offset);
+        if lines.len() > cursor_row {
+            lines.drain(..lines.len() - cursor_row);
+        }
+        lines
+    }
+
+    /// The selection, cursors and highlights drawn over the text in view
+    fn overlays(&self, text: &Rope) -> Overlays {
+        let mut overlays = Overlays::new();
+        overlays.draw_layers(self, OVERLAY_LAYERS, text, self.chars_in_view(text));
+        overlays
+    }
+
+    fn draw_symbol_highlight(&self, _: &Rope, _: Range<CharIndex>, overlays: &mut Overlays) {
+        overlays.add_all(
+            self.symbol_highlight.matches.iter().cloned(),
+            OverlayStyle::background(self.properties.theme.syntax.symbol_highlight_background),
+            priority::SYMBOL,
+        );
+    }
+
+    fn draw_todos(&self, text: &Rope, in_view: Range<CharIndex>, overlays: &mut Overlays) {
+        overlays.add_all(
+            todo::find_todos(
+                &self.properties.context.config.get(),
+                text,
+                in_view,
+                self.properties.parse_tree.as_ref(),
+            ),
+            OverlayStyle::text(self.properties.theme.syntax.code_todo),
+            priority::TODO,
+        );
+    }
+
+    fn draw_misspellings(&self, text: &Rope, _: Range<CharIndex>, overlays: &mut Overlays) {
+        overlays.add_all(
+            self.find_misspellings(text),
+            OverlayStyle::underline(),
+            priority::MISSPELLED,
+        );
+    }
+
+    fn draw_search_matches(&self, _: &Rope, in_view: Range<CharIndex>, overlays: &mut Overlays) {
+        let theme = &self.properties.theme.syntax;
+        let search = match self.properties.search {
+            Some(ref search) => search,
+            None => return,
+        };
+        let first = search
+            .matches
+            .partition_point(|range| range.end <= in_view.start);
+        overlays.add_all(
+            search.matches[first..]
+                .iter()
+                .take_while(|range| range.start < in_view.end)
+                .cloned(),
+            OverlayStyle::colours(theme.search_match),
+            priority::SEARCH,
+        );
+        if let Some((_, ref current)) = search.current {
+            overlays.add(
+                current.clone(),
+                OverlayStyle::colours(theme.search_current_match),
+                priority::SEARCH,
+            );
+        }
+    }
+
+    fn draw_matching_bracket(
+        &self,
+        text: &Rope,
+        in_view: Range<CharIndex>,
+        overlays: &mut Overlays,
+    ) {
+        let position = self.properties.cursor.inner().range().start;
+        if let Some(matching) = movement::matching_bracket(text, position, in_view, |_| true) {
+            overlays.add_all(
+                [position..position + 1, matching..matching + 1],
+                OverlayStyle::background(self.properties.theme.syntax.matching_bracket_background),
+                priority::BRACKET,
+            );
+        }
+    }
+
+    fn draw_selection(&self, text: &Rope, _: Range<CharIndex>, overlays: &mut Overlays) {
+        let theme = &self.properties.theme.syntax;
+        overlays.add(
+            self.properties.cursor.inner().selection(),
+            OverlayStyle::background(theme.selection_background),
+            priority::SELECTION,
+        );
+
+        // The cells of a rectangular selection on each line, or where typing
+        // inserts text if it's empty. Cells past the end of a line are drawn
+        // by the textarea.
+        if let Some(ref rectangle) = self.properties.rectangle {
+            let lines = cmp::max(rectangle.lines().start, self.line_offset)
+                ..cmp::min(
+                    rectangle.lines().end,
+                    cmp::min(self.line_offset + self.frame.size.height, text.len_lines()),
+                );
+            let tab_width = self.properties.mode.indentation.tab_width();
+            for line_index in lines {
+                let selected = rectangle.char_range(text, line_index);
+                if !rectangle.columns().is_empty() {
+                    overlays.add(
+                        selected,
+                        OverlayStyle::background(theme.selection_background),
+                        priority::SELECTION,
+                    );
+                } else if rectangle.columns().start
+                    <= rectangle::line_width(text, line_index, tab_width)
+                    && selected.start < text.line_to_char(line_index + 1)
+                {
+                    overlays.add(
+                        selected.start..selected.start + 1,
+                        OverlayStyle::colours(theme.cursor_unfocused),
+                        priority::CURSOR,
+                    );
+                }
+            }
+        }
+    }
+
+    fn draw_cursors(&self, _: &Rope, in_view: Range<CharIndex>, overlays: &mut Overlays) {
+        let theme = &self.properties.theme.syntax;
+        if let Some(ref multi_cursor) = self.properties.multi_cursor {
+            overlays.add_all(
+                multi_cursor
+                    .positions()
+                    .iter()
+                    .filter(|&position| in_view.contains(position))
+                    .map(|&position| position..position + 1),
+                OverlayStyle::colours(theme.cursor_unfocused),
+                priority::CURSOR,
+            );
+            overlays.add_all(
+                multi_cursor.selections().filter(|selection| {
+                    !selection.is_empty()
+                        && selection.end > in_view.start
+                        && selection.start < in_view.end
+                }),
+                OverlayStyle::background(theme.selection_background),
+                priority::SELECTION,
+            );
+        }
+
+        // The cursor of the focused window has the configured shape, except
+        // while selecting, and is hidden when it blinks
+        let cursor = self.properties.cursor.inner();
+        let range = cursor.range();
+        let style = match self.properties.context.config.get().cursor_shape {
+            _ if !self.properties.focused => OverlayStyle::colours(theme.cursor_unfocused),
+            CursorShape::Underline if cursor.selection().is_empty() => OverlayStyle {
+                foreground: Some(theme.cursor_focused.background),
+                underline: Some(true),
+                ..OverlayStyle::default()
+            },
+            _ => OverlayStyle::colours(theme.cursor_focused),
+        };
+        let hidden = self.properties.focused
+            && self.properties.context.config.get().cursor_blink
+            && !self.cursor_blink.visible;
+        if !hidden {
+            overlays.add(
+                range.start..cmp::max(range.end, range.start + 1),
+                style,
+                priority::CURSOR,
+            );
+        }
+    }
+
+    /// The characters of the lines in view
+    fn chars_in_view(&self, text: &Rope) -> Range<CharIndex> {
+        let end_line = cmp::min(self.line_offset + self.frame.size.height, text.len_lines());
+        text.line_to_char(cmp::min(self.line_offset, end_line))..text.line_to_char(end_line)
+    }
+
     fn move_up(&self) {
         if self.viewing_edit_tree {
             self.properties.cursor.undo();
@@ -142,16 +752,23 @@ impl Buffer {
         }
     }
 
-    fn move_page_down(&self) {
-        self.properties
-            .cursor
-            .move_down_n(self.frame.size.height.saturating_sub(1));
-    }
-
-    fn move_page_up(&self) {
-        self.properties
-            .cursor
-            .move_up_n(self.frame.size.height.saturating_sub(1));
+    /// Moves the cursor a page down or up, the height of the window less
+    /// `page_context_lines`. With `page_keeps_cursor_row` the view scrolls by
+    /// as much, keeping the cursor on the same row of the window.
+    fn move_page(&mut self, direction: Direction) -> ShouldRender {
+        let config = self.properties.context.config.get();
+        let height = self.frame.size.height.saturating_sub(1);
+        let page = page_size(height, config.page_context_lines);
+        let line_offset = self.line_offset;
+        if config.page_keeps_cursor_row {
+            let num_lines = self.properties.content.upgrade().len_lines();
+            self.line_offset = scroll_page(line_offset, num_lines, height, page, direction);
+        }
+        match direction {
+            Direction::Forward => self.properties.cursor.move_down_n(page),
+            Direction::Backward => self.properties.cursor.move_up_n(page),
+        }
+        (line_offset != self.line_offset).into()
     }
 
     fn move_start_of_line(&self) {
@@ -191,47 +808,380 @@ impl Component for Buffer {
     type Properties = Properties;
     type Message = Message;
 
-    fn create(properties: Self::Properties, frame: Rect, _link: ComponentLink<Self>) -> Self {
+    fn create(properties: Self::Properties, frame: Rect, link: ComponentLink<Self>) -> Self {
         let mut buffer = Self {
             line_offset: 0,
             viewing_edit_tree: false,
+            revision_input: String::new(),
+            saved_diff: None,
+            symbol_highlight: SymbolHighlight {
+                enabled: properties.context.config.get().highlight_symbol_at_point,
+                matches: Rc::new(Vec::new()),
+                due: None,
+            },
+            breadcrumb: Breadcrumb {
+                path: None,
+                due: None,
+            },
+            spell_check: false,
+            unsaved_lines: UnsavedLines {
+                enabled: properties.context.config.get().show_unsaved_lines,
+                ranges: Rc::new(Vec::new()),
+                found_for: None,
+            },
+            scroll_policy: properties.context.config.get().scroll_policy,
+            sticky_scopes: properties.context.config.get().sticky_scopes,
+            cursor_blink: CursorBlink::new(),
+            numeric_argument: None,
+            last_key_at: Cell::new(None),
             properties,
             frame,
+            link,
         };
         buffer.ensure_cursor_in_view();
+        buffer.schedule_symbol_highlight(false);
+        buffer.schedule_breadcrumb();
+        buffer.spell_check =
+            buffer.properties.context.config.get().spell_check && buffer.load_dictionary();
         buffer
     }
 
     fn change(&mut self, properties: Self::Properties) -> ShouldRender {
         let changed_properties = self.properties != properties;
+        let current_match = |search: &Option<SearchStatus>| {
+            search
+                .as_ref()
+                .and_then(|search| search.current.as_ref().map(|(index, _)| *index))
+        };
+        let moved_to_match = current_match(&properties.search).is_some()
+            && current_match(&properties.search) != current_match(&self.properties.search);
+        let moved_to_edit = self.properties.edit_point_jumps != properties.edit_point_jumps;
+        let saved_or_reverted = self.properties.modified_status != properties.modified_status;
+        let edited = self.properties.content.version() != properties.content.version();
+        let moved = self.properties.cursor.inner().range().start
+            != properties.cursor.inner().range().start
+            || self.properties.case_mode != properties.case_mode;
+        let focused = self.properties.focused != properties.focused;
+        let reparsed = self.properties.parse_tree.as_ref().map(|tree| tree.version)
+            != properties.parse_tree.as_ref().map(|tree| tree.version);
+        let line_offset = self.line_offset;
         self.properties = properties;
-        self.ensure_cursor_in_view() | changed_properties.into()
+        if let Some(ref mut saved_diff) = self.saved_diff {
+            if saved_or_reverted {
+                *saved_diff = SavedDiff::new(
+                    read_saved(self.properties.file_path.as_deref()),
+                    &self.properties.content,
+                );
+            } else if saved_diff.version != self.properties.content.version() {
+                *saved_diff = SavedDiff::new(saved_diff.saved.clone(), &self.properties.content);
+            }
+        }
+        let should_render = if moved_to_match || moved_to_edit {
+            // Show search matches and edits jumped to in the middle of the
+            // screen
+            self.center_cursor_line();
+            ShouldRender::Yes
+        } else {
+            self.ensure_cursor_in_view() | changed_properties.into()
+        };
+        if edited || moved || line_offset != self.line_offset {
+            self.schedule_symbol_highlight(edited);
+        }
+        if moved || reparsed {
+            self.schedule_breadcrumb();
+        }
+        if edited || moved || focused {
+            self.cursor_blink = CursorBlink::new();
+        }
+        should_render
     }
 
     fn resize(&mut self, frame: Rect) -> ShouldRender {
         let changed_frame = self.frame != frame;
         self.frame = frame;
+        if changed_frame {
+            self.schedule_symbol_highlight(false);
+        }
         self.ensure_cursor_in_view() | changed_frame.into()
     }
 
     fn update(&mut self, message: Message) -> ShouldRender {
         match message {
+            Message::ConfirmCommand(name, keys) => {
+                let description = commands::find(&bindings::commands(), name)
+                    .map_or("", |command| command.description);
+                let link = self.link.clone();
+                self.properties
+                    .context
+                    .confirm(format!("Run {}: {}?", name, description), move || {
+                        link.send(Message::RunConfirmed(name, keys))
+                    });
+                ShouldRender::No
+            }
+            Message::RunConfirmed(name, keys) => {
+                match commands::find(&bindings::commands(), name)
+                    .and_then(|command| command.run(self, &keys))
+                {
+                    Some(message) => self.update(message),
+                    None => ShouldRender::Yes,
+                }
+            }
             Message::CenterCursorVisually => {
                 self.center_visual_cursor();
+                self.schedule_symbol_highlight(false);
+                ShouldRender::Yes
+            }
+            Message::MovePage(direction) => self.move_page(direction),
+            Message::MoveToWindowLine => {
+                self.move_to_window_line();
+                ShouldRender::No
+            }
+            Message::ScrollCursorLineTo(position) => {
+                self.scroll_cursor_line_to(position);
+                self.schedule_symbol_highlight(false);
                 ShouldRender::Yes
             }
             Message::ClearSelection if self.viewing_edit_tree => {
                 self.viewing_edit_tree = false;
+                self.revision_input.clear();
+                ShouldRender::Yes
+            }
+            Message::ClearSelection if self.saved_diff.is_some() => {
+                self.saved_diff = None;
                 ShouldRender::Yes
             }
             Message::ClearSelection => ShouldRender::No,
             Message::ToggleEditTree => {
                 self.viewing_edit_tree = !self.viewing_edit_tree;
+                self.revision_input.clear();
+                ShouldRender::Yes
+            }
+            Message::ToggleDiff => {
+                // The file is read every time the diff is opened, so changes
+                // made on disk by other programs show up too
+                self.saved_diff = match self.saved_diff {
+                    Some(_) => None,
+                    None => Some(SavedDiff::new(
+                        read_saved(self.properties.file_path.as_deref()),
+                        &self.properties.content,
+                    )),
+                };
+                ShouldRender::Yes
+            }
+            Message::TypeRevisionDigit(digit) => {
+                self.revision_input.push(digit);
+                ShouldRender::Yes
+            }
+            Message::DeleteRevisionDigit => {
+                self.revision_input.pop();
+                ShouldRender::Yes
+            }
+            Message::CheckoutRevision => {
+                let num_revisions = self.properties.content.upgrade().revisions.len();
+                match self
+                    .revision_input
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&index| index < num_revisions)
+                {
+                    Some(index) => self.properties.cursor.checkout_revision(index),
+                    None => ring_bell(),
+                }
+                self.revision_input.clear();
+                ShouldRender::Yes
+            }
+            Message::ToggleSymbolHighlight => {
+                self.symbol_highlight.enabled = !self.symbol_highlight.enabled;
+                self.symbol_highlight.matches = Rc::new(Vec::new());
+                self.symbol_highlight.due = self.symbol_highlight.enabled.then(Instant::now);
+                let state = if self.symbol_highlight.enabled {
+                    "enabled"
+                } else {
+                    "disabled"
+                };
+                self.properties
+                    .context
+                    .log(format!("Symbol highlighting {}", state));
+                ShouldRender::Yes
+            }
+            Message::ToggleCenteredCursor => {
+                self.scroll_policy = match (
+                    self.scroll_policy,
+                    self.properties.context.config.get().scroll_policy,
+                ) {
+                    (ScrollPolicy::Centered, ScrollPolicy::Centered) => ScrollPolicy::Margin(0),
+                    (ScrollPolicy::Centered, configured) => configured,
+                    (ScrollPolicy::Margin(_), _) => ScrollPolicy::Centered,
+                };
+                let state = if self.scroll_policy == ScrollPolicy::Centered {
+                    "enabled"
+                } else {
+                    "disabled"
+                };
+                self.properties
+                    .context
+                    .log(format!("Centered cursor {}", state));
+                self.ensure_cursor_in_view()
+            }
+            Message::ToggleStickyScopes => {
+                self.sticky_scopes = !self.sticky_scopes;
+                let state = if self.sticky_scopes {
+                    "enabled"
+                } else {
+                    "disabled"
+                };
+                self.properties
+                    .context
+                    .log(format!("Sticky scopes {}", state));
+                ShouldRender::Yes
+            }
+            Message::ToggleUnsavedLines => {
+                self.unsaved_lines.enabled = !self.unsaved_lines.enabled;
+                self.unsaved_lines.found_for = None;
+                let state = if self.unsaved_lines.enabled {
+                    "enabled"
+                } else {
+                    "disabled"
+                };
+                self.properties
+                    .context
+                    .log(format!("Unsaved line markers {}", state));
+                ShouldRender::Yes
+            }
+            Message::HighlightSymbol => {
+                self.symbol_highlight.due = None;
+                self.symbol_highlight.matches = Rc::new(self.find_symbol_matches());
+                ShouldRender::Yes
+            }
+            Message::UpdateBreadcrumb => {
+                self.breadcrumb.due = None;
+                let path = self.find_breadcrumb();
+                let changed = self.breadcrumb.path != path;
+                self.breadcrumb.path = path;
+                changed.into()
+            }
+            Message::UpdateUnsavedLines => {
+                let content = self.properties.content.upgrade();
+                self.unsaved_lines.ranges = Rc::new(content.modified_line_ranges());
+                self.unsaved_lines.found_for = Some((
+                    self.properties.content.version(),
+                    self.properties.modified_status,
+                ));
+                ShouldRender::Yes
+            }
+            Message::BlinkCursor => {
+                self.cursor_blink.visible = !self.cursor_blink.visible;
+                self.cursor_blink.toggle_at = Instant::now() + CURSOR_BLINK_INTERVAL;
+                ShouldRender::Yes
+            }
+            Message::ToggleSpellCheck => {
+                self.spell_check = !self.spell_check && self.load_dictionary();
+                let state = if self.spell_check {
+                    "enabled"
+                } else {
+                    "disabled"
+                };
+                self.properties
+                    .context
+                    .log(format!("Spell checking {}", state));
+                ShouldRender::Yes
+            }
+            Message::SpellSuggestions => {
+                let word = match self.word_at_cursor() {
+                    Some(word) if self.load_dictionary() => word,
+                    _ => return ShouldRender::No,
+                };
+                let suggestions = self
+                    .properties
+                    .context
+                    .spell_checker
+                    .with_dictionary(|dictionary| {
+                        (!dictionary.check(&word)).then(|| dictionary.suggestions(&word, 8))
+                    })
+                    .flatten();
+                self.properties.context.log(match suggestions {
+                    None => format!("`{}` is spelled correctly", word),
+                    Some(suggestions) if suggestions.is_empty() => {
+                        format!("No suggestions for `{}`", word)
+                    }
+                    Some(suggestions) => {
+                        format!("Suggestions for `{}`: {}", word, suggestions.join(", "))
+                    }
+                });
+                ShouldRender::No
+            }
+            Message::Paste(character) => {
+                self.properties.cursor.paste_char(character);
+                ShouldRender::No
+            }
+            Message::QuotedKey(key) => {
+                self.properties
+                    .context
+                    .link
+                    .send(EditorMessage::QuotedKey(key));
+                ShouldRender::No
+            }
+            Message::NumericArgument(argument) => {
+                self.numeric_argument = Some(argument);
+                ShouldRender::No
+            }
+            Message::EndNumericArgument(message) => {
+                self.numeric_argument = None;
+                match message {
+                    Some(message) => self.update(*message),
+                    None => ShouldRender::No,
+                }
+            }
+            Message::DescribePosition => {
+                let content = self.properties.content.upgrade();
+                self.properties.context.log(describe_position(
+                    content.staged(),
+                    self.properties.cursor.inner(),
+                    self.properties.mode.indentation.tab_width(),
+                ));
+                ShouldRender::No
+            }
+            Message::AddToDictionary => {
+                let word = match self.word_at_cursor() {
+                    Some(word) => word,
+                    None => return ShouldRender::No,
+                };
+                let message = match self.properties.context.spell_checker.add_word(&word) {
+                    Ok(()) => format!("Added `{}` to the personal dictionary", word),
+                    Err(error) => format!("{:#}", error),
+                };
+                self.properties.context.log(message);
                 ShouldRender::Yes
             }
         }
     }
 
+    fn tick(&self) -> Option<Message> {
+        let now = Instant::now();
+        let blinks = self.properties.focused && self.properties.context.config.get().cursor_blink;
+        self.symbol_highlight
+            .due
+            .filter(|&due| now >= due)
+            .map(|_| Message::HighlightSymbol)
+            .or_else(|| {
+                self.breadcrumb
+                    .due
+                    .filter(|&due| now >= due)
+                    .map(|_| Message::UpdateBreadcrumb)
+            })
+            .or_else(|| {
+                let found_for = (
+                    self.properties.content.version(),
+                    self.properties.modified_status,
+                );
+                (self.unsaved_lines.enabled && self.unsaved_lines.found_for != Some(found_for))
+                    .then(|| Message::UpdateUnsavedLines)
+            })
+            .or_else(|| {
+                (blinks && now >= self.cursor_blink.toggle_at).then(|| Message::BlinkCursor)
+            })
+    }
+
     fn view(&self) -> Layout {
         let content = self.properties.content.upgrade();
 
@@ -244,6 +1194,12 @@ impl Component for Buffer {
             mode: self.properties.mode,
             line_offset: self.line_offset,
             parse_tree: self.properties.parse_tree.clone(),
+            control_characters: self.properties.context.config.get().control_characters,
+            overlays: Rc::new(self.overlays(content.staged())),
+            rectangle: self.properties.rectangle.clone(),
+            column_guides: self.properties.context.config.get().column_guides.clone(),
+            tab_stop_guides: self.properties.context.config.get().tab_stop_guides,
+            sticky_lines: self.sticky_lines(content.staged()),
         });
 
         // Vertical info bar which shows line specific diagnostics
@@ -256,9 +1212,25 @@ impl Component for Buffer {
                 } else {
                     1
                 },
+            unsaved_lines: if self.unsaved_lines.enabled {
+                self.unsaved_lines.ranges.clone()
+            } else {
+                Rc::new(Vec::new())
+            },
+            unsaved_style: self.properties.theme.unsaved_line,
         });
 
         // The "status bar" which shows information about the file etc.
+        let cursor = self.properties.cursor.inner();
+        let selection = cursor.has_selection().then(|| {
+            let selection = cursor.selection();
+            let last_char = cmp::max(selection.start, selection.end.saturating_sub(1));
+            SelectionSize {
+                num_lines: content.char_to_line(last_char) - content.char_to_line(selection.start)
+                    + 1,
+                num_chars: selection.len(),
+            }
+        });
         let status_bar = StatusBar::with(StatusBarProperties {
             current_line_index: content.char_to_line(self.properties.cursor.inner().range().start),
             column_offset: self
@@ -267,14 +1239,33 @@ impl Component for Buffer {
                 .inner()
                 .column_offset(self.properties.mode.indentation.tab_width(), &content),
             file_path: self.properties.file_path.clone(),
+            missing_final_newline: !has_final_newline(content.staged()),
+            crlf: ends_lines_with_crlf(content.staged()),
             focused: self.properties.focused,
             frame_id: self.properties.frame_id,
             modified_status: self.properties.modified_status,
+            disk_status: self.properties.disk_status,
+            large_file: self.properties.large_file,
+            loading_progress: self.properties.loading_progress,
+            read_only: self.properties.read_only,
             mode: self.properties.mode.into(),
             num_lines: content.len_lines(),
             repository: self.properties.repo.clone(),
+            scroll_position: ScrollPosition::new(
+                self.line_offset,
+                self.frame.size.height.saturating_sub(1),
+                content.len_lines(),
+            ),
+            search: self.properties.search.as_ref().map(|search| SearchMatches {
+                current: search.current.as_ref().map(|(index, _)| *index),
+                num_matches: search.num_matches,
+            }),
+            selection,
+            pending_keys: self.properties.pending_keys.clone(),
             size_bytes: content.len_bytes() as u64,
             theme: self.properties.theme.status_bar.clone(),
+            layout: self.properties.context.config.get().status_bar.clone(),
+            breadcrumb: self.breadcrumb.path.clone(),
         });
 
         // Edit-tree viewer (aka. undo/redo tree)
@@ -290,7 +1281,11 @@ impl Component for Buffer {
                     })),
                     Item::fixed(1)(Text::with(
                         TextProperties::new()
-                            .content("Edit Tree Viewer 🌴")
+                            .content(if self.revision_input.is_empty() {
+                                "Edit Tree Viewer 🌴".into()
+                            } else {
+                                format!("Go to revision {}", self.revision_input)
+                            })
                             .style(self.properties.theme.border)
                             .align(TextAlign::Centre),
                     )),
@@ -300,11 +1295,37 @@ impl Component for Buffer {
             None
         };
 
+        // Diff against the saved file
+        let diff_viewer = self.saved_diff.as_ref().map(|saved_diff| {
+            Item::fixed(self.frame.size.width / 2)(Container::row([
+                Item::fixed(1)(Text::with(
+                    TextProperties::new().style(self.properties.theme.border),
+                )),
+                Item::auto(Container::column([
+                    Item::auto(DiffViewer::with(DiffViewerProperties {
+                        theme: self.properties.theme.diff_viewer.clone(),
+                        saved: saved_diff.saved.clone(),
+                        text: content.staged().clone(),
+                        hunks: saved_diff.hunks.clone(),
+                        current_line: content
+                            .char_to_line(self.properties.cursor.inner().range().start),
+                    })),
+                    Item::fixed(1)(Text::with(
+                        TextProperties::new()
+                            .content("Diff Against Saved")
+                            .style(self.properties.theme.border)
+                            .align(TextAlign::Centre),
+                    )),
+                ])),
+            ]))
+        });
+
         Layout::column([
             Item::auto(Layout::row(
                 iter::once(edit_tree_viewer)
                     .chain(iter::once(Some(Item::fixed(1)(line_info))))
                     .chain(iter::once(Some(Item::auto(textarea))))
+                    .chain(iter::once(diff_viewer))
                     .flatten(),
             )),
             Item::fixed(1)(status_bar),
@@ -312,214 +1333,489 @@ impl Component for Buffer {
     }
 
     fn bindings(&self, bindings: &mut Bindings<Self>) {
-        use Key::*;
-
         bindings.set_focus(self.properties.focused);
         if !bindings.is_empty() {
             return;
         }
+        commands::register_intercepted(
+            bindings,
+            &self::bindings::commands(),
+            &self::bindings::keymap(),
+            Self::intercept_keys,
+        );
+    }
+}
 
-        // Cursor movement
-        //
-        // Up
-        bindings
-            .command("move-backward-line", Self::move_up)
-            .with([Ctrl('p')])
-            .with([Up]);
-
-        // Down
-        bindings
-            .command("move-forward-line", Self::move_down)
-            .with([Ctrl('n')])
-            .with([Down]);
-
-        // Left
-        bindings
-            .command("move-backward", Self::move_left)
-            .with([Ctrl('b')])
-            .with([Left]);
-
-        // Right
-        bindings
-            .command("move-forward", Self::move_right)
-            .with([Ctrl('f')])
-            .with([Right]);
-
-        // Move by word
-        //
-        // TODO: Add Alt + Left / Right / Up / Down alternative key bindings
-        //       For this to be possible, zi should support Alt + a key, not just char
-        bindings
-            .command("move-backward-word", |this: &Self| {
-                this.properties
-                    .cursor
-                    .send_cursor(CursorMessage::MoveWord(Direction::Backward, 1))
-            })
-            .with([Alt('b')]);
-        bindings
-            .command("move-forward-word", |this: &Self| {
-                this.properties
-                    .cursor
-                    .send_cursor(CursorMessage::MoveWord(Direction::Forward, 1))
-            })
-            .with([Alt('f')]);
-
-        // Move by paragraph
-        bindings
-            .command("move-backward-paragraph", |this: &Self| {
-                this.properties
-                    .cursor
-                    .send_cursor(CursorMessage::MoveParagraph(Direction::Backward, 1))
-            })
-            .with([Alt('p')]);
-        bindings
-            .command("move-forward-paragraph", |this: &Self| {
-                this.properties
-                    .cursor
-                    .send_cursor(CursorMessage::MoveParagraph(Direction::Forward, 1))
-            })
-            .with([Alt('n')]);
-
-        // Page down
-        bindings
-            .command("move-page-down", Self::move_page_down)
-            .with([Ctrl('v')])
-            .with([PageDown]);
-
-        // Page up
-        bindings
-            .command("move-page-up", Self::move_page_up)
-            .with([Alt('v')])
-            .with([PageUp]);
-
-        // Start/end of line
-        bindings
-            .command("move-start-of-line", Self::move_start_of_line)
-            .with([Ctrl('a')])
-            .with([Home]);
-        bindings
-            .command("move-end-of-line", Self::move_end_of_line)
-            .with([Ctrl('e')])
-            .with([End]);
-
-        // Start/end of buffer
-        bindings.add(
-            "move-start-of-buffer",
-            [Alt('<')],
-            Self::move_start_of_buffer,
+/// How a window scrolls to keep the cursor in view, as set in the
+/// configuration file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ScrollPolicy {
+    /// Scroll once the cursor is within this many lines of the top or bottom
+    /// of the window
+    Margin(usize),
+    /// Keep the cursor line in the middle of the window, except near the
+    /// start and end of the text
+    Centered,
+}
+
+impl Default for ScrollPolicy {
+    fn default() -> Self {
+        Self::Margin(0)
+    }
+}
+
+/// A line of a window, where the cursor line is moved or scrolled to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowLine {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// The line offset which shows `line_index` at `position` in a window of
+/// `height` lines. The first lines of the text can't be scrolled further
+/// down than the top of the window.
+fn line_offset_showing(line_index: LineIndex, position: WindowLine, height: usize) -> usize {
+    match position {
+        WindowLine::Top => line_index,
+        WindowLine::Middle => line_index.saturating_sub(height / 2),
+        WindowLine::Bottom => (line_index + 1).saturating_sub(height),
+    }
+}
+
+/// The line offset of a window `height` lines tall after the cursor moved to
+/// `cursor_line` of a text with `num_lines` lines. A margin is only kept
+/// where the text has lines to scroll, so the cursor still reaches the edges
+/// of the window at the start and end of the text. It's at most half the
+/// window.
+fn line_offset_following(
+    line_offset: usize,
+    cursor_line: LineIndex,
+    height: usize,
+    num_lines: usize,
+    policy: ScrollPolicy,
+) -> usize {
+    match policy {
+        ScrollPolicy::Margin(margin) => {
+            let margin = cmp::min(margin, height.saturating_sub(1) / 2);
+            let top = cmp::min(margin, cursor_line);
+            let bottom = cmp::min(margin, num_lines.saturating_sub(cursor_line + 1));
+            if cursor_line - top < line_offset {
+                cursor_line - top
+            } else if cursor_line + bottom >= line_offset + height {
+                (cursor_line + bottom + 1).saturating_sub(height)
+            } else {
+                line_offset
+            }
+        }
+        ScrollPolicy::Centered => cmp::min(
+            cursor_line.saturating_sub(height / 2),
+            num_lines.saturating_sub(height),
+        ),
+    }
+}
+
+/// The number of lines a page up or down moves, keeping `context_lines` of
+/// the previous page in view
+fn page_size(height: usize, context_lines: usize) -> usize {
+    cmp::max(height.saturating_sub(context_lines), 1)
+}
+
+/// The line offset of a window of `height` lines scrolled by a `page` from
+/// `line_offset`. Scrolling down stops once the last line is at the bottom.
+fn scroll_page(
+    line_offset: usize,
+    num_lines: usize,
+    height: usize,
+    page: usize,
+    direction: Direction,
+) -> usize {
+    match direction {
+        Direction::Forward => cmp::min(
+            line_offset + page,
+            cmp::max(line_offset, num_lines.saturating_sub(height)),
+        ),
+        Direction::Backward => line_offset.saturating_sub(page),
+    }
+}
+
+/// The line at `position` in a window of `height` lines scrolled to
+/// `line_offset`. At the end of the text, the bottom is the last line.
+fn line_at(line_offset: usize, position: WindowLine, height: usize, num_lines: usize) -> LineIndex {
+    let last_line = cmp::min(line_offset + height, num_lines).saturating_sub(1);
+    let first_line = cmp::min(line_offset, last_line);
+    match position {
+        WindowLine::Top => first_line,
+        WindowLine::Middle => first_line + (last_line - first_line) / 2,
+        WindowLine::Bottom => last_line,
+    }
+}
+
+/// Describes the character under the cursor, e.g. `Char: é (U+00E9), char 12
+/// of 120 (10%), byte 13, line 2, column 3`. Every code point of a grapheme
+/// cluster made of several is listed.
+fn describe_position(text: &Rope, cursor: &Cursor, tab_width: usize) -> String {
+    let char_index = cursor.range().start;
+    let len_chars = text.len_chars();
+    let line_index = text.char_to_line(char_index);
+    let column = cursor.column_offset(tab_width, text);
+    if char_index >= len_chars {
+        return format!(
+            "End of buffer, char {} of {}, line {}, column {}",
+            char_index,
+            len_chars,
+            line_index + 1,
+            column
         );
-        bindings.add("move-end-of-buffer", [Alt('>')], Self::move_end_of_buffer);
-
-        // Editing
-        //
-        // Delete forward
-        bindings
-            .command("delete-forward", Self::delete_forward)
-            .with([Ctrl('d')])
-            .with([Delete]);
-
-        // Delete backward
-        bindings.add("delete-backward", [Backspace], Self::delete_backward);
-
-        // Delete line
-        bindings.add("delete-line", [Ctrl('k')], Self::delete_line);
-
-        // Insert new line
-        bindings.add("insert-new-line", [Char('\n')], Self::insert_new_line);
-        bindings.add("insert-new-line-after", [Ctrl('o')], |this: &Self| {
-            this.properties.cursor.insert_char('\n', false)
-        });
+    }
 
-        // Insert tab
-        bindings.add("insert-tab", [Char('\t')], |this: &Self| {
-            this.properties.cursor.insert_tab()
-        });
+    let grapheme = text.slice(cursor.range());
+    let code_points: Vec<_> = grapheme
+        .chars()
+        .map(|character| format!("U+{:04X}", u32::from(character)))
+        .collect();
+    format!(
+        "Char: {} ({}), char {} of {} ({}%), byte {}, line {}, column {}",
+        grapheme
+            .chars()
+            .flat_map(char::escape_debug)
+            .collect::<String>(),
+        code_points.join(" "),
+        char_index,
+        len_chars,
+        char_index * 100 / len_chars,
+        text.char_to_byte(char_index),
+        line_index + 1,
+        column
+    )
+}
 
-        // Insert character
-        bindings.add(
-            "insert-character",
-            AnyCharacter,
-            |this: &Self, keys: &[Key]| match keys {
-                &[Char(character)] if character != '\n' => {
-                    this.properties.cursor.insert_char(character, true)
-                }
-                _ => {}
-            },
+/// Whether the first line of `text` ends with `\r\n`, taken to be the line
+/// ending of the file
+fn ends_lines_with_crlf(text: &Rope) -> bool {
+    let line = text.line(0);
+    let mut chars = line.chars_at(line.len_chars());
+    chars.prev() == Some('\n') && chars.prev() == Some('\r')
+}
+
+const EDIT_TREE_WIDTH: usize = 36;
+const SYMBOL_HIGHLIGHT_DELAY: Duration = Duration::from_millis(250);
+const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(530);
+// Terminals send pasted text as keys pressed far quicker than anyone types
+const PASTE_BURST_GAP: Duration = Duration::from_millis(2);
+const BREADCRUMB_DELAY: Duration = Duration::from_millis(150);
+const MAX_STICKY_SCOPES: usize = 3;
+
+/// The features drawing overlays over the text, e.g. search matches. Each
+/// adds its overlays with its own priority, so the order here doesn't change
+/// what's drawn on top.
+const OVERLAY_LAYERS: &[OverlayLayer<Buffer>] = &[
+    Buffer::draw_symbol_highlight,
+    Buffer::draw_todos,
+    Buffer::draw_misspellings,
+    Buffer::draw_search_matches,
+    Buffer::draw_matching_bracket,
+    Buffer::draw_selection,
+    Buffer::draw_cursors,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commands::Confirm,
+        config::EditorConfig,
+        testing::{temp_file, TestEditor},
+    };
+
+    #[test]
+    fn scroll_the_cursor_line_in_a_window() {
+        let height = 10;
+        assert_eq!(25, line_offset_showing(25, WindowLine::Top, height));
+        assert_eq!(20, line_offset_showing(25, WindowLine::Middle, height));
+        assert_eq!(16, line_offset_showing(25, WindowLine::Bottom, height));
+
+        // Near the start of the text the first line stays at the top
+        assert_eq!(3, line_offset_showing(3, WindowLine::Top, height));
+        assert_eq!(0, line_offset_showing(3, WindowLine::Middle, height));
+        assert_eq!(0, line_offset_showing(3, WindowLine::Bottom, height));
+        assert_eq!(0, line_offset_showing(9, WindowLine::Bottom, height));
+        assert_eq!(1, line_offset_showing(10, WindowLine::Bottom, height));
+    }
+
+    #[test]
+    fn keep_the_cursor_centered() {
+        let follow = |cursor_line, num_lines| {
+            line_offset_following(0, cursor_line, 10, num_lines, ScrollPolicy::Centered)
+        };
+        assert_eq!(45, follow(50, 100));
+
+        // The cursor drifts towards the top and bottom at the ends of the text
+        assert_eq!(0, follow(3, 100));
+        assert_eq!(0, follow(5, 100));
+        assert_eq!(1, follow(6, 100));
+        assert_eq!(90, follow(97, 100));
+        assert_eq!(90, follow(99, 100));
+        assert_eq!(0, follow(4, 5));
+    }
+
+    #[test]
+    fn keep_a_scroll_margin() {
+        let follow = |line_offset, cursor_line, margin| {
+            line_offset_following(
+                line_offset,
+                cursor_line,
+                10,
+                100,
+                ScrollPolicy::Margin(margin),
+            )
+        };
+        assert_eq!(0, follow(0, 6, 3));
+        assert_eq!(1, follow(0, 7, 3));
+        assert_eq!(19, follow(20, 22, 3));
+        assert_eq!(20, follow(20, 23, 3));
+
+        // The margin shrinks at the ends of the text and to half the window
+        assert_eq!(0, follow(0, 1, 3));
+        assert_eq!(90, follow(85, 98, 3));
+        assert_eq!(90, follow(90, 99, 3));
+        assert_eq!(1, follow(0, 6, 20));
+
+        // Without a margin the view scrolls once the cursor leaves it
+        assert_eq!(0, follow(0, 9, 0));
+        assert_eq!(1, follow(0, 10, 0));
+        assert_eq!(5, follow(10, 5, 0));
+    }
+
+    #[test]
+    fn describe_the_position_of_the_cursor() {
+        let text = Rope::from("ab\n\té👍🏽\n");
+        let describe =
+            |range: Range<CharIndex>| describe_position(&text, &Cursor::with_range(range), 4);
+        assert_eq!(
+            "Char: a (U+0061), char 0 of 8 (0%), byte 0, line 1, column 0",
+            describe(0..1)
+        );
+        assert_eq!(
+            "Char: \\n (U+000A), char 2 of 8 (25%), byte 2, line 1, column 2",
+            describe(2..3)
+        );
+        assert_eq!(
+            "Char: é (U+00E9), char 4 of 8 (50%), byte 4, line 2, column 4",
+            describe(4..5)
+        );
+        assert_eq!(
+            "Char: 👍🏽 (U+1F44D U+1F3FD), char 5 of 8 (62%), byte 6, line 2, column 5",
+            describe(5..7)
+        );
+        assert_eq!(
+            "End of buffer, char 8 of 8, line 3, column 0",
+            describe(8..8)
         );
+    }
 
-        // Selections
-        //
-        // Begin selection
-        bindings
-            .command("begin-selection", |this: &Self| {
-                this.properties.cursor.begin_selection();
-            })
-            .with([Null])
-            .with([Ctrl(' ')]);
+    #[test]
+    fn page_with_context_lines() {
+        let height = 20;
+        let page = page_size(height, 2);
+        assert_eq!(18, page);
+        assert_eq!(20, page_size(height, 0));
+        assert_eq!(1, page_size(height, 25));
 
-        // Select all
-        bindings.add("select-all", [Ctrl('x'), Char('h')], |this: &Self| {
-            this.properties.cursor.select_all();
-        });
-        // Copy selection to clipboard
-        bindings.add("copy-selection", [Alt('w')], |this: &Self| {
-            this.properties.cursor.copy_selection_to_clipboard();
-        });
-        // Cut selection to clipboard
-        bindings.add("cut-selection", [Ctrl('w')], |this: &Self| {
-            this.properties.cursor.cut_selection_to_clipboard();
-        });
-        // Paste from clipboard
-        bindings.add("paste-clipboard", [Ctrl('y')], |this: &Self| {
-            this.properties.cursor.paste_from_clipboard();
-        });
+        // The view scrolls as much as the cursor, so a cursor on line 5 moves
+        // to line 23 and stays on the sixth row
+        assert_eq!(18, scroll_page(0, 100, height, page, Direction::Forward));
+        assert_eq!(36, scroll_page(18, 100, height, page, Direction::Forward));
+        assert_eq!(0, scroll_page(18, 100, height, page, Direction::Backward));
 
-        // Undo / Redo
-        //
-        // Undo
-        bindings
-            .command("undo", |this: &Self| {
-                this.properties.cursor.undo();
-            })
-            .with([Ctrl('_')])
-            .with([Ctrl('z')])
-            .with([Ctrl('/')]);
+        // Near the ends of the text the view stops, the last line at the
+        // bottom of the window
+        assert_eq!(80, scroll_page(70, 100, height, page, Direction::Forward));
+        assert_eq!(80, scroll_page(80, 100, height, page, Direction::Forward));
+        assert_eq!(90, scroll_page(90, 100, height, page, Direction::Forward));
+        assert_eq!(0, scroll_page(0, 10, height, page, Direction::Forward));
+        assert_eq!(0, scroll_page(10, 100, height, page, Direction::Backward));
+    }
 
-        // Redo
-        bindings.add("redo", [Ctrl('q')], |this: &Self| {
-            this.properties.cursor.redo();
-        });
+    #[test]
+    fn lines_of_a_window() {
+        let height = 10;
+        assert_eq!(20, line_at(20, WindowLine::Top, height, 100));
+        assert_eq!(24, line_at(20, WindowLine::Middle, height, 100));
+        assert_eq!(29, line_at(20, WindowLine::Bottom, height, 100));
 
-        // Save buffer
-        bindings
-            .command("save-buffer", |this: &Self| {
-                this.properties.cursor.save();
-            })
-            .with([Ctrl('x'), Ctrl('s')])
-            .with([Ctrl('x'), Char('s')]);
+        // The window is past the end of the text
+        assert_eq!(95, line_at(95, WindowLine::Top, height, 100));
+        assert_eq!(97, line_at(95, WindowLine::Middle, height, 100));
+        assert_eq!(99, line_at(95, WindowLine::Bottom, height, 100));
+        assert_eq!(0, line_at(0, WindowLine::Bottom, height, 1));
+        assert_eq!(4, line_at(10, WindowLine::Top, height, 5));
+    }
 
-        // Centre cursor visually
-        bindings.add("center-cursor-visually", [Ctrl('l')], || {
-            Message::CenterCursorVisually
-        });
+    #[test]
+    fn buffer_keys_are_bound_to_buffer_commands() {
+        let buffer_commands = bindings::commands();
+        let keymap = bindings::keymap();
+        for (index, command) in buffer_commands.iter().enumerate() {
+            assert!(buffer_commands[..index]
+                .iter()
+                .all(|other| other.name != command.name));
+        }
+        for (name, _) in keymap.iter() {
+            assert!(commands::find(&buffer_commands, name).is_some(), "{}", name);
+        }
+        assert!(
+            commands::find(&buffer_commands, "move-forward-word")
+                .unwrap()
+                .repeatable
+        );
+    }
 
-        // View edit tree
-        //
-        // Toggle
-        bindings.add("toggle-edit-tree", [Ctrl('x'), Char('u')], || {
-            Message::ToggleEditTree
-        });
+    #[test]
+    fn change_a_number_by_the_count() {
+        let path = temp_file("counted-increment.txt", "41 apples\n");
+        let mut editor = TestEditor::open(&[&path]);
+        editor.press([
+            Key::Ctrl('u'),
+            Key::Char('3'),
+            Key::Ctrl('c'),
+            Key::Char('+'),
+        ]);
+        assert!(editor.screen().contains("44 apples"));
+        editor.press([
+            Key::Ctrl('u'),
+            Key::Char('5'),
+            Key::Ctrl('c'),
+            Key::Char('-'),
+        ]);
+        assert!(editor.screen().contains("39 apples"));
 
-        // Close
-        bindings.add("clear-selection", [Ctrl('g')], |this: &Self| {
-            if this.viewing_edit_tree {
-                Some(Message::ClearSelection)
-            } else {
-                this.properties.cursor.clear_selection();
-                None
-            }
-        });
+        // A negative count reverses the direction
+        editor.press([
+            Key::Ctrl('u'),
+            Key::Char('-'),
+            Key::Char('4'),
+            Key::Ctrl('c'),
+            Key::Char('+'),
+        ]);
+        assert!(editor.screen().contains("35 apples"));
+        std::fs::remove_file(path).unwrap();
     }
-}
 
-const EDIT_TREE_WIDTH: usize = 36;
+    #[test]
+    fn count_repeats_a_character_as_one_edit_or_a_command() {
+        let path = temp_file("counted-insert.txt", "end\n");
+        let mut editor = TestEditor::open(&[&path]);
+        editor.press([Key::Ctrl('u')]);
+        editor.type_text("40-");
+        assert!(editor.screen().contains(&format!("{}end", "-".repeat(40))));
+
+        // Undoing removes all of the characters at once
+        editor.press([Key::Ctrl('_')]);
+        assert!(!editor.screen().contains("-end"));
+
+        // Repeatable commands run the count times, `C-u C-u` is 16 and a
+        // count of zero does nothing
+        editor.press([Key::Ctrl('u'), Key::Char('3'), Key::Ctrl('f')]);
+        editor.press([Key::Ctrl('u'), Key::Ctrl('u'), Key::Char('!')]);
+        editor.press([Key::Ctrl('u'), Key::Char('0'), Key::Char('x')]);
+        assert!(editor
+            .screen()
+            .contains(&format!("end{}\n", "!".repeat(16))));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unsaved_lines_are_found_again_on_a_tick() {
+        let path = temp_file("unsaved-lines.txt", "one\ntwo\n");
+        let config = EditorConfig {
+            show_unsaved_lines: true,
+            ..EditorConfig::default()
+        };
+        let mut editor = TestEditor::with_config(&[&path], config);
+        editor.tick();
+        assert!(!editor.screen().contains('▎'));
+
+        editor.type_text("x");
+        editor.tick();
+        assert_eq!(1, editor.screen().matches('▎').count());
+
+        editor.press([Key::Ctrl('x'), Key::Ctrl('s')]);
+        editor.wait_for(|_| std::fs::read_to_string(&path).unwrap() == "xone\ntwo\n");
+        editor.tick();
+        assert!(!editor.screen().contains('▎'));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn type_over_the_matches_and_move_every_cursor() {
+        let path = temp_file("multiple-cursors.txt", "x = 1\nx = 2\n");
+        let mut editor = TestEditor::open(&[&path]);
+        editor.press([Key::Ctrl('c'), Key::Char('m'), Key::Char('m')]);
+        editor.press([Key::Ctrl('c'), Key::Char('m'), Key::Char('m')]);
+        editor.type_text("val");
+        let screen = editor.screen();
+        assert!(screen.contains("val = 1\n") && screen.contains("val = 2\n"));
+
+        // Moving to the end of the line keeps a cursor on each line
+        editor.press([Key::Ctrl('e')]);
+        editor.type_text(";");
+        let screen = editor.screen();
+        assert!(screen.contains("val = 1;\n") && screen.contains("val = 2;\n"));
+
+        // Each edit is undone at every cursor at once
+        editor.press([Key::Ctrl('_')]);
+        let screen = editor.screen();
+        assert!(screen.contains("val = 1\n") && screen.contains("val = 2\n"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn confirmed_commands_run_by_name() {
+        let path = temp_file("run-by-name.txt", "first line\nsecond line\n");
+        let mut config = EditorConfig::default();
+        config
+            .confirmations
+            .commands
+            .insert("kill-line".into(), Confirm::Always);
+        let mut editor = TestEditor::with_config(&[&path], config);
+        editor.press([Key::Ctrl('k')]);
+        assert!(editor
+            .screen()
+            .contains("Run kill-line: Kill to the end of the line"));
+        assert!(editor.screen().contains("first line"));
+
+        editor.type_text("y");
+        let screen = editor.screen();
+        assert!(!screen.contains("first line") && screen.contains("second line"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn syntax_selection_waits_for_the_parse_tree() {
+        let path = temp_file("no-syntax-tree.txt", "(one, two)\n");
+        let mut editor = TestEditor::open(&[&path]);
+        editor.press([Key::Ctrl('c'), Key::Char('y'), Key::Char('p')]);
+        assert!(editor.screen().contains("Syntax tree not ready"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn paste_is_inserted_literally_as_one_edit() {
+        let path = temp_file("paste.txt", "end\n");
+        let mut editor = TestEditor::open(&[&path]);
+        editor.type_text("a");
+        editor.paste("one\n\ttwo\n");
+        assert!(editor.screen().contains("aone"));
+
+        // Undoing removes the whole paste, but not what was typed before
+        editor.press([Key::Ctrl('_')]);
+        let screen = editor.screen();
+        assert!(screen.contains("aend") && !screen.contains("one"));
+
+        editor.paste("one\n\ttwo\n");
+        editor.press([Key::Ctrl('x'), Key::Ctrl('s')]);
+        editor.wait_for(|_| std::fs::read_to_string(&path).unwrap() == "aone\n\ttwo\nend\n");
+        std::fs::remove_file(path).unwrap();
+    }
+}
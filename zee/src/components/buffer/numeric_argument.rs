@@ -0,0 +1,122 @@
+use zi::terminal::Key;
+
+/// The numeric argument typed after `universal-argument` (`C-u`), applied
+/// to the next command. `C-u` alone is 4 and each further `C-u` multiplies
+/// it by 4. Digits give the number instead and a `-` before them negates it,
+/// with `C-u -` alone being -1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumericArgument {
+    digits: Option<usize>,
+    multiplier: usize,
+    negative: bool,
+    // A `C-u` after the digits ends them, so that digits can be repeated,
+    // e.g. `C-u 5 C-u 0` for `00000`
+    ended: bool,
+}
+
+/// What typing a key does while a numeric argument is typed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArgumentKey {
+    /// The key is part of the argument
+    Argument(NumericArgument),
+    /// The key runs a command with the argument as its count
+    Command(isize),
+}
+
+impl NumericArgument {
+    pub fn new() -> Self {
+        Self {
+            digits: None,
+            multiplier: 4,
+            negative: false,
+            ended: false,
+        }
+    }
+
+    /// Takes the next key typed
+    pub fn key(self, key: Key) -> ArgumentKey {
+        match key {
+            Key::Ctrl('u') if self.digits.is_some() || self.negative => {
+                ArgumentKey::Argument(Self {
+                    ended: true,
+                    ..self
+                })
+            }
+            Key::Ctrl('u') => ArgumentKey::Argument(Self {
+                multiplier: self.multiplier.saturating_mul(4),
+                ..self
+            }),
+            Key::Char(digit @ '0'..='9') if !self.ended => ArgumentKey::Argument(Self {
+                digits: Some(
+                    self.digits
+                        .unwrap_or(0)
+                        .saturating_mul(10)
+                        .saturating_add(digit.to_digit(10).unwrap_or(0) as usize),
+                ),
+                ..self
+            }),
+            Key::Char('-') if !self.ended && !self.negative && self.digits.is_none() => {
+                ArgumentKey::Argument(Self {
+                    negative: true,
+                    ..self
+                })
+            }
+            _ => ArgumentKey::Command(self.count()),
+        }
+    }
+
+    fn count(&self) -> isize {
+        let count = match self.digits {
+            Some(digits) => digits,
+            None if self.negative => 1,
+            None => self.multiplier,
+        };
+        let count = isize::try_from(count).unwrap_or(isize::MAX);
+        if self.negative {
+            -count
+        } else {
+            count
+        }
+    }
+}
+
+impl Default for NumericArgument {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(keys: &[Key]) -> Option<isize> {
+        let mut argument = NumericArgument::new();
+        for &key in keys {
+            match argument.key(key) {
+                ArgumentKey::Argument(next) => argument = next,
+                ArgumentKey::Command(count) => return Some(count),
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn numeric_argument_counts() {
+        use Key::{Char, Ctrl};
+
+        assert_eq!(Some(40), count(&[Char('4'), Char('0'), Char('-')]));
+        assert_eq!(Some(4), count(&[Char('x')]));
+        assert_eq!(Some(16), count(&[Ctrl('u'), Char('x')]));
+        assert_eq!(Some(-1), count(&[Char('-'), Char('x')]));
+        assert_eq!(
+            Some(-12),
+            count(&[Char('-'), Char('1'), Char('2'), Char('x')])
+        );
+        assert_eq!(Some(0), count(&[Char('0'), Ctrl('f')]));
+
+        // `C-u` ends the digits, so the next digit runs a command
+        assert_eq!(Some(5), count(&[Char('5'), Ctrl('u'), Char('0')]));
+        assert_eq!(None, count(&[Char('5'), Ctrl('u')]));
+    }
+}
@@ -0,0 +1,261 @@
+use ropey::Rope;
+use std::{cmp, ops::Range};
+use zi::terminal::{Colour, Style};
+
+use zee_edit::CharIndex;
+
+/// The priorities of the overlays drawn by the buffer. An overlay with a
+/// higher priority is drawn over the ones with a lower priority.
+pub mod priority {
+    pub const SYMBOL: u8 = 10;
+    pub const TODO: u8 = 20;
+    pub const MISSPELLED: u8 = 30;
+    pub const SEARCH: u8 = 35;
+    pub const BRACKET: u8 = 38;
+    pub const SELECTION: u8 = 40;
+    pub const CURSOR: u8 = 50;
+}
+
+/// The parts of the style of a character an overlay changes. The others are
+/// left as they are drawn underneath.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OverlayStyle {
+    pub background: Option<Colour>,
+    pub foreground: Option<Colour>,
+    pub bold: Option<bool>,
+    pub underline: Option<bool>,
+}
+
+impl OverlayStyle {
+    pub fn background(background: Colour) -> Self {
+        Self {
+            background: Some(background),
+            ..Self::default()
+        }
+    }
+
+    pub fn underline() -> Self {
+        Self {
+            underline: Some(true),
+            ..Self::default()
+        }
+    }
+
+    /// Changes the colours of a character, keeping it bold or underlined
+    pub fn colours(style: Style) -> Self {
+        Self {
+            background: Some(style.background),
+            foreground: Some(style.foreground),
+            ..Self::default()
+        }
+    }
+
+    /// Changes the foreground of a character and whether it's bold
+    pub fn text(style: Style) -> Self {
+        Self {
+            foreground: Some(style.foreground),
+            bold: Some(style.bold),
+            ..Self::default()
+        }
+    }
+
+    pub fn apply(&self, style: Style) -> Style {
+        Style {
+            background: self.background.unwrap_or(style.background),
+            foreground: self.foreground.unwrap_or(style.foreground),
+            bold: self.bold.unwrap_or(style.bold),
+            underline: self.underline.unwrap_or(style.underline),
+        }
+    }
+}
+
+/// Styling drawn over the syntax highlighting of a range of text, e.g. the
+/// selection or a misspelled word
+#[derive(Clone, Debug, PartialEq)]
+pub struct Overlay {
+    pub range: Range<CharIndex>,
+    pub style: OverlayStyle,
+    pub priority: u8,
+}
+
+/// The overlays drawn by a textarea, composited over the syntax highlighting
+/// in priority order. Overlays with the same priority are drawn in the order
+/// they were added.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Overlays {
+    // Ordered by priority
+    overlays: Vec<Overlay>,
+}
+
+impl Overlays {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, range: Range<CharIndex>, style: OverlayStyle, priority: u8) {
+        if range.is_empty() {
+            return;
+        }
+        let index = self
+            .overlays
+            .partition_point(|overlay| overlay.priority <= priority);
+        self.overlays.insert(
+            index,
+            Overlay {
+                range,
+                style,
+                priority,
+            },
+        );
+    }
+
+    /// Adds an overlay with the same style and priority for each range
+    pub fn add_all(
+        &mut self,
+        ranges: impl IntoIterator<Item = Range<CharIndex>>,
+        style: OverlayStyle,
+        priority: u8,
+    ) {
+        for range in ranges {
+            self.add(range, style, priority);
+        }
+    }
+
+    /// The overlays which intersect `range`, cut to fit inside it
+    pub fn clip(&self, range: Range<CharIndex>) -> Self {
+        let overlays = self
+            .overlays
+            .iter()
+            .filter_map(|overlay| {
+                let clipped = cmp::max(overlay.range.start, range.start)
+                    ..cmp::min(overlay.range.end, range.end);
+                (!clipped.is_empty()).then(|| Overlay {
+                    range: clipped,
+                    ..overlay.clone()
+                })
+            })
+            .collect();
+        Self { overlays }
+    }
+
+    /// Adds the overlays of each layer for the text in `range`
+    pub fn draw_layers<T>(
+        &mut self,
+        component: &T,
+        layers: &[OverlayLayer<T>],
+        text: &Rope,
+        range: Range<CharIndex>,
+    ) {
+        for layer in layers {
+            layer(component, text, range.clone(), self);
+        }
+    }
+
+    /// Draws the overlays covering `char_index` over its `style`
+    pub fn style_at(&self, char_index: CharIndex, style: Style) -> Style {
+        self.overlays
+            .iter()
+            .filter(|overlay| overlay.range.contains(&char_index))
+            .fold(style, |style, overlay| overlay.style.apply(style))
+    }
+}
+
+/// A feature's overlays, e.g. the search matches, registered with the
+/// component drawing the text. It adds them for the text in a range, usually
+/// the lines in view, each with the priority it's drawn at.
+pub type OverlayLayer<T> = fn(&T, &Rope, Range<CharIndex>, &mut Overlays);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Colour = Colour::rgb(0, 0, 0);
+    const RED: Colour = Colour::rgb(255, 0, 0);
+    const GREEN: Colour = Colour::rgb(0, 255, 0);
+    const BLUE: Colour = Colour::rgb(0, 0, 255);
+
+    #[test]
+    fn overlapping_overlays_resolve_by_priority() {
+        let text = Style::normal(BLACK, BLUE);
+        let mut overlays = Overlays::new();
+        overlays.add(0..10, OverlayStyle::background(RED), priority::SELECTION);
+        overlays.add(5..15, OverlayStyle::background(GREEN), priority::SYMBOL);
+        overlays.add(8..9, OverlayStyle::underline(), priority::MISSPELLED);
+
+        // The selection is drawn over the symbol, whichever was added first
+        assert_eq!(Style::normal(RED, BLUE), overlays.style_at(6, text));
+        assert_eq!(Style::normal(GREEN, BLUE), overlays.style_at(12, text));
+        assert_eq!(Style::underline(RED, BLUE), overlays.style_at(8, text));
+        assert_eq!(text, overlays.style_at(15, text));
+
+        // Overlays with the same priority are drawn in the order they're added
+        overlays.add(0..2, OverlayStyle::background(BLUE), priority::SELECTION);
+        overlays.add(1..2, OverlayStyle::background(GREEN), priority::SELECTION);
+        assert_eq!(Style::normal(BLUE, BLUE), overlays.style_at(0, text));
+        assert_eq!(Style::normal(GREEN, BLUE), overlays.style_at(1, text));
+    }
+
+    #[test]
+    fn layers_draw_the_overlays_in_range() {
+        struct Highlights {
+            words: Vec<Range<CharIndex>>,
+            selection: Range<CharIndex>,
+        }
+        const LAYERS: &[OverlayLayer<Highlights>] = &[
+            |this, _, _, overlays| {
+                overlays.add(
+                    this.selection.clone(),
+                    OverlayStyle::background(RED),
+                    priority::SELECTION,
+                )
+            },
+            |this, _, range, overlays| {
+                overlays.add_all(
+                    this.words
+                        .iter()
+                        .filter(|word| word.start < range.end && range.start < word.end)
+                        .cloned(),
+                    OverlayStyle::background(GREEN),
+                    priority::SYMBOL,
+                )
+            },
+        ];
+
+        let text = Rope::from("one two one two one");
+        let highlights = Highlights {
+            words: vec![0..3, 8..11, 16..19],
+            selection: 9..12,
+        };
+        let mut overlays = Overlays::new();
+        overlays.draw_layers(&highlights, LAYERS, &text, 4..12);
+        let style = Style::normal(BLACK, BLUE);
+        assert_eq!(style, overlays.style_at(0, style));
+        assert_eq!(Style::normal(GREEN, BLUE), overlays.style_at(8, style));
+        // Registered first, the selection is still drawn over the words
+        assert_eq!(Style::normal(RED, BLUE), overlays.style_at(10, style));
+        assert_eq!(style, overlays.style_at(16, style));
+    }
+
+    #[test]
+    fn overlays_clip_to_visible_lines() {
+        let mut overlays = Overlays::new();
+        overlays.add_all(
+            vec![0..4, 6..12, 20..30, 35..40],
+            OverlayStyle::underline(),
+            priority::MISSPELLED,
+        );
+        overlays.add(3..3, OverlayStyle::underline(), priority::MISSPELLED);
+
+        let ranges = |overlays: &Overlays| -> Vec<Range<CharIndex>> {
+            overlays
+                .overlays
+                .iter()
+                .map(|overlay| overlay.range.clone())
+                .collect()
+        };
+        assert_eq!(vec![10..12, 20..25], ranges(&overlays.clip(10..25)));
+        assert_eq!(vec![0..4], ranges(&overlays.clip(0..6)));
+        assert!(overlays.clip(12..20).overlays.is_empty());
+        assert!(overlays.clip(40..50).overlays.is_empty());
+    }
+}
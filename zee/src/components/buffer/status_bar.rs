@@ -1,5 +1,6 @@
+use serde_derive::{Deserialize, Serialize};
 use size_format::SizeFormatterBinary;
-use std::{ops::Range, path::PathBuf};
+use std::{fmt, ops::Range, path::PathBuf};
 use zi::{
     unicode_width::UnicodeWidthStr, Canvas, Component, ComponentLink, Layout, Rect, ShouldRender,
     Size, Style,
@@ -8,7 +9,7 @@ use zi::{
 use zee_grammar::Mode;
 
 use crate::{
-    editor::buffer::{ModifiedStatus, RepositoryRc},
+    editor::buffer::{DiskStatus, ModifiedStatus, RepositoryRc},
     utils::StaticRefEq,
 };
 
@@ -25,21 +26,305 @@ pub struct Theme {
     pub mode: Style,
 }
 
+/// The extent of the active selection, displayed in the status bar
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SelectionSize {
+    pub num_lines: usize,
+    pub num_chars: usize,
+}
+
+/// The position of the cursor among the matches of a search
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchMatches {
+    pub current: Option<usize>,
+    pub num_matches: usize,
+}
+
+/// How far through the text the window is scrolled, like Emacs shows it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScrollPosition {
+    /// The whole text fits in the window
+    All,
+    Top,
+    /// The last line is in the window
+    Bottom,
+    /// The percentage of the text above the window
+    Percent(usize),
+}
+
+impl ScrollPosition {
+    /// The scroll position of a window of `height` lines showing `num_lines`
+    /// from `line_offset`
+    pub fn new(line_offset: usize, height: usize, num_lines: usize) -> Self {
+        let at_bottom = line_offset + height >= num_lines;
+        match (line_offset, at_bottom) {
+            (0, true) => Self::All,
+            (0, false) => Self::Top,
+            (_, true) => Self::Bottom,
+            (_, false) => Self::Percent(100 * line_offset / num_lines),
+        }
+    }
+}
+
+impl fmt::Display for ScrollPosition {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::All => write!(formatter, "All"),
+            Self::Top => write!(formatter, "Top"),
+            Self::Bottom => write!(formatter, "Bot"),
+            Self::Percent(percent) => write!(formatter, "{:>2}%", percent),
+        }
+    }
+}
+
+/// A part of the status bar, as named in the configuration file. A segment
+/// without anything to show, e.g. the branch outside a repository, is left
+/// out.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Segment {
+    /// The number of the window
+    FrameId,
+    /// `+` if the buffer has unsaved changes, `-` otherwise
+    Modified,
+    FileSize,
+    FileName,
+    /// `[loading 10%]`, `[read-only]` or `[large file]`
+    FileStatus,
+    /// `[changed on disk]` or `[deleted]`
+    DiskStatus,
+    /// `[noeol]` if the file doesn't end with a newline
+    MissingFinalNewline,
+    Encoding,
+    /// `LF` or `CRLF`
+    LineEnding,
+    Mode,
+    /// The branch checked out in the file's repository
+    Branch,
+    /// How far the window is scrolled, e.g. `Top` or `42%`
+    ScrollPosition,
+    /// A bar showing the line of the cursor in the file
+    PositionBar,
+    /// The line and column of the cursor, e.g. `12:4`
+    LineColumn,
+    /// The number of lines and characters selected
+    Selection,
+    /// The current match of a search, e.g. `match 2 of 5`
+    SearchMatches,
+    /// The prefix keys typed so far, e.g. `C-x-`
+    PendingKeys,
+    /// The scopes enclosing the cursor, e.g. `mod foo › impl Bar › fn baz`
+    Breadcrumb,
+    /// Literal text, e.g. a separator
+    Text(String),
+}
+
+/// A segment and the end of the status bar it's drawn from
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum StatusBarItem {
+    Left(Segment),
+    Right(Segment),
+}
+
+/// The segments of the status bar, most important first. Segments are
+/// drawn from the left or the right end in order, once one doesn't fit it
+/// and the segments after it are left out.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct StatusBarLayout(pub Vec<StatusBarItem>);
+
+impl StatusBarLayout {
+    /// Whether a segment is drawn from either end
+    pub fn contains(&self, segment: &Segment) -> bool {
+        self.0.iter().any(|item| match item {
+            StatusBarItem::Left(current) | StatusBarItem::Right(current) => current == segment,
+        })
+    }
+}
+
+impl Default for StatusBarLayout {
+    fn default() -> Self {
+        use self::{Segment::*, StatusBarItem::*};
+
+        Self(vec![
+            Left(FrameId),
+            Left(Modified),
+            Right(PositionBar),
+            Left(FileSize),
+            Left(FileName),
+            Left(FileStatus),
+            Left(DiskStatus),
+            Left(MissingFinalNewline),
+            Right(ScrollPosition),
+            Right(LineColumn),
+            Right(Selection),
+            Right(SearchMatches),
+            Right(PendingKeys),
+            Left(Mode),
+            Right(Branch),
+        ])
+    }
+}
+
 #[derive(Clone, PartialEq)]
 pub struct Properties {
     pub theme: Theme,
+    pub layout: StatusBarLayout,
     pub current_line_index: usize,
     pub column_offset: usize,
     pub file_path: Option<PathBuf>,
+    pub missing_final_newline: bool,
+    pub crlf: bool,
     pub focused: bool,
     pub frame_id: usize,
     pub modified_status: ModifiedStatus,
+    pub disk_status: DiskStatus,
+    pub large_file: bool,
+    pub loading_progress: Option<usize>,
+    pub read_only: bool,
     pub mode: StaticRefEq<Mode>,
     pub num_lines: usize,
     pub repository: Option<RepositoryRc>,
+    pub scroll_position: ScrollPosition,
+    pub search: Option<SearchMatches>,
+    pub selection: Option<SelectionSize>,
+    /// The prefix keys typed so far, e.g. `C-x-`
+    pub pending_keys: Option<String>,
+    /// The scopes enclosing the cursor, `None` without a parse tree
+    pub breadcrumb: Option<String>,
     pub size_bytes: u64,
 }
 
+impl Properties {
+    /// The text of a segment and its style, `None` if it has nothing to show
+    fn segment(&self, segment: &Segment) -> Option<(Style, String)> {
+        let theme = &self.theme;
+        let (style, text) = match *segment {
+            Segment::FrameId => (
+                if self.focused {
+                    theme.frame_id_focused
+                } else {
+                    theme.frame_id_unfocused
+                },
+                format!(" {} ", self.frame_id),
+            ),
+            Segment::Modified => match self.modified_status {
+                ModifiedStatus::Unchanged => (theme.is_not_modified, " - ".into()),
+                ModifiedStatus::Changed | ModifiedStatus::Saving => {
+                    (theme.is_modified, " + ".into())
+                }
+            },
+            Segment::FileSize => (
+                theme.file_size,
+                format!(" {}", SizeFormatterBinary::new(self.size_bytes)),
+            ),
+            Segment::FileName => {
+                let path = self.file_path.as_ref()?;
+                let text = match path.file_name().and_then(|file_name| file_name.to_str()) {
+                    Some(file_name) => format!(" {}", file_name),
+                    None => format!(" {}", path.display()),
+                };
+                (theme.file_name, text)
+            }
+            // Large files are opened with fewer features
+            Segment::FileStatus => (
+                theme.is_modified,
+                match (self.loading_progress, self.large_file, self.read_only) {
+                    (Some(percent), _, _) => format!(" [loading {}%]", percent),
+                    (None, _, true) => " [read-only]".into(),
+                    (None, true, false) => " [large file]".into(),
+                    (None, false, false) => return None,
+                },
+            ),
+            // The file changed on disk since it was read or written
+            Segment::DiskStatus => (
+                theme.is_modified,
+                match self.disk_status {
+                    DiskStatus::InSync => return None,
+                    DiskStatus::Changed => " [changed on disk]".into(),
+                    DiskStatus::Deleted => " [deleted]".into(),
+                },
+            ),
+            Segment::MissingFinalNewline if self.missing_final_newline => {
+                (theme.is_modified, " [noeol]".into())
+            }
+            Segment::MissingFinalNewline => return None,
+            Segment::Encoding => (theme.position_in_file, " UTF-8 ".into()),
+            Segment::LineEnding => (
+                theme.position_in_file,
+                if self.crlf { " CRLF " } else { " LF " }.into(),
+            ),
+            Segment::Mode => (theme.mode, format!("  {}", self.mode.name)),
+            Segment::Branch => {
+                let head = self.repository.as_ref()?.head().ok()?;
+                (theme.position_in_file, format!("{}  ", head.shorthand()?))
+            }
+            Segment::ScrollPosition => (
+                theme.position_in_file,
+                format!(" {} ", self.scroll_position),
+            ),
+            // Visual indicator for the current position in the file
+            Segment::PositionBar if self.focused => (
+                theme.frame_id_focused,
+                PROGRESS_SYMBOLS[((PROGRESS_SYMBOLS.len() - 1) as f32
+                    * (self.current_line_index as f32 / self.num_lines as f32))
+                    .round() as usize]
+                    .to_string(),
+            ),
+            Segment::PositionBar => (theme.position_in_file, " ".into()),
+            Segment::LineColumn => (
+                theme.is_not_modified,
+                format!(
+                    " {one_based_line_index:>3}:{column_offset:>2} ",
+                    one_based_line_index = self.current_line_index + 1,
+                    column_offset = self.column_offset
+                ),
+            ),
+            Segment::Selection => {
+                let SelectionSize {
+                    num_lines,
+                    num_chars,
+                } = self.selection?;
+                (
+                    theme.position_in_file,
+                    format!(
+                        " {} line{}, {} char{} ",
+                        num_lines,
+                        if num_lines == 1 { "" } else { "s" },
+                        num_chars,
+                        if num_chars == 1 { "" } else { "s" },
+                    ),
+                )
+            }
+            Segment::SearchMatches => match self.search? {
+                SearchMatches {
+                    current: Some(current),
+                    num_matches,
+                } => (
+                    theme.position_in_file,
+                    format!(" match {} of {} ", current + 1, num_matches),
+                ),
+                SearchMatches { num_matches: 0, .. } => {
+                    (theme.position_in_file, " no matches ".into())
+                }
+                _ => return None,
+            },
+            Segment::PendingKeys => (
+                theme.frame_id_focused,
+                format!(" {} ", self.pending_keys.as_ref()?),
+            ),
+            Segment::Breadcrumb => (
+                theme.position_in_file,
+                format!(
+                    " {} ",
+                    self.breadcrumb.as_ref().filter(|path| !path.is_empty())?
+                ),
+            ),
+            Segment::Text(ref text) => (theme.base, text.clone()),
+        };
+        (!text.is_empty()).then(|| (style, text))
+    }
+}
+
 pub struct StatusBar {
     properties: Properties,
     frame: Rect,
@@ -68,137 +353,33 @@ impl Component for StatusBar {
     }
 
     fn view(&self) -> Layout {
-        let Self {
-            properties:
-                Properties {
-                    ref file_path,
-                    ref modified_status,
-                    ref mode,
-                    ref repository,
-                    ref theme,
-                    current_line_index,
-                    focused,
-                    frame_id,
-                    num_lines,
-                    size_bytes,
-                    column_offset,
-                },
-            frame,
-        } = *self;
-
-        let mut canvas = StatusCanvas::new(frame.size, theme.base);
-        Some(&mut canvas)
-            // Buffer number
-            .and_then(|canvas| {
-                canvas.append_start(
-                    if focused {
-                        theme.frame_id_focused
-                    } else {
-                        theme.frame_id_unfocused
-                    },
-                    &format!(" {} ", frame_id),
-                )
-            })
-            // Has unsaved changes
-            .and_then(|canvas| {
-                canvas.append_start(
-                    match modified_status {
-                        ModifiedStatus::Unchanged => theme.is_not_modified,
-                        _ => theme.is_modified,
-                    },
-                    match modified_status {
-                        ModifiedStatus::Unchanged => " - ",
-                        ModifiedStatus::Changed | ModifiedStatus::Saving => " + ",
-                    },
-                )
-            })
-            // Visual indicator for current position in the file, right-aligned
-            .and_then(|canvas| {
-                if focused {
-                    canvas.append_end(
-                        theme.frame_id_focused,
-                        &format!(
-                            "{}",
-                            PROGRESS_SYMBOLS[((PROGRESS_SYMBOLS.len() - 1) as f32
-                                * (current_line_index as f32 / num_lines as f32))
-                                .round() as usize],
-                        ),
-                    )
-                } else {
-                    canvas.append_end(theme.position_in_file, " ")
-                }
-            })
-            // File size
-            .and_then(|canvas| {
-                canvas.append_start(
-                    theme.file_size,
-                    &format!(" {}", SizeFormatterBinary::new(size_bytes)),
-                )
-            })
-            // File name if buffer is backed by a file
-            .and_then(|canvas| {
-                canvas.append_start(
-                    theme.file_name,
-                    &file_path
-                        .as_ref()
-                        .map(|path| {
-                            match path.file_name().and_then(|file_name| file_name.to_str()) {
-                                Some(file_name) => format!(" {}", file_name),
-                                None => format!(" {}", path.display()),
-                            }
-                        })
-                        .unwrap_or_else(String::new),
-                )
-            })
-            // The current position in the file as a percentage, right-aligned
-            .and_then(|canvas| {
-                canvas.append_end(
-                    theme.position_in_file,
-                    &if current_line_index == 0 {
-                        " Top ".into()
-                    } else if current_line_index >= num_lines.saturating_sub(2) {
-                        " End ".into()
-                    } else {
-                        format!(
-                            " {percent:>2}% ",
-                            percent = if num_lines > 0 {
-                                100 * (current_line_index + 1) / num_lines
-                            } else {
-                                100
-                            }
-                        )
-                    },
-                )
-            })
-            // The row:column in the file, right-aligned
-            .and_then(|canvas| {
-                let line_status = format!(
-                    " {one_based_line_index:>3}:{column_offset:>2} ",
-                    one_based_line_index = current_line_index + 1
-                );
-                canvas.append_end(theme.is_not_modified, &line_status)
-            })
-            // Name of the current mode
-            .and_then(|canvas| canvas.append_start(theme.mode, &format!("  {}", mode.name)))
-            // Name of the repo right aligned
-            .and_then(|canvas| {
-                canvas.append_end(
-                    theme.position_in_file,
-                    &match repository
-                        .as_ref()
-                        .and_then(|repo| repo.head().ok())
-                        .as_ref()
-                        .and_then(|reference| reference.shorthand())
-                    {
-                        Some(reference) => format!("{}  ", reference),
-                        None => String::new(),
-                    },
-                )
-            });
-        canvas.into()
+        render(&self.properties, self.frame.size).into()
     }
 }
 
+/// Draws the segments of the status bar's layout which fit in it
+fn render(properties: &Properties, size: Size) -> StatusCanvas {
+    let mut canvas = StatusCanvas::new(size, properties.theme.base);
+    for item in properties.layout.0.iter() {
+        let fits = match item {
+            StatusBarItem::Left(segment) => {
+                properties.segment(segment).map_or(true, |(style, text)| {
+                    canvas.append_start(style, &text).is_some()
+                })
+            }
+            StatusBarItem::Right(segment) => {
+                properties.segment(segment).map_or(true, |(style, text)| {
+                    canvas.append_end(style, &text).is_some()
+                })
+            }
+        };
+        if !fits {
+            break;
+        }
+    }
+    canvas
+}
+
 struct StatusCanvas {
     canvas: Canvas,
     free: Range<usize>,
@@ -246,3 +427,128 @@ impl From<StatusCanvas> for Layout {
 }
 
 const PROGRESS_SYMBOLS: [char; 8] = ['▇', '▆', '▅', '▄', '▃', '▂', '▁', ' '];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PLAIN_TEXT_MODE;
+
+    fn properties(layout: StatusBarLayout) -> Properties {
+        let style = Style::default();
+        Properties {
+            theme: Theme {
+                base: style,
+                frame_id_focused: style,
+                frame_id_unfocused: style,
+                is_modified: style,
+                is_not_modified: style,
+                file_name: style,
+                file_size: style,
+                position_in_file: style,
+                mode: style,
+            },
+            layout,
+            current_line_index: 11,
+            column_offset: 4,
+            file_path: Some("src/main.rs".into()),
+            missing_final_newline: false,
+            crlf: false,
+            focused: false,
+            frame_id: 1,
+            modified_status: ModifiedStatus::Changed,
+            disk_status: DiskStatus::InSync,
+            large_file: false,
+            loading_progress: None,
+            read_only: false,
+            mode: (&*PLAIN_TEXT_MODE).into(),
+            num_lines: 100,
+            repository: None,
+            scroll_position: ScrollPosition::Percent(5),
+            search: None,
+            selection: None,
+            pending_keys: None,
+            breadcrumb: None,
+            size_bytes: 2048,
+        }
+    }
+
+    fn render_to_string(properties: &Properties, width: usize) -> String {
+        let canvas = render(properties, Size::new(width, 1)).canvas;
+        (0..width)
+            .map(|x| match canvas.textel(x, 0) {
+                Some(textel) => textel.grapheme.to_string(),
+                None => String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scroll_position_of_the_window() {
+        let height = 10;
+        assert_eq!(ScrollPosition::All, ScrollPosition::new(0, height, 5));
+        assert_eq!(ScrollPosition::All, ScrollPosition::new(0, height, 10));
+        assert_eq!(ScrollPosition::Top, ScrollPosition::new(0, height, 11));
+        assert_eq!(
+            ScrollPosition::Percent(1),
+            ScrollPosition::new(1, height, 100)
+        );
+        assert_eq!(
+            ScrollPosition::Percent(50),
+            ScrollPosition::new(50, height, 100)
+        );
+        assert_eq!(
+            ScrollPosition::Percent(89),
+            ScrollPosition::new(89, height, 100)
+        );
+        assert_eq!(ScrollPosition::Bottom, ScrollPosition::new(90, height, 100));
+        assert_eq!(ScrollPosition::Bottom, ScrollPosition::new(99, height, 100));
+        assert_eq!(" 5%", ScrollPosition::Percent(5).to_string());
+        assert_eq!("Bot", ScrollPosition::Bottom.to_string());
+    }
+
+    #[test]
+    fn render_the_default_layout() {
+        let properties = properties(StatusBarLayout::default());
+        let start = format!(
+            " 1  +  {} main.rs  {}",
+            SizeFormatterBinary::new(properties.size_bytes),
+            PLAIN_TEXT_MODE.name
+        );
+        let end = "  12: 4   5%  ";
+        assert_eq!(
+            format!(
+                "{}{}{}",
+                start,
+                " ".repeat(80 - start.len() - end.len()),
+                end
+            ),
+            render_to_string(&properties, 80)
+        );
+    }
+
+    #[test]
+    fn render_a_custom_layout() {
+        use self::{Segment::*, StatusBarItem::*};
+
+        let mut properties = properties(StatusBarLayout(vec![
+            Left(FileName),
+            Left(Text(" |".into())),
+            Left(Branch),
+            Left(LineEnding),
+            Right(LineColumn),
+            Right(Encoding),
+        ]));
+        // The branch is left out outside a repository
+        assert_eq!(
+            format!(" main.rs | LF {} UTF-8   12: 4 ", " ".repeat(7)),
+            render_to_string(&properties, 36)
+        );
+
+        // Segments which don't fit are left out, with the ones after them
+        properties.crlf = true;
+        assert_eq!(
+            format!(" main.rs | CRLF {}  12: 4 ", " ".repeat(4)),
+            render_to_string(&properties, 28)
+        );
+    }
+}
@@ -1,20 +1,94 @@
 use euclid::default::SideOffsets2D;
 use ropey::{Rope, RopeSlice};
-use std::{iter, ops::Range};
+use serde_derive::{Deserialize, Serialize};
+use std::{cmp, iter, ops::Range, rc::Rc};
 use tree_sitter::{Node, Query, QueryCursor, TextProvider};
 use zi::{
-    terminal::GraphemeCluster, Canvas, Component, ComponentLink, Layout, Position, Rect,
-    ShouldRender, Size,
+    terminal::{GraphemeCluster, Style},
+    Canvas, Component, ComponentLink, Layout, Position, Rect, ShouldRender, Size,
 };
 
-use zee_edit::{ByteIndex, Cursor, LineIndex, RopeGraphemes};
+use zee_edit::{
+    rectangle::{self, Rectangle},
+    ByteIndex, Cursor, LineIndex, RopeGraphemes,
+};
 use zee_grammar::Mode;
 
+use super::{
+    bidi::{self, VisualGrapheme},
+    overlay::Overlays,
+};
 use crate::syntax::{
     highlight::{text_style_at_char, Theme as SyntaxTheme},
     parse::ParseTree,
 };
 
+/// How control characters like form feeds are shown, as set in the
+/// configuration file. Either way they take up two columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ControlCharacterStyle {
+    /// Caret notation, e.g. `^L` for a form feed
+    Caret,
+    /// The symbol from the Control Pictures block, e.g. `␌` for a form feed
+    Picture,
+}
+
+impl Default for ControlCharacterStyle {
+    fn default() -> Self {
+        Self::Caret
+    }
+}
+
+/// How the cursor of the focused window is drawn, as set in the
+/// configuration file. The cursors of other windows are always blocks.
+///
+/// There's no bar shape: cursors are drawn by styling the character under
+/// them, which can't draw a line between two characters, so configurations
+/// asking for one fail to parse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CursorShape {
+    /// The character under the cursor in the cursor's colours
+    Block,
+    /// The character under the cursor underlined in the cursor's colour
+    Underline,
+}
+
+impl Default for CursorShape {
+    fn default() -> Self {
+        Self::Block
+    }
+}
+
+/// A vertical guide drawn down a column of the text area
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Guide {
+    /// One of the configured `column_guides`
+    Column,
+    TabStop,
+}
+
+/// The guides of a text area `width` cells wide, in order of column. Tab
+/// stops are every `tab_width` columns when given, though a column guide
+/// takes precedence where both fall. Guides past the width are left out.
+fn guide_columns(columns: &[usize], tab_width: Option<usize>, width: usize) -> Vec<(usize, Guide)> {
+    let mut guides: Vec<_> = columns
+        .iter()
+        .filter(|&&column| column < width)
+        .map(|&column| (column, Guide::Column))
+        .collect();
+    if let Some(tab_width) = tab_width.filter(|&tab_width| tab_width > 0) {
+        guides.extend(
+            (tab_width..width)
+                .step_by(tab_width)
+                .filter(|column| !columns.contains(column))
+                .map(|column| (column, Guide::TabStop)),
+        );
+    }
+    guides.sort_unstable();
+    guides.dedup();
+    guides
+}
+
 #[derive(Clone)]
 pub struct Properties {
     pub theme: SyntaxTheme,
@@ -24,6 +98,19 @@ pub struct Properties {
     pub mode: &'static Mode,
     pub line_offset: usize,
     pub parse_tree: Option<ParseTree>,
+    pub control_characters: ControlCharacterStyle,
+    /// The selection, cursors and highlights drawn over the text
+    pub overlays: Rc<Overlays>,
+    /// A rectangular selection, whose cells past the end of a line are drawn
+    /// as well
+    pub rectangle: Option<Rectangle>,
+    /// The columns of vertical guides, e.g. `[72, 100]`
+    pub column_guides: Vec<usize>,
+    /// Mark the tab stops in the indentation of each line
+    pub tab_stop_guides: bool,
+    /// Lines pinned to the top of the text area, e.g. the first lines of the
+    /// scopes enclosing the text in view
+    pub sticky_lines: Vec<LineIndex>,
 }
 
 pub struct TextArea {
@@ -52,16 +139,16 @@ impl Component for TextArea {
     fn view(&self) -> Layout {
         let mut canvas = Canvas::new(self.frame.size);
         canvas.clear(self.properties.theme.text);
-        self.draw_text(&mut canvas);
+        let expanse = self.text_expanse_in_view(&canvas);
+        self.draw_text(&mut canvas, expanse);
+        self.draw_sticky_lines(&mut canvas);
         canvas.into()
     }
 }
 
 impl TextArea {
     #[inline]
-    fn draw_text(&self, canvas: &mut Canvas) {
-        let expanse = self.text_expanse_in_view(canvas);
-
+    fn draw_text(&self, canvas: &mut Canvas, expanse: TextExpanse) {
         let parse_tree = self
             .properties
             .parse_tree
@@ -113,16 +200,25 @@ impl TextArea {
         canvas: &mut Canvas,
         get_scope: &mut impl FnMut(ByteIndex) -> Option<&'a str>,
     ) {
+        let guides = guide_columns(
+            &self.properties.column_guides,
+            self.properties
+                .tab_stop_guides
+                .then(|| self.properties.mode.indentation.tab_width()),
+            canvas.size().width,
+        );
+        let first_line = expanse.line_range.start;
         for line_index in expanse.line_range {
             self.draw_line(
                 canvas,
                 Rect::from_size(canvas.size()).inner_rect(SideOffsets2D::new(
-                    line_index - self.properties.line_offset,
+                    expanse.first_row + line_index - first_line,
                     0,
                     0,
                     0,
                 )),
                 line_index,
+                &guides,
                 get_scope,
             );
         }
@@ -134,6 +230,7 @@ impl TextArea {
         canvas: &mut Canvas,
         frame: Rect,
         line_index: LineIndex,
+        guides: &[(usize, Guide)],
         get_scope: &mut impl FnMut(ByteIndex) -> Option<&'a str>,
     ) {
         // Get references to the relevant bits of context
@@ -162,36 +259,107 @@ impl TextArea {
             );
         }
 
-        let mut visual_x = frame.origin.x;
-        let mut char_index = text.line_to_char(line_index);
+        let line_start = text.line_to_char(line_index);
+        let mut char_index = line_start;
         let line_start_byte = text.char_to_byte(char_index);
+        let overlays = self
+            .properties
+            .overlays
+            .clip(line_start..line_start + line.len_chars());
+
+        // Left-to-right lines are drawn in logical order, so the graphemes
+        // past the right edge of the frame are never segmented. Lines with
+        // right-to-left text are reordered as a whole.
+        let tab_width = self.properties.mode.indentation.tab_width();
+        let left_to_right = bidi::is_left_to_right(line);
+        let mut line_width = 0;
+        let graphemes: Vec<_> = RopeGraphemes::new(&line.slice(..))
+            .take_while(|grapheme| {
+                let in_view = !left_to_right || line_width <= frame.size.width;
+                line_width += zee_edit::graphemes::width(tab_width, grapheme).max(1);
+                in_view
+            })
+            .collect();
+
+        // Lines with right-to-left text are drawn in visual order
+        let byte_ranges: Vec<_> = graphemes
+            .iter()
+            .map(|grapheme| grapheme.byte_start..grapheme.byte_end)
+            .collect();
+        let visual_order = bidi::visual_order(line, &byte_ranges).unwrap_or_else(|| {
+            (0..graphemes.len())
+                .map(|index| VisualGrapheme { index, rtl: false })
+                .collect()
+        });
+
+        // Only the graphemes in view are styled. Styles are computed in
+        // logical order, as scopes are looked up by increasing byte index.
+        let mut in_view = vec![false; graphemes.len()];
+        let mut visual_width = 0;
+        for &VisualGrapheme { index, .. } in visual_order.iter() {
+            if visual_width > frame.size.width {
+                break;
+            }
+            in_view[index] = true;
+            visual_width += zee_edit::graphemes::width(tab_width, &graphemes[index]).max(1);
+        }
+        let styles: Vec<_> = graphemes
+            .iter()
+            .zip(in_view)
+            .map(|(grapheme, in_view)| {
+                let style = in_view.then(|| {
+                    let is_error = false;
+                    let scope = get_scope(line_start_byte + grapheme.byte_start).unwrap_or("");
+                    overlays.style_at(
+                        char_index,
+                        text_style_at_char(theme, line_under_cursor && focused, scope, is_error),
+                    )
+                });
+                char_index += grapheme.len_chars();
+                style
+            })
+            .collect();
 
-        for grapheme in RopeGraphemes::new(&line.slice(..)) {
-            let is_error = false;
-
-            let scope = get_scope(line_start_byte + grapheme.byte_start).unwrap_or("");
-            let style = text_style_at_char(
-                theme,
-                cursor,
-                char_index,
-                focused,
-                line_under_cursor,
-                scope,
-                is_error,
-            );
-            let grapheme_width =
-                zee_edit::graphemes::width(self.properties.mode.indentation.tab_width(), &grapheme);
+        let mut visual_x = frame.origin.x;
+        let mut truncated = false;
+        for VisualGrapheme { index, rtl } in visual_order {
+            let grapheme = &graphemes[index];
+            let style = match styles[index] {
+                Some(style) => style,
+                None => {
+                    truncated = true;
+                    break;
+                }
+            };
+            let grapheme_width = zee_edit::graphemes::width(tab_width, grapheme);
             let horizontal_bounds_inclusive = frame.min_x()..=frame.max_x();
             if !horizontal_bounds_inclusive.contains(&(visual_x + grapheme_width)) {
+                truncated = true;
                 break;
             }
 
+            let first_char = grapheme.get_char(0).filter(|_| grapheme.len_chars() == 1);
+            let mirrored = first_char.and_then(bidi::mirror).filter(|_| rtl);
             if grapheme.slice == "\t" {
                 for offset in 0..grapheme_width {
                     canvas.draw_str(visual_x + offset, frame.origin.y, style, " ");
                 }
+            } else if let Some(control) =
+                first_char.filter(|&character| zee_edit::graphemes::is_control(character))
+            {
+                let symbol = match self.properties.control_characters {
+                    ControlCharacterStyle::Caret => zee_edit::graphemes::caret_notation(control)
+                        .map(String::from_iter)
+                        .unwrap_or_default(),
+                    ControlCharacterStyle::Picture => zee_edit::graphemes::control_picture(control)
+                        .map(|picture| format!("{} ", picture))
+                        .unwrap_or_default(),
+                };
+                canvas.draw_str(visual_x, frame.origin.y, style, &symbol);
             } else if grapheme_width == 0 {
                 canvas.draw_str(visual_x, frame.origin.y, style, " ");
+            } else if let Some(mirrored) = mirrored {
+                canvas.draw_str(visual_x, frame.origin.y, style, &mirrored.to_string());
             } else {
                 canvas.draw_graphemes(
                     visual_x,
@@ -201,26 +369,155 @@ impl TextArea {
                 );
             }
 
-            char_index += grapheme.len_chars();
             visual_x += grapheme_width.max(1);
         }
 
-        if line.get_char(line.len_chars().saturating_sub(1)) != Some('\n')
+        if !truncated {
+            self.draw_virtual_cells(canvas, frame, line_index, visual_x);
+        }
+        self.draw_guides(canvas, frame, guides);
+
+        if !truncated
+            && line.get_char(line.len_chars().saturating_sub(1)) != Some('\n')
             && cursor.range().start == char_index
         {
             canvas.draw_str(
                 visual_x,
                 frame.origin.y,
-                if focused {
-                    theme.cursor_focused
-                } else {
-                    theme.cursor_unfocused
-                },
+                self.properties.overlays.style_at(
+                    char_index,
+                    text_style_at_char(theme, line_under_cursor && focused, "", false),
+                ),
                 " ",
             );
         }
     }
 
+    /// Draws the sticky lines over the first rows, underlining the last one to
+    /// set them apart from the text scrolling below
+    fn draw_sticky_lines(&self, canvas: &mut Canvas) {
+        let Properties {
+            ref text,
+            ref sticky_lines,
+            ..
+        } = self.properties;
+        let size = canvas.size();
+        let num_rows = cmp::min(sticky_lines.len(), size.height);
+        for (row, &line_index) in sticky_lines.iter().take(num_rows).enumerate() {
+            let (start_byte, end_byte) = match (
+                text.try_line_to_byte(line_index),
+                text.try_line_to_byte(line_index + 1),
+            ) {
+                (Ok(start_byte), Ok(end_byte)) => (start_byte, end_byte),
+                _ => continue,
+            };
+            canvas.clear_region(
+                Rect::new(Position::new(0, row), Size::new(size.width, 1)),
+                self.properties.theme.text,
+            );
+            self.draw_text(
+                canvas,
+                TextExpanse {
+                    byte_range: start_byte..end_byte,
+                    line_range: line_index..line_index + 1,
+                    first_row: row,
+                },
+            );
+        }
+
+        if let Some(row) = num_rows.checked_sub(1) {
+            for x in 0..size.width {
+                if let Some(textel) = canvas.textel(x, row).clone() {
+                    let style = Style {
+                        underline: true,
+                        ..textel.style
+                    };
+                    canvas.draw_str(x, row, style, &textel.grapheme);
+                }
+            }
+        }
+    }
+
+    /// Draws the cells of a rectangular selection past the end of a line,
+    /// starting at `visual_x` after the last character drawn
+    #[inline]
+    fn draw_virtual_cells(
+        &self,
+        canvas: &mut Canvas,
+        frame: Rect,
+        line_index: LineIndex,
+        visual_x: usize,
+    ) {
+        let rectangle = match self.properties.rectangle {
+            Some(ref rectangle) if rectangle.lines().contains(&line_index) => rectangle,
+            _ => return,
+        };
+        let Properties {
+            ref theme,
+            ref text,
+            ..
+        } = self.properties;
+
+        // The line break takes up a cell too
+        let tab_width = self.properties.mode.indentation.tab_width();
+        let line_width = rectangle::line_width(text, line_index, tab_width);
+        let first_virtual_column =
+            if rectangle::line_length(text, line_index) < text.line(line_index).len_chars() {
+                line_width + 1
+            } else {
+                line_width
+            };
+        let columns = rectangle.columns();
+        let (virtual_columns, style) = if columns.is_empty() {
+            (columns.start..columns.start + 1, theme.cursor_unfocused)
+        } else {
+            (
+                columns,
+                Style {
+                    background: theme.selection_background,
+                    ..theme.text
+                },
+            )
+        };
+        for column in cmp::max(virtual_columns.start, first_virtual_column)..virtual_columns.end {
+            let x = visual_x + column - first_virtual_column;
+            if x >= frame.max_x() {
+                break;
+            }
+            canvas.draw_str(x, frame.origin.y, style, " ");
+        }
+    }
+
+    /// Draws the guides of a line in the cells left blank by its text, so
+    /// they never cover a character. Tab stops are only marked in the
+    /// indentation of lines with text.
+    #[inline]
+    fn draw_guides(&self, canvas: &mut Canvas, frame: Rect, guides: &[(usize, Guide)]) {
+        let y = frame.origin.y;
+        let blank_style = |canvas: &Canvas, x: usize| match canvas.textel(x, y) {
+            Some(textel) if &*textel.grapheme == " " => Some(textel.style),
+            _ => None,
+        };
+        let indentation_end = (frame.min_x()..frame.max_x())
+            .find(|&x| blank_style(canvas, x).is_none())
+            .unwrap_or(frame.min_x());
+        for &(column, guide) in guides {
+            let x = frame.origin.x + column;
+            let symbol = match guide {
+                Guide::Column => "│",
+                Guide::TabStop if x < indentation_end => "┆",
+                Guide::TabStop => continue,
+            };
+            if let Some(style) = blank_style(canvas, x) {
+                let style = Style {
+                    foreground: self.properties.theme.code_comment.foreground,
+                    ..style
+                };
+                canvas.draw_str(x, y, style, symbol);
+            }
+        }
+    }
+
     #[inline]
     fn text_expanse_in_view(&self, canvas: &Canvas) -> TextExpanse {
         let line_range = self.properties.line_offset
@@ -241,6 +538,7 @@ impl TextArea {
         TextExpanse {
             byte_range: start_byte..end_byte,
             line_range,
+            first_row: 0,
         }
     }
 
@@ -258,6 +556,8 @@ impl TextArea {
 struct TextExpanse {
     byte_range: Range<ByteIndex>,
     line_range: Range<LineIndex>,
+    // The row the first line is drawn at
+    first_row: usize,
 }
 
 struct ChunksBytes<'a> {
@@ -286,3 +586,42 @@ impl<'a> TextProvider<'a> for RopeProvider<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{temp_file, TestEditor};
+
+    #[test]
+    fn long_lines_are_cut_at_the_edge_of_the_window() {
+        let long_line = format!("{}end\n", "word ".repeat(20_000));
+        let right_to_left = format!("{}שלום\n", "word ".repeat(100));
+        let path = temp_file("long-lines.txt", &(long_line + &right_to_left));
+        let mut editor = TestEditor::open(&[&path]);
+        let screen = editor.screen();
+        let rows: Vec<_> = screen.lines().map(str::trim).collect();
+        assert!(rows[0].starts_with("word word") && !rows[0].contains("end"));
+        // Reordered, the right-to-left word is past the edge too
+        assert!(rows[1].starts_with("word word") && !rows[1].contains('ש'));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn guide_columns_with_tab_stops() {
+        assert_eq!(
+            vec![(72, Guide::Column)],
+            guide_columns(&[100, 72, 72], None, 80)
+        );
+        assert_eq!(
+            vec![
+                (4, Guide::TabStop),
+                (6, Guide::Column),
+                (8, Guide::Column),
+                (12, Guide::TabStop)
+            ],
+            guide_columns(&[8, 6, 20], Some(4), 14)
+        );
+        assert_eq!(vec![(3, Guide::Column)], guide_columns(&[3], Some(0), 10));
+        assert!(guide_columns(&[], Some(8), 8).is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+use ropey::Rope;
+use std::rc::Rc;
+use zi::{Canvas, Component, ComponentLink, Layout, Rect, ShouldRender, Style};
+
+use zee_edit::LineHunk;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub base: Style,
+    pub header: Style,
+    pub added: Style,
+    pub removed: Style,
+}
+
+pub struct Properties {
+    pub theme: Theme,
+    pub saved: Rope,
+    pub text: Rope,
+    pub hunks: Rc<Vec<LineHunk>>,
+    pub current_line: usize,
+}
+
+/// Shows the lines that changed between the saved version of a file and the
+/// text of its buffer, in the style of a unified diff without context lines.
+pub struct DiffViewer {
+    properties: Properties,
+    frame: Rect,
+}
+
+impl Component for DiffViewer {
+    type Properties = Properties;
+    type Message = ();
+
+    fn create(properties: Self::Properties, frame: Rect, _link: ComponentLink<Self>) -> Self {
+        Self { properties, frame }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        self.properties = properties;
+        ShouldRender::Yes
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        self.frame = frame;
+        ShouldRender::Yes
+    }
+
+    fn view(&self) -> Layout {
+        let Properties {
+            ref theme,
+            ref saved,
+            ref text,
+            ref hunks,
+            current_line,
+        } = self.properties;
+        let mut canvas = Canvas::new(self.frame.size);
+        canvas.clear(theme.base);
+
+        if hunks.is_empty() {
+            canvas.draw_str(1, 0, theme.base, "No changes");
+            return canvas.into();
+        }
+
+        // Start at the first hunk that isn't above the cursor, or the last one
+        let first_hunk = hunks
+            .iter()
+            .position(|hunk| hunk.new_lines.end >= current_line)
+            .unwrap_or(hunks.len() - 1);
+
+        let height = canvas.size().height;
+        let mut y = 0;
+        let mut draw_line = |style: Style, line: &str| {
+            if y < height {
+                canvas.draw_str(0, y, style, line);
+                y += 1;
+            }
+        };
+        for hunk in hunks[first_hunk..].iter() {
+            draw_line(
+                theme.header,
+                &format!(
+                    "@@ -{} +{} @@",
+                    format_range(&hunk.old_lines),
+                    format_range(&hunk.new_lines)
+                ),
+            );
+            for line_index in hunk.old_lines.clone() {
+                draw_line(theme.removed, &format_line('-', saved, line_index));
+            }
+            for line_index in hunk.new_lines.clone() {
+                draw_line(theme.added, &format_line('+', text, line_index));
+            }
+        }
+
+        canvas.into()
+    }
+}
+
+/// The one-based start and the length of a range of lines, e.g. `3,2`
+fn format_range(lines: &std::ops::Range<usize>) -> String {
+    format!("{},{}", lines.start + 1, lines.len())
+}
+
+fn format_line(prefix: char, text: &Rope, line_index: usize) -> String {
+    let line = text.line(line_index).to_string();
+    format!(
+        "{}{}",
+        prefix,
+        line.trim_end_matches(&['\n', '\r'][..])
+            .replace('\t', TAB_REPLACEMENT)
+    )
+}
+
+const TAB_REPLACEMENT: &str = "    ";
@@ -1,7 +1,14 @@
-use std::cmp;
-use zi::{Canvas, Component, ComponentLink, Layout, Rect, ShouldRender, Style};
+use std::{
+    cmp,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use zi::{
+    unicode_width::UnicodeWidthStr, Canvas, Component, ComponentLink, Layout, Rect, ShouldRender,
+    Style,
+};
 
-use zee_edit::tree::{self, EditTree};
+use zee_edit::tree::{self, DiffStats, EditTree};
 
 use crate::versioned::WeakHandle;
 
@@ -56,19 +63,72 @@ impl Component for EditTreeViewer {
         canvas.clear(theme.current_revision);
 
         let formatted_tree = tree::format_tree(&tree);
+        let now = Instant::now();
+        let labels: Vec<_> = tree
+            .revisions
+            .iter()
+            .enumerate()
+            .map(|(revision_index, revision)| {
+                let index = format!(
+                    "{}{}",
+                    revision_index,
+                    if revision_index == tree.head_index {
+                        "*"
+                    } else {
+                        ""
+                    }
+                );
+                let annotation = format!(
+                    "{} {}",
+                    revision
+                        .diff_stats()
+                        .map(format_diff_stats)
+                        .unwrap_or_else(|| "root".into()),
+                    format_elapsed(now.saturating_duration_since(revision.timestamp))
+                );
+                (index, annotation)
+            })
+            .collect();
 
+        // Scroll to keep the current revision and its label in view. The tree
+        // starts at the top left corner, below the header, when it fits.
         let (middle_x, middle_y) = {
             let transform = formatted_tree[tree.head_index].transform;
-            let middle_x = (canvas.size().width / 2) as isize - transform.x;
-            let middle_y = (canvas.size().height / 2) as isize - transform.y;
+            let (ref index, ref annotation) = labels[tree.head_index];
+            let label_width = (index.width() + 1 + annotation.width()) as isize;
+            let middle_x = cmp::min(
+                LEFT_MARGIN,
+                canvas.size().width as isize - label_width - transform.x - 1,
+            );
+            let middle_y = cmp::min(
+                HEADER_HEIGHT,
+                (canvas.size().height / 2) as isize - transform.y,
+            );
             (middle_x, middle_y)
         };
 
+        // The columns of the revisions on each row, for checking whether
+        // there's room for a revision's annotation
+        let mut revisions_on_row: HashMap<isize, Vec<isize>> = HashMap::new();
+        for formatted in formatted_tree.iter() {
+            revisions_on_row
+                .entry(formatted.transform.y)
+                .or_default()
+                .push(formatted.transform.x);
+        }
+
         // let mut y = middle_y + 8;
         // let mut revision_index = tree.parent_revision_index;
         for (revision_index, formatted) in formatted_tree.iter().enumerate() {
             let (revision_style, connector_style) = if revision_index == tree.head_index {
-                (theme.current_revision, theme.master_connector)
+                // Highlight the current revision by swapping its colours
+                (
+                    Style::bold(
+                        theme.current_revision.foreground,
+                        theme.current_revision.background,
+                    ),
+                    theme.master_connector,
+                )
             } else if formatted.current_branch {
                 (theme.master_revision, theme.master_connector)
             } else {
@@ -78,24 +138,25 @@ impl Component for EditTreeViewer {
             let x = middle_x + formatted.transform.x;
             let y = middle_y + formatted.transform.y;
             if x >= 0
-                && y >= 0
+                && y >= HEADER_HEIGHT
                 && x < canvas.size().width as isize
                 && y < canvas.size().height as isize
             {
-                canvas.draw_str(
-                    x as usize,
-                    y as usize,
-                    revision_style,
-                    &format!(
-                        "{:.5}{}",
-                        revision_index,
-                        if revision_index == tree.head_index {
-                            "*"
-                        } else {
-                            ""
-                        },
-                    ),
-                );
+                let (ref index, ref annotation) = labels[revision_index];
+                let label_end =
+                    formatted.transform.x + (index.width() + 1 + annotation.width()) as isize;
+                let has_room = revisions_on_row[&formatted.transform.y]
+                    .iter()
+                    .all(|&other_x| other_x <= formatted.transform.x || other_x > label_end);
+                let written = canvas.draw_str(x as usize, y as usize, revision_style, index);
+                if has_room {
+                    canvas.draw_str(
+                        x as usize + written + 1,
+                        y as usize,
+                        connector_style,
+                        annotation,
+                    );
+                }
             }
 
             let num_children = revision.children.len();
@@ -104,7 +165,7 @@ impl Component for EditTreeViewer {
                 let x = middle_x + formatted_child.transform.x;
                 let y = middle_y + formatted_child.transform.y - 1;
                 if x >= 0
-                    && y >= 0
+                    && y >= HEADER_HEIGHT
                     && x < canvas.size().width as isize
                     && y < canvas.size().height as isize
                 {
@@ -139,7 +200,7 @@ impl Component for EditTreeViewer {
                 let y = middle_y + formatted_left.transform.y - 1;
                 if end_x >= 0
                     && start_x < canvas.size().width as isize
-                    && y >= 0
+                    && y >= HEADER_HEIGHT
                     && y < canvas.size().height as isize
                 {
                     canvas.draw_str(
@@ -166,6 +227,39 @@ impl Component for EditTreeViewer {
             // }
         }
 
+        // The number of revisions that can be undone and redone
+        canvas.draw_str(
+            0,
+            0,
+            theme.master_revision,
+            &format!(" undo {} · redo {}", tree.num_undos(), tree.num_redos()),
+        );
+
         canvas.into()
     }
 }
+
+/// A short summary of an edit, e.g. `+3-1` for 3 chars inserted and 1 deleted
+fn format_diff_stats(stats: DiffStats) -> String {
+    match (stats.inserted_chars, stats.deleted_chars) {
+        (0, 0) => "=".into(),
+        (inserted, 0) => format!("+{}", inserted),
+        (0, deleted) => format!("-{}", deleted),
+        (inserted, deleted) => format!("+{}-{}", inserted, deleted),
+    }
+}
+
+/// The time since a revision was created, e.g. `2m` for two minutes ago
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    match seconds {
+        0..=4 => "now".into(),
+        5..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m", seconds / 60),
+        3600..=86399 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
+
+const HEADER_HEIGHT: isize = 2;
+const LEFT_MARGIN: isize = 1;
@@ -1,4 +1,5 @@
 pub mod buffer;
+pub mod diff_viewer;
 pub mod edit_tree_viewer;
 pub mod prompt;
 pub mod splash;
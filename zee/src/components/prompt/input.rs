@@ -0,0 +1,215 @@
+use ropey::Rope;
+use std::{borrow::Cow, ops::RangeInclusive};
+use zi::{
+    components::{
+        input::{Cursor, Input, InputChange, InputProperties, InputStyle},
+        text::{Text, TextProperties},
+    },
+    prelude::*,
+    unicode_width::UnicodeWidthStr,
+    Callback,
+};
+
+use super::{
+    status::{Status, StatusProperties},
+    Theme,
+};
+
+#[derive(Debug)]
+pub enum Message {
+    ChangeInput(InputChange),
+    Accept,
+}
+
+/// Checks the input before it's accepted, returning the error to show if
+/// it's rejected. The prompt stays open until the input is corrected or `C-g`
+/// cancels it.
+pub type Validator = Callback<String, Result<(), String>>;
+
+#[derive(Clone)]
+pub struct Properties {
+    pub theme: Cow<'static, Theme>,
+    pub message: Cow<'static, str>,
+    /// Accepted if nothing is typed, shown after the message
+    pub default: Option<String>,
+    pub validate: Option<Validator>,
+    pub on_input: Callback<String>,
+}
+
+/// Reads a line of text, e.g. the percentage to go to in a buffer
+pub struct InputPrompt {
+    properties: Properties,
+    link: ComponentLink<Self>,
+    input: Rope,
+    cursor: Cursor,
+    // Why the input was rejected, until it's changed
+    error: Option<String>,
+}
+
+impl Component for InputPrompt {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, _frame: Rect, link: ComponentLink<Self>) -> Self {
+        Self {
+            properties,
+            link,
+            input: "\n".into(),
+            cursor: Cursor::new(),
+            error: None,
+        }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        // A prompt asked straight after another, e.g. for the replacement
+        // after the pattern, starts with an empty input
+        let asked_again = self.properties.message != properties.message;
+        if asked_again {
+            self.input = "\n".into();
+            self.cursor = Cursor::new();
+            self.error = None;
+        }
+        let should_render = (asked_again
+            || self.properties.theme != properties.theme
+            || self.properties.default != properties.default)
+            .into();
+        self.properties = properties;
+        should_render
+    }
+
+    fn update(&mut self, message: Message) -> ShouldRender {
+        match message {
+            Message::ChangeInput(InputChange { content, cursor }) => {
+                self.cursor = cursor;
+                if let Some(content) = content {
+                    self.input = content;
+                    self.error = None;
+                }
+            }
+            Message::Accept => {
+                let input = self.input.slice(..).to_string();
+                let validate = self.properties.validate.as_ref();
+                match accept(&input, self.properties.default.as_deref(), |input| {
+                    validate.map_or(Ok(()), |validate| validate.emit(input))
+                }) {
+                    Ok(input) => self.properties.on_input.emit(input),
+                    Err(error) => self.error = Some(error),
+                }
+            }
+        }
+        ShouldRender::Yes
+    }
+
+    fn view(&self) -> Layout {
+        let input = Input::with(InputProperties {
+            style: InputStyle {
+                content: self.properties.theme.input,
+                cursor: self.properties.theme.cursor,
+            },
+            content: self.input.clone(),
+            cursor: self.cursor.clone(),
+            on_change: Some(self.link.callback(Message::ChangeInput)),
+            focused: true,
+        });
+
+        let message: Cow<'static, str> = match self.properties.default {
+            Some(ref default) => format!("{} ({})", self.properties.message, default).into(),
+            None => self.properties.message.clone(),
+        };
+        let error = self
+            .error
+            .as_ref()
+            .map(|error| format!(" [{}]", error))
+            .unwrap_or_default();
+        Layout::row([
+            Item::fixed(message.width())(Status::with(StatusProperties {
+                action_name: message,
+                pending: false,
+                style: self.properties.theme.action,
+            })),
+            Item::fixed(1)(Text::with(
+                TextProperties::new().style(self.properties.theme.input),
+            )),
+            Item::auto(input),
+            Item::fixed(error.width())(Text::with(
+                TextProperties::new()
+                    .style(self.properties.theme.action)
+                    .content(error),
+            )),
+        ])
+    }
+
+    fn bindings(&self, bindings: &mut Bindings<Self>) {
+        if !bindings.is_empty() {
+            return;
+        }
+
+        bindings.set_focus(true);
+        bindings.add("accept-input", [Key::Char('\n')], || Message::Accept);
+    }
+}
+
+/// The input to accept, trimmed, or `default` if nothing was typed. Either
+/// has to pass `validate`, otherwise its error is returned.
+fn accept(
+    input: &str,
+    default: Option<&str>,
+    validate: impl Fn(String) -> Result<(), String>,
+) -> Result<String, String> {
+    let input = match (input.trim(), default) {
+        ("", Some(default)) => default,
+        (input, _) => input,
+    };
+    validate(input.to_owned())?;
+    Ok(input.to_owned())
+}
+
+/// Accepts a whole number in `range`, e.g. a line number
+pub fn integer_in(range: RangeInclusive<usize>) -> Validator {
+    (move |input: String| check_integer(&input, &range)).into()
+}
+
+fn check_integer(input: &str, range: &RangeInclusive<usize>) -> Result<(), String> {
+    match input.parse::<usize>() {
+        Ok(number) if range.contains(&number) => Ok(()),
+        _ if *range.end() == usize::MAX => {
+            Err(format!("Enter a number of {} or more", range.start()))
+        }
+        _ => Err(format!(
+            "Enter a number from {} to {}",
+            range.start(),
+            range.end()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejected_input_can_be_corrected() {
+        let percent = 0..=100;
+        let validate = |input: String| check_integer(&input, &percent);
+        let error = Err("Enter a number from 0 to 100".to_owned());
+        assert_eq!(error, accept("abc\n", None, validate));
+        assert_eq!(error, accept("101\n", None, validate));
+        assert_eq!(error, accept("-1\n", None, validate));
+        assert_eq!(error, accept("\n", None, validate));
+        assert_eq!(Ok("42".to_owned()), accept(" 42 \n", None, validate));
+        assert_eq!(Ok("100".to_owned()), accept("100\n", None, validate));
+
+        // Empty input accepts the default
+        assert_eq!(Ok("50".to_owned()), accept("\n", Some("50"), validate));
+        assert_eq!(Ok("7".to_owned()), accept("7\n", Some("50"), validate));
+        assert_eq!(error, accept("\n", Some("many"), validate));
+        assert_eq!(Ok("".to_owned()), accept("\n", None, |_| Ok(())));
+
+        // Ranges without an end don't mention it
+        let line_number = 1..=usize::MAX;
+        let validate = |input: String| check_integer(&input, &line_number);
+        let error = Err("Enter a number of 1 or more".to_owned());
+        assert_eq!(error, accept("0\n", None, validate));
+        assert_eq!(Ok("12".to_owned()), accept("12\n", None, validate));
+    }
+}
@@ -3,26 +3,123 @@ use std::borrow::Cow;
 use zi::{
     components::text::{Text, TextProperties},
     prelude::*,
-    Callback,
+    AnyCharacter, Callback,
 };
 
 use super::Theme;
 
 // Message type handled by the `InteractiveMessage` component
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Message {
-    Accept,
-    Decline,
+    Key(Key),
+}
+
+/// The answers a question in the prompt accepts
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Question {
+    /// `yes` or `no`, typed in full and ended with Enter
+    YesOrNo,
+    /// A single key, one of the characters
+    Char(Vec<char>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Answer {
+    Yes,
+    No,
+    Char(char),
+}
+
+/// What typing a key does while a question is asked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnswerKey {
+    /// The question is still asked, again after an answer it doesn't accept
+    Pending,
+    Answered(Answer),
+    /// `C-g` or `ESC`
+    Cancelled,
+}
+
+/// The answer typed so far to a question
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnswerInput {
+    question: Question,
+    typed: String,
+    // Set by an answer the question doesn't accept, until the next key
+    invalid: bool,
+}
+
+impl AnswerInput {
+    pub fn new(question: Question) -> Self {
+        Self {
+            question,
+            typed: String::new(),
+            invalid: false,
+        }
+    }
+
+    /// Takes the next key typed
+    pub fn key(&mut self, key: Key) -> AnswerKey {
+        self.invalid = false;
+        match (&self.question, key) {
+            (_, Key::Ctrl('g') | Key::Esc) => return AnswerKey::Cancelled,
+            (Question::YesOrNo, Key::Char('\n')) => match self.typed.trim() {
+                "yes" => return AnswerKey::Answered(Answer::Yes),
+                "no" => return AnswerKey::Answered(Answer::No),
+                _ => {
+                    self.typed.clear();
+                    self.invalid = true;
+                }
+            },
+            (Question::YesOrNo, Key::Char(character)) => self.typed.push(character),
+            (Question::YesOrNo, Key::Backspace) => {
+                self.typed.pop();
+            }
+            (Question::Char(allowed), Key::Char(character)) if allowed.contains(&character) => {
+                return AnswerKey::Answered(Answer::Char(character))
+            }
+            _ => self.invalid = true,
+        }
+        AnswerKey::Pending
+    }
+
+    /// The question asked in the prompt, after `message`, with the answer
+    /// typed so far
+    pub fn prompt(&self, message: &str) -> String {
+        let choices = match self.question {
+            Question::YesOrNo => "yes or no".to_owned(),
+            Question::Char(ref allowed) => allowed
+                .iter()
+                .map(char::to_string)
+                .collect::<Vec<_>>()
+                .join("/"),
+        };
+        let prompt = format!(
+            "{}{} ({}) {}",
+            if self.invalid {
+                format!("Please answer {}. ", choices)
+            } else {
+                String::new()
+            },
+            message,
+            choices,
+            self.typed
+        );
+        prompt.trim_end().into()
+    }
 }
 
 pub struct Properties {
     pub theme: Cow<'static, Theme>,
-    pub on_input: Callback<bool>,
+    pub question: Question,
+    /// Called with the answer, or `None` if the question was cancelled
+    pub on_answer: Callback<Option<Answer>>,
     pub message: String,
 }
 
 pub struct InteractiveMessage {
     properties: Properties,
+    input: AnswerInput,
 }
 
 impl Component for InteractiveMessage {
@@ -31,20 +128,25 @@ impl Component for InteractiveMessage {
     type Properties = Properties;
 
     fn create(properties: Self::Properties, _frame: Rect, _link: ComponentLink<Self>) -> Self {
-        Self { properties }
+        let input = AnswerInput::new(properties.question.clone());
+        Self { properties, input }
     }
 
     fn view(&self) -> Layout {
-        let message = format!("{} (y/n)", self.properties.message);
         Text::with(
             TextProperties::new()
                 .style(self.properties.theme.input)
-                .content(message),
+                .content(self.input.prompt(&self.properties.message)),
         )
     }
 
     fn update(&mut self, message: Self::Message) -> ShouldRender {
-        self.properties.on_input.emit(message == Message::Accept);
+        let Message::Key(key) = message;
+        match self.input.key(key) {
+            AnswerKey::Pending => return ShouldRender::Yes,
+            AnswerKey::Answered(answer) => self.properties.on_answer.emit(Some(answer)),
+            AnswerKey::Cancelled => self.properties.on_answer.emit(None),
+        }
         ShouldRender::No
     }
 
@@ -57,12 +159,82 @@ impl Component for InteractiveMessage {
         bindings.set_focus(true);
 
         bindings
-            .command("accept", || Message::Accept)
-            .with([Key::Char('y')]);
-
-        bindings
-            .command("decline", || Message::Decline)
+            .command("answer", |_: &Self, keys: &[Key]| {
+                keys.first().copied().map(Message::Key)
+            })
+            .with(AnyCharacter)
+            .with([Key::Backspace])
             .with([Key::Esc])
-            .with([Key::Char('n')]);
+            .with([Key::Ctrl('g')]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(question: Question, keys: &[Key]) -> Vec<AnswerKey> {
+        let mut input = AnswerInput::new(question);
+        keys.iter().map(|&key| input.key(key)).collect()
+    }
+
+    #[test]
+    fn answer_yes_or_no() {
+        use AnswerKey::*;
+        use Key::{Backspace, Char, Ctrl};
+
+        assert_eq!(
+            vec![Pending, Pending, Pending, Answered(Answer::Yes)],
+            answer(
+                Question::YesOrNo,
+                &[Char('y'), Char('e'), Char('s'), Char('\n')]
+            )
+        );
+        assert_eq!(
+            vec![Pending, Pending, Pending, Pending, Answered(Answer::No)],
+            answer(
+                Question::YesOrNo,
+                &[Char('n'), Char('x'), Backspace, Char('o'), Char('\n')]
+            )
+        );
+        assert_eq!(
+            vec![Pending, Cancelled],
+            answer(Question::YesOrNo, &[Char('y'), Ctrl('g')])
+        );
+
+        // Anything else asks again
+        let mut input = AnswerInput::new(Question::YesOrNo);
+        input.key(Char('y'));
+        assert_eq!("Delete? (yes or no) y", input.prompt("Delete?"));
+        assert_eq!(Pending, input.key(Char('\n')));
+        assert_eq!(
+            "Please answer yes or no. Delete? (yes or no)",
+            input.prompt("Delete?")
+        );
+        input.key(Char('n'));
+        input.key(Char('o'));
+        assert_eq!(Answered(Answer::No), input.key(Char('\n')));
+    }
+
+    #[test]
+    fn answer_with_a_single_key() {
+        use AnswerKey::*;
+        use Key::{Char, Esc, Up};
+
+        let question = || Question::Char(vec!['y', 'n', '!']);
+        assert_eq!(
+            vec![Answered(Answer::Char('!'))],
+            answer(question(), &[Char('!')])
+        );
+        assert_eq!(vec![Cancelled], answer(question(), &[Esc]));
+        assert_eq!(
+            vec![Pending, Pending, Answered(Answer::Char('n'))],
+            answer(question(), &[Char('x'), Up, Char('n')])
+        );
+
+        let mut input = AnswerInput::new(question());
+        assert_eq!("Save? (y/n/!)", input.prompt("Save?"));
+        input.key(Char('x'));
+        assert_eq!("Please answer y/n/!. Save? (y/n/!)", input.prompt("Save?"));
     }
 }
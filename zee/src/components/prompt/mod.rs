@@ -1,11 +1,14 @@
 pub mod buffers;
+pub mod input;
+pub mod interactive;
 pub mod picker;
+pub mod progress;
+pub mod replace;
+pub mod search;
 
 mod matcher;
 mod status;
 
-mod interactive;
-
 use std::{borrow::Cow, path::PathBuf};
 use zi::{
     components::text::{Text, TextProperties},
@@ -13,12 +16,22 @@ use zi::{
     ShouldRender, Style,
 };
 
-use crate::editor::{BufferId, ContextHandle};
+use zee_edit::Direction;
+
+use crate::editor::{
+    buffer::{SearchOptions, SearchQuery},
+    BufferId, ContextHandle,
+};
 
 use self::{
     buffers::{BufferEntry, BufferPicker, Properties as BufferPickerProperties},
-    interactive::{InteractiveMessage, Properties as InteractiveMessageProperties},
+    input::{InputPrompt, Properties as InputPromptProperties, Validator},
+    interactive::{
+        Answer, InteractiveMessage, Properties as InteractiveMessageProperties, Question,
+    },
     picker::{FilePicker, FileSource, Properties as FilePickerProperties},
+    replace::{Properties as ReplaceInFilesProperties, ReplaceInFiles, Replacement},
+    search::{Properties as SearchPromptProperties, SearchPrompt},
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -51,9 +64,35 @@ pub enum Action {
         on_open: Callback<PathBuf>,
         on_change_height: Callback<usize>,
     },
+    PickPath {
+        action_name: Cow<'static, str>,
+        initial_path: PathBuf,
+        on_pick: Callback<PathBuf>,
+        on_change_height: Callback<usize>,
+    },
     InteractiveMessage {
         message: Cow<'static, str>,
-        on_input: Callback<bool>,
+        question: Question,
+        on_answer: Callback<Option<Answer>>,
+    },
+    Input {
+        message: Cow<'static, str>,
+        default: Option<String>,
+        validate: Option<Validator>,
+        on_input: Callback<String>,
+    },
+    Search {
+        direction: Direction,
+        options: SearchOptions,
+        on_search: Callback<Option<SearchQuery>>,
+        on_change_options: Callback<SearchOptions>,
+        on_accept: Callback<()>,
+        on_cancel: Callback<()>,
+    },
+    ReplaceInFiles {
+        options: SearchOptions,
+        on_replace: Callback<Replacement>,
+        on_change_height: Callback<usize>,
     },
 }
 
@@ -145,16 +184,73 @@ impl Component for Prompt {
                 context: self.properties.context.clone(),
                 theme: self.properties.theme.clone(),
                 source: *source,
+                action_name: None,
+                initial_path: None,
                 on_open: on_open.clone(),
                 on_change_height: on_change_height.clone(),
             }),
-            Action::InteractiveMessage { on_input, message } => {
-                InteractiveMessage::with(InteractiveMessageProperties {
-                    theme: self.properties.theme.clone(),
-                    on_input: on_input.clone(),
-                    message: message.to_string(),
-                })
-            }
+            Action::PickPath {
+                action_name,
+                initial_path,
+                on_pick,
+                on_change_height,
+            } => FilePicker::with(FilePickerProperties {
+                context: self.properties.context.clone(),
+                theme: self.properties.theme.clone(),
+                source: FileSource::Directory,
+                action_name: Some(action_name.clone()),
+                initial_path: Some(initial_path.clone()),
+                on_open: on_pick.clone(),
+                on_change_height: on_change_height.clone(),
+            }),
+            Action::InteractiveMessage {
+                message,
+                question,
+                on_answer,
+            } => InteractiveMessage::with(InteractiveMessageProperties {
+                theme: self.properties.theme.clone(),
+                question: question.clone(),
+                on_answer: on_answer.clone(),
+                message: message.to_string(),
+            }),
+            Action::Input {
+                message,
+                default,
+                validate,
+                on_input,
+            } => InputPrompt::with(InputPromptProperties {
+                theme: self.properties.theme.clone(),
+                message: message.clone(),
+                default: default.clone(),
+                validate: validate.clone(),
+                on_input: on_input.clone(),
+            }),
+            Action::Search {
+                direction,
+                options,
+                on_search,
+                on_change_options,
+                on_accept,
+                ..
+            } => SearchPrompt::with(SearchPromptProperties {
+                theme: self.properties.theme.clone(),
+                direction: *direction,
+                options: *options,
+                on_search: on_search.clone(),
+                on_change_options: on_change_options.clone(),
+                on_accept: on_accept.clone(),
+            }),
+            Action::ReplaceInFiles {
+                options,
+                on_replace,
+                on_change_height,
+            } => ReplaceInFiles::with(ReplaceInFilesProperties {
+                context: self.properties.context.clone(),
+                theme: self.properties.theme.clone(),
+                options: *options,
+                on_replace: on_replace.clone(),
+                on_change_height: on_change_height.clone(),
+            }),
         }
     }
 }
@@ -14,6 +14,7 @@ use zi::{
         text::{Text, TextProperties},
     },
     prelude::*,
+    unicode_width::UnicodeWidthStr,
     Callback,
 };
 
@@ -67,6 +68,11 @@ pub struct Properties {
     pub context: ContextHandle,
     pub theme: Cow<'static, Theme>,
     pub source: FileSource,
+    // Shown instead of the source's name, e.g. when picking a path to rename
+    // a file to
+    pub action_name: Option<Cow<'static, str>>,
+    // The path to start from, the current working directory by default
+    pub initial_path: Option<PathBuf>,
     pub on_open: Callback<PathBuf>,
     pub on_change_height: Callback<usize>,
 }
@@ -108,14 +114,24 @@ impl Component for FilePicker {
 
     fn create(properties: Self::Properties, _frame: Rect, link: ComponentLink<Self>) -> Self {
         let mut cursor = Cursor::new();
-        let mut current_working_dir: String = properties
-            .context
-            .current_working_dir
-            .to_string_lossy()
-            .into();
-        current_working_dir.push('/');
-        current_working_dir.push('\n');
-        let input = current_working_dir.into();
+        let input = match properties.initial_path {
+            Some(ref initial_path) => {
+                let mut input: String = initial_path.to_string_lossy().into();
+                input.push('\n');
+                input
+            }
+            None => {
+                let mut current_working_dir: String = properties
+                    .context
+                    .current_working_dir
+                    .to_string_lossy()
+                    .into();
+                current_working_dir.push('/');
+                current_working_dir.push('\n');
+                current_working_dir
+            }
+        }
+        .into();
         cursor.move_to_end_of_line(&input);
 
         let mut picker = Self {
@@ -232,6 +248,11 @@ impl Component for FilePicker {
             focused: true,
         });
 
+        let action_name = self
+            .properties
+            .action_name
+            .clone()
+            .unwrap_or_else(|| self.properties.source.status_name());
         let listing = self.listing.clone();
         let selected_index = self.selected_index;
         let theme = self.properties.theme.clone();
@@ -272,8 +293,8 @@ impl Component for FilePicker {
                 item_size: 1,
             })),
             Item::fixed(1)(Container::row([
-                Item::fixed(4)(Status::with(StatusProperties {
-                    action_name: self.properties.source.status_name(),
+                Item::fixed(action_name.width())(Status::with(StatusProperties {
+                    action_name,
                     pending: self.current_task_id.is_some(),
                     style: self.properties.theme.action,
                 })),
@@ -0,0 +1,82 @@
+use zi::{
+    components::text::{Text, TextProperties},
+    prelude::*,
+    unicode_width::UnicodeWidthStr,
+};
+
+use crate::editor::operations::ActiveOperation;
+
+#[derive(Clone, PartialEq)]
+pub struct Properties {
+    pub label: String,
+    pub style: Style,
+}
+
+/// A spinner followed by a description of the operations in progress
+pub struct ProgressIndicator {
+    properties: Properties,
+    frame_index: usize,
+}
+
+impl ProgressIndicator {
+    /// Describes the latest operation, e.g. `loading log.txt 35% (+1)` when
+    /// another operation is in progress too
+    pub fn label(latest: &ActiveOperation, num_operations: usize) -> String {
+        let mut label = latest.name.to_string();
+        if let Some(percent) = latest.percent {
+            label.push_str(&format!(" {}%", percent));
+        }
+        if num_operations > 1 {
+            label.push_str(&format!(" (+{})", num_operations - 1));
+        }
+        label
+    }
+
+    /// The width of the indicator for a label, including the spinner
+    pub fn width(label: &str) -> usize {
+        label.width() + 4
+    }
+}
+
+impl Component for ProgressIndicator {
+    type Message = ();
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, _frame: Rect, _link: ComponentLink<Self>) -> Self {
+        Self {
+            properties,
+            frame_index: 0,
+        }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        if self.properties != properties {
+            self.properties = properties;
+            ShouldRender::Yes
+        } else {
+            ShouldRender::No
+        }
+    }
+
+    fn update(&mut self, _message: Self::Message) -> ShouldRender {
+        self.frame_index = (self.frame_index + 1) % SPINNER_FRAMES.len();
+        ShouldRender::Yes
+    }
+
+    fn view(&self) -> Layout {
+        Text::with(
+            TextProperties::new()
+                .content(format!(
+                    " {} {} ",
+                    SPINNER_FRAMES[self.frame_index], self.properties.label
+                ))
+                .style(self.properties.style),
+        )
+    }
+
+    fn tick(&self) -> Option<Self::Message> {
+        Some(())
+    }
+}
+
+const SPINNER_FRAMES: [char; 6] = ['◜', '◠', '◝', '◞', '◡', '◟'];
@@ -0,0 +1,470 @@
+use ignore::WalkBuilder;
+use ropey::Rope;
+use std::{
+    borrow::Cow,
+    cmp, fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use zi::{
+    components::{
+        input::{Cursor, Input, InputChange, InputProperties, InputStyle},
+        select::{Select, SelectProperties},
+        text::{Text, TextProperties},
+    },
+    prelude::*,
+    unicode_width::UnicodeWidthStr,
+    Callback,
+};
+
+use zee_edit::search;
+use zee_grammar::Mode;
+
+use super::{
+    status::{Status, StatusProperties},
+    Theme, PROMPT_MAX_HEIGHT,
+};
+use crate::{
+    editor::{
+        buffer::SearchOptions,
+        mode_by_filename,
+        operations::{CancelToken, Operation},
+        ContextHandle,
+    },
+    task::TaskId,
+};
+
+/// A replacement to apply to every occurrence of `query` in the files at
+/// `paths`
+#[derive(Clone, Debug)]
+pub struct Replacement {
+    pub query: String,
+    pub replacement: String,
+    pub options: SearchOptions,
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct SearchDone {
+    task_id: TaskId,
+    files: Vec<FileMatches>,
+}
+
+#[derive(Debug)]
+pub enum Message {
+    ChangeInput(InputChange),
+    ChangeSelected(usize),
+    SearchDone(SearchDone),
+    Accept,
+}
+
+#[derive(Clone)]
+pub struct Properties {
+    pub context: ContextHandle,
+    pub theme: Cow<'static, Theme>,
+    pub options: SearchOptions,
+    pub on_replace: Callback<Replacement>,
+    pub on_change_height: Callback<usize>,
+}
+
+/// Prompts for a query and its replacement, then previews the matching lines
+/// of every file under the current working directory before replacing them.
+pub struct ReplaceInFiles {
+    properties: Properties,
+    link: ComponentLink<Self>,
+    input: Rope,
+    cursor: Cursor,
+    stage: Stage,
+    selected_index: usize,
+    // Stops the search in files when the prompt closes
+    cancel_search: Option<CancelToken>,
+}
+
+enum Stage {
+    Query,
+    Replacement {
+        query: String,
+    },
+    Preview {
+        query: String,
+        replacement: String,
+        preview: Rc<Preview>,
+        current_task_id: Option<TaskId>,
+    },
+}
+
+impl ReplaceInFiles {
+    fn input_text(&self) -> String {
+        self.input.slice(..).to_string().trim_end().to_string()
+    }
+
+    fn find_in_files(&mut self, query: String) -> TaskId {
+        let link = self.link.clone();
+        let root = self.properties.context.current_working_dir.clone();
+        let modes = &self.properties.context.0.modes;
+        let options = self.properties.options;
+        let operation = self.properties.context.start_operation("searching files");
+        self.cancel_search = Some(operation.cancel_token());
+        self.properties.context.task_pool.spawn(move |task_id| {
+            link.send(Message::SearchDone(SearchDone {
+                task_id,
+                files: find_in_files(&root, modes, &query, options, &operation),
+            }))
+        })
+    }
+
+    fn height(&self) -> usize {
+        match self.stage {
+            Stage::Preview { ref preview, .. } => {
+                1 + cmp::min(preview.items.len(), PROMPT_MAX_HEIGHT)
+            }
+            _ => 1,
+        }
+    }
+}
+
+impl Component for ReplaceInFiles {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, _frame: Rect, link: ComponentLink<Self>) -> Self {
+        Self {
+            properties,
+            link,
+            input: "\n".into(),
+            cursor: Cursor::new(),
+            stage: Stage::Query,
+            selected_index: 0,
+            cancel_search: None,
+        }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        let should_render = (self.properties.theme != properties.theme).into();
+        self.properties = properties;
+        should_render
+    }
+
+    fn update(&mut self, message: Message) -> ShouldRender {
+        let initial_height = self.height();
+        match message {
+            Message::ChangeInput(InputChange { content, cursor }) => {
+                if matches!(self.stage, Stage::Preview { .. }) {
+                    return ShouldRender::No;
+                }
+                self.cursor = cursor;
+                if let Some(content) = content {
+                    self.input = content;
+                }
+            }
+            Message::ChangeSelected(index) => {
+                self.selected_index = index;
+            }
+            Message::SearchDone(SearchDone { task_id, files }) => match self.stage {
+                Stage::Preview {
+                    ref mut preview,
+                    ref mut current_task_id,
+                    ..
+                } if *current_task_id == Some(task_id) => {
+                    *preview = Rc::new(Preview::new(files));
+                    *current_task_id = None;
+                    self.selected_index = 0;
+                }
+                _ => return ShouldRender::No,
+            },
+            Message::Accept => match self.stage {
+                Stage::Query => {
+                    let query = self.input_text();
+                    if query.is_empty() {
+                        return ShouldRender::No;
+                    }
+                    self.stage = Stage::Replacement { query };
+                    self.input = "\n".into();
+                    self.cursor = Cursor::new();
+                }
+                Stage::Replacement { ref query } => {
+                    let query = query.clone();
+                    let replacement = self.input_text();
+                    self.stage = Stage::Preview {
+                        current_task_id: Some(self.find_in_files(query.clone())),
+                        query,
+                        replacement,
+                        preview: Rc::new(Preview::default()),
+                    };
+                }
+                Stage::Preview {
+                    ref query,
+                    ref replacement,
+                    ref preview,
+                    current_task_id: None,
+                } => {
+                    self.properties.on_replace.emit(Replacement {
+                        query: query.clone(),
+                        replacement: replacement.clone(),
+                        options: self.properties.options,
+                        paths: preview.files.iter().map(|file| file.path.clone()).collect(),
+                    });
+                }
+                Stage::Preview { .. } => return ShouldRender::No,
+            },
+        }
+
+        if initial_height != self.height() {
+            self.properties.on_change_height.emit(self.height());
+        }
+        ShouldRender::Yes
+    }
+
+    fn view(&self) -> Layout {
+        match self.stage {
+            Stage::Query => self.view_input("replace".into(), self.input.clone(), false),
+            Stage::Replacement { ref query } => self.view_input(
+                format!("replace {} with", query).into(),
+                self.input.clone(),
+                false,
+            ),
+            Stage::Preview {
+                ref query,
+                ref replacement,
+                ref preview,
+                current_task_id,
+            } => {
+                let summary = if current_task_id.is_some() {
+                    "\n".into()
+                } else {
+                    format!(
+                        "{} matches in {} files, RET to replace all\n",
+                        preview.num_matches,
+                        preview.files.len()
+                    )
+                    .into()
+                };
+                Layout::column([
+                    Item::auto(self.view_preview(preview.clone())),
+                    Item::fixed(1)(self.view_input(
+                        format!("replace {} with {}", query, replacement).into(),
+                        summary,
+                        current_task_id.is_some(),
+                    )),
+                ])
+            }
+        }
+    }
+
+    fn bindings(&self, bindings: &mut Bindings<Self>) {
+        if !bindings.is_empty() {
+            return;
+        }
+
+        bindings.set_focus(true);
+
+        bindings.add("accept-replace", [Key::Char('\n')], || Message::Accept);
+    }
+}
+
+impl Drop for ReplaceInFiles {
+    fn drop(&mut self) {
+        if let Some(cancel_search) = self.cancel_search.as_ref() {
+            cancel_search.cancel();
+        }
+    }
+}
+
+impl ReplaceInFiles {
+    fn view_input(&self, action_name: Cow<'static, str>, content: Rope, pending: bool) -> Layout {
+        let focused = !matches!(self.stage, Stage::Preview { .. });
+        let input = Input::with(InputProperties {
+            style: InputStyle {
+                content: self.properties.theme.input,
+                cursor: if focused {
+                    self.properties.theme.cursor
+                } else {
+                    self.properties.theme.input
+                },
+            },
+            content,
+            cursor: if focused {
+                self.cursor.clone()
+            } else {
+                Cursor::new()
+            },
+            on_change: Some(self.link.callback(Message::ChangeInput)),
+            focused,
+        });
+
+        Layout::row([
+            Item::fixed(action_name.width())(Status::with(StatusProperties {
+                action_name,
+                pending,
+                style: self.properties.theme.action,
+            })),
+            Item::fixed(1)(Text::with(
+                TextProperties::new().style(self.properties.theme.input),
+            )),
+            Item::auto(input),
+        ])
+    }
+
+    fn view_preview(&self, preview: Rc<Preview>) -> Layout {
+        let selected_index = self.selected_index;
+        let theme = self.properties.theme.clone();
+        let root = self.properties.context.current_working_dir.clone();
+        let num_items = preview.items.len();
+        let item_at = move |index: usize| {
+            let background = if index == selected_index {
+                theme.item_focused_background
+            } else {
+                theme.item_unfocused_background
+            };
+            let (content, style) = match preview.items[index] {
+                PreviewItem::File(file_index) => {
+                    let file = &preview.files[file_index];
+                    (
+                        format!(
+                            "{} ({})",
+                            file.path
+                                .strip_prefix(&root)
+                                .unwrap_or(&file.path)
+                                .display(),
+                            file.num_matches
+                        ),
+                        Style::bold(background, theme.item_directory_foreground),
+                    )
+                }
+                PreviewItem::Line(file_index, line_index) => {
+                    let (line_number, ref line) = preview.files[file_index].lines[line_index];
+                    (
+                        format!("{:>6}: {}", line_number + 1, line),
+                        Style::normal(background, theme.item_file_foreground),
+                    )
+                }
+            };
+            Item::fixed(1)(Text::with_key(
+                index.to_string().as_str(),
+                TextProperties::new().content(content).style(style),
+            ))
+        };
+
+        Select::with(SelectProperties {
+            background: Style::normal(
+                self.properties.theme.item_unfocused_background,
+                self.properties.theme.item_file_foreground,
+            ),
+            direction: FlexDirection::ColumnReverse,
+            item_at: item_at.into(),
+            focused: true,
+            num_items,
+            selected: self.selected_index,
+            on_change: self.link.callback(Message::ChangeSelected).into(),
+            item_size: 1,
+        })
+    }
+}
+
+/// The lines of a file containing matches, with their zero-based line index
+#[derive(Debug)]
+struct FileMatches {
+    path: PathBuf,
+    lines: Vec<(usize, String)>,
+    num_matches: usize,
+}
+
+enum PreviewItem {
+    File(usize),
+    Line(usize, usize),
+}
+
+#[derive(Default)]
+struct Preview {
+    files: Vec<FileMatches>,
+    items: Vec<PreviewItem>,
+    num_matches: usize,
+}
+
+impl Preview {
+    fn new(files: Vec<FileMatches>) -> Self {
+        let mut items = Vec::new();
+        for (file_index, file) in files.iter().enumerate() {
+            items.push(PreviewItem::File(file_index));
+            items.extend(
+                (0..file.lines.len()).map(|line_index| PreviewItem::Line(file_index, line_index)),
+            );
+        }
+        Self {
+            num_matches: files.iter().map(|file| file.num_matches).sum(),
+            files,
+            items,
+        }
+    }
+}
+
+/// Searches the files under `root` that aren't ignored by git. Binary files
+/// and files that aren't valid UTF-8 are skipped. The contents on disk are
+/// searched, unsaved changes in open buffers aren't taken into account.
+/// Whole words are made of the word characters of each file's mode.
+fn find_in_files(
+    root: &Path,
+    modes: &[Mode],
+    query: &str,
+    options: SearchOptions,
+    operation: &Operation,
+) -> Vec<FileMatches> {
+    let mut files: Vec<_> = WalkBuilder::new(root)
+        .build()
+        .take_while(|_| !operation.is_cancelled())
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_type()
+                .map_or(false, |file_type| file_type.is_file())
+        })
+        .filter_map(|entry| {
+            let mode = mode_by_filename(modes, entry.path());
+            find_in_file(entry.path(), query, options, mode)
+        })
+        .collect();
+    files.sort_by(|left, right| left.path.cmp(&right.path));
+    files
+}
+
+fn find_in_file(
+    path: &Path,
+    query: &str,
+    options: SearchOptions,
+    mode: &Mode,
+) -> Option<FileMatches> {
+    let bytes = fs::read(path).ok()?;
+    if bytes[..cmp::min(bytes.len(), BINARY_DETECTION_LENGTH)].contains(&0) {
+        return None;
+    }
+    let text = Rope::from(String::from_utf8(bytes).ok()?);
+    let matches = search::find_all(&text, query, options.case_mode, options.whole_word_in(mode));
+    if matches.is_empty() {
+        return None;
+    }
+
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for range in matches.iter() {
+        let line_index = text.char_to_line(range.start);
+        if lines.last().map_or(true, |(last, _)| *last != line_index) {
+            let line = text.line(line_index);
+            lines.push((
+                line_index,
+                line.chars()
+                    .take(MAX_PREVIEW_LINE_LENGTH)
+                    .collect::<String>()
+                    .trim()
+                    .to_string(),
+            ));
+        }
+    }
+    Some(FileMatches {
+        path: path.to_path_buf(),
+        lines,
+        num_matches: matches.len(),
+    })
+}
+
+/// Files with a NUL byte in their first few kilobytes are considered binary
+const BINARY_DETECTION_LENGTH: usize = 8192;
+const MAX_PREVIEW_LINE_LENGTH: usize = 256;
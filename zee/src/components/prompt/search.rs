@@ -0,0 +1,181 @@
+use ropey::Rope;
+use std::borrow::Cow;
+use zi::{
+    components::{
+        input::{Cursor, Input, InputChange, InputProperties, InputStyle},
+        text::{Text, TextProperties},
+    },
+    prelude::*,
+    Callback,
+};
+
+use zee_edit::{search::CaseMode, Direction};
+
+use super::{
+    status::{Status, StatusProperties},
+    Theme,
+};
+use crate::editor::buffer::{SearchOptions, SearchQuery};
+
+#[derive(Debug)]
+pub enum Message {
+    ChangeQuery(InputChange),
+    SearchAgain(Direction),
+    ToggleCaseMode,
+    ToggleWholeWord,
+    Accept,
+}
+
+#[derive(Clone)]
+pub struct Properties {
+    pub theme: Cow<'static, Theme>,
+    pub direction: Direction,
+    pub options: SearchOptions,
+    pub on_search: Callback<Option<SearchQuery>>,
+    pub on_change_options: Callback<SearchOptions>,
+    pub on_accept: Callback<()>,
+}
+
+pub struct SearchPrompt {
+    properties: Properties,
+    link: ComponentLink<Self>,
+    input: Rope,
+    cursor: Cursor,
+    direction: Direction,
+    options: SearchOptions,
+}
+
+impl SearchPrompt {
+    fn change_options(&mut self, options: SearchOptions) {
+        self.options = options;
+        self.properties.on_change_options.emit(options);
+        self.search(false);
+    }
+
+    fn search(&self, skip_current: bool) {
+        self.properties.on_search.emit(Some(SearchQuery {
+            query: self.input.slice(..).to_string().trim_end().to_string(),
+            direction: self.direction,
+            options: self.options,
+            skip_current,
+        }));
+    }
+}
+
+impl Component for SearchPrompt {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(properties: Self::Properties, _frame: Rect, link: ComponentLink<Self>) -> Self {
+        Self {
+            direction: properties.direction,
+            options: properties.options,
+            properties,
+            link,
+            input: "\n".into(),
+            cursor: Cursor::new(),
+        }
+    }
+
+    fn change(&mut self, properties: Self::Properties) -> ShouldRender {
+        let should_render = (self.properties.theme != properties.theme).into();
+        self.properties = properties;
+        should_render
+    }
+
+    fn update(&mut self, message: Message) -> ShouldRender {
+        match message {
+            Message::ChangeQuery(InputChange { content, cursor }) => {
+                self.cursor = cursor;
+                if let Some(content) = content {
+                    self.input = content;
+                    self.search(false);
+                }
+            }
+            Message::SearchAgain(direction) => {
+                self.direction = direction;
+                self.search(true);
+            }
+            Message::ToggleCaseMode => self.change_options(SearchOptions {
+                case_mode: match self.options.case_mode {
+                    CaseMode::Smart => CaseMode::Sensitive,
+                    CaseMode::Sensitive => CaseMode::Insensitive,
+                    CaseMode::Insensitive => CaseMode::Smart,
+                },
+                ..self.options
+            }),
+            Message::ToggleWholeWord => self.change_options(SearchOptions {
+                whole_word: !self.options.whole_word,
+                ..self.options
+            }),
+            Message::Accept => {
+                self.properties.on_accept.emit(());
+            }
+        }
+        ShouldRender::Yes
+    }
+
+    fn view(&self) -> Layout {
+        let input = Input::with(InputProperties {
+            style: InputStyle {
+                content: self.properties.theme.input,
+                cursor: self.properties.theme.cursor,
+            },
+            content: self.input.clone(),
+            cursor: self.cursor.clone(),
+            on_change: Some(self.link.callback(Message::ChangeQuery)),
+            focused: true,
+        });
+
+        let action_name = format!(
+            "{}{}{}",
+            match self.direction {
+                Direction::Forward => "search",
+                Direction::Backward => "rsearch",
+            },
+            match self.options.case_mode {
+                CaseMode::Smart => "",
+                CaseMode::Sensitive => " [Aa]",
+                CaseMode::Insensitive => " [aa]",
+            },
+            if self.options.whole_word {
+                " [word]"
+            } else {
+                ""
+            }
+        );
+        Layout::row([
+            Item::fixed(action_name.len())(Status::with(StatusProperties {
+                action_name: action_name.into(),
+                pending: false,
+                style: self.properties.theme.action,
+            })),
+            Item::fixed(1)(Text::with(
+                TextProperties::new().style(self.properties.theme.input),
+            )),
+            Item::auto(input),
+        ])
+    }
+
+    fn bindings(&self, bindings: &mut Bindings<Self>) {
+        if !bindings.is_empty() {
+            return;
+        }
+
+        bindings.set_focus(true);
+
+        bindings.add("search-forward", [Key::Ctrl('s')], || {
+            Message::SearchAgain(Direction::Forward)
+        });
+        bindings.add("search-backward", [Key::Ctrl('r')], || {
+            Message::SearchAgain(Direction::Backward)
+        });
+        bindings.add("toggle-search-case", [Key::Alt('c')], || {
+            Message::ToggleCaseMode
+        });
+        bindings.add("toggle-search-whole-word", [Key::Alt('w')], || {
+            Message::ToggleWholeWord
+        });
+        bindings.add("accept-search", [Key::Char('\n')], || Message::Accept);
+    }
+}
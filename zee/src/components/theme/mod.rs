@@ -5,6 +5,7 @@ use zi::terminal::{Colour, Style};
 
 use super::{
     buffer::{status_bar::Theme as StatusBarTheme, Theme as BufferTheme},
+    diff_viewer::Theme as DiffViewerTheme,
     edit_tree_viewer::Theme as EditTreeViewerTheme,
     prompt::Theme as PromptTheme,
     splash::Theme as SplashTheme,
@@ -123,6 +124,10 @@ impl Theme {
                     cursor_focused: normal(LIGHT0, DARK0),
                     cursor_unfocused: normal(GRAY_245, DARK0_HARD),
                     selection_background: DARK0_HARD,
+                    symbol_highlight_background: DARK2,
+                    matching_bracket_background: DARK3,
+                    search_match: normal(FADED_YELLOW, DARK0_HARD),
+                    search_current_match: bold(BRIGHT_ORANGE, DARK0_HARD),
                     text: normal(DARK0, LIGHT1),
                     text_current_line: normal(DARK0_HARD, LIGHT1),
                     code_char: normal(DARK0_SOFT, BRIGHT_GREEN),
@@ -139,6 +144,7 @@ impl Theme {
                     code_string: normal(DARK0_SOFT, BRIGHT_GREEN),
                     code_type: normal(DARK0_SOFT, BRIGHT_YELLOW),
                     code_variant: normal(DARK0_SOFT, BRIGHT_PURPLE),
+                    code_todo: bold(DARK0_SOFT, BRIGHT_YELLOW),
                 },
                 edit_tree_viewer: EditTreeViewerTheme {
                     current_revision: bold(DARK0, BRIGHT_RED),
@@ -147,7 +153,14 @@ impl Theme {
                     alternate_revision: normal(DARK0, DARK4),
                     alternate_connector: normal(DARK0, DARK4),
                 },
+                diff_viewer: DiffViewerTheme {
+                    base: normal(DARK0, LIGHT1),
+                    header: bold(DARK0, BRIGHT_BLUE),
+                    added: normal(DARK0, BRIGHT_GREEN),
+                    removed: normal(DARK0, BRIGHT_RED),
+                },
                 border: normal(DARK0_HARD, GRAY_245),
+                unsaved_line: normal(DARK0_HARD, FADED_YELLOW),
                 status_bar: StatusBarTheme {
                     base: normal(DARK0_SOFT, DARK0),
                     frame_id_focused: normal(BRIGHT_BLUE, DARK0_HARD),
@@ -221,6 +234,10 @@ impl Theme {
                     cursor_focused: normal(light_foreground, default_background),
                     cursor_unfocused: normal(comments, default_background),
                     selection_background,
+                    symbol_highlight_background: comments,
+                    matching_bracket_background: selection_background,
+                    search_match: normal(classes, default_background),
+                    search_current_match: bold(constants, default_background),
                     text: normal(default_background, default_foreground),
                     text_current_line: normal(lighter_background, default_foreground),
                     code_char: normal(default_background, support),
@@ -237,6 +254,7 @@ impl Theme {
                     code_string: normal(default_background, strings),
                     code_type: normal(default_background, classes),
                     code_variant: normal(default_background, classes),
+                    code_todo: bold(default_background, keywords),
                 },
                 edit_tree_viewer: EditTreeViewerTheme {
                     current_revision: bold(default_background, embedded),
@@ -245,7 +263,14 @@ impl Theme {
                     alternate_revision: normal(default_background, default_foreground),
                     alternate_connector: normal(default_background, comments),
                 },
+                diff_viewer: DiffViewerTheme {
+                    base: normal(default_background, default_foreground),
+                    header: bold(default_background, functions),
+                    added: normal(default_background, strings),
+                    removed: normal(default_background, variables),
+                },
                 border: normal(lighter_background, dark_foreground),
+                unsaved_line: normal(lighter_background, constants),
                 status_bar: StatusBarTheme {
                     base: normal(lighter_background, default_background),
                     frame_id_focused: normal(functions, default_background),
@@ -1,11 +1,26 @@
 use include_dir::{include_dir, Dir};
 use once_cell::sync::Lazy;
 use serde_derive::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    mem,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 
 use zee_grammar::{config::ModeConfig, Mode};
 
-use crate::error::{Context, Result};
+use crate::{
+    clipboard::ClipboardSetting,
+    commands::Confirmations,
+    components::buffer::{
+        status_bar::StatusBarLayout,
+        textarea::{ControlCharacterStyle, CursorShape},
+        ScrollPolicy,
+    },
+    editor::buffer::SelectionMode,
+    error::{Context, Result},
+};
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename = "Zee")]
@@ -17,6 +32,142 @@ pub struct EditorConfig {
     /// impact performance. Default: `true`.
     #[serde(default)]
     pub trim_trailing_whitespace_on_save: bool,
+    /// End files with exactly one newline when saving, removing any extra
+    /// blank lines at the end. Empty files are left empty. Default: `true`.
+    #[serde(default = "default_true")]
+    pub ensure_final_newline: bool,
+    /// Indent with tabs or spaces like the lines of an opened file already
+    /// are, rather than as set by its mode. Default: `true`.
+    #[serde(default = "default_true")]
+    pub detect_indentation: bool,
+    /// Save buffers to their files automatically once they're idle after an
+    /// edit. Default: `false`.
+    #[serde(default)]
+    pub autosave: bool,
+    /// How many seconds without edits before a buffer is autosaved.
+    /// Default: `5`.
+    #[serde(default = "default_autosave_idle_seconds")]
+    pub autosave_idle_seconds: u64,
+    /// Stop at the start and end of lines when moving by words, rather than
+    /// moving across lines to the next word. Default: `false`.
+    #[serde(default)]
+    pub word_movement_stops_at_eol: bool,
+    /// Move and delete by subwords, i.e. stop at case changes and underscores
+    /// inside identifiers like `getHTTPResponse`. Default: `false`.
+    #[serde(default)]
+    pub subword_movement: bool,
+    /// How long a selection lasts. Default: `Sticky`, until it's cleared.
+    /// With `Transient`, editing the text ends the selection and movement
+    /// only extends a selection begun with the mark.
+    #[serde(default)]
+    pub selection_mode: SelectionMode,
+    /// Which clipboard to copy to and paste from. Default: `Auto`, which uses
+    /// OSC 52 escape sequences over SSH and the native clipboard otherwise.
+    #[serde(default)]
+    pub clipboard: ClipboardSetting,
+    /// Open the files changed by replace in files as buffers, so the changes
+    /// can be undone. Otherwise files that aren't already open are edited on
+    /// disk directly. Default: `true`.
+    #[serde(default = "default_true")]
+    pub replace_in_files_opens_buffers: bool,
+    /// Files larger than this many bytes are loaded in the background and
+    /// opened without syntax parsing. Zero disables the threshold. Default: `52428800` (50 MiB).
+    #[serde(default = "default_large_file_threshold")]
+    pub large_file_threshold: u64,
+    /// Open files above `large_file_threshold` read-only. Default: `true`.
+    #[serde(default = "default_true")]
+    pub large_files_read_only: bool,
+    /// The number of undo revisions kept for each buffer, pruning the
+    /// branches least recently visited first. Zero keeps every revision.
+    /// Default: `10000`.
+    #[serde(default = "default_max_undo_revisions")]
+    pub max_undo_revisions: usize,
+    /// The bytes of text inserted and deleted by the undo revisions kept for
+    /// each buffer, which bounds the memory they take up. Zero for no limit.
+    /// Default: `67108864` (64 MiB).
+    #[serde(default = "default_max_undo_bytes")]
+    pub max_undo_bytes: usize,
+    /// Kill external programs run by the editor after this many seconds. Zero
+    /// disables the timeout. Default: `30`.
+    #[serde(default = "default_command_timeout_seconds")]
+    pub command_timeout_seconds: u64,
+    /// How control characters like form feeds are shown. Default: `Caret`,
+    /// e.g. `^L`.
+    #[serde(default)]
+    pub control_characters: ControlCharacterStyle,
+    /// How the cursor of the focused window is drawn. Default: `Block`.
+    #[serde(default)]
+    pub cursor_shape: CursorShape,
+    /// Blink the cursor of the focused window. It stays visible while it
+    /// moves or the text is edited. Default: `false`.
+    #[serde(default)]
+    pub cursor_blink: bool,
+    /// The columns vertical guides are drawn at, e.g. `[72, 100]`. Default:
+    /// none.
+    #[serde(default)]
+    pub column_guides: Vec<usize>,
+    /// Mark the tab stops in the indentation of lines. Default: `false`.
+    #[serde(default)]
+    pub tab_stop_guides: bool,
+    /// The number of lines of the previous page kept in view by a page up or
+    /// down. Default: `2`.
+    #[serde(default = "default_page_context_lines")]
+    pub page_context_lines: usize,
+    /// Scroll the view as much as the cursor on a page up or down, keeping
+    /// the cursor on the same row of the window. Default: `false`.
+    #[serde(default)]
+    pub page_keeps_cursor_row: bool,
+    /// How the view scrolls to follow the cursor, either keeping a margin of
+    /// lines around it or keeping it centered. Default: `Margin(0)`.
+    #[serde(default)]
+    pub scroll_policy: ScrollPolicy,
+    /// Pin the first lines of the functions, types and other scopes enclosing
+    /// the text in view to the top of the window. Default: `false`.
+    #[serde(default)]
+    pub sticky_scopes: bool,
+    /// Highlight the occurrences of the word under the cursor in view.
+    /// Default: `false`.
+    #[serde(default)]
+    pub highlight_symbol_at_point: bool,
+    /// Mark the lines changed since the buffer was last saved in the gutter.
+    /// Default: `false`.
+    #[serde(default)]
+    pub show_unsaved_lines: bool,
+    /// Keywords highlighted in comments, e.g. `TODO`, which `C-c t n` and
+    /// `C-c t p` move between. Default: `["TODO", "FIXME", "XXX", "HACK"]`.
+    #[serde(default = "default_todo_keywords")]
+    pub todo_keywords: Vec<String>,
+    /// Match `todo_keywords` regardless of letter case. Default: `false`.
+    #[serde(default)]
+    pub todo_keywords_ignore_case: bool,
+    /// Spell check comments and strings, or all the text in prose modes like
+    /// Markdown, underlining misspelled words. Default: `false`.
+    #[serde(default)]
+    pub spell_check: bool,
+    /// The word list used to spell check, one word per line. Words added to
+    /// the personal dictionary are saved to `personal_dictionary.txt` in the
+    /// configuration directory. Default: `"/usr/share/dict/words"`.
+    #[serde(default = "default_spell_check_word_list")]
+    pub spell_check_word_list: PathBuf,
+    /// The format of the date inserted by `insert-date`, using `strftime`
+    /// specifiers. Default: `"%Y-%m-%d"`.
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    /// Templates inserted by name with `insert-snippet`, with `$1`, `$2`, ...
+    /// tab stops and `$0` as the final position. Default: empty.
+    #[serde(default)]
+    pub snippets: BTreeMap<String, String>,
+    /// The segments of the status bar, most important first, each drawn from
+    /// the left or the right. Default: the frame id, modified status and file
+    /// on the left, the cursor position on the right.
+    #[serde(default)]
+    pub status_bar: StatusBarLayout,
+    /// Which destructive commands ask for confirmation first, by name, and
+    /// whether any do. Default: `(enabled: true, commands: {})`, where
+    /// `delete-file` always asks and `kill-buffer` and `quit` ask before
+    /// discarding unsaved changes.
+    #[serde(default)]
+    pub confirmations: Confirmations,
 }
 
 impl Default for EditorConfig {
@@ -25,25 +176,98 @@ impl Default for EditorConfig {
     }
 }
 
-/// Finds the editor configuration. If we cannot for any reason, we'll use the
-/// default configuration to ensure the editor opens in any environment.
-pub fn find_editor_config(config_dir: Option<PathBuf>) -> EditorConfig {
+// The defaults of settings missing from a configuration file, matching the
+// packaged `config.ron`
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_autosave_idle_seconds() -> u64 {
+    5
+}
+
+fn default_large_file_threshold() -> u64 {
+    50 * 1024 * 1024
+}
+
+fn default_max_undo_revisions() -> usize {
+    10000
+}
+
+fn default_max_undo_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+fn default_command_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_page_context_lines() -> usize {
+    2
+}
+
+fn default_todo_keywords() -> Vec<String> {
+    ["TODO", "FIXME", "XXX", "HACK"]
+        .iter()
+        .map(|keyword| keyword.to_string())
+        .collect()
+}
+
+fn default_spell_check_word_list() -> PathBuf {
+    PathBuf::from("/usr/share/dict/words")
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".into()
+}
+
+/// The configuration the editor runs with, which `reload-config` replaces.
+/// A configuration got before a reload stays as it was.
+pub struct SharedConfig(RwLock<Arc<EditorConfig>>);
+
+impl SharedConfig {
+    pub fn new(config: EditorConfig) -> Self {
+        Self(RwLock::new(Arc::new(config)))
+    }
+
+    /// The current configuration
+    pub fn get(&self) -> Arc<EditorConfig> {
+        Arc::clone(&self.0.read().unwrap())
+    }
+
+    /// Replaces the configuration with the file at `path`, or the defaults if
+    /// there's no such file, returning the one replaced. The current
+    /// configuration is kept on errors.
+    pub fn reload(&self, path: &Path) -> Result<Arc<EditorConfig>> {
+        let config = if path.exists() {
+            parse_config_file(path)?
+        } else {
+            EditorConfig::default()
+        };
+        Ok(mem::replace(
+            &mut *self.0.write().unwrap(),
+            Arc::new(config),
+        ))
+    }
+}
+
+/// The path of the configuration file, in `config_dir` if given
+pub fn editor_config_path(config_dir: Option<PathBuf>) -> Option<PathBuf> {
     config_dir
         .or_else(|| zee_grammar::config::config_dir().ok())
         .map(|config_dir| config_dir.join("config.ron"))
-        .map_or_else(Default::default, |path| read_config_file(&path))
+}
+
+/// Finds the editor configuration. If we cannot for any reason, we'll use the
+/// default configuration to ensure the editor opens in any environment.
+pub fn find_editor_config(path: Option<&Path>) -> EditorConfig {
+    path.map_or_else(Default::default, read_config_file)
 }
 
 fn read_config_file(path: &Path) -> EditorConfig {
     if path.exists() {
-        std::fs::read_to_string(path)
-            .with_context(|| format!("Could not read configuration file `{}`", path.display()))
-            .and_then(|contents| {
-                log::info!("Reading configuration file `{}`", path.display());
-                ron::de::from_str(&contents).with_context(|| {
-                    format!("Could not parse configuration file `{}`", path.display())
-                })
-            })
+        parse_config_file(path)
             .map_err(|err| log::error!("{}", err))
             .unwrap_or_else(|_| Default::default())
     } else {
@@ -51,6 +275,16 @@ fn read_config_file(path: &Path) -> EditorConfig {
     }
 }
 
+fn parse_config_file(path: &Path) -> Result<EditorConfig> {
+    std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read configuration file `{}`", path.display()))
+        .and_then(|contents| {
+            log::info!("Reading configuration file `{}`", path.display());
+            ron::de::from_str(&contents)
+                .with_context(|| format!("Could not parse configuration file `{}`", path.display()))
+        })
+}
+
 pub fn create_default_config_file(path: &Path) -> Result<()> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)
@@ -77,3 +311,67 @@ static DEFAULT_EDITOR_CONFIG: Lazy<EditorConfig> = Lazy::new(|| {
     ron::de::from_str(default_config_str())
         .expect("packaged default configuration file is well formed")
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_settings_take_their_documented_defaults() {
+        let config: EditorConfig = ron::de::from_str("Zee(modes: [])").unwrap();
+        let packaged = EditorConfig::default();
+        assert!(config.ensure_final_newline && packaged.ensure_final_newline);
+        assert!(config.detect_indentation && packaged.detect_indentation);
+        assert!(config.replace_in_files_opens_buffers && packaged.replace_in_files_opens_buffers);
+        assert_eq!(packaged.autosave_idle_seconds, config.autosave_idle_seconds);
+        assert_eq!(packaged.large_file_threshold, config.large_file_threshold);
+        assert!(config.large_files_read_only && packaged.large_files_read_only);
+        assert_eq!(packaged.max_undo_revisions, config.max_undo_revisions);
+        assert_eq!(packaged.max_undo_bytes, config.max_undo_bytes);
+        assert_eq!(30, config.command_timeout_seconds);
+        assert_eq!(
+            packaged.command_timeout_seconds,
+            config.command_timeout_seconds
+        );
+        assert_eq!(2, config.page_context_lines);
+        assert_eq!(packaged.page_context_lines, config.page_context_lines);
+        assert_eq!(packaged.todo_keywords, config.todo_keywords);
+        assert_eq!(packaged.spell_check_word_list, config.spell_check_word_list);
+        assert_eq!(packaged.date_format, config.date_format);
+    }
+
+    #[test]
+    fn reject_unsupported_cursor_shapes() {
+        let config = |shape| format!("Zee(modes: [], cursor_shape: {})", shape);
+        let underline: EditorConfig = ron::de::from_str(&config("Underline")).unwrap();
+        assert_eq!(CursorShape::Underline, underline.cursor_shape);
+
+        let error = ron::de::from_str::<EditorConfig>(&config("Bar")).unwrap_err();
+        assert!(error.to_string().contains("Bar"), "{}", error);
+    }
+
+    #[test]
+    fn reload_changed_settings() {
+        let path =
+            std::env::temp_dir().join(format!("zee-reload-config-{}.ron", std::process::id()));
+        let config = SharedConfig::new(EditorConfig::default());
+        let before = config.get();
+        assert_eq!(2, before.page_context_lines);
+
+        let changed =
+            default_config_str().replace("page_context_lines: 2", "page_context_lines: 5");
+        std::fs::write(&path, changed).unwrap();
+        assert_eq!(2, config.reload(&path).unwrap().page_context_lines);
+        assert_eq!(5, config.get().page_context_lines);
+        assert_eq!(2, before.page_context_lines);
+
+        // A configuration which doesn't parse keeps the current one
+        std::fs::write(&path, "Zee(page_context_lines: ").unwrap();
+        assert!(config.reload(&path).is_err());
+        assert_eq!(5, config.get().page_context_lines);
+
+        std::fs::remove_file(&path).unwrap();
+        config.reload(&path).unwrap();
+        assert_eq!(2, config.get().page_context_lines);
+    }
+}
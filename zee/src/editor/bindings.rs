@@ -1,6 +1,9 @@
 use zi::{terminal::Key, Bindings, EndsWith, FlexDirection};
 
-use super::{Editor, FileSource, Message};
+use zee_edit::Direction;
+
+use super::{quoted_insert::QuotedInsert, Editor, FileSource, Message};
+use crate::commands::{self, Command, Confirm, Handler, Keymap};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(super) struct KeySequenceSlice<'a> {
@@ -41,73 +44,264 @@ impl<'a> std::fmt::Display for KeySequenceSlice<'a> {
 pub(super) fn initialize(bindings: &mut Bindings<Editor>) {
     bindings.set_focus(true);
     bindings.set_notify(true);
+    commands::register_intercepted(bindings, &commands(), &keymap(), Editor::intercept_keys);
+}
 
-    // Cancel
-    bindings.add("cancel", EndsWith(Key::Ctrl('g')), || Message::Cancel);
-
-    // Open a file
-    bindings.add("find-file", [Key::Ctrl('x'), Key::Ctrl('f')], || {
-        Message::OpenFilePicker(FileSource::Directory)
-    });
-    bindings.add(
-        "find-file-in-repo",
-        [Key::Ctrl('x'), Key::Ctrl('v')],
-        || Message::OpenFilePicker(FileSource::Repository),
-    );
-
-    // Buffer management
-    bindings.add("switch-buffer", [Key::Ctrl('x'), Key::Char('b')], || {
-        Message::SelectBufferPicker
-    });
-    bindings.add("kill-buffer", [Key::Ctrl('x'), Key::Char('k')], || {
-        Message::KillBufferPicker
-    });
-
-    // Window management
-    //
-    // Change focus
-    bindings
-        .command("focus-next-window", || Message::FocusNextWindow)
-        .with([Key::Ctrl('x'), Key::Char('o')])
-        .with([Key::Ctrl('x'), Key::Ctrl('o')]);
-    bindings
-        .command("focus-previous-window", || Message::FocusPreviousWindow)
-        .with([Key::Ctrl('x'), Key::Char('i')])
-        .with([Key::Ctrl('x'), Key::Ctrl('i')]);
+/// The commands which apply anywhere in the editor
+pub(super) fn commands() -> Vec<Command<Editor>> {
+    vec![
+        Command::new(
+            "cancel",
+            "Cancel the current prompt or key sequence",
+            Handler::Send(|| Message::Cancel),
+        ),
+        // Open a file
+        Command::new(
+            "find-file",
+            "Open a file in the current directory",
+            Handler::Send(|| Message::OpenFilePicker(FileSource::Directory)),
+        ),
+        Command::new(
+            "find-file-in-repo",
+            "Open a file in the repository",
+            Handler::Send(|| Message::OpenFilePicker(FileSource::Repository)),
+        ),
+        Command::new(
+            "open-related-file",
+            "Open the counterpart of the current file, e.g. the header of a C file",
+            Handler::Send(|| Message::OpenRelatedFile),
+        ),
+        Command::new(
+            "toggle-test-file",
+            "Toggle between a file and its tests",
+            Handler::Send(|| Message::OpenTestFile),
+        ),
+        // File management
+        Command::new(
+            "rename-file",
+            "Rename the current file",
+            Handler::Send(|| Message::RenameFilePrompt),
+        ),
+        Command::new(
+            "delete-file",
+            "Delete the current file",
+            Handler::Send(|| Message::DeleteFilePrompt),
+        )
+        .confirms(Confirm::Always),
+        Command::new(
+            "create-directory",
+            "Create a directory",
+            Handler::Send(|| Message::CreateDirectoryPrompt),
+        ),
+        Command::new(
+            "insert-char",
+            "Insert a character by Unicode name or code point, e.g. `U+1F600`",
+            Handler::Send(|| Message::InsertCharPrompt),
+        ),
+        Command::new(
+            "shell-command-on-region",
+            "Replace the selection, or the buffer, with its output through a shell command",
+            Handler::Send(|| Message::ShellCommandPrompt),
+        ),
+        Command::new(
+            "quoted-insert",
+            "Insert the next key typed literally, or the character of an octal code",
+            Handler::Send(|| Message::QuotedInsert(Some(QuotedInsert::new()))),
+        ),
+        Command::new(
+            "insert-snippet",
+            "Insert a snippet of the buffer's mode",
+            Handler::Send(|| Message::InsertSnippetPrompt),
+        ),
+        // Navigation
+        Command::new(
+            "goto-line",
+            "Go to a line number",
+            Handler::Send(|| Message::GotoLinePrompt),
+        ),
+        Command::new(
+            "goto-percent",
+            "Go to a percentage of the buffer",
+            Handler::Send(|| Message::GotoPercentPrompt),
+        ),
+        // Search
+        Command::new(
+            "search-forward",
+            "Search forward in the buffer",
+            Handler::Send(|| Message::SearchPrompt(Direction::Forward)),
+        ),
+        Command::new(
+            "search-backward",
+            "Search backward in the buffer",
+            Handler::Send(|| Message::SearchPrompt(Direction::Backward)),
+        ),
+        Command::new(
+            "query-replace-regexp",
+            "Replace the matches of a regular expression, asking about each one",
+            Handler::Send(|| Message::QueryReplacePrompt),
+        ),
+        Command::new(
+            "replace-in-files",
+            "Replace text in the files of the repository",
+            Handler::Send(|| Message::ReplaceInFilesPrompt),
+        ),
+        // Buffer management
+        Command::new(
+            "switch-buffer",
+            "Switch to another buffer",
+            Handler::Send(|| Message::SelectBufferPicker),
+        ),
+        Command::new(
+            "kill-buffer",
+            "Close a buffer",
+            Handler::Send(|| Message::KillBufferPicker),
+        )
+        .confirms(Confirm::WhenModified),
+        Command::new(
+            "revert-all-buffers",
+            "Reload every buffer from its file",
+            Handler::Send(|| Message::RevertAllBuffers),
+        ),
+        Command::new(
+            "detect-external-changes",
+            "Check whether files were changed outside the editor",
+            Handler::Send(|| Message::CheckExternalChanges),
+        ),
+        // Window management
+        Command::new(
+            "focus-next-window",
+            "Focus the next window",
+            Handler::Send(|| Message::FocusNextWindow),
+        ),
+        Command::new(
+            "focus-previous-window",
+            "Focus the previous window",
+            Handler::Send(|| Message::FocusPreviousWindow),
+        ),
+        Command::new(
+            "fullscreen-window",
+            "Make the current window fullscreen",
+            Handler::Send(|| Message::FullscreenWindow),
+        ),
+        Command::new(
+            "split-window-below",
+            "Split the current window into a column",
+            Handler::Send(|| Message::SplitWindow(FlexDirection::Column)),
+        ),
+        Command::new(
+            "split-window-right",
+            "Split the current window into a row",
+            Handler::Send(|| Message::SplitWindow(FlexDirection::Row)),
+        ),
+        Command::new(
+            "clone-view-other-window",
+            "Show the current buffer with its own cursor in another window",
+            Handler::Send(|| Message::CloneViewOtherWindow),
+        ),
+        Command::new(
+            "delete-window",
+            "Close the current window",
+            Handler::Send(|| Message::DeleteWindow),
+        ),
+        // Theme
+        Command::new(
+            "change-theme",
+            "Switch to the next theme",
+            Handler::Send(|| Message::ChangeTheme),
+        ),
+        Command::new(
+            "reload-config",
+            "Read the configuration file again and apply it",
+            Handler::Send(|| Message::ReloadConfig),
+        ),
+        Command::new("quit", "Quit the editor", Handler::Send(|| Message::Quit))
+            .confirms(Confirm::WhenModified),
+    ]
+}
 
-    // Make current window fullscreen
-    bindings
-        .command("fullscreen-window", || Message::FullscreenWindow)
-        .with([Key::Ctrl('x'), Key::Char('1')])
-        .with([Key::Ctrl('x'), Key::Ctrl('1')]);
+/// The default key bindings of the editor's commands
+pub(super) fn keymap() -> Keymap {
+    use Key::*;
 
-    // Split window below (column)
-    bindings
-        .command("split-window-below", || {
-            Message::SplitWindow(FlexDirection::Column)
-        })
-        .with([Key::Ctrl('x'), Key::Char('2')])
-        .with([Key::Ctrl('x'), Key::Ctrl('2')]);
+    let mut keymap = Keymap::new();
+    keymap
+        // Cancel
+        .bind("cancel", EndsWith(Ctrl('g')))
+        // Open a file
+        .bind("find-file", [Ctrl('x'), Ctrl('f')])
+        .bind("find-file-in-repo", [Ctrl('x'), Ctrl('v')])
+        .bind("open-related-file", [Ctrl('c'), Char('o')])
+        .bind("toggle-test-file", [Ctrl('c'), Char('p'), Char('t')])
+        // File management
+        .bind("rename-file", [Ctrl('x'), Char('f'), Char('r')])
+        .bind("delete-file", [Ctrl('x'), Char('f'), Char('d')])
+        .bind("create-directory", [Ctrl('x'), Char('f'), Char('m')])
+        .bind("insert-char", [Ctrl('x'), Char('8'), Char('\n')])
+        .bind("insert-snippet", [Ctrl('c'), Char('i'), Char('s')])
+        .bind("quoted-insert", [Ctrl('q')])
+        .bind("shell-command-on-region", [Alt('|')])
+        // Navigation
+        .bind("goto-line", [Alt('g'), Char('g')])
+        .bind("goto-percent", [Alt('g'), Char('%')])
+        // Search
+        .bind("search-forward", [Ctrl('s')])
+        .bind("search-backward", [Ctrl('r')])
+        .bind("query-replace-regexp", [Alt('%')])
+        .bind("replace-in-files", [Ctrl('x'), Char('p'), Char('r')])
+        // Buffer management
+        .bind("switch-buffer", [Ctrl('x'), Char('b')])
+        .bind("kill-buffer", [Ctrl('x'), Char('k')])
+        .bind("revert-all-buffers", [Ctrl('x'), Char('R')])
+        .bind("detect-external-changes", [Ctrl('x'), Ctrl('r')])
+        // Window management
+        .bind("focus-next-window", [Ctrl('x'), Char('o')])
+        .bind("focus-next-window", [Ctrl('x'), Ctrl('o')])
+        .bind("focus-previous-window", [Ctrl('x'), Char('i')])
+        .bind("focus-previous-window", [Ctrl('x'), Ctrl('i')])
+        .bind("fullscreen-window", [Ctrl('x'), Char('1')])
+        .bind("fullscreen-window", [Ctrl('x'), Ctrl('1')])
+        .bind("split-window-below", [Ctrl('x'), Char('2')])
+        .bind("split-window-below", [Ctrl('x'), Ctrl('2')])
+        .bind("split-window-right", [Ctrl('x'), Char('3')])
+        .bind("split-window-right", [Ctrl('x'), Ctrl('3')])
+        .bind("clone-view-other-window", [Ctrl('x'), Char('4'), Char('c')])
+        .bind("delete-window", [Ctrl('x'), Char('0')])
+        .bind("delete-window", [Ctrl('x'), Ctrl('0')])
+        // Theme
+        .bind("change-theme", [Ctrl('x'), Ctrl('t')])
+        // Configuration
+        .bind("reload-config", [Ctrl('c'), Char('r'), Char('c')])
+        // Quit
+        .bind("quit", [Ctrl('x'), Ctrl('c')]);
+    keymap
+}
 
-    // Split window right (row)
-    bindings
-        .command("split-window-right", || {
-            Message::SplitWindow(FlexDirection::Row)
-        })
-        .with([Key::Ctrl('x'), Key::Char('3')])
-        .with([Key::Ctrl('x'), Key::Ctrl('3')]);
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
 
-    // Delete window
-    bindings
-        .command("delete-window", || Message::DeleteWindow)
-        .with([Key::Ctrl('x'), Key::Char('0')])
-        .with([Key::Ctrl('x'), Key::Ctrl('0')]);
+    use super::*;
 
-    // Theme
-    bindings.add("change-theme", [Key::Ctrl('x'), Key::Ctrl('t')], || {
-        Message::ChangeTheme
-    });
+    #[test]
+    fn commands_are_registered_once_and_bound_by_name() {
+        let editor_commands = commands();
+        let names: HashSet<_> = editor_commands.iter().map(|command| command.name).collect();
+        assert_eq!(editor_commands.len(), names.len());
+        assert!(keymap().iter().all(|(name, _)| names.contains(name)));
+        assert!(names.contains("quit"));
 
-    // Quit
-    bindings.add("quit", [Key::Ctrl('x'), Key::Ctrl('c')], || Message::Quit);
+        assert_eq!(
+            "Open a file in the current directory",
+            commands::find(&editor_commands, "find-file")
+                .unwrap()
+                .description
+        );
+        assert!(commands::find(&editor_commands, "no-such-command").is_none());
+        assert_eq!(
+            Confirm::WhenModified,
+            commands::find(&editor_commands, "kill-buffer")
+                .unwrap()
+                .confirm
+        );
+    }
 }
@@ -1,24 +1,52 @@
+use chrono::{DateTime, FixedOffset, Local};
 use git2::Repository;
+use regex::Regex;
 use ropey::Rope;
+use serde_derive::{Deserialize, Serialize};
 use std::{
-    fmt::Display,
-    fs::File,
-    io::{self, BufWriter},
+    cmp,
+    collections::HashSet,
+    fmt::{self, Display, Write},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read},
+    ops::Range,
     path::{Path, PathBuf},
     rc::Rc,
+    time::{Duration, Instant, SystemTime},
 };
+use tree_sitter::Node;
 use zi::ComponentLink;
 
 use zee_edit::{
-    graphemes::strip_trailing_whitespace, movement, tree::EditTree, Cursor, Direction, OpaqueDiff,
+    apply_edits,
+    edit_points::EditPoints,
+    expression,
+    graphemes::{ensure_final_newline, strip_trailing_whitespace},
+    indent::{self, Indentation},
+    movement::{self, WordCharacters},
+    multi_cursor::{selected_lines, MultiCursor},
+    number,
+    rectangle::{Rectangle, RectangleMove},
+    replace_text,
+    search::{self, CaseMode, RegexMatch},
+    snippet::{Snippet, SnippetSession},
+    tree::EditTree,
+    whitespace, CharIndex, Cursor, Direction, LineIndex, OpaqueDiff, RopeExt,
+};
+use zee_grammar::{
+    config::{IndentationConfig, IndentationUnit},
+    Mode,
 };
-use zee_grammar::Mode;
 
-use super::{ContextHandle, Editor};
+use super::{operations::CancelToken, ContextHandle, Editor, Message};
 use crate::{
-    config::PLAIN_TEXT_MODE,
+    clipboard::ContentKind,
+    config::{EditorConfig, PLAIN_TEXT_MODE},
     error::Result,
-    syntax::parse::{ParseTree, ParserPool, ParserStatus},
+    syntax::{
+        parse::{ParseTree, ParserPool, ParserStatus},
+        structure, todo,
+    },
     versioned::{Versioned, WeakHandle},
 };
 
@@ -89,6 +117,19 @@ impl Buffers {
         buffer_id
     }
 
+    /// Adds a buffer for a large file which is read in the background. The
+    /// text is shown as it loads and can't be edited until fully loaded.
+    pub fn load(
+        &mut self,
+        file_path: PathBuf,
+        size_bytes: u64,
+        repo: Option<RepositoryRc>,
+    ) -> BufferId {
+        let buffer_id = self.add(Rope::new(), Some(file_path), repo);
+        self.get_mut(buffer_id).unwrap().spawn_load_file(size_bytes);
+        buffer_id
+    }
+
     pub fn remove(&mut self, id: BufferId) -> Option<Buffer> {
         self.buffers
             .iter()
@@ -152,16 +193,111 @@ pub enum ModifiedStatus {
     Saving,
 }
 
+/// A command the next one of the same cursor continues: consecutive kills
+/// join into one kill ring entry and `yank-pop` replaces the text pasted
+#[derive(Clone, Debug)]
+enum ChainedCommand {
+    Kill,
+    /// The text and cursor before pasting, the text after it and the index
+    /// of the kill ring entry `yank-pop` pastes next
+    Yank {
+        text: Rope,
+        cursor: Cursor,
+        yanked: Rope,
+        next: usize,
+    },
+}
+
+/// Whether the file of a buffer changed on disk since it was last read or
+/// written by the editor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiskStatus {
+    InSync,
+    Changed,
+    Deleted,
+}
+
 pub struct Buffer {
     context: ContextHandle,
     id: BufferId,
     mode: &'static Mode,
+    // How `TAB` indents, the mode's default unless detected from the text
+    indentation: IndentationConfig,
     repo: Option<RepositoryRc>,
     content: Versioned<EditTree>,
     file_path: Option<PathBuf>,
     modified_status: ModifiedStatus,
+    // The modification time of the file when the editor last read or wrote it
+    disk_modified: Option<SystemTime>,
+    disk_status: DiskStatus,
+    autosave: Autosave,
     cursors: Vec<Cursor>,
+    // The cursors whose selection was begun with the mark, which movement
+    // extends rather than ends with a transient selection
+    marked: HashSet<CursorId>,
     parser: Option<ParserPool>,
+    search: Option<SearchState>,
+    // The query and cursor after each step of the search in progress, the
+    // first one being where it started
+    search_history: Vec<(String, Cursor)>,
+    // The snippets being filled in by `snippet_cursor`, innermost last
+    snippets: Vec<SnippetSession>,
+    snippet_cursor: CursorId,
+    // The rectangular selection being extended by a cursor with the arrow keys
+    rectangle: Option<(CursorId, Rectangle)>,
+    // The insertion points on several lines a cursor is typing into
+    multi_cursor: Option<(CursorId, MultiCursor)>,
+    // The regular expression replacement asking about each match in turn
+    query_replace: Option<QueryReplace>,
+    // Where the text was recently edited, and how many times a cursor jumped
+    // back to one of them
+    edit_points: EditPoints,
+    edit_point_jumps: usize,
+    // The line a cursor jumped to the inline tests from, to toggle back to it
+    inline_tests_origin: Option<LineIndex>,
+    // The last command of a cursor, if it was a kill or a yank the next
+    // command can continue
+    chained_command: Option<(CursorId, ChainedCommand)>,
+    // Files above the configured size threshold are opened without syntax
+    // parsing, and optionally read-only, until the user enables them
+    large_file: bool,
+    read_only: bool,
+    loading: Option<Loading>,
+}
+
+/// Saves a buffer to its file once it's been idle for a while after an edit
+#[derive(Clone, Copy, Debug)]
+struct Autosave {
+    enabled: bool,
+    // When the buffer was last edited, if it hasn't been saved since
+    edited_at: Option<Instant>,
+}
+
+impl Autosave {
+    fn edited(&mut self, now: Instant) {
+        self.edited_at = Some(now);
+    }
+
+    fn saved(&mut self) {
+        self.edited_at = None;
+    }
+
+    fn is_pending(&self) -> bool {
+        self.enabled && self.edited_at.is_some()
+    }
+
+    fn is_due(&self, now: Instant, idle: Duration) -> bool {
+        self.enabled
+            && self
+                .edited_at
+                .map_or(false, |edited_at| now.duration_since(edited_at) >= idle)
+    }
+}
+
+/// A file being read into a buffer in the background
+struct Loading {
+    size_bytes: u64,
+    cancel: CancelToken,
 }
 
 impl Buffer {
@@ -177,34 +313,58 @@ impl Buffer {
             .map(|path| context.0.mode_by_filename(path))
             .unwrap_or(&PLAIN_TEXT_MODE);
 
-        let mut parser = mode
-            .language()
-            .and_then(|result| result.ok())
-            .map(ParserPool::new);
-        if let Some(parser) = parser.as_mut() {
-            let link = context.link.clone();
-            parser.ensure_tree(
-                &context.task_pool,
-                || text.clone(),
-                move |status| {
-                    link.send(
-                        BuffersMessage::new(id, BufferMessage::ParseSyntax { version: 0, status })
-                            .into(),
-                    )
-                },
-            );
+        let threshold = context.config.get().large_file_threshold;
+        let large_file = threshold > 0 && text.len_bytes() as u64 > threshold;
+        let parser = if large_file {
+            None
+        } else {
+            start_parser(&context, id, mode, &text, 0)
+        };
+        let read_only = large_file && context.config.get().large_files_read_only;
+        let disk_modified = file_path.as_deref().and_then(read_modified_time);
+        let autosave = Autosave {
+            enabled: context.config.get().autosave,
+            edited_at: None,
+        };
+        let indentation = detect_indentation(&context, mode, &text);
+        report_whitespace_issues(&context, &text);
+        let content = edit_tree(&context.config.get(), text);
+        // A new file is modified until it's first saved, even if the edits
+        // to it are undone
+        let content = match disk_modified {
+            Some(_) => content,
+            None => content.unsaved(),
         };
 
         Self {
             context,
             id,
             mode,
+            indentation,
             repo,
-            content: Versioned::new(EditTree::new(text)),
+            content: Versioned::new(content),
             file_path,
             modified_status: ModifiedStatus::Unchanged,
+            disk_modified,
+            disk_status: DiskStatus::InSync,
+            autosave,
             cursors: vec![Cursor::new()],
+            marked: HashSet::new(),
             parser,
+            search: None,
+            search_history: Vec::new(),
+            snippets: Vec::new(),
+            snippet_cursor: CursorId::default(),
+            rectangle: None,
+            multi_cursor: None,
+            query_replace: None,
+            edit_points: EditPoints::new(),
+            edit_point_jumps: 0,
+            inline_tests_origin: None,
+            chained_command: None,
+            large_file,
+            read_only,
+            loading: None,
         }
     }
 
@@ -243,11 +403,378 @@ impl Buffer {
         &self.cursors[cursor_id.0]
     }
 
+    #[inline]
+    pub fn rectangle(&self, cursor_id: CursorId) -> Option<&Rectangle> {
+        match self.rectangle {
+            Some((id, ref rectangle)) if id == cursor_id => Some(rectangle),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn multi_cursor(&self, cursor_id: CursorId) -> Option<&MultiCursor> {
+        match self.multi_cursor {
+            Some((id, ref multi_cursor)) if id == cursor_id => Some(multi_cursor),
+            _ => None,
+        }
+    }
+
     #[inline]
     pub fn modified_status(&self) -> ModifiedStatus {
         self.modified_status
     }
 
+    #[inline]
+    pub fn disk_status(&self) -> DiskStatus {
+        self.disk_status
+    }
+
+    #[inline]
+    pub fn is_large_file(&self) -> bool {
+        self.large_file
+    }
+
+    #[inline]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The percentage of the file loaded so far, while it's loading
+    pub fn loading_progress(&self) -> Option<usize> {
+        self.loading.as_ref().map(|loading| {
+            (100 * self.content.len_bytes() as u64 / cmp::max(loading.size_bytes, 1)) as usize
+        })
+    }
+
+    /// The number of jumps to recent edits so far, to centre the view on the
+    /// cursor after each one
+    #[inline]
+    pub fn edit_point_jumps(&self) -> usize {
+        self.edit_point_jumps
+    }
+
+    /// The matches of the active search, unless the text changed since
+    pub fn search_status(&self) -> Option<SearchStatus> {
+        self.search
+            .as_ref()
+            .filter(|search| search.version == self.content.version())
+            .map(|search| SearchStatus {
+                current: search
+                    .current
+                    .map(|index| (index, search.matches[index].clone())),
+                num_matches: search.matches.len(),
+                matches: search.matches.clone(),
+            })
+    }
+
+    /// Replaces every occurrence of `query` as a single undoable edit and
+    /// returns the number of replacements
+    pub fn replace_all(&mut self, query: &str, replacement: &str, options: SearchOptions) -> usize {
+        if self.read_only {
+            self.log_read_only();
+            return 0;
+        }
+        let matches = search::find_all(
+            &self.content,
+            query,
+            options.case_mode,
+            options.whole_word_in(self.mode),
+        );
+        let diff = search::replace_all(&mut self.content, &matches, replacement);
+        self.commit_edit(CursorId::default(), diff);
+        matches.len()
+    }
+
+    /// The text a shell command filters, the selection or the whole buffer
+    /// without one, with its range and the version of the text it's from
+    pub fn filter_region(&self, cursor_id: CursorId) -> (Range<CharIndex>, String, usize) {
+        let cursor = &self.cursors[cursor_id.0];
+        let range = if cursor.has_selection() {
+            cursor.selection()
+        } else {
+            0..self.content.len_chars()
+        };
+        (
+            range.clone(),
+            self.content.slice(range).to_string(),
+            self.content.version(),
+        )
+    }
+
+    /// Replaces a region from [`filter_region`](Self::filter_region) with the
+    /// output of the command it was filtered through, as a single edit.
+    /// Nothing is replaced if the text was edited since `version`. Returns
+    /// whether it was replaced.
+    pub fn replace_filtered_region(
+        &mut self,
+        cursor_id: CursorId,
+        range: Range<CharIndex>,
+        version: usize,
+        replacement: &str,
+    ) -> bool {
+        if self.read_only {
+            self.log_read_only();
+            return false;
+        }
+        if self.content.version() != version || cursor_id.0 >= self.cursors.len() {
+            return false;
+        }
+        let diff = self.cursors[cursor_id.0].replace_range(&mut self.content, range, replacement);
+        self.commit_edit(cursor_id, diff);
+        true
+    }
+
+    /// Starts replacing the matches of `regex` after the cursor, asking about
+    /// each one. `$1` or `${name}` in `template` is replaced by the capture
+    /// group. Returns the first match to ask about, if there is one.
+    pub fn begin_query_replace(
+        &mut self,
+        cursor_id: CursorId,
+        regex: Regex,
+        template: String,
+    ) -> Option<PendingReplacement> {
+        if self.read_only {
+            self.log_read_only();
+            return None;
+        }
+        self.query_replace = Some(QueryReplace {
+            cursor_id,
+            regex,
+            template,
+            resume: self.cursors[cursor_id.0].range().start,
+            current: None,
+            num_replaced: 0,
+        });
+        self.next_query_replace()
+    }
+
+    /// Replaces or skips the match asked about. Each replacement is undone on
+    /// its own, replacing all the remaining matches is a single edit. Returns
+    /// the next match to ask about, or `None` once the replacement is done.
+    pub fn answer_query_replace(
+        &mut self,
+        answer: QueryReplaceAnswer,
+    ) -> Option<PendingReplacement> {
+        let query_replace = self.query_replace.as_mut()?;
+        let current = query_replace.current.take()?;
+        let cursor_id = query_replace.cursor_id;
+        let edits: Vec<_> = match answer {
+            QueryReplaceAnswer::Replace => {
+                let replacement = current.expand(&query_replace.regex, &query_replace.template);
+                // Move past an empty match as when skipping it, or it would
+                // be found again after the replacement
+                query_replace.resume = current.range.start
+                    + replacement.chars().count()
+                    + usize::from(current.range.is_empty());
+                vec![(current.range, replacement)]
+            }
+            QueryReplaceAnswer::Skip => {
+                // Move past an empty match, or it would be found again
+                query_replace.resume = if current.range.is_empty() {
+                    current.range.end + 1
+                } else {
+                    current.range.end
+                };
+                Vec::new()
+            }
+            QueryReplaceAnswer::ReplaceAll => {
+                search::find_regex(&self.content, &query_replace.regex, current.range.start)
+                    .map(|found| {
+                        let replacement =
+                            found.expand(&query_replace.regex, &query_replace.template);
+                        (found.range, replacement)
+                    })
+                    .collect()
+            }
+            QueryReplaceAnswer::Quit => Vec::new(),
+        };
+        query_replace.num_replaced += edits.len();
+
+        let diff = apply_edits(&mut self.content, &edits).expect("regex matches don't overlap");
+        self.commit_edit(cursor_id, diff);
+        match answer {
+            QueryReplaceAnswer::Replace | QueryReplaceAnswer::Skip => self.next_query_replace(),
+            QueryReplaceAnswer::ReplaceAll | QueryReplaceAnswer::Quit => {
+                self.end_query_replace();
+                None
+            }
+        }
+    }
+
+    /// Stops the regular expression replacement, logging how many matches
+    /// were replaced
+    pub fn end_query_replace(&mut self) {
+        if let Some(query_replace) = self.query_replace.take() {
+            self.cursors[query_replace.cursor_id.0].clear_selection();
+            self.context.log(format!(
+                "Replaced {} occurrence{}",
+                query_replace.num_replaced,
+                if query_replace.num_replaced == 1 {
+                    ""
+                } else {
+                    "s"
+                },
+            ));
+        }
+    }
+
+    /// Selects the next match of the regular expression replacement, ending
+    /// it if there are no more
+    fn next_query_replace(&mut self) -> Option<PendingReplacement> {
+        let query_replace = self.query_replace.as_mut()?;
+        let next = if query_replace.resume <= self.content.len_chars() {
+            search::find_regex(&self.content, &query_replace.regex, query_replace.resume).next()
+        } else {
+            None
+        };
+        let next = match next {
+            Some(next) => next,
+            None => {
+                self.end_query_replace();
+                return None;
+            }
+        };
+        let pending = PendingReplacement {
+            matched: self.content.slice(next.range.clone()).to_string(),
+            replacement: next.expand(&query_replace.regex, &query_replace.template),
+        };
+        self.cursors[query_replace.cursor_id.0].select(&self.content, next.range.clone());
+        query_replace.current = Some(next);
+        Some(pending)
+    }
+
+    /// Records an edit made outside of a cursor command as a revision, moving
+    /// the cursors to keep them in place
+    fn commit_edit(&mut self, cursor_id: CursorId, diff: OpaqueDiff) {
+        if diff.is_empty() {
+            return;
+        }
+        self.mark_changed();
+        for cursor in self.cursors.iter_mut() {
+            cursor.reconcile(&self.content, &diff);
+        }
+        self.edit_points.reconcile(&diff);
+        self.content
+            .create_revision(diff.clone(), self.cursors[cursor_id.0].clone());
+        self.update_parse_tree(&diff, false);
+    }
+
+    /// Points the buffer at a new path after its file was renamed. The mode
+    /// and repository are picked again for the new path.
+    pub fn set_file_path(&mut self, file_path: PathBuf, repo: Option<RepositoryRc>) {
+        let mode = self.context.0.mode_by_filename(&file_path);
+        if !std::ptr::eq(mode, self.mode) {
+            self.mode = mode;
+            self.parser = if self.large_file {
+                None
+            } else {
+                start_parser(
+                    &self.context,
+                    self.id,
+                    self.mode,
+                    self.content.staged(),
+                    self.content.version(),
+                )
+            };
+        }
+        self.disk_modified = read_modified_time(&file_path);
+        self.disk_status = DiskStatus::InSync;
+        self.file_path = Some(file_path);
+        self.repo = repo;
+    }
+
+    /// Checks whether the file changed on disk since the editor last read or
+    /// wrote it
+    pub fn check_disk(&mut self) -> DiskStatus {
+        let file_path = match self.file_path {
+            Some(ref file_path) if self.loading.is_none() => file_path,
+            _ => return self.disk_status,
+        };
+        self.disk_status = match fs::metadata(file_path) {
+            // A new file which hasn't been saved yet isn't on disk either
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                if self.disk_modified.is_some() {
+                    DiskStatus::Deleted
+                } else {
+                    DiskStatus::InSync
+                }
+            }
+            Err(_) => self.disk_status,
+            Ok(metadata) if metadata.modified().ok() != self.disk_modified => DiskStatus::Changed,
+            Ok(_) => DiskStatus::InSync,
+        };
+        self.disk_status
+    }
+
+    /// Whether the buffer was edited and will be autosaved once it's idle.
+    /// Buffers without a file and read-only ones never are.
+    pub fn autosave_pending(&self) -> bool {
+        self.file_path.is_some()
+            && !self.read_only
+            && self.loading.is_none()
+            && self.modified_status == ModifiedStatus::Changed
+            && self.autosave.is_pending()
+    }
+
+    /// Whether the buffer has been idle long enough since an edit to be
+    /// autosaved
+    pub fn autosave_due(&self, now: Instant) -> bool {
+        self.autosave_pending()
+            && self.autosave.is_due(
+                now,
+                Duration::from_secs(self.context.config.get().autosave_idle_seconds),
+            )
+    }
+
+    /// Saves the buffer to its file unless another program changed the file
+    /// since the editor last read or wrote it. The editor's own write isn't
+    /// reported as a change, saving records the file's new modification time.
+    pub fn autosave(&mut self) {
+        self.autosave.saved();
+        match self.check_disk() {
+            DiskStatus::InSync => self.spawn_save_file(),
+            DiskStatus::Changed | DiskStatus::Deleted => self.context.log(format!(
+                "Not autosaving `{}`, the file changed on disk",
+                self.file_path
+                    .as_deref()
+                    .map_or_else(Default::default, |path| path.display().to_string())
+            )),
+        }
+    }
+
+    /// Replaces the text with the contents of the file on disk, discarding
+    /// any changes. The reload is a single revision which can be undone.
+    pub fn revert(&mut self) -> Result<()> {
+        let file_path = match self.file_path {
+            Some(ref file_path) => file_path,
+            None => return Ok(()),
+        };
+        if self.loading.is_some() || self.large_file {
+            anyhow::bail!("large files can't be reverted");
+        }
+        let disk_modified = read_modified_time(file_path);
+        let new_text = Rope::from_reader(BufReader::new(File::open(file_path)?))?;
+
+        let old_text = self.content.staged().clone();
+        let diff = replace_text(&mut self.content, &new_text);
+        for cursor in self.cursors.iter_mut() {
+            cursor.sync(&old_text, &self.content);
+        }
+        if !diff.is_empty() {
+            self.content
+                .create_revision(diff.clone(), self.cursors[0].clone());
+        }
+        // Before parsing, which is for the latest version of the content
+        self.content.mark_saved();
+        if !diff.is_empty() {
+            self.update_parse_tree(&diff, false);
+        }
+        self.modified_status = ModifiedStatus::Unchanged;
+        self.disk_modified = disk_modified;
+        self.disk_status = DiskStatus::InSync;
+        Ok(())
+    }
+
     #[inline]
     pub fn new_cursor(&mut self) -> CursorId {
         let new_cursor_id = CursorId(self.cursors.len());
@@ -268,16 +795,48 @@ impl Buffer {
         self.parser.as_ref().and_then(|parser| parser.tree.as_ref())
     }
 
+    /// The line to move a cursor to, to toggle between the code and its
+    /// inline tests, the first syntax node of a kind with a name, like a Rust
+    /// `mod tests`. From the tests, it's the line the cursor jumped from, or
+    /// the start of the text. `None` if there's no such node, or the text
+    /// changed since it was last parsed.
+    pub fn toggle_inline_tests(
+        &mut self,
+        cursor_id: CursorId,
+        kind: &str,
+        name: &str,
+    ) -> Option<LineIndex> {
+        let parse_tree = self
+            .parse_tree()
+            .filter(|parse_tree| parse_tree.version == self.content.version())?;
+        let tests = structure::char_range(
+            &self.content,
+            structure::find_named_node(parse_tree, &self.content, kind, name)?,
+        );
+        let char_index = self.cursors[cursor_id.0].range().start;
+        if tests.contains(&char_index) {
+            Some(self.inline_tests_origin.take().unwrap_or(0))
+        } else {
+            self.inline_tests_origin = Some(self.content.char_to_line(char_index));
+            Some(self.content.char_to_line(tests.start))
+        }
+    }
+
     #[inline]
     pub fn handle_message(&mut self, message: BufferMessage) {
         match message {
             // Start writing the buffer to disk asynchronously
+            BufferMessage::SaveBufferStart if self.loading.is_some() => {
+                self.context.log("Cannot save a file that's still loading");
+            }
             BufferMessage::SaveBufferStart => {
                 self.spawn_save_file();
             }
             // Saved the buffer successfully
             BufferMessage::SaveBufferEnd(Ok(new_content)) => {
                 self.modified_status = ModifiedStatus::Unchanged;
+                self.disk_modified = self.file_path.as_deref().and_then(read_modified_time);
+                self.disk_status = DiskStatus::InSync;
 
                 // For now, we just assume the content may have changed
                 //
@@ -290,6 +849,7 @@ impl Buffer {
                 self.content
                     .create_revision(OpaqueDiff::empty(), self.cursors[0].clone());
                 *self.content.staged_mut() = new_content;
+                self.content.mark_saved();
 
                 // We don't know the diff, so we just use OpaqueDiff::Empty.
                 // This is ok as we pass in fresh=true, so the previous parser
@@ -298,7 +858,9 @@ impl Buffer {
             }
             // Failed to save the buffer
             BufferMessage::SaveBufferEnd(Err(error)) => {
-                self.context.log(error.to_string());
+                self.modified_status = ModifiedStatus::Changed;
+                self.context
+                    .log(format!("Could not save the file ({})", error));
             }
             // The syntax parser finished parsing the code (tree-sitter)
             BufferMessage::ParseSyntax { version, status } => {
@@ -312,11 +874,186 @@ impl Buffer {
             }
             BufferMessage::PreviousChildRevision => self.content.previous_child(),
             BufferMessage::NextChildRevision => self.content.next_child(),
+            BufferMessage::CheckoutRevision(_) if self.read_only => self.log_read_only(),
+            BufferMessage::CheckoutRevision(index) => self.checkout_revision(index),
+            BufferMessage::EnableAllFeatures => self.enable_all_features(),
+            BufferMessage::ToggleAutosave => {
+                self.autosave.enabled = !self.autosave.enabled;
+                self.autosave.saved();
+                if self.autosave.enabled && self.modified_status == ModifiedStatus::Changed {
+                    self.autosave.edited(Instant::now());
+                }
+                self.context
+                    .log(match (self.autosave.enabled, &self.file_path) {
+                        (true, Some(_)) => "Autosave enabled",
+                        (true, None) => "Autosave enabled, once the buffer is saved to a file",
+                        (false, _) => "Autosave disabled",
+                    });
+            }
+            BufferMessage::LoadChunk(LoadedChunk(chunk)) => {
+                // Each chunk becomes part of the original text, there's
+                // nothing to undo while loading
+                let mut text = self.content.staged().clone();
+                text.insert(text.len_chars(), &chunk);
+                *self.content = edit_tree(&self.context.config.get(), text);
+            }
+            BufferMessage::LoadEnd(result) => {
+                self.loading = None;
+                match result {
+                    Ok(()) => {
+                        self.read_only = self.context.config.get().large_files_read_only;
+                        self.indentation =
+                            detect_indentation(&self.context, self.mode, self.content.staged());
+                        report_whitespace_issues(&self.context, self.content.staged());
+                        self.disk_modified = self.file_path.as_deref().and_then(read_modified_time);
+                    }
+                    // Keep the buffer read-only, saving the part loaded so far
+                    // would truncate the file
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => {
+                        self.context
+                            .log("Stopped loading the file, the buffer is read-only");
+                    }
+                    Err(error) => {
+                        self.context
+                            .log(format!("Could not finish loading the file ({})", error));
+                    }
+                }
+            }
+        };
+    }
+
+    /// Turns on syntax parsing and editing for a large file, accepting the
+    /// cost of parsing it
+    fn enable_all_features(&mut self) {
+        if self.loading.is_some() {
+            self.context
+                .log("Wait for the file to finish loading to enable all features");
+            return;
+        }
+        if !self.large_file {
+            self.context.log("All features are already enabled");
+            return;
+        }
+        self.large_file = false;
+        self.read_only = false;
+        self.parser = start_parser(
+            &self.context,
+            self.id,
+            self.mode,
+            self.content.staged(),
+            self.content.version(),
+        );
+        self.context.log("Enabled syntax parsing and editing");
+    }
+
+    /// Marks the buffer as changed after an edit, or unchanged if it was an
+    /// undo back to the text last saved
+    fn mark_changed(&mut self) {
+        if self.content.is_saved() {
+            self.modified_status = ModifiedStatus::Unchanged;
+            self.autosave.saved();
+        } else {
+            self.modified_status = ModifiedStatus::Changed;
+            self.autosave.edited(Instant::now());
+        }
+    }
+
+    fn log_read_only(&self) {
+        if self.loading.is_some() {
+            self.context.log("Buffer is read-only until the file loads");
+        } else {
+            self.context
+                .log("Buffer is read-only, C-x C-q enables editing");
+        }
+    }
+
+    /// Reads the buffer's file in chunks on the task pool, sending each one
+    /// to the buffer as it's read
+    fn spawn_load_file(&mut self, size_bytes: u64) {
+        let file_path = match self.file_path.clone() {
+            Some(file_path) => file_path,
+            None => return,
         };
+
+        let mut operation = self.context.start_operation(format!(
+            "loading {}",
+            Path::new(
+                file_path
+                    .file_name()
+                    .unwrap_or_else(|| file_path.as_os_str())
+            )
+            .display()
+        ));
+        let cancel = operation.cancel_token();
+        self.large_file = true;
+        self.read_only = true;
+        self.parser = None;
+        self.loading = Some(Loading {
+            size_bytes,
+            cancel: cancel.clone(),
+        });
+
+        let buffer_id = self.id;
+        let link = self.context.link.clone();
+        self.context.task_pool.spawn(move |_| {
+            let send = |message| link.send(BuffersMessage::new(buffer_id, message).into());
+            let mut loaded_bytes = 0;
+            let result = read_chunks(&file_path, &cancel, |chunk| {
+                loaded_bytes += chunk.len() as u64;
+                operation.progress((100 * loaded_bytes / cmp::max(size_bytes, 1)) as usize);
+                send(BufferMessage::LoadChunk(LoadedChunk(chunk)))
+            })
+            .and_then(|()| match cancel.is_cancelled() {
+                true => Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled")),
+                false => Ok(()),
+            });
+            send(BufferMessage::LoadEnd(result));
+        });
     }
 
     #[inline]
     fn handle_cursor_message(&mut self, cursor_id: CursorId, message: CursorMessage) {
+        if self.read_only && message.is_edit() {
+            self.log_read_only();
+            return;
+        }
+
+        // The arrow keys extend a rectangular selection and typing or deleting
+        // edits each of its lines, other commands end it
+        if self.rectangle(cursor_id).is_some() {
+            if let Some((movement, count)) = message.rectangle_move() {
+                self.extend_rectangle(cursor_id, movement, count);
+                return;
+            }
+            if !message.keeps_rectangle() {
+                self.rectangle = None;
+            }
+        }
+        // Moving along a line or by words moves every insertion point, so
+        // that the edits which follow still apply at each of them
+        if self.multi_cursor(cursor_id).is_some() {
+            if message.moves_multi_cursor() {
+                self.move_multi_cursor(cursor_id, &message);
+                return;
+            }
+            if !message.keeps_multi_cursor() {
+                self.multi_cursor = None;
+            }
+        }
+
+        let previous_command = self
+            .chained_command
+            .take()
+            .filter(|&(id, _)| id == cursor_id)
+            .map(|(_, command)| command);
+
+        let selection_before = {
+            let cursor = &self.cursors[cursor_id.0];
+            cursor.has_selection().then(|| cursor.selection())
+        };
+        let selection_effect = message.selection_effect();
+        let pasted = matches!(message, CursorMessage::InsertChar { pasted: true, .. });
+
         {
             let content = &self.content;
             let cursor = &mut self.cursors[cursor_id.0];
@@ -346,16 +1083,117 @@ impl Buffer {
                 CursorMessage::EndOfLine => movement::move_to_end_of_line(content, cursor),
                 CursorMessage::StartOfBuffer => movement::move_to_start_of_buffer(content, cursor),
                 CursorMessage::EndOfBuffer => movement::move_to_end_of_buffer(content, cursor),
-                CursorMessage::MoveWord(direction, count) => {
-                    movement::move_word(content, cursor, direction, count)
+                CursorMessage::MoveToPercent(percent) => {
+                    movement::move_to_percent(content, cursor, percent)
+                }
+                CursorMessage::MoveToLine(line_index) => {
+                    movement::move_to_line(content, cursor, line_index)
+                }
+                CursorMessage::MoveToMatchingBracket
+                    if !movement::move_to_matching_bracket(content, cursor) =>
+                {
+                    self.context.log("No matching bracket");
+                }
+                CursorMessage::MoveToMatchingBracket => {}
+                CursorMessage::MoveWord(direction, count) => move_by_words(
+                    &self.context.config.get(),
+                    self.mode,
+                    content,
+                    cursor,
+                    direction,
+                    count,
+                ),
+                CursorMessage::MoveParagraph(direction, count) => movement::move_paragraph(
+                    content,
+                    cursor,
+                    self.mode.indentation.tab_width(),
+                    direction,
+                    count,
+                ),
+                CursorMessage::MovePageBreak(direction, count) => {
+                    movement::move_page_break(content, cursor, direction, count)
+                }
+                CursorMessage::MoveTodo(direction, count) => {
+                    let parse_tree = self.parser.as_ref().and_then(|parser| parser.tree.as_ref());
+                    let todos = todo::find_todos(
+                        &self.context.config.get(),
+                        content,
+                        0..content.len_chars(),
+                        parse_tree,
+                    );
+                    if !movement::move_to_match(content, cursor, &todos, direction, count) {
+                        self.context.log(match direction {
+                            Direction::Forward => "No more TODOs after the cursor",
+                            Direction::Backward => "No more TODOs before the cursor",
+                        });
+                    }
+                }
+                CursorMessage::MoveToLastEdit => {
+                    match self.edit_points.previous(cursor.range().start) {
+                        Some(char_index) => {
+                            *cursor = Cursor::with_range(
+                                char_index..content.next_grapheme_boundary(char_index),
+                            );
+                            self.edit_point_jumps += 1;
+                        }
+                        None => self.context.log("No edits to go back to"),
+                    }
+                }
+                CursorMessage::SelectSyntaxSibling(direction) => {
+                    let parse_tree = self
+                        .parser
+                        .as_ref()
+                        .and_then(|parser| parser.tree.as_ref())
+                        .filter(|parse_tree| parse_tree.version == content.version());
+                    match parse_tree {
+                        None => self.context.log("Syntax tree not ready"),
+                        Some(parse_tree)
+                            if !select_syntax_node(parse_tree, content, cursor, |node| {
+                                structure::sibling(node, direction)
+                            }) =>
+                        {
+                            self.context.log(match direction {
+                                Direction::Forward => "No syntax node after the selection",
+                                Direction::Backward => "No syntax node before the selection",
+                            });
+                        }
+                        Some(_) => {}
+                    }
                 }
-                CursorMessage::MoveParagraph(direction, count) => {
-                    movement::move_paragraph(content, cursor, direction, count)
+                CursorMessage::SelectSyntaxParent => {
+                    let parse_tree = self
+                        .parser
+                        .as_ref()
+                        .and_then(|parser| parser.tree.as_ref())
+                        .filter(|parse_tree| parse_tree.version == content.version());
+                    match parse_tree {
+                        None => self.context.log("Syntax tree not ready"),
+                        Some(parse_tree)
+                            if !select_syntax_node(
+                                parse_tree,
+                                content,
+                                cursor,
+                                structure::parent,
+                            ) =>
+                        {
+                            self.context.log("No syntax node around the selection");
+                        }
+                        Some(_) => {}
+                    }
                 }
 
                 CursorMessage::BeginSelection => cursor.begin_selection(),
+                CursorMessage::BeginRectangle => {
+                    cursor.clear_selection();
+                    let tab_width = self.mode.indentation.tab_width();
+                    self.rectangle = Some((
+                        cursor_id,
+                        Rectangle::new(content, cursor.range().start, tab_width),
+                    ));
+                }
                 CursorMessage::ClearSelection => {
                     cursor.clear_selection();
+                    self.snippets.clear();
                 }
                 CursorMessage::SelectAll => cursor.select_all(content),
 
@@ -364,8 +1202,23 @@ impl Buffer {
         }
 
         let mut undoing = false;
+        let mut new_snippet = None;
         let diff = {
             match message {
+                CursorMessage::DeleteForward if self.rectangle(cursor_id).is_some() => {
+                    self.delete_in_rectangle(cursor_id, Direction::Forward)
+                }
+                CursorMessage::DeleteBackward if self.rectangle(cursor_id).is_some() => {
+                    self.delete_in_rectangle(cursor_id, Direction::Backward)
+                }
+                CursorMessage::DeleteForward if self.multi_cursor(cursor_id).is_some() => self
+                    .edit_multi_cursor(cursor_id, |multi_cursor, text| {
+                        multi_cursor.delete_forward(text)
+                    }),
+                CursorMessage::DeleteBackward if self.multi_cursor(cursor_id).is_some() => self
+                    .edit_multi_cursor(cursor_id, |multi_cursor, text| {
+                        multi_cursor.delete_backward(text)
+                    }),
                 CursorMessage::DeleteForward => {
                     let operation = self.cursors[cursor_id.0].delete_forward(&mut self.content);
                     if operation.diff.is_empty() {
@@ -380,6 +1233,21 @@ impl Buffer {
                     }
                     operation.diff
                 }
+                CursorMessage::DeleteWord(direction) => {
+                    let cursor = &mut self.cursors[cursor_id.0];
+                    let mut target = cursor.clone();
+                    move_by_words(
+                        &self.context.config.get(),
+                        self.mode,
+                        &self.content,
+                        &mut target,
+                        direction,
+                        1,
+                    );
+                    let range = cmp::min(cursor.range().start, target.range().start)
+                        ..cmp::max(cursor.range().start, target.range().start);
+                    cursor.delete_range(&mut self.content, range).diff
+                }
                 CursorMessage::DeleteLine => {
                     let diff = self.delete_line(cursor_id);
                     if diff.is_empty() {
@@ -387,14 +1255,49 @@ impl Buffer {
                     }
                     diff
                 }
+                CursorMessage::JoinLines => self.cursors[cursor_id.0].join_lines(&mut self.content),
+                CursorMessage::Duplicate => self.cursors[cursor_id.0].duplicate(&mut self.content),
+                CursorMessage::KillLine => {
+                    let diff = self.kill_line(cursor_id, previous_command);
+                    if diff.is_empty() {
+                        self.context.log("End of buffer");
+                    }
+                    diff
+                }
                 CursorMessage::Yank => self.paste_from_clipboard(cursor_id),
+                CursorMessage::YankPop => self.yank_pop(cursor_id, previous_command),
+                CursorMessage::YankPrimary => self.paste_from_primary_selection(cursor_id),
+                CursorMessage::Search(query) => {
+                    self.search(cursor_id, query);
+                    OpaqueDiff::empty()
+                }
+                CursorMessage::CancelSearch => {
+                    self.cancel_search(cursor_id);
+                    OpaqueDiff::empty()
+                }
+                CursorMessage::CopyFilePath(format) => {
+                    self.copy_file_path(cursor_id, format);
+                    OpaqueDiff::empty()
+                }
+                CursorMessage::CopySelection if self.rectangle(cursor_id).is_some() => {
+                    self.copy_rectangle_to_clipboard(cursor_id, false)
+                }
+                CursorMessage::CutSelection if self.rectangle(cursor_id).is_some() => {
+                    self.copy_rectangle_to_clipboard(cursor_id, true)
+                }
                 CursorMessage::CopySelection => self.copy_selection_to_clipboard(cursor_id),
                 CursorMessage::CutSelection => self.cut_selection_to_clipboard(cursor_id),
+                CursorMessage::InsertTab if self.in_snippet(cursor_id) => {
+                    let session = self.snippets.last_mut().unwrap();
+                    session.next_field(&mut self.cursors[cursor_id.0], &self.content);
+                    if session.is_last_field() {
+                        self.snippets.pop();
+                    }
+                    OpaqueDiff::empty()
+                }
                 CursorMessage::InsertTab => {
-                    let (indentation_unit, indentation_count) = (
-                        self.mode.indentation.to_char(),
-                        self.mode.indentation.char_count(),
-                    );
+                    let (indentation_unit, indentation_count) =
+                        (self.indentation.to_char(), self.indentation.char_count());
                     let diff = self.cursors[cursor_id.0].insert_chars(
                         &mut self.content,
                         std::iter::repeat(indentation_unit).take(indentation_count),
@@ -420,11 +1323,52 @@ impl Buffer {
                     movement::move_to_start_of_line(&self.content, cursor);
                     diff
                 }
+                CursorMessage::InsertChar { character, .. }
+                    if self.rectangle(cursor_id).is_some() =>
+                {
+                    self.insert_in_rectangle(cursor_id, character)
+                }
+                CursorMessage::InsertChar { character, .. }
+                    if self.multi_cursor(cursor_id).is_some() =>
+                {
+                    self.insert_in_multi_cursor(cursor_id, character)
+                }
+                CursorMessage::InsertAtLineEnds => {
+                    self.begin_multi_cursor(cursor_id, MultiCursor::at_line_ends);
+                    OpaqueDiff::empty()
+                }
+                CursorMessage::InsertAtLineStarts => {
+                    self.begin_multi_cursor(cursor_id, MultiCursor::at_line_starts);
+                    OpaqueDiff::empty()
+                }
+                CursorMessage::AddCursor(direction) => {
+                    self.add_cursor_on_adjacent_line(cursor_id, direction);
+                    OpaqueDiff::empty()
+                }
+                CursorMessage::AddCursorAtNextMatch => {
+                    self.add_cursor_at_next_match(cursor_id);
+                    OpaqueDiff::empty()
+                }
                 CursorMessage::InsertChar {
                     character,
                     move_forward,
+                    ..
                 } => {
-                    let diff = self.cursors[cursor_id.0].insert_char(&mut self.content, character);
+                    // Typing into a field that's still selected replaces its text
+                    let cursor = &mut self.cursors[cursor_id.0];
+                    let field_diff = match self.snippets.last() {
+                        Some(session)
+                            if self.snippet_cursor == cursor_id
+                                && cursor.has_selection()
+                                && cursor.selection() == session.current_field() =>
+                        {
+                            cursor
+                                .delete_range(&mut self.content, session.current_field())
+                                .diff
+                        }
+                        _ => OpaqueDiff::empty(),
+                    };
+                    let diff = field_diff.merge(&cursor.insert_char(&mut self.content, character));
                     if move_forward {
                         movement::move_horizontally(
                             &self.content,
@@ -435,32 +1379,546 @@ impl Buffer {
                     }
                     diff
                 }
+                CursorMessage::InsertRepeated { count: 0, .. } => OpaqueDiff::empty(),
+                CursorMessage::InsertRepeated { character, count } => {
+                    let diff = self.cursors[cursor_id.0]
+                        .insert_chars(&mut self.content, std::iter::repeat(character).take(count));
+                    movement::move_horizontally(
+                        &self.content,
+                        &mut self.cursors[cursor_id.0],
+                        Direction::Forward,
+                        count,
+                    );
+                    diff
+                }
+                CursorMessage::Undo if self.cursors[cursor_id.0].has_selection() => {
+                    self.undo_in_region(cursor_id)
+                }
                 CursorMessage::Undo => {
                     undoing = true;
+                    self.snippets.clear();
                     self.undo(cursor_id)
                 }
                 CursorMessage::Redo => {
                     undoing = true;
+                    self.snippets.clear();
                     self.redo(cursor_id)
                 }
-
-                _ => OpaqueDiff::empty(),
-            }
-        };
-
-        if !diff.is_empty() {
-            self.modified_status = ModifiedStatus::Changed;
-            for (id, cursor) in self.cursors.iter_mut().enumerate() {
-                if id != cursor_id.0 {
-                    cursor.reconcile(&self.content, &diff);
+                CursorMessage::InsertSnippet(ref snippet) => {
+                    let (diff, session) =
+                        self.cursors[cursor_id.0].insert_snippet(&mut self.content, snippet);
+                    new_snippet = session;
+                    diff
                 }
+                CursorMessage::InsertDate => self.insert_date(cursor_id),
+                CursorMessage::EvaluateExpression => self.evaluate_expression(cursor_id),
+                CursorMessage::ChangeCase(change) => {
+                    let word_characters = WordCharacters::new(&self.mode.word_characters);
+                    let cursor = &mut self.cursors[cursor_id.0];
+                    match change {
+                        CaseChange::Upcase => {
+                            cursor.upcase_selection(&mut self.content, word_characters)
+                        }
+                        CaseChange::Downcase => {
+                            cursor.downcase_selection(&mut self.content, word_characters)
+                        }
+                        CaseChange::Capitalize => {
+                            cursor.capitalize_selection(&mut self.content, word_characters)
+                        }
+                    }
+                }
+                CursorMessage::IndentLines => {
+                    let indentation: String = std::iter::repeat(self.indentation.to_char())
+                        .take(self.indentation.char_count())
+                        .collect();
+                    self.cursors[cursor_id.0].indent(&mut self.content, &indentation)
+                }
+                CursorMessage::UnindentLines => {
+                    let tab_width = self.indentation.tab_width();
+                    self.cursors[cursor_id.0].unindent(&mut self.content, tab_width)
+                }
+                CursorMessage::ToggleComment => match self.mode.comment {
+                    Some(ref comment) => self.cursors[cursor_id.0]
+                        .toggle_comment_lines(&mut self.content, &comment.token),
+                    None => {
+                        self.context
+                            .log(format!("No comment syntax for {}", self.mode.name));
+                        OpaqueDiff::empty()
+                    }
+                },
+                CursorMessage::ConvertIndentation(unit) => {
+                    let tab_width = self.mode.indentation.tab_width();
+                    let unit = unit.unwrap_or_else(|| self.indentation.unit.clone());
+                    let cursor = &mut self.cursors[cursor_id.0];
+                    match unit {
+                        IndentationUnit::Tab => cursor.tabify(&mut self.content, tab_width),
+                        IndentationUnit::Space => cursor.untabify(&mut self.content, tab_width),
+                    }
+                }
+                CursorMessage::DragSyntaxNode(direction) => {
+                    self.drag_syntax_node(cursor_id, direction)
+                }
+                CursorMessage::TransposeChars => {
+                    let diff = self.cursors[cursor_id.0].transpose_chars(&mut self.content);
+                    if diff.is_empty() {
+                        self.context.log("Beginning of buffer");
+                    }
+                    diff
+                }
+                CursorMessage::IncrementNumber(step) => self.increment_number(cursor_id, step),
+                CursorMessage::SumNumbers { insert } => self.sum_numbers(cursor_id, insert),
+
+                _ => OpaqueDiff::empty(),
+            }
+        };
+
+        let diff = if diff.is_empty() || undoing {
+            diff
+        } else {
+            self.reconcile_snippets(cursor_id, diff)
+        };
+        if let Some(session) = new_snippet {
+            if self.snippet_cursor != cursor_id {
+                self.snippets.clear();
+                self.snippet_cursor = cursor_id;
+            }
+            self.snippets.push(session);
+        }
+        self.update_selection(cursor_id, selection_effect, !diff.is_empty());
+
+        if !diff.is_empty() {
+            self.mark_changed();
+            for (id, cursor) in self.cursors.iter_mut().enumerate() {
+                if id != cursor_id.0 {
+                    cursor.reconcile(&self.content, &diff);
+                }
+            }
+            if let Some((id, ref mut multi_cursor)) = self.multi_cursor {
+                if id != cursor_id {
+                    multi_cursor.reconcile(&self.content, &diff);
+                }
+            }
+            if !undoing {
+                let cursor = self.cursors[cursor_id.0].clone();
+                if pasted {
+                    self.content.amend_revision(diff.clone(), cursor);
+                } else {
+                    self.content.create_revision(diff.clone(), cursor);
+                }
+                self.update_parse_tree(&diff, false);
+                self.edit_points.reconcile(&diff);
+                self.edit_points
+                    .record(&self.content, self.cursors[cursor_id.0].range().start);
+            }
+        } else if let Some(selection) = selection_before {
+            // Set the primary selection once a selection is done with, rather
+            // than on every movement that extends it
+            if !self.cursors[cursor_id.0].has_selection() {
+                self.set_primary_selection(self.content.slice(selection).into());
+            }
+        }
+    }
+
+    /// Ends the selection of a cursor after a command, if it's transient and
+    /// the command doesn't keep it
+    fn update_selection(&mut self, cursor_id: CursorId, effect: SelectionEffect, changed: bool) {
+        match effect {
+            SelectionEffect::Mark => {
+                self.marked.insert(cursor_id);
+            }
+            SelectionEffect::Select => {
+                self.marked.remove(&cursor_id);
+            }
+            _ => {}
+        }
+        let cursor = &mut self.cursors[cursor_id.0];
+        if self.context.config.get().selection_mode == SelectionMode::Transient
+            && !keeps_transient_selection(effect, self.marked.contains(&cursor_id), changed)
+        {
+            cursor.clear_selection();
+        }
+        if !cursor.has_selection() {
+            self.marked.remove(&cursor_id);
+        }
+    }
+
+    /// Moves the corner of the rectangular selection and the cursor with it.
+    /// The selection ends if it collapses back to a single line.
+    fn extend_rectangle(&mut self, cursor_id: CursorId, movement: RectangleMove, count: usize) {
+        let rectangle = match self.rectangle {
+            Some((_, ref mut rectangle)) => rectangle,
+            None => return,
+        };
+        let active = (0..count).all(|_| rectangle.extend(&self.content, movement));
+        let corner = rectangle.corner_char_index(&self.content);
+        self.cursors[cursor_id.0] =
+            Cursor::with_range(corner..self.content.next_grapheme_boundary(corner));
+        if !active {
+            self.rectangle = None;
+        }
+    }
+
+    /// Deletes the selected columns of the rectangular selection, or the
+    /// column before or after it if none are selected
+    fn delete_in_rectangle(&mut self, cursor_id: CursorId, direction: Direction) -> OpaqueDiff {
+        let rectangle = match self.rectangle {
+            Some((_, ref mut rectangle)) => rectangle,
+            None => return OpaqueDiff::empty(),
+        };
+        let diff = rectangle.delete(&mut self.content, direction);
+        let corner = rectangle.corner_char_index(&self.content);
+        self.cursors[cursor_id.0] =
+            Cursor::with_range(corner..self.content.next_grapheme_boundary(corner));
+        diff
+    }
+
+    /// Copies the rectangular selection to be pasted as a rectangle, deleting
+    /// it if `cut` is set. Ends the selection.
+    fn copy_rectangle_to_clipboard(&mut self, cursor_id: CursorId, cut: bool) -> OpaqueDiff {
+        let mut rectangle = match self.rectangle.take() {
+            Some((_, rectangle)) => rectangle,
+            None => return OpaqueDiff::empty(),
+        };
+        self.copy_to_clipboard(
+            rectangle.contents(&self.content).join("\n"),
+            ContentKind::Rectangular,
+        );
+        if !cut || rectangle.columns().is_empty() {
+            return OpaqueDiff::empty();
+        }
+        let diff = rectangle.delete(&mut self.content, Direction::Forward);
+        let corner = rectangle.corner_char_index(&self.content);
+        self.cursors[cursor_id.0] =
+            Cursor::with_range(corner..self.content.next_grapheme_boundary(corner));
+        diff
+    }
+
+    /// Types `character` on every line of the rectangular selection
+    fn insert_in_rectangle(&mut self, cursor_id: CursorId, character: char) -> OpaqueDiff {
+        let rectangle = match self.rectangle {
+            Some((_, ref mut rectangle)) => rectangle,
+            None => return OpaqueDiff::empty(),
+        };
+        let diff = rectangle.insert(&mut self.content, character.encode_utf8(&mut [0; 4]));
+        let corner = rectangle.corner_char_index(&self.content);
+        self.cursors[cursor_id.0] =
+            Cursor::with_range(corner..self.content.next_grapheme_boundary(corner));
+        diff
+    }
+
+    /// Places an insertion point on each selected line, or the cursor's line
+    /// without a selection, moving the cursor to the one on its line
+    fn begin_multi_cursor(
+        &mut self,
+        cursor_id: CursorId,
+        place: impl FnOnce(&Rope, Range<LineIndex>, LineIndex) -> MultiCursor,
+    ) {
+        let cursor = &mut self.cursors[cursor_id.0];
+        let lines = selected_lines(&self.content, cursor.selection());
+        let multi_cursor = place(
+            &self.content,
+            lines,
+            self.content.char_to_line(cursor.range().start),
+        );
+        let primary = multi_cursor.primary();
+        *cursor = Cursor::with_range(primary..self.content.next_grapheme_boundary(primary));
+        self.multi_cursor = Some((cursor_id, multi_cursor));
+    }
+
+    /// Adds an insertion point on the line below or above the existing ones,
+    /// starting with one at the cursor, and moves the cursor to it
+    fn add_cursor_on_adjacent_line(&mut self, cursor_id: CursorId, direction: Direction) {
+        let cursor = &mut self.cursors[cursor_id.0];
+        let multi_cursor = match self.multi_cursor {
+            Some((id, ref mut multi_cursor)) if id == cursor_id => multi_cursor,
+            _ => {
+                &mut self
+                    .multi_cursor
+                    .insert((cursor_id, MultiCursor::at(cursor.range().start)))
+                    .1
+            }
+        };
+        if !multi_cursor.add_on_adjacent_line(&self.content, direction) {
+            self.context.log(match direction {
+                Direction::Forward => "No more lines below",
+                Direction::Backward => "No more lines above",
+            });
+            return;
+        }
+        let primary = multi_cursor.primary();
+        *cursor = Cursor::with_range(primary..self.content.next_grapheme_boundary(primary));
+    }
+
+    /// Adds an insertion point at the end of the next occurrence of the
+    /// selection, or of the word under the cursor, and selects it
+    fn add_cursor_at_next_match(&mut self, cursor_id: CursorId) {
+        let cursor = &mut self.cursors[cursor_id.0];
+        let word_characters = WordCharacters::new(&self.mode.word_characters);
+        let (query, whole_word) = if cursor.has_selection() {
+            (cursor.selection(), None)
+        } else {
+            match search::word_at(&self.content, cursor.range().start, word_characters) {
+                Some(word) => (word, Some(word_characters)),
+                None => {
+                    self.context.log("No word under the cursor");
+                    return;
+                }
+            }
+        };
+        if query.is_empty() {
+            return;
+        }
+        let multi_cursor = match self.multi_cursor {
+            Some((id, ref mut multi_cursor)) if id == cursor_id => multi_cursor,
+            _ => {
+                self.multi_cursor = Some((cursor_id, MultiCursor::at_selection(query.clone())));
+                cursor.select(&self.content, query);
+                return;
+            }
+        };
+
+        // The first occurrence after the last insertion point, wrapping
+        // around to the start of the buffer
+        let matches = search::find_all(
+            &self.content,
+            &self.content.slice(query).to_string(),
+            CaseMode::Sensitive,
+            whole_word,
+        );
+        let last = multi_cursor.positions()[multi_cursor.positions().len() - 1];
+        let next = matches
+            .iter()
+            .find(|range| range.end > last)
+            .into_iter()
+            .chain(matches.iter())
+            .find(|range| !multi_cursor.positions().contains(&range.end))
+            .cloned();
+        match next {
+            Some(range) => {
+                multi_cursor.add(range.end);
+                cursor.select(&self.content, range);
+            }
+            None => self.context.log("No more matches"),
+        }
+    }
+
+    /// Moves every insertion point the way `message` moves the cursor, and
+    /// the cursor to the primary one
+    fn move_multi_cursor(&mut self, cursor_id: CursorId, message: &CursorMessage) {
+        let multi_cursor = match self.multi_cursor {
+            Some((_, ref mut multi_cursor)) => multi_cursor,
+            None => return,
+        };
+        let (config, mode) = (self.context.config.get(), self.mode);
+        multi_cursor.move_points(&self.content, |text, cursor| match *message {
+            CursorMessage::Left => {
+                movement::move_horizontally(text, cursor, Direction::Backward, 1)
+            }
+            CursorMessage::Right => {
+                movement::move_horizontally(text, cursor, Direction::Forward, 1)
+            }
+            CursorMessage::StartOfLine => movement::move_to_start_of_line(text, cursor),
+            CursorMessage::EndOfLine => movement::move_to_end_of_line(text, cursor),
+            CursorMessage::MoveWord(direction, count) => {
+                move_by_words(&config, mode, text, cursor, direction, count)
+            }
+            _ => {}
+        });
+        let primary = multi_cursor.primary();
+        self.cursors[cursor_id.0] =
+            Cursor::with_range(primary..self.content.next_grapheme_boundary(primary));
+    }
+
+    /// Types `character` at every insertion point of the multi-line edit
+    fn insert_in_multi_cursor(&mut self, cursor_id: CursorId, character: char) -> OpaqueDiff {
+        self.edit_multi_cursor(cursor_id, |multi_cursor, text| {
+            multi_cursor.insert(text, character.encode_utf8(&mut [0; 4]))
+        })
+    }
+
+    /// Makes an edit at every insertion point, moving the cursor to the
+    /// primary one
+    fn edit_multi_cursor(
+        &mut self,
+        cursor_id: CursorId,
+        edit: impl FnOnce(&mut MultiCursor, &mut Rope) -> OpaqueDiff,
+    ) -> OpaqueDiff {
+        let multi_cursor = match self.multi_cursor {
+            Some((_, ref mut multi_cursor)) => multi_cursor,
+            None => return OpaqueDiff::empty(),
+        };
+        let diff = edit(multi_cursor, &mut self.content);
+        let primary = multi_cursor.primary();
+        self.cursors[cursor_id.0] =
+            Cursor::with_range(primary..self.content.next_grapheme_boundary(primary));
+        diff
+    }
+
+    fn in_snippet(&self, cursor_id: CursorId) -> bool {
+        self.snippet_cursor == cursor_id && !self.snippets.is_empty()
+    }
+
+    /// Updates the fields of the active snippets after an edit and copies the
+    /// current field to its mirrors. Returns the edit merged with the changes
+    /// to the mirrors.
+    fn reconcile_snippets(&mut self, cursor_id: CursorId, diff: OpaqueDiff) -> OpaqueDiff {
+        let (current, outer) = match self.snippets.split_last_mut() {
+            Some(split) => split,
+            None => return diff,
+        };
+        current.reconcile(&diff);
+        for session in outer.iter_mut() {
+            session.reconcile(&diff);
+        }
+
+        let mirrors_diff = current.update_mirrors(&mut self.content);
+        if mirrors_diff.is_empty() {
+            return diff;
+        }
+        for session in outer.iter_mut() {
+            session.reconcile(&mirrors_diff);
+        }
+        self.cursors[cursor_id.0].reconcile(&self.content, &mirrors_diff);
+        diff.merge(&mirrors_diff)
+    }
+
+    fn insert_date(&mut self, cursor_id: CursorId) -> OpaqueDiff {
+        let config = self.context.config.get();
+        let format = match config.date_format.as_str() {
+            "" => DEFAULT_DATE_FORMAT,
+            format => format,
+        };
+        match format_date(&Local::now().into(), format) {
+            Some(date) => {
+                self.cursors[cursor_id.0]
+                    .insert_snippet(&mut self.content, &Snippet::literal(date))
+                    .0
+            }
+            None => {
+                self.context
+                    .log(format!("Invalid date format `{}`", format));
+                OpaqueDiff::empty()
+            }
+        }
+    }
+
+    /// Replaces the selection, or the arithmetic expression at the cursor,
+    /// with its value. Errors are reported and leave the text unchanged.
+    fn evaluate_expression(&mut self, cursor_id: CursorId) -> OpaqueDiff {
+        let cursor = &self.cursors[cursor_id.0];
+        let range = if cursor.has_selection() {
+            Some(cursor.selection())
+        } else {
+            expression::expression_at(&self.content, cursor.range().start)
+        };
+        let range = match range {
+            Some(range) => range,
+            None => {
+                self.context.log("No expression at the cursor");
+                return OpaqueDiff::empty();
+            }
+        };
+
+        let input = self.content.slice(range.clone()).to_string();
+        let value = match expression::evaluate(&input) {
+            Ok(value) => value.to_string(),
+            Err(error) => {
+                self.context
+                    .log(format!("Could not evaluate `{}` ({})", input.trim(), error));
+                return OpaqueDiff::empty();
             }
-            if !undoing {
-                self.content
-                    .create_revision(diff.clone(), self.cursors[cursor_id.0].clone());
-                self.update_parse_tree(&diff, false);
-            }
+        };
+        let end = range.start + value.chars().count();
+        let diff = apply_edits(&mut self.content, &[(range, value)])
+            .expect("a single edit doesn't overlap");
+        self.cursors[cursor_id.0] =
+            Cursor::with_range(end..self.content.next_grapheme_boundary(end));
+        diff
+    }
+
+    /// Adds `step` to the number under or after the cursor on its line,
+    /// leaving the cursor on the number's last digit
+    fn increment_number(&mut self, cursor_id: CursorId, step: i64) -> OpaqueDiff {
+        let cursor = &mut self.cursors[cursor_id.0];
+        let range = match number::number_at(&self.content, cursor.range().start) {
+            Some(range) => range,
+            None => {
+                self.context.log("No number at the cursor");
+                return OpaqueDiff::empty();
+            }
+        };
+        let current = self.content.slice(range.clone()).to_string();
+        let incremented = match number::increment(&current, step) {
+            Some(incremented) => incremented,
+            None => {
+                self.context
+                    .log(format!("Cannot change `{}` by {}", current, step));
+                return OpaqueDiff::empty();
+            }
+        };
+        let diff = cursor.replace_range(&mut self.content, range, &incremented);
+        movement::move_horizontally(&self.content, cursor, Direction::Backward, 1);
+        diff
+    }
+
+    /// Swaps the selected syntax node with its next or previous sibling. It
+    /// needs a parse tree of the current text, as the nodes of an edited tree
+    /// may not match the text until it's parsed again.
+    fn drag_syntax_node(&mut self, cursor_id: CursorId, direction: Direction) -> OpaqueDiff {
+        let version = self.content.version();
+        let parse_tree = match self.parser.as_ref().and_then(|parser| parser.tree.as_ref()) {
+            Some(parse_tree) if parse_tree.version == version => parse_tree,
+            Some(_) => {
+                self.context.log("The syntax tree is being updated");
+                return OpaqueDiff::empty();
+            }
+            None => {
+                self.context.log("No syntax tree for the buffer");
+                return OpaqueDiff::empty();
+            }
+        };
+        let cursor = &mut self.cursors[cursor_id.0];
+        let nodes = structure::node_at(parse_tree, &self.content, cursor.selection())
+            .and_then(|node| Some((node, structure::sibling(node, direction)?)));
+        let (selected, other) = match nodes {
+            Some((node, sibling)) => (
+                structure::char_range(&self.content, node),
+                structure::char_range(&self.content, sibling),
+            ),
+            None => {
+                self.context.log("No syntax node to swap with");
+                return OpaqueDiff::empty();
+            }
+        };
+        cursor.swap_ranges(&mut self.content, selected, other)
+    }
+
+    /// Reports the sum of the numbers in the selection, inserting it after
+    /// the selection if `insert` is set
+    fn sum_numbers(&mut self, cursor_id: CursorId, insert: bool) -> OpaqueDiff {
+        let cursor = &mut self.cursors[cursor_id.0];
+        let selected = if cursor.has_selection() {
+            self.content.slice(cursor.selection()).to_string()
+        } else {
+            String::new()
+        };
+        let (count, total) = number::sum(&selected);
+        self.context.log(format!(
+            "Sum of {} number{}: {}",
+            count,
+            if count == 1 { "" } else { "s" },
+            total
+        ));
+        if !insert {
+            return OpaqueDiff::empty();
         }
+
+        let end = if cursor.has_selection() {
+            cursor.selection().end
+        } else {
+            cursor.range().start
+        };
+        cursor.replace_range(&mut self.content, end..end, &total.to_string())
     }
 
     fn delete_line(&mut self, cursor_id: CursorId) -> OpaqueDiff {
@@ -469,32 +1927,316 @@ impl Buffer {
             .diff
     }
 
+    /// Kills to the end of the line, or the line ending at the end of a line,
+    /// keeping the killed text in the clipboard and the kill ring. Consecutive
+    /// kills are joined into one entry.
+    fn kill_line(&mut self, cursor_id: CursorId, previous: Option<ChainedCommand>) -> OpaqueDiff {
+        self.chained_command = Some((cursor_id, ChainedCommand::Kill));
+        let operation = self.cursors[cursor_id.0].kill_to_end_of_line(&mut self.content);
+        if operation.diff.is_empty() {
+            return operation.diff;
+        }
+
+        let killed = String::from(operation.deleted);
+        if let Some(ChainedCommand::Kill) = previous {
+            let contents = self.context.kill_ring.append(&killed);
+            if let Err(error) = self.context.clipboard().set_contents(contents) {
+                self.context.log(error.to_string());
+            }
+        } else {
+            self.copy_to_clipboard(killed, ContentKind::Charwise);
+        }
+        operation.diff
+    }
+
     fn copy_selection_to_clipboard(&mut self, cursor_id: CursorId) -> OpaqueDiff {
-        let selection = self.cursors[cursor_id.0].selection();
-        self.context
-            .clipboard
-            .set_contents(self.content.slice(selection.start..selection.end).into())
-            .unwrap();
-        self.cursors[cursor_id.0].clear_selection();
+        let cursor = &mut self.cursors[cursor_id.0];
+        let (contents, kind) = if cursor.has_selection() {
+            let selection = cursor.selection();
+            (
+                self.content.slice(selection.start..selection.end).into(),
+                ContentKind::Charwise,
+            )
+        } else {
+            (cursor.whole_line(&self.content), ContentKind::Linewise)
+        };
+        cursor.clear_selection();
+        self.copy_to_clipboard(contents, kind);
         OpaqueDiff::empty()
     }
 
     fn cut_selection_to_clipboard(&mut self, cursor_id: CursorId) -> OpaqueDiff {
-        let operation = self.cursors[cursor_id.0].delete_selection(&mut self.content);
-        self.context
-            .clipboard
-            .set_contents(operation.deleted.into())
-            .unwrap();
-        operation.diff
+        let cursor = &mut self.cursors[cursor_id.0];
+        let (contents, kind, diff) = if cursor.has_selection() {
+            let operation = cursor.delete_selection(&mut self.content);
+            (
+                operation.deleted.into(),
+                ContentKind::Charwise,
+                operation.diff,
+            )
+        } else {
+            let line = cursor.whole_line(&self.content);
+            let operation = cursor.delete_line(&mut self.content);
+            (line, ContentKind::Linewise, operation.diff)
+        };
+        self.copy_to_clipboard(contents, kind);
+        diff
+    }
+
+    fn copy_to_clipboard(&self, contents: String, kind: ContentKind) {
+        if let Err(error) = self.context.clipboard().set_contents(contents.clone()) {
+            self.context.log(error.to_string());
+        }
+        self.context.kill_ring.push(contents, kind);
+    }
+
+    fn search(&mut self, cursor_id: CursorId, query: Option<SearchQuery>) {
+        let SearchQuery {
+            query,
+            direction,
+            options,
+            skip_current,
+        } = match query {
+            Some(query) => query,
+            None => {
+                self.search = None;
+                self.search_history.clear();
+                return;
+            }
+        };
+
+        // Deleting from the query goes back to where the search was before
+        // the deleted characters were typed
+        let cursor = &mut self.cursors[cursor_id.0];
+        if self.search_history.is_empty() {
+            self.search_history.push((String::new(), cursor.clone()));
+        }
+        let backtracking = self
+            .search_history
+            .last()
+            .map_or(false, |(previous, _)| query.len() < previous.len());
+        if backtracking {
+            while self.search_history.len() > 1
+                && self.search_history[self.search_history.len() - 1].0.len() > query.len()
+            {
+                self.search_history.pop();
+            }
+            *cursor = self.search_history[self.search_history.len() - 1].1.clone();
+        }
+        if query.is_empty() {
+            self.search = None;
+            return;
+        }
+
+        // Matches are cached until the query or the text changes
+        let version = self.content.version();
+        let mut search = match self.search.take() {
+            Some(search)
+                if search.query == query
+                    && search.options == options
+                    && search.version == version =>
+            {
+                search
+            }
+            _ => SearchState {
+                matches: Rc::new(search::find_all(
+                    &self.content,
+                    &query,
+                    options.case_mode,
+                    options.whole_word_in(self.mode),
+                )),
+                query,
+                options,
+                version,
+                current: None,
+            },
+        };
+
+        let cursor = &mut self.cursors[cursor_id.0];
+        search.current = search::next_match_index(
+            &search.matches,
+            cursor.range().start,
+            direction,
+            skip_current,
+        );
+        if let Some(index) = search.current {
+            let start = search.matches[index].start;
+            *cursor = Cursor::with_range(start..self.content.next_grapheme_boundary(start));
+        }
+        if !backtracking {
+            self.search_history
+                .push((search.query.clone(), cursor.clone()));
+        }
+        self.search = Some(search);
+    }
+
+    /// Ends the search, moving the cursor back to where it started
+    fn cancel_search(&mut self, cursor_id: CursorId) {
+        self.search = None;
+        if let Some((_, origin)) = self.search_history.drain(..).next() {
+            self.cursors[cursor_id.0] = origin;
+        }
+    }
+
+    fn copy_file_path(&self, cursor_id: CursorId, format: FilePathFormat) {
+        let file_path = match self.file_path.as_ref() {
+            Some(file_path) => self.context.current_working_dir.join(file_path),
+            None => {
+                self.context.log("Buffer is not visiting a file");
+                return;
+            }
+        };
+
+        let path = match format {
+            FilePathFormat::Absolute => file_path.display().to_string(),
+            FilePathFormat::Relative | FilePathFormat::RelativeWithLine => {
+                let root = self
+                    .repo
+                    .as_ref()
+                    .and_then(|repo| repo.workdir())
+                    .unwrap_or(&self.context.current_working_dir);
+                let relative_path = file_path.strip_prefix(root).unwrap_or(&file_path);
+                if format == FilePathFormat::RelativeWithLine {
+                    let line_index = self
+                        .content
+                        .char_to_line(self.cursors[cursor_id.0].range().start);
+                    format!("{}:{}", relative_path.display(), line_index + 1)
+                } else {
+                    relative_path.display().to_string()
+                }
+            }
+        };
+        self.context.log(format!("Copied `{}`", path));
+        self.copy_to_clipboard(path, ContentKind::Charwise);
+    }
+
+    /// Sets the primary selection, waiting on the task pool for a program
+    /// setting it as it may be slow to respond
+    fn set_primary_selection(&self, contents: String) {
+        match self.context.clipboard().set_primary(contents) {
+            Ok(Some(pending)) => {
+                let link = self.context.link.clone();
+                self.context.task_pool.spawn(move |_| {
+                    if let Err(error) = pending() {
+                        link.send(Message::Log(Some(error.to_string())));
+                    }
+                });
+            }
+            Ok(None) => {}
+            Err(error) => self.context.log(error.to_string()),
+        }
+    }
+
+    fn paste_from_primary_selection(&mut self, cursor_id: CursorId) -> OpaqueDiff {
+        match self.context.clipboard().get_primary() {
+            Ok(Some(contents)) => {
+                self.cursors[cursor_id.0].insert_chars(&mut self.content, contents.chars())
+            }
+            Ok(None) => {
+                self.context.log("No primary selection");
+                OpaqueDiff::empty()
+            }
+            Err(error) => {
+                self.context.log(error.to_string());
+                OpaqueDiff::empty()
+            }
+        }
     }
 
     fn paste_from_clipboard(&mut self, cursor_id: CursorId) -> OpaqueDiff {
-        let clipboard_str = self.context.clipboard.get_contents().unwrap();
-        if !clipboard_str.is_empty() {
-            self.cursors[cursor_id.0].insert_chars(&mut self.content, clipboard_str.chars())
-        } else {
-            OpaqueDiff::empty()
+        let contents = match self.context.clipboard().get_contents() {
+            Ok(contents) => contents,
+            Err(error) => {
+                self.context.log(error.to_string());
+                return OpaqueDiff::empty();
+            }
+        };
+        // Write-only clipboards fall back to the text last copied in the editor
+        let (clipboard_str, kind) = match contents {
+            Some(contents) => {
+                let kind = self.context.kill_ring.kind_of(&contents);
+                (contents, kind)
+            }
+            None => match self.context.kill_ring.last() {
+                Some(entry) => entry,
+                None => return OpaqueDiff::empty(),
+            },
+        };
+        if clipboard_str.is_empty() {
+            return OpaqueDiff::empty();
         }
+
+        // `yank-pop` continues with the entry before the one pasted, or the
+        // most recent one if the clipboard was set by another program
+        let next = match self.context.kill_ring.last() {
+            Some((last, _)) if last == clipboard_str => 1,
+            _ => 0,
+        };
+        let text = self.content.staged().clone();
+        let cursor = self.cursors[cursor_id.0].clone();
+        let diff = insert_contents(
+            &mut self.cursors[cursor_id.0],
+            &mut self.content,
+            &clipboard_str,
+            kind,
+            self.mode.indentation.tab_width(),
+        );
+        self.chained_command = Some((
+            cursor_id,
+            ChainedCommand::Yank {
+                text,
+                cursor,
+                yanked: self.content.staged().clone(),
+                next,
+            },
+        ));
+        diff
+    }
+
+    /// Replaces the text just pasted with the previous entry of the kill
+    /// ring, cycling back to the most recent one after the oldest
+    fn yank_pop(&mut self, cursor_id: CursorId, previous: Option<ChainedCommand>) -> OpaqueDiff {
+        let (text, cursor, next) = match previous {
+            Some(ChainedCommand::Yank {
+                text,
+                cursor,
+                yanked,
+                next,
+            }) if *self.content.staged() == yanked => (text, cursor, next),
+            _ => {
+                self.context.log("Previous command was not a yank");
+                return OpaqueDiff::empty();
+            }
+        };
+        let (contents, kind) = match self.context.kill_ring.get(next) {
+            Some(entry) => entry,
+            None => {
+                self.context.log("Kill ring is empty");
+                return OpaqueDiff::empty();
+            }
+        };
+
+        let mut yanked = text.clone();
+        let mut new_cursor = cursor.clone();
+        insert_contents(
+            &mut new_cursor,
+            &mut yanked,
+            &contents,
+            kind,
+            self.mode.indentation.tab_width(),
+        );
+        let diff = replace_text(&mut self.content, &yanked);
+        self.cursors[cursor_id.0] = new_cursor;
+        self.chained_command = Some((
+            cursor_id,
+            ChainedCommand::Yank {
+                text,
+                cursor,
+                yanked,
+                next: next + 1,
+            },
+        ));
+        diff
     }
 
     fn undo(&mut self, cursor_id: CursorId) -> OpaqueDiff {
@@ -503,15 +2245,48 @@ impl Buffer {
             .map(|(diff, cursor)| {
                 self.cursors[cursor_id.0] = cursor;
                 self.update_parse_tree(&diff, true);
+                self.edit_points.reconcile_undo(&diff);
                 diff
             })
             .unwrap_or_else(OpaqueDiff::empty)
     }
 
+    /// Undoes the latest change within the selection, as a new edit
+    fn undo_in_region(&mut self, cursor_id: CursorId) -> OpaqueDiff {
+        let selection = self.cursors[cursor_id.0].selection();
+        match self.content.undo_in_region(selection) {
+            Some(diff) => {
+                self.cursors[cursor_id.0] = Cursor::with_range(
+                    diff.char_index..self.content.next_grapheme_boundary(diff.char_index),
+                );
+                diff
+            }
+            None => {
+                self.context.log("No changes to undo in the region");
+                OpaqueDiff::empty()
+            }
+        }
+    }
+
+    fn checkout_revision(&mut self, index: usize) {
+        let current_text = self.content.staged().clone();
+        if !self.content.checkout(index) {
+            return;
+        }
+        for cursor in self.cursors.iter_mut() {
+            cursor.sync(&current_text, &self.content);
+        }
+        self.edit_points = EditPoints::new();
+        self.mark_changed();
+        self.update_parse_tree(&OpaqueDiff::empty(), true);
+    }
+
     fn redo(&mut self, cursor_id: CursorId) -> OpaqueDiff {
         self.content
             .redo()
             .map(|(diff, cursor)| {
+                self.edit_points.reconcile(&diff);
+                self.edit_points.record(&self.content, cursor.range().start);
                 self.cursors[cursor_id.0] = cursor;
                 self.update_parse_tree(&diff, true);
                 diff
@@ -547,24 +2322,23 @@ impl Buffer {
         };
 
         self.modified_status = ModifiedStatus::Saving;
+        self.autosave.saved();
         let buffer_id = self.id;
         let text = self.content.staged().clone();
         let link = self.context.link.clone();
-        let trim_trailing_whitespace = self.context.config.trim_trailing_whitespace_on_save;
+        let trim_trailing_whitespace = self.context.config.get().trim_trailing_whitespace_on_save;
+        let final_newline = self.context.config.get().ensure_final_newline;
         self.context.task_pool.spawn(move |_| {
             let text = match trim_trailing_whitespace {
                 true => strip_trailing_whitespace(text),
                 false => text,
             };
+            let text = match final_newline {
+                true => ensure_final_newline(text),
+                false => text,
+            };
 
-            let buffer_message = BufferMessage::SaveBufferEnd(
-                File::create(&file_path)
-                    .map(BufWriter::new)
-                    .and_then(|writer| {
-                        text.write_to(writer)?;
-                        Ok(text)
-                    }),
-            );
+            let buffer_message = BufferMessage::SaveBufferEnd(write_file(&file_path, text));
             link.send(BuffersMessage::new(buffer_id, buffer_message).into())
         });
     }
@@ -629,6 +2403,18 @@ impl BufferCursor {
         self.send_message(BufferMessage::NextChildRevision)
     }
 
+    pub fn checkout_revision(&self, index: usize) {
+        self.send_message(BufferMessage::CheckoutRevision(index))
+    }
+
+    pub fn enable_all_features(&self) {
+        self.send_message(BufferMessage::EnableAllFeatures)
+    }
+
+    pub fn toggle_autosave(&self) {
+        self.send_message(BufferMessage::ToggleAutosave)
+    }
+
     #[inline]
     pub fn move_up(&self) {
         self.send_cursor(CursorMessage::Up(1));
@@ -679,11 +2465,56 @@ impl BufferCursor {
         self.send_cursor(CursorMessage::EndOfBuffer);
     }
 
+    #[inline]
+    pub fn move_to_percent(&self, percent: u8) {
+        self.send_cursor(CursorMessage::MoveToPercent(percent));
+    }
+
+    #[inline]
+    pub fn move_to_line(&self, line_index: LineIndex) {
+        self.send_cursor(CursorMessage::MoveToLine(line_index));
+    }
+
+    #[inline]
+    pub fn move_to_matching_bracket(&self) {
+        self.send_cursor(CursorMessage::MoveToMatchingBracket);
+    }
+
     #[inline]
     pub fn begin_selection(&self) {
         self.send_cursor(CursorMessage::BeginSelection);
     }
 
+    #[inline]
+    pub fn begin_rectangle(&self) {
+        self.send_cursor(CursorMessage::BeginRectangle);
+    }
+
+    #[inline]
+    pub fn insert_at_line_ends(&self) {
+        self.send_cursor(CursorMessage::InsertAtLineEnds);
+    }
+
+    #[inline]
+    pub fn insert_at_line_starts(&self) {
+        self.send_cursor(CursorMessage::InsertAtLineStarts);
+    }
+
+    #[inline]
+    pub fn add_cursor_below(&self) {
+        self.send_cursor(CursorMessage::AddCursor(Direction::Forward));
+    }
+
+    #[inline]
+    pub fn add_cursor_above(&self) {
+        self.send_cursor(CursorMessage::AddCursor(Direction::Backward));
+    }
+
+    #[inline]
+    pub fn add_cursor_at_next_match(&self) {
+        self.send_cursor(CursorMessage::AddCursorAtNextMatch);
+    }
+
     #[inline]
     pub fn clear_selection(&self) {
         self.send_cursor(CursorMessage::ClearSelection);
@@ -699,6 +2530,32 @@ impl BufferCursor {
         self.send_cursor(CursorMessage::Yank);
     }
 
+    #[inline]
+    pub fn yank_pop(&self) {
+        self.send_cursor(CursorMessage::YankPop);
+    }
+
+    /// Moves to the next match of `query`, ending the search if `None`
+    #[inline]
+    pub fn search(&self, query: Option<SearchQuery>) {
+        self.send_cursor(CursorMessage::Search(query));
+    }
+
+    #[inline]
+    pub fn cancel_search(&self) {
+        self.send_cursor(CursorMessage::CancelSearch);
+    }
+
+    #[inline]
+    pub fn copy_file_path(&self, format: FilePathFormat) {
+        self.send_cursor(CursorMessage::CopyFilePath(format));
+    }
+
+    #[inline]
+    pub fn paste_from_primary_selection(&self) {
+        self.send_cursor(CursorMessage::YankPrimary);
+    }
+
     #[inline]
     pub fn copy_selection_to_clipboard(&self) {
         self.send_cursor(CursorMessage::CopySelection);
@@ -734,6 +2591,21 @@ impl BufferCursor {
         self.send_cursor(CursorMessage::DeleteLine);
     }
 
+    #[inline]
+    pub fn kill_line(&self) {
+        self.send_cursor(CursorMessage::KillLine);
+    }
+
+    #[inline]
+    pub fn join_lines(&self) {
+        self.send_cursor(CursorMessage::JoinLines);
+    }
+
+    #[inline]
+    pub fn duplicate(&self) {
+        self.send_cursor(CursorMessage::Duplicate);
+    }
+
     #[inline]
     pub fn insert_new_line(&self) {
         self.send_cursor(CursorMessage::InsertNewLine);
@@ -749,8 +2621,95 @@ impl BufferCursor {
         self.send_cursor(CursorMessage::InsertChar {
             character,
             move_forward,
+            pasted: false,
         });
     }
+
+    /// Inserts a character of a paste literally, adding it to the revision
+    /// of the paste so far
+    #[inline]
+    pub fn paste_char(&self, character: char) {
+        self.send_cursor(CursorMessage::InsertChar {
+            character,
+            move_forward: true,
+            pasted: true,
+        });
+    }
+
+    #[inline]
+    pub fn insert_repeated(&self, character: char, count: usize) {
+        self.send_cursor(CursorMessage::InsertRepeated { character, count });
+    }
+
+    #[inline]
+    pub fn insert_snippet(&self, snippet: Snippet) {
+        self.send_cursor(CursorMessage::InsertSnippet(snippet));
+    }
+
+    #[inline]
+    pub fn insert_date(&self) {
+        self.send_cursor(CursorMessage::InsertDate);
+    }
+
+    #[inline]
+    pub fn increment_number(&self, step: i64) {
+        self.send_cursor(CursorMessage::IncrementNumber(step));
+    }
+
+    #[inline]
+    pub fn sum_numbers(&self, insert: bool) {
+        self.send_cursor(CursorMessage::SumNumbers { insert });
+    }
+
+    #[inline]
+    pub fn evaluate_expression(&self) {
+        self.send_cursor(CursorMessage::EvaluateExpression);
+    }
+
+    #[inline]
+    pub fn convert_indentation(&self, unit: Option<IndentationUnit>) {
+        self.send_cursor(CursorMessage::ConvertIndentation(unit));
+    }
+
+    #[inline]
+    pub fn indent_lines(&self) {
+        self.send_cursor(CursorMessage::IndentLines);
+    }
+
+    #[inline]
+    pub fn unindent_lines(&self) {
+        self.send_cursor(CursorMessage::UnindentLines);
+    }
+
+    #[inline]
+    pub fn toggle_comment(&self) {
+        self.send_cursor(CursorMessage::ToggleComment);
+    }
+
+    #[inline]
+    pub fn change_case(&self, change: CaseChange) {
+        self.send_cursor(CursorMessage::ChangeCase(change));
+    }
+
+    #[inline]
+    pub fn move_to_last_edit(&self) {
+        self.send_cursor(CursorMessage::MoveToLastEdit);
+    }
+
+    #[inline]
+    pub fn select_syntax_sibling(&self, direction: Direction) {
+        self.send_cursor(CursorMessage::SelectSyntaxSibling(direction));
+    }
+
+    #[inline]
+    pub fn select_syntax_parent(&self) {
+        self.send_cursor(CursorMessage::SelectSyntaxParent);
+    }
+
+    #[inline]
+    pub fn drag_syntax_node(&self, direction: Direction) {
+        self.send_cursor(CursorMessage::DragSyntaxNode(direction));
+    }
 }
 
 #[derive(Debug)]
@@ -763,6 +2722,11 @@ pub enum BufferMessage {
     },
     PreviousChildRevision,
     NextChildRevision,
+    CheckoutRevision(usize),
+    EnableAllFeatures,
+    ToggleAutosave,
+    LoadChunk(LoadedChunk),
+    LoadEnd(io::Result<()>),
     CursorMessage {
         cursor_id: CursorId,
         message: CursorMessage,
@@ -780,29 +2744,562 @@ pub enum CursorMessage {
     EndOfLine,
     StartOfBuffer,
     EndOfBuffer,
+    MoveToPercent(u8),
+    MoveToLine(LineIndex),
+    /// Moves to the bracket matching the one under the cursor
+    MoveToMatchingBracket,
     MoveWord(Direction, usize),
     MoveParagraph(Direction, usize),
+    MovePageBreak(Direction, usize),
+    MoveTodo(Direction, usize),
+    /// Moves to the most recent edit, or to the one before it when repeated
+    MoveToLastEdit,
+    /// Selects the syntax node after or before the one selected, e.g. the
+    /// next argument of a function call
+    SelectSyntaxSibling(Direction),
+    SelectSyntaxParent,
 
     // Editing
     BeginSelection,
+    BeginRectangle,
+    InsertAtLineEnds,
+    InsertAtLineStarts,
+    /// Adds an insertion point on the line below or above the existing ones
+    AddCursor(Direction),
+    /// Adds an insertion point after the next occurrence of the selection, or
+    /// of the word under the cursor
+    AddCursorAtNextMatch,
     ClearSelection,
     SelectAll,
     Yank,
+    /// Replaces the text just pasted with the previous kill ring entry
+    YankPop,
+    YankPrimary,
     CopySelection,
+    CopyFilePath(FilePathFormat),
+    Search(Option<SearchQuery>),
+    /// Ends the search, moving the cursor back to where it started
+    CancelSearch,
     CutSelection,
 
     DeleteForward,
     DeleteBackward,
+    DeleteWord(Direction),
     DeleteLine,
+    /// Kills to the end of the line, or the line ending at the end of a line
+    KillLine,
+    /// Joins the next line, or the selected lines, onto the cursor's line
+    JoinLines,
+    /// Inserts a copy of the selection after it, or of the cursor's line
+    /// below it
+    Duplicate,
     InsertTab,
     InsertNewLine,
-    InsertChar { character: char, move_forward: bool },
+    InsertChar {
+        character: char,
+        move_forward: bool,
+        /// Part of a paste after its first character, which is added to the
+        /// paste's revision
+        pasted: bool,
+    },
+    /// Inserts a character `count` times as a single edit, e.g. with a
+    /// numeric argument
+    InsertRepeated {
+        character: char,
+        count: usize,
+    },
+    InsertSnippet(Snippet),
+    InsertDate,
+    EvaluateExpression,
+    IncrementNumber(i64),
+    SumNumbers {
+        insert: bool,
+    },
+    /// Converts the indentation of the selected lines, or the whole buffer,
+    /// to tabs or spaces. `None` converts it to the buffer's indentation.
+    ConvertIndentation(Option<IndentationUnit>),
+    /// Adds a level of indentation to the selected lines, or the cursor's line
+    IndentLines,
+    /// Removes a level of indentation from the selected lines, or the
+    /// cursor's line
+    UnindentLines,
+    /// Comments out the selected lines, or the cursor's line, with the mode's
+    /// comment token, or uncomments them if they're all commented
+    ToggleComment,
+    ChangeCase(CaseChange),
+    /// Swaps the selected syntax node with the next or previous one, keeping
+    /// it selected
+    DragSyntaxNode(Direction),
+    TransposeChars,
 
     // Undo / Redo
     Undo,
     Redo,
 }
 
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        // Stop loading the file if the buffer is killed before it's loaded
+        if let Some(loading) = self.loading.as_ref() {
+            loading.cancel.cancel();
+        }
+    }
+}
+
+/// A chunk of text read from a file. Only its length is shown when debugging.
+pub struct LoadedChunk(String);
+
+impl fmt::Debug for LoadedChunk {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "LoadedChunk({} bytes)", self.0.len())
+    }
+}
+
+/// Reads a UTF-8 file in chunks, calling `on_chunk` with each one. A character
+/// split between two reads is carried over to the next chunk.
+fn read_chunks(
+    file_path: &Path,
+    cancel: &CancelToken,
+    mut on_chunk: impl FnMut(String),
+) -> io::Result<()> {
+    let mut file = File::open(file_path)?;
+    let mut buffer = vec![0; LOAD_CHUNK_SIZE];
+    let mut pending = Vec::new();
+    while !cancel.is_cancelled() {
+        let num_read = file.read(&mut buffer)?;
+        if num_read == 0 {
+            break;
+        }
+        pending.extend_from_slice(&buffer[..num_read]);
+        let valid_length = match std::str::from_utf8(&pending) {
+            Ok(_) => pending.len(),
+            // The end of the chunk is an incomplete character
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+        };
+        let incomplete = pending.split_off(valid_length);
+        let chunk = std::mem::replace(&mut pending, incomplete);
+        if !chunk.is_empty() {
+            on_chunk(
+                String::from_utf8(chunk)
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?,
+            );
+        }
+    }
+    if pending.is_empty() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "file ends with an incomplete UTF-8 character",
+        ))
+    }
+}
+
+const LOAD_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+impl CursorMessage {
+    /// How the message moves the corner of a rectangular selection, if it's
+    /// one of the arrow keys
+    fn rectangle_move(&self) -> Option<(RectangleMove, usize)> {
+        match *self {
+            Self::Up(count) => Some((RectangleMove::Up, count)),
+            Self::Down(count) => Some((RectangleMove::Down, count)),
+            Self::Left => Some((RectangleMove::Left, 1)),
+            Self::Right => Some((RectangleMove::Right, 1)),
+            _ => None,
+        }
+    }
+
+    /// How the message affects a transient selection
+    fn selection_effect(&self) -> SelectionEffect {
+        match self {
+            Self::BeginSelection => SelectionEffect::Mark,
+            Self::SelectAll
+            | Self::SelectSyntaxSibling(_)
+            | Self::SelectSyntaxParent
+            | Self::AddCursorAtNextMatch => SelectionEffect::Select,
+            Self::Up(_)
+            | Self::Down(_)
+            | Self::Left
+            | Self::Right
+            | Self::StartOfLine
+            | Self::EndOfLine
+            | Self::StartOfBuffer
+            | Self::EndOfBuffer
+            | Self::MoveToPercent(_)
+            | Self::MoveToLine(_)
+            | Self::MoveToMatchingBracket
+            | Self::MoveWord(..)
+            | Self::MoveParagraph(..)
+            | Self::MovePageBreak(..)
+            | Self::MoveTodo(..)
+            | Self::MoveToLastEdit => SelectionEffect::Move,
+            // A snippet's fields, a dragged node, duplicated text and indented
+            // or commented lines stay selected to be edited again
+            Self::InsertSnippet(_)
+            | Self::Duplicate
+            | Self::DragSyntaxNode(_)
+            | Self::IndentLines
+            | Self::UnindentLines
+            | Self::ToggleComment
+            | Self::ChangeCase(_) => SelectionEffect::Keep,
+            message if message.is_edit() => SelectionEffect::Edit,
+            _ => SelectionEffect::Keep,
+        }
+    }
+
+    /// Whether a rectangular selection lasts past the message. Copying or
+    /// cutting it ends it after.
+    fn keeps_rectangle(&self) -> bool {
+        matches!(
+            self,
+            Self::InsertChar { .. }
+                | Self::DeleteForward
+                | Self::DeleteBackward
+                | Self::CopySelection
+                | Self::CutSelection
+        )
+    }
+
+    /// Whether the message moves every insertion point of a multiple cursor
+    /// edit, rather than ending it
+    fn moves_multi_cursor(&self) -> bool {
+        matches!(
+            self,
+            Self::Left | Self::Right | Self::StartOfLine | Self::EndOfLine | Self::MoveWord(..)
+        )
+    }
+
+    /// Whether the insertion points of a multiple cursor edit last past the
+    /// message
+    fn keeps_multi_cursor(&self) -> bool {
+        matches!(
+            self,
+            Self::InsertChar { .. }
+                | Self::DeleteForward
+                | Self::DeleteBackward
+                | Self::AddCursor(_)
+                | Self::AddCursorAtNextMatch
+        )
+    }
+
+    /// Whether the message changes the text of the buffer
+    fn is_edit(&self) -> bool {
+        matches!(
+            self,
+            Self::Yank
+                | Self::YankPop
+                | Self::YankPrimary
+                | Self::CutSelection
+                | Self::DeleteForward
+                | Self::DeleteBackward
+                | Self::DeleteWord(_)
+                | Self::DeleteLine
+                | Self::KillLine
+                | Self::JoinLines
+                | Self::Duplicate
+                | Self::InsertTab
+                | Self::InsertNewLine
+                | Self::InsertChar { .. }
+                | Self::InsertRepeated { .. }
+                | Self::InsertSnippet(_)
+                | Self::InsertDate
+                | Self::EvaluateExpression
+                | Self::ConvertIndentation(_)
+                | Self::IndentLines
+                | Self::UnindentLines
+                | Self::ToggleComment
+                | Self::ChangeCase(_)
+                | Self::DragSyntaxNode(_)
+                | Self::TransposeChars
+                | Self::IncrementNumber(_)
+                | Self::SumNumbers { insert: true }
+                | Self::Undo
+                | Self::Redo
+        )
+    }
+}
+
+/// How long a selection lasts, as set in the configuration file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SelectionMode {
+    /// A selection lasts until it's cleared, movement extends it
+    Sticky,
+    /// Editing the text ends the selection. Movement extends a selection
+    /// begun with the mark, but ends one made by a command like `select-all`.
+    Transient,
+}
+
+impl Default for SelectionMode {
+    fn default() -> Self {
+        Self::Sticky
+    }
+}
+
+/// How a command affects a transient selection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SelectionEffect {
+    /// Begins a selection with the mark
+    Mark,
+    /// Selects text without the mark
+    Select,
+    /// Moves the cursor
+    Move,
+    /// Edits the text
+    Edit,
+    /// Leaves the selection as it is, e.g. copying it
+    Keep,
+}
+
+/// Whether a transient selection survives a command. Movement only extends
+/// a `marked` selection and an edit which failed to `change` the text keeps
+/// it.
+fn keeps_transient_selection(effect: SelectionEffect, marked: bool, changed: bool) -> bool {
+    match effect {
+        SelectionEffect::Move => marked,
+        SelectionEffect::Edit => !changed,
+        SelectionEffect::Mark | SelectionEffect::Select | SelectionEffect::Keep => true,
+    }
+}
+
+/// Pastes text copied from a buffer at the cursor, as its kind is pasted
+fn insert_contents(
+    cursor: &mut Cursor,
+    text: &mut Rope,
+    contents: &str,
+    kind: ContentKind,
+    tab_width: usize,
+) -> OpaqueDiff {
+    match kind {
+        ContentKind::Charwise => cursor.insert_chars(text, contents.chars()),
+        ContentKind::Linewise => cursor.insert_lines_above(text, contents),
+        ContentKind::Rectangular => {
+            let lines: Vec<_> = contents.split('\n').collect();
+            cursor.insert_rectangle(text, &lines, tab_width)
+        }
+    }
+}
+
+/// Formats a date with `strftime`-like specifiers, e.g. `%Y-%m-%d`. Returns
+/// `None` if the format is invalid.
+fn format_date(date: &DateTime<FixedOffset>, format: &str) -> Option<String> {
+    let mut formatted = String::new();
+    write!(formatted, "{}", date.format(format)).ok()?;
+    Some(formatted)
+}
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+fn write_file(file_path: &Path, text: Rope) -> io::Result<Rope> {
+    let writer = BufWriter::new(File::create(file_path)?);
+    text.write_to(writer)?;
+    Ok(text)
+}
+
+fn read_modified_time(file_path: &Path) -> Option<SystemTime> {
+    fs::metadata(file_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// How `TAB` indents in a buffer. If enabled in the configuration, the
+/// indentation of the text is used when it can be detected and the mode's
+/// default otherwise.
+fn detect_indentation(
+    context: &ContextHandle,
+    mode: &'static Mode,
+    text: &Rope,
+) -> IndentationConfig {
+    let detected = match indent::detect_indentation(text) {
+        Some(detected) if context.config.get().detect_indentation => detected,
+        _ => return mode.indentation.clone(),
+    };
+    match detected.indentation {
+        Indentation::Tabs => IndentationConfig {
+            width: mode.indentation.width,
+            unit: IndentationUnit::Tab,
+        },
+        Indentation::Spaces(width) => IndentationConfig {
+            width,
+            unit: IndentationUnit::Space,
+        },
+    }
+}
+
+/// Logs a summary of the whitespace a file being opened is inconsistent
+/// about, e.g. mixed line endings, to notice it before editing the file
+fn report_whitespace_issues(context: &ContextHandle, text: &Rope) {
+    if let Some(summary) = whitespace::scan_whitespace(text).summary() {
+        context.log(format!("The file has {}", summary));
+    }
+}
+
+/// Starts parsing `text` in the background if the mode has a grammar
+fn start_parser(
+    context: &ContextHandle,
+    buffer_id: BufferId,
+    mode: &'static Mode,
+    text: &Rope,
+    version: usize,
+) -> Option<ParserPool> {
+    let mut parser = mode
+        .language()
+        .and_then(|result| result.ok())
+        .map(ParserPool::new)?;
+    let link = context.link.clone();
+    parser.ensure_tree(
+        &context.task_pool,
+        || text.clone(),
+        move |status| {
+            link.send(
+                BuffersMessage::new(buffer_id, BufferMessage::ParseSyntax { version, status })
+                    .into(),
+            )
+        },
+    );
+    Some(parser)
+}
+
+/// An edit tree for `text`, keeping the undo history the configuration allows
+fn edit_tree(config: &EditorConfig, text: Rope) -> EditTree {
+    EditTree::new(text)
+        .with_max_revisions(config.max_undo_revisions)
+        .with_max_edit_bytes(config.max_undo_bytes)
+}
+
+/// Selects the node `related` finds from the syntax node of the cursor's
+/// selection, returning whether there is one
+fn select_syntax_node(
+    parse_tree: &ParseTree,
+    text: &Rope,
+    cursor: &mut Cursor,
+    related: impl FnOnce(Node) -> Option<Node>,
+) -> bool {
+    let node = structure::node_at(parse_tree, text, cursor.selection()).and_then(related);
+    if let Some(node) = node {
+        cursor.select(text, structure::char_range(text, node));
+    }
+    node.is_some()
+}
+
+/// Moves by subwords or words, depending on the configuration
+fn move_by_words(
+    config: &EditorConfig,
+    mode: &Mode,
+    text: &Rope,
+    cursor: &mut Cursor,
+    direction: Direction,
+    count: usize,
+) {
+    if config.subword_movement {
+        movement::move_subword(text, cursor, direction, count)
+    } else {
+        movement::move_word(
+            text,
+            cursor,
+            WordCharacters::new(&mode.word_characters),
+            direction,
+            count,
+            config.word_movement_stops_at_eol,
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchQuery {
+    pub query: String,
+    pub direction: Direction,
+    pub options: SearchOptions,
+    /// Whether to move past a match at the cursor, e.g. when repeating a search
+    pub skip_current: bool,
+}
+
+/// How a search query is matched, kept by the editor between searches
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchOptions {
+    pub case_mode: CaseMode,
+    /// Skip matches that are part of a longer word
+    pub whole_word: bool,
+}
+
+impl SearchOptions {
+    /// The characters words are made of in `mode` when matching whole words
+    pub fn whole_word_in(self, mode: &Mode) -> Option<WordCharacters<'_>> {
+        self.whole_word
+            .then(|| WordCharacters::new(&mode.word_characters))
+    }
+}
+
+/// The position of the cursor among the matches of a search
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchStatus {
+    /// The index and range of the match under the cursor
+    pub current: Option<(usize, Range<CharIndex>)>,
+    pub num_matches: usize,
+    /// The ranges of all the matches, in order
+    pub matches: Rc<Vec<Range<CharIndex>>>,
+}
+
+/// A regular expression replacement asking about each match after the cursor
+/// in turn
+#[derive(Debug)]
+struct QueryReplace {
+    cursor_id: CursorId,
+    regex: Regex,
+    template: String,
+    // Where to look for the next match, past the last one replaced or skipped
+    resume: CharIndex,
+    current: Option<RegexMatch>,
+    num_replaced: usize,
+}
+
+/// The answers to a query replace about a match
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryReplaceAnswer {
+    Replace,
+    Skip,
+    /// Replaces this match and all the ones after it
+    ReplaceAll,
+    Quit,
+}
+
+/// The match a query replace asks about and what it would be replaced with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingReplacement {
+    pub matched: String,
+    pub replacement: String,
+}
+
+#[derive(Debug)]
+struct SearchState {
+    query: String,
+    options: SearchOptions,
+    version: usize,
+    matches: Rc<Vec<Range<CharIndex>>>,
+    current: Option<usize>,
+}
+
+/// How the case of the selection, or the word at the cursor, is converted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaseChange {
+    Upcase,
+    Downcase,
+    /// Uppercases the first letter of each word and lowercases the rest
+    Capitalize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilePathFormat {
+    /// The absolute path of the file
+    Absolute,
+    /// The path relative to the repository root, or the working directory
+    /// if the file isn't in a repository
+    Relative,
+    /// The relative path followed by the line of the cursor, i.e. `path:line`
+    RelativeWithLine,
+}
+
 #[derive(Clone)]
 pub struct RepositoryRc(pub Rc<Repository>);
 
@@ -825,3 +3322,114 @@ impl std::ops::Deref for RepositoryRc {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_date_with_custom_format() {
+        let date = DateTime::parse_from_rfc3339("2022-07-30T09:05:00+01:00").unwrap();
+        assert_eq!(
+            Some("2022-07-30".into()),
+            format_date(&date, DEFAULT_DATE_FORMAT)
+        );
+        assert_eq!(
+            Some("30/07/2022 09:05 +0100".into()),
+            format_date(&date, "%d/%m/%Y %H:%M %z")
+        );
+        assert_eq!(None, format_date(&date, "%Q"));
+    }
+
+    #[test]
+    fn transient_selection_ends_with_plain_movement() {
+        let effect = CursorMessage::Right.selection_effect();
+        assert!(!keeps_transient_selection(effect, false, false));
+        assert!(keeps_transient_selection(effect, true, false));
+        let effect = CursorMessage::MoveWord(Direction::Forward, 1).selection_effect();
+        assert!(!keeps_transient_selection(effect, false, false));
+
+        // Copying keeps the selection, whether or not it was begun with the mark
+        let effect = CursorMessage::CopySelection.selection_effect();
+        assert!(keeps_transient_selection(effect, false, false));
+        assert!(keeps_transient_selection(effect, true, false));
+
+        // Edits end the selection, unless they fail
+        let effect = CursorMessage::DeleteForward.selection_effect();
+        assert!(!keeps_transient_selection(effect, true, true));
+        assert!(keeps_transient_selection(effect, true, false));
+        let effect = CursorMessage::InsertSnippet(Snippet::parse("$1")).selection_effect();
+        assert!(keeps_transient_selection(effect, false, true));
+        let effect = CursorMessage::Duplicate.selection_effect();
+        assert!(keeps_transient_selection(effect, true, true));
+    }
+
+    #[test]
+    fn select_syntax_siblings_and_parents() {
+        let text = Rope::from("fn f(a: u8, b: u8) {}\n");
+        let parse_tree = crate::testing::parse_rust(&text);
+        let mut cursor = Cursor::new();
+        fn next(node: Node) -> Option<Node> {
+            structure::sibling(node, Direction::Forward)
+        }
+        fn previous(node: Node) -> Option<Node> {
+            structure::sibling(node, Direction::Backward)
+        }
+
+        // From the parameter name, to the parameter, to its siblings
+        cursor.select(&text, 5..6);
+        assert!(select_syntax_node(
+            &parse_tree,
+            &text,
+            &mut cursor,
+            structure::parent
+        ));
+        assert_eq!(5..10, cursor.selection());
+        assert!(select_syntax_node(&parse_tree, &text, &mut cursor, next));
+        assert_eq!(12..17, cursor.selection());
+        assert!(!select_syntax_node(&parse_tree, &text, &mut cursor, next));
+        assert_eq!(12..17, cursor.selection());
+        assert!(select_syntax_node(
+            &parse_tree,
+            &text,
+            &mut cursor,
+            previous
+        ));
+        assert_eq!(5..10, cursor.selection());
+        assert!(select_syntax_node(
+            &parse_tree,
+            &text,
+            &mut cursor,
+            structure::parent
+        ));
+        assert_eq!(4..18, cursor.selection());
+    }
+
+    #[test]
+    fn idle_after_an_edit_writes_the_file() {
+        let idle = Duration::from_secs(5);
+        let start = Instant::now();
+        let mut autosave = Autosave {
+            enabled: true,
+            edited_at: None,
+        };
+        assert!(!autosave.is_pending() && !autosave.is_due(start + idle, idle));
+
+        autosave.edited(start);
+        assert!(autosave.is_pending());
+        assert!(!autosave.is_due(start + Duration::from_secs(1), idle));
+        assert!(autosave.is_due(start + idle, idle));
+
+        let file_path = std::env::temp_dir().join(format!("zee-autosave-{}", std::process::id()));
+        autosave.saved();
+        write_file(&file_path, Rope::from("notes\n")).unwrap();
+        assert_eq!("notes\n", fs::read_to_string(&file_path).unwrap());
+        assert!(!autosave.is_due(start + idle, idle));
+        fs::remove_file(file_path).unwrap();
+
+        // Turning autosave off keeps the edits in the buffer
+        autosave.edited(start);
+        autosave.enabled = false;
+        assert!(!autosave.is_due(start + idle, idle));
+    }
+}
@@ -1,45 +1,67 @@
 mod bindings;
 pub mod buffer;
+pub mod operations;
+mod quoted_insert;
 mod windows;
 
-pub use self::buffer::{BufferId, ModifiedStatus};
+pub use self::buffer::{BufferId, DiskStatus, ModifiedStatus};
 
 use git2::Repository;
+use parking_lot::RwLock;
+use regex::Regex;
 use ropey::Rope;
 use std::{
     borrow::Cow,
+    cmp,
     fmt::Display,
-    fs::File,
-    io::{self, BufReader},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    ops::Range,
     path::{Path, PathBuf},
-    sync::Arc,
+    process::Command,
+    sync::{atomic::AtomicUsize, Arc},
+    time::{Duration, Instant},
 };
 use zi::{
     Bindings, Callback, Component, ComponentExt, ComponentLink, FlexBasis, FlexDirection, Item,
     Key, Layout, NamedBindingQuery, Rect, ShouldRender,
 };
 
-use zee_grammar::Mode;
+use zee_edit::{search, snippet::Snippet, unicode, CharIndex, Direction};
+use zee_grammar::{config::TestFilePattern, Mode};
 
 use crate::{
-    clipboard::Clipboard,
+    clipboard::{self, ClipboardProvider, KillRing},
+    commands::{self, Confirm},
     components::{
         buffer::{Buffer as BufferView, Properties as BufferViewProperties},
         prompt::{
-            buffers::BufferEntry, picker::FileSource, Action as PromptAction, Prompt,
-            Properties as PromptProperties, PROMPT_INACTIVE_HEIGHT,
+            buffers::BufferEntry,
+            input::{self, Validator},
+            interactive::{Answer, Question},
+            picker::FileSource,
+            progress::{ProgressIndicator, Properties as ProgressIndicatorProperties},
+            replace::Replacement,
+            Action as PromptAction, Prompt, Properties as PromptProperties, PROMPT_INACTIVE_HEIGHT,
         },
         splash::{Properties as SplashProperties, Splash},
         theme::{Theme, THEMES},
     },
-    config::{EditorConfig, PLAIN_TEXT_MODE},
+    config::{EditorConfig, SharedConfig, PLAIN_TEXT_MODE},
     error::Result,
+    process::{run_command, CommandError, CommandOutput},
+    syntax::spelling::SpellChecker,
     task::TaskPool,
 };
 
 use self::{
     bindings::KeySequenceSlice,
-    buffer::{BufferCursor, Buffers, BuffersMessage, CursorId, RepositoryRc},
+    buffer::{
+        BufferCursor, BufferMessage, Buffers, BuffersMessage, CursorId, PendingReplacement,
+        QueryReplaceAnswer, RepositoryRc, SearchOptions,
+    },
+    operations::{Operation, OperationMessage, Operations},
+    quoted_insert::{QuotedInsert, QuotedKey},
     windows::{CycleFocus, Window, WindowTree},
 };
 
@@ -50,26 +72,97 @@ pub enum Message {
     FocusNextWindow,
     FocusPreviousWindow,
     SplitWindow(FlexDirection),
+    CloneViewOtherWindow,
     FullscreenWindow,
 
     // Prompt
     SelectBufferPicker,
     SelectBuffer(BufferId),
     KillBufferPicker,
+    KillBufferPrompt(BufferId),
     KillBuffer(BufferId),
     OpenFilePicker(FileSource),
+    SearchPrompt(Direction),
+    ChangeSearchOptions(SearchOptions),
+    EndSearch,
+    ReplaceInFilesPrompt,
+    ReplaceInFiles(Replacement),
+    QueryReplacePrompt,
+    QueryReplaceWith(String),
+    QueryReplace {
+        pattern: String,
+        replacement: String,
+    },
+    AnswerQueryReplace(char),
     OpenFile(PathBuf),
+    OpenRelatedFile,
+    OpenTestFile,
+    RenameFilePrompt,
+    RenameFile {
+        buffer_id: BufferId,
+        path: PathBuf,
+        overwrite: bool,
+    },
+    DeleteFilePrompt,
+    DeleteFile(BufferId),
+    CreateDirectoryPrompt,
+    CreateDirectory(PathBuf),
+    GotoLinePrompt,
+    GotoLine(String),
+    GotoPercentPrompt,
+    GotoPercent(String),
+    InsertSnippetPrompt,
+    InsertSnippet(String),
+    InsertCharPrompt,
+    InsertCharByName(String),
+    ShellCommandPrompt,
+    ShellCommand(String),
+    ShellCommandDone {
+        buffer_id: BufferId,
+        cursor_id: CursorId,
+        region: Range<CharIndex>,
+        version: usize,
+        result: std::result::Result<CommandOutput, CommandError>,
+    },
+    RevertAllBuffers,
+    RevertBuffers(Vec<BufferId>),
+    CheckExternalChanges,
+    Autosave,
     ChangePromptHeight(usize),
     Buffer(BuffersMessage),
+    Operation(OperationMessage),
     Log(Option<String>),
+    PendingKeys(Option<String>),
+    /// Starts, continues or ends a quoted insert
+    QuotedInsert(Option<QuotedInsert>),
+    /// A key typed during a quoted insert
+    QuotedKey(Key),
     PostInteractionQuit(bool),
+    Confirm(String, OnConfirm),
+    Confirmed(bool),
 
     // Global
     ChangeTheme,
+    ReloadConfig,
     Cancel,
     Quit,
 }
 
+/// An action run once confirmed in the prompt, e.g. a buffer command
+pub struct OnConfirm(Box<dyn FnOnce() + Send>);
+
+impl OnConfirm {
+    pub fn new(on_confirm: impl FnOnce() + Send + 'static) -> Self {
+        Self(Box::new(on_confirm))
+    }
+}
+
+impl std::fmt::Debug for OnConfirm {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "OnConfirm")
+    }
+}
+
 impl From<BuffersMessage> for Message {
     fn from(message: BuffersMessage) -> Message {
         Message::Buffer(message)
@@ -80,29 +173,45 @@ pub struct Properties {
     pub args_files: Vec<PathBuf>,
     pub current_working_dir: PathBuf,
     pub config: EditorConfig,
+    /// Where `reload-config` reads the configuration from
+    pub config_path: Option<PathBuf>,
     pub task_pool: TaskPool,
-    pub clipboard: Arc<dyn Clipboard>,
+    pub clipboard: Arc<dyn ClipboardProvider>,
 }
 
 pub struct Context {
     pub args_files: Vec<PathBuf>,
     pub current_working_dir: PathBuf,
-    pub config: EditorConfig,
+    pub config: SharedConfig,
+    pub config_path: Option<PathBuf>,
     pub modes: Vec<Mode>,
     pub task_pool: TaskPool,
-    pub clipboard: Arc<dyn Clipboard>,
+    clipboard: RwLock<Arc<dyn ClipboardProvider>>,
+    pub kill_ring: KillRing,
+    pub spell_checker: SpellChecker,
     pub link: ComponentLink<Editor>,
+    next_operation_id: AtomicUsize,
 }
 
 impl Context {
+    /// The clipboard provider set in the configuration
+    pub fn clipboard(&self) -> Arc<dyn ClipboardProvider> {
+        Arc::clone(&self.clipboard.read())
+    }
+
     pub fn mode_by_filename(&self, filename: impl AsRef<Path>) -> &Mode {
-        self.modes
-            .iter()
-            .find(|&mode| mode.matches_by_filename(filename.as_ref()))
-            .unwrap_or(&PLAIN_TEXT_MODE)
+        mode_by_filename(&self.modes, filename.as_ref())
     }
 }
 
+/// The first of `modes` matching `filename`, or plain text if none does
+pub fn mode_by_filename<'a>(modes: &'a [Mode], filename: &Path) -> &'a Mode {
+    modes
+        .iter()
+        .find(|&mode| mode.matches_by_filename(filename))
+        .unwrap_or(&PLAIN_TEXT_MODE)
+}
+
 #[derive(Clone)]
 pub struct ContextHandle(pub &'static Context);
 
@@ -118,6 +227,43 @@ impl Context {
     pub fn log(&self, message: impl Into<String>) {
         self.link.send(Message::Log(Some(message.into())));
     }
+
+    /// Asks "`message` (y/n)" in the prompt, running `on_confirm` on yes
+    pub fn confirm(&self, message: impl Into<String>, on_confirm: impl FnOnce() + Send + 'static) {
+        self.link
+            .send(Message::Confirm(message.into(), OnConfirm::new(on_confirm)));
+    }
+
+    /// Shows a long running operation in the echo area until the returned
+    /// handle is dropped. `C-g` cancels the latest operation.
+    pub fn start_operation(&self, name: impl Into<Cow<'static, str>>) -> Operation {
+        Operation::start(&self.next_operation_id, self.link.clone(), name.into())
+    }
+
+    /// Runs an external program on the task pool so the editor never waits
+    /// for it, calling `on_done` with its output. The program is shown as an
+    /// operation in progress and is killed after the configured timeout or
+    /// when cancelled.
+    pub fn spawn_command(
+        &self,
+        command: Command,
+        input: Option<String>,
+        on_done: impl FnOnce(std::result::Result<CommandOutput, CommandError>) + Send + 'static,
+    ) {
+        let operation = self.start_operation(format!(
+            "running {}",
+            command.get_program().to_string_lossy()
+        ));
+        let timeout = match self.config.get().command_timeout_seconds {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        };
+        self.task_pool.spawn(move |_| {
+            let result = run_command(command, input, timeout, &operation.cancel_token());
+            drop(operation);
+            on_done(result)
+        });
+    }
 }
 
 pub struct Editor {
@@ -127,9 +273,19 @@ pub struct Editor {
 
     prompt_action: PromptAction,
     prompt_height: usize,
+    search_options: SearchOptions,
+    // The prefix keys typed so far, e.g. `C-x-`, shown in the status bar
+    // until the command they start is complete
+    pending_keys: Option<String>,
+    // Set after `quoted-insert`, the next key is inserted literally in the
+    // focused buffer rather than running the command of any window
+    quoted_insert: Option<QuotedInsert>,
+    // Runs if the question in the prompt is answered with yes
+    on_confirm: Option<OnConfirm>,
 
     buffers: Buffers,
     windows: WindowTree<BufferViewId>,
+    operations: Operations,
 }
 
 impl Editor {
@@ -144,6 +300,21 @@ impl Editor {
         }
     }
 
+    /// Clears the search in the focused buffer if the search prompt is open
+    fn end_search(&self) {
+        if let PromptAction::Search { ref on_search, .. } = self.prompt_action {
+            on_search.emit(None);
+        }
+    }
+
+    /// Clears the search in the focused buffer if the search prompt is open,
+    /// moving the cursor back to where the search started
+    fn cancel_search(&self) {
+        if let PromptAction::Search { ref on_cancel, .. } = self.prompt_action {
+            on_cancel.emit(());
+        }
+    }
+
     fn open_file(&mut self, file_path: PathBuf) -> Result<bool> {
         // Check if the buffer is already open
         if let Some(buffer_id) = self.buffers.find_by_path(&file_path) {
@@ -151,6 +322,16 @@ impl Editor {
             return Ok(false);
         }
 
+        // Large files are loaded in the background
+        let threshold = self.context.config.get().large_file_threshold;
+        let size_bytes = fs::metadata(&file_path).map_or(0, |metadata| metadata.len());
+        if threshold > 0 && size_bytes > threshold {
+            let repo = Repository::discover(&file_path).ok().map(RepositoryRc::new);
+            let buffer_id = self.buffers.load(file_path, size_bytes, repo);
+            self.focus_on_buffer(buffer_id);
+            return Ok(false);
+        }
+
         let (is_new_file, text) = if file_path.exists() {
             (
                 false,
@@ -195,6 +376,365 @@ impl Editor {
         Ok(is_new_file)
     }
 
+    /// The buffer in the focused window, if it has a file. Logs a message for
+    /// untitled buffers.
+    fn focused_file_buffer(&self) -> Option<(BufferId, PathBuf)> {
+        let buffer = self.buffers.get(self.windows.get_focused()?.buffer_id)?;
+        match buffer.file_path() {
+            Some(file_path) => Some((buffer.id(), file_path.clone())),
+            None => {
+                self.context.log("The buffer isn't associated with a file");
+                None
+            }
+        }
+    }
+
+    /// Toggles between the focused file and its tests, trying the mode's
+    /// conventions in order. Offers to create the test file if there's none.
+    fn open_test_file(&mut self) {
+        let (buffer_id, file_path) = match self.focused_file_buffer() {
+            Some(focused) => focused,
+            None => return,
+        };
+        let cursor_id = self.windows.get_focused().unwrap().cursor_id;
+        let mut candidates = Vec::new();
+        for pattern in self
+            .buffers
+            .get(buffer_id)
+            .unwrap()
+            .mode()
+            .test_files
+            .iter()
+        {
+            match pattern {
+                TestFilePattern::Inline(ref kind, ref name) => {
+                    if let Some(line_index) = self
+                        .buffers
+                        .get_mut(buffer_id)
+                        .unwrap()
+                        .toggle_inline_tests(cursor_id, kind, name)
+                    {
+                        if let Some(cursor) = self.focused_cursor() {
+                            cursor.move_to_line(line_index);
+                        }
+                        return;
+                    }
+                }
+                pattern => match pattern.counterpart_path(&file_path) {
+                    Some(path) if path.exists() => {
+                        self.context.link.send(Message::OpenFile(path));
+                        return;
+                    }
+                    Some(path) => candidates.push(path),
+                    None => {}
+                },
+            }
+        }
+        match candidates.into_iter().next() {
+            Some(path) => self.confirm(
+                format!("No test file found. Create {}?", path.display()),
+                Message::OpenFile(path),
+            ),
+            None => self
+                .context
+                .log(format!("No test file found for {}", file_path.display())),
+        }
+    }
+
+    fn confirm(&mut self, message: String, on_confirm: Message) {
+        let link = self.context.link.clone();
+        self.confirm_with(message, OnConfirm::new(move || link.send(on_confirm)));
+    }
+
+    fn confirm_with(&mut self, message: String, on_confirm: OnConfirm) {
+        self.on_confirm = Some(on_confirm);
+        self.prompt_char(
+            message,
+            &['y', 'n'],
+            self.context
+                .link
+                .callback(|answer| Message::Confirmed(answer == 'y')),
+        );
+    }
+
+    /// Asks `message` in the prompt, to be answered by typing `yes` or `no`
+    /// in full. Other answers ask again and `C-g` cancels.
+    fn prompt_yes_no(&mut self, message: String, on_answer: Callback<bool>) {
+        let link = self.context.link.clone();
+        self.ask(
+            message,
+            Question::YesOrNo,
+            (move |answer| match answer {
+                Some(answer) => on_answer.emit(answer == Answer::Yes),
+                None => link.send(Message::Cancel),
+            })
+            .into(),
+        );
+    }
+
+    /// Asks `message` in the prompt, to be answered by one of the `allowed`
+    /// keys. Other keys ask again and `C-g` cancels.
+    fn prompt_char(&mut self, message: String, allowed: &[char], on_answer: Callback<char>) {
+        let link = self.context.link.clone();
+        self.ask(
+            message,
+            Question::Char(allowed.to_vec()),
+            (move |answer| match answer {
+                Some(Answer::Char(answer)) => on_answer.emit(answer),
+                _ => link.send(Message::Cancel),
+            })
+            .into(),
+        );
+    }
+
+    /// Reads a line of text in the prompt, accepting `default` if nothing is
+    /// typed. Input `validate` rejects shows its error and keeps the prompt
+    /// open, `C-g` cancels.
+    fn prompt_input(
+        &mut self,
+        message: Cow<'static, str>,
+        default: Option<String>,
+        validate: Option<Validator>,
+        on_input: Callback<String>,
+    ) {
+        self.prompt_action = PromptAction::Input {
+            message,
+            default,
+            validate,
+            on_input,
+        };
+        self.prompt_height = self.prompt_action.initial_height();
+    }
+
+    /// Runs `command_line` in a shell with the focused buffer's selection, or
+    /// the whole buffer, as its input and replaces it with the output. The
+    /// editor keeps running while the command does.
+    fn filter_region(&mut self, command_line: String) {
+        let view_id = match self.windows.get_focused() {
+            Some(view_id) => view_id,
+            None => return,
+        };
+        let (buffer_id, cursor_id) = (view_id.buffer_id, view_id.cursor_id);
+        let (region, input, version) = match self.buffers.get(buffer_id) {
+            Some(buffer) => buffer.filter_region(cursor_id),
+            None => return,
+        };
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(command_line)
+            .current_dir(&self.context.current_working_dir);
+        let link = self.context.link.clone();
+        self.context
+            .spawn_command(command, Some(input), move |result| {
+                link.send(Message::ShellCommandDone {
+                    buffer_id,
+                    cursor_id,
+                    region,
+                    version,
+                    result,
+                })
+            });
+    }
+
+    fn ask(&mut self, message: String, question: Question, on_answer: Callback<Option<Answer>>) {
+        self.prompt_action = PromptAction::InteractiveMessage {
+            message: message.into(),
+            question,
+            on_answer,
+        };
+        self.prompt_height = self.prompt_action.initial_height();
+    }
+
+    /// Whether the editor command `name` asks before running, given whether
+    /// it would discard unsaved changes
+    fn confirms(&self, name: &str, modified: bool) -> bool {
+        let default = commands::find(&bindings::commands(), name)
+            .map_or(Confirm::Never, |command| command.confirm);
+        self.context
+            .config
+            .get()
+            .confirmations
+            .policy(name, default)
+            .asks(modified)
+    }
+
+    fn rename_file(&mut self, buffer_id: BufferId, path: PathBuf) -> Result<()> {
+        let buffer = match self.buffers.get(buffer_id) {
+            Some(buffer) => buffer,
+            None => return Ok(()),
+        };
+        // A new buffer may not have been saved yet, there's nothing to move
+        if let Some(old_path) = buffer.file_path().filter(|old_path| old_path.exists()) {
+            if let Some(parent) = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                fs::create_dir_all(parent)?;
+            }
+            // Renaming fails across file systems, fall back to copying
+            if fs::rename(old_path, &path).is_err() {
+                fs::copy(old_path, &path)?;
+                fs::remove_file(old_path)?;
+            }
+        }
+        let repo = Repository::discover(&path).ok().map(RepositoryRc::new);
+        self.context.log(format!("Renamed to {}", path.display()));
+        self.buffers
+            .get_mut(buffer_id)
+            .unwrap()
+            .set_file_path(path, repo);
+        Ok(())
+    }
+
+    /// Takes the keys of a command during a quoted insert, which inserts the
+    /// first key of the binding and drops the rest, e.g. the `C-f` of
+    /// `C-x C-f`
+    fn intercept_keys(&self, _: &commands::Command<Self>, keys: &[Key]) -> Option<Message> {
+        let key = *keys.first()?;
+        self.quoted_insert.map(|_| Message::QuotedKey(key))
+    }
+
+    /// A handle to the cursor of the focused window
+    fn focused_cursor(&self) -> Option<BufferCursor> {
+        let view_id = self.windows.get_focused()?;
+        Some(BufferCursor::new(
+            view_id.buffer_id,
+            view_id.cursor_id,
+            self.buffers
+                .get(view_id.buffer_id)?
+                .cursor(view_id.cursor_id)
+                .clone(),
+            self.context.link.clone(),
+        ))
+    }
+
+    /// Reverts the given buffers, returning how many were reverted
+    fn revert_buffers(&mut self, buffer_ids: impl IntoIterator<Item = BufferId>) -> usize {
+        let mut num_reverted = 0;
+        for buffer_id in buffer_ids {
+            let buffer = match self.buffers.get_mut(buffer_id) {
+                Some(buffer) => buffer,
+                None => continue,
+            };
+            match buffer.revert() {
+                Ok(()) => num_reverted += 1,
+                Err(error) => self.context.log(format!(
+                    "Could not revert {} ({})",
+                    buffer
+                        .file_path()
+                        .map(|path| path.display().to_string())
+                        .unwrap_or_default(),
+                    error
+                )),
+            }
+        }
+        num_reverted
+    }
+
+    fn replace_in_files(&mut self, replacement: Replacement) {
+        let Replacement {
+            query,
+            replacement,
+            options,
+            paths,
+        } = replacement;
+        let (mut num_files, mut num_replaced) = (0, 0);
+        for path in paths {
+            match self.replace_in_file(&path, &query, &replacement, options) {
+                Ok(0) => {}
+                Ok(num_matches) => {
+                    num_files += 1;
+                    num_replaced += num_matches;
+                }
+                Err(error) => {
+                    self.context.log(format!(
+                        "Could not replace in {} ({})",
+                        path.display(),
+                        error
+                    ));
+                }
+            }
+        }
+        self.context.log(format!(
+            "Replaced {} occurrence{} in {} file{}",
+            num_replaced,
+            if num_replaced == 1 { "" } else { "s" },
+            num_files,
+            if num_files == 1 { "" } else { "s" },
+        ));
+    }
+
+    fn replace_in_file(
+        &mut self,
+        path: &Path,
+        query: &str,
+        replacement: &str,
+        options: SearchOptions,
+    ) -> Result<usize> {
+        // Edit open buffers in place, the changes are saved by the user
+        let canonical_path = fs::canonicalize(path)?;
+        let open_buffer_id = self
+            .buffers
+            .iter()
+            .find(|buffer| {
+                buffer.file_path().map_or(false, |buffer_path| {
+                    fs::canonicalize(buffer_path)
+                        .map_or(false, |buffer_path| buffer_path == canonical_path)
+                })
+            })
+            .map(|buffer| buffer.id());
+        if let Some(buffer_id) = open_buffer_id {
+            let buffer = self.buffers.get_mut(buffer_id).unwrap();
+            return Ok(buffer.replace_all(query, replacement, options));
+        }
+
+        let mut text = Rope::from_reader(BufReader::new(File::open(path)?))?;
+        if self.context.config.get().replace_in_files_opens_buffers {
+            let repo = Repository::discover(path).ok().map(RepositoryRc::new);
+            let buffer_id = self.buffers.add(text, Some(path.to_path_buf()), repo);
+            let buffer = self.buffers.get_mut(buffer_id).unwrap();
+            let num_replaced = buffer.replace_all(query, replacement, options);
+            buffer.handle_message(BufferMessage::SaveBufferStart);
+            Ok(num_replaced)
+        } else {
+            let mode = self.context.mode_by_filename(path);
+            let matches =
+                search::find_all(&text, query, options.case_mode, options.whole_word_in(mode));
+            search::replace_all(&mut text, &matches, replacement);
+            text.write_to(BufWriter::new(File::create(path)?))?;
+            Ok(matches.len())
+        }
+    }
+
+    /// Asks whether to replace the match the focused buffer's query replace
+    /// is on, or closes the prompt once it's done
+    fn ask_query_replace(&mut self, pending: Option<PendingReplacement>) {
+        match pending {
+            Some(PendingReplacement {
+                matched,
+                replacement,
+            }) => self.prompt_char(
+                format!("Replace {} with {}?", matched, replacement),
+                &['y', 'n', '!', 'q'],
+                self.context.link.callback(Message::AnswerQueryReplace),
+            ),
+            None => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+        }
+    }
+
+    /// Stops the query replace in the focused buffer, if there is one
+    fn end_query_replace(&mut self) {
+        if let Some(view_id) = self.windows.get_focused() {
+            if let Some(buffer) = self.buffers.get_mut(view_id.buffer_id) {
+                buffer.end_query_replace();
+            }
+        }
+    }
+
     fn open_buffer_picker(&mut self, message: Cow<'static, str>, on_select: Callback<BufferId>) {
         self.prompt_action = PromptAction::PickBuffer {
             message,
@@ -218,6 +758,25 @@ impl Editor {
     }
 }
 
+/// The longest prefix the paths have in common, e.g. `src/foo.c` for
+/// `src/foo.c` and `src/foo.cpp`
+fn common_prefix(paths: &[PathBuf]) -> String {
+    let mut prefix = paths
+        .first()
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    for path in paths.iter().skip(1) {
+        let path = path.to_string_lossy();
+        let prefix_len = prefix
+            .char_indices()
+            .zip(path.chars())
+            .find(|((_, prefix_char), path_char)| prefix_char != path_char)
+            .map_or(cmp::min(prefix.len(), path.len()), |((index, _), _)| index);
+        prefix.truncate(prefix_len);
+    }
+    prefix
+}
+
 impl Component for Editor {
     type Message = Message;
     type Properties = Properties;
@@ -242,10 +801,14 @@ impl Component for Editor {
                     .cloned()
                     .map(Mode::new)
                     .collect(),
-                config: properties.config,
+                spell_checker: SpellChecker::new(properties.config.spell_check_word_list.clone()),
+                config: SharedConfig::new(properties.config),
+                config_path: properties.config_path,
                 task_pool: properties.task_pool,
-                clipboard: properties.clipboard,
+                clipboard: RwLock::new(properties.clipboard),
+                kill_ring: KillRing::default(),
                 link,
+                next_operation_id: AtomicUsize::new(0),
             }
             .into(),
         ));
@@ -264,18 +827,79 @@ impl Component for Editor {
             theme_index,
             prompt_action: PromptAction::None,
             prompt_height: PROMPT_INACTIVE_HEIGHT,
+            search_options: SearchOptions::default(),
+            pending_keys: None,
+            quoted_insert: None,
+            on_confirm: None,
             buffers: Buffers::new(context.clone()),
             context,
             windows: WindowTree::new(),
+            operations: Operations::default(),
         }
     }
 
     fn update(&mut self, message: Self::Message) -> ShouldRender {
         match message {
             Message::Cancel => {
+                // Prompts are cancelled before operations in progress
+                let cancelled_operation = if self.prompt_action.is_interactive() {
+                    None
+                } else {
+                    self.operations.cancel_latest()
+                };
+                self.cancel_search();
+                self.end_query_replace();
+                self.on_confirm = None;
                 self.prompt_action = PromptAction::None;
                 self.prompt_height = self.prompt_action.initial_height();
-                self.context.log("Cancel");
+                match cancelled_operation {
+                    Some(name) => self.context.log(format!("Cancelled {}", name)),
+                    None => self.context.log("Quit"),
+                }
+            }
+            Message::ReloadConfig => {
+                let path = match self.context.config_path {
+                    Some(ref path) => path,
+                    None => {
+                        self.context
+                            .log("Could not find the configuration directory");
+                        return ShouldRender::No;
+                    }
+                };
+                // A theme changed with `C-x C-t` is kept unless the
+                // configuration changes it too
+                match self.context.config.reload(path) {
+                    Ok(old_config) => {
+                        let config = self.context.config.get();
+                        let theme_name = &config.theme;
+                        if *theme_name != old_config.theme {
+                            match THEMES.iter().position(|(_, name)| name == theme_name) {
+                                Some(theme_index) => self.theme_index = theme_index,
+                                None => self.context.log(format!("Unknown theme `{}`", theme_name)),
+                            }
+                        }
+                        if config.clipboard != old_config.clipboard {
+                            match clipboard::create(config.clipboard) {
+                                Ok(clipboard) => *self.context.clipboard.write() = clipboard,
+                                Err(error) => {
+                                    self.context.log(format!("{:#}", error));
+                                    return ShouldRender::Yes;
+                                }
+                            }
+                        }
+                        if let Err(error) = self
+                            .context
+                            .spell_checker
+                            .set_word_list(config.spell_check_word_list.clone())
+                        {
+                            self.context.log(format!("{:#}", error));
+                            return ShouldRender::Yes;
+                        }
+                        self.context
+                            .log(format!("Reloaded configuration {}", path.display()));
+                    }
+                    Err(error) => self.context.log(format!("{:#}", error)),
+                }
             }
             Message::ChangeTheme => {
                 self.theme_index = (self.theme_index + 1) % self.themes.len();
@@ -294,6 +918,106 @@ impl Component for Editor {
                 };
                 self.prompt_height = self.prompt_action.initial_height();
             }
+            Message::SearchPrompt(direction) if !self.prompt_action.is_interactive() => {
+                if let Some(cursor) = self.focused_cursor() {
+                    let on_cancel = {
+                        let cursor = cursor.clone();
+                        (move |()| cursor.cancel_search()).into()
+                    };
+                    self.prompt_action = PromptAction::Search {
+                        direction,
+                        options: self.search_options,
+                        on_search: (move |query| cursor.search(query)).into(),
+                        on_change_options: self.context.link.callback(Message::ChangeSearchOptions),
+                        on_accept: self.context.link.callback(|()| Message::EndSearch),
+                        on_cancel,
+                    };
+                    self.prompt_height = self.prompt_action.initial_height();
+                }
+            }
+            Message::ChangeSearchOptions(options) => {
+                self.search_options = options;
+            }
+            Message::ReplaceInFilesPrompt if !self.prompt_action.is_interactive() => {
+                self.prompt_action = PromptAction::ReplaceInFiles {
+                    options: self.search_options,
+                    on_replace: self.context.link.callback(Message::ReplaceInFiles),
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::ReplaceInFiles(replacement) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                self.replace_in_files(replacement);
+            }
+            Message::QueryReplacePrompt
+                if !self.prompt_action.is_interactive() && !self.windows.is_empty() =>
+            {
+                self.prompt_input(
+                    "query replace regexp".into(),
+                    None,
+                    Some(
+                        (|pattern: String| {
+                            Regex::new(&pattern)
+                                .map(|_| ())
+                                .map_err(|error| error.to_string())
+                        })
+                        .into(),
+                    ),
+                    self.context.link.callback(Message::QueryReplaceWith),
+                );
+            }
+            Message::QueryReplaceWith(pattern) => {
+                self.prompt_input(
+                    format!("query replace {} with", pattern).into(),
+                    None,
+                    None,
+                    self.context
+                        .link
+                        .callback(move |replacement| Message::QueryReplace {
+                            pattern: pattern.clone(),
+                            replacement,
+                        }),
+                );
+            }
+            Message::QueryReplace {
+                pattern,
+                replacement,
+            } => {
+                let pending = match (Regex::new(&pattern), self.windows.get_focused()) {
+                    (Ok(regex), Some(view_id)) => {
+                        self.buffers.get_mut(view_id.buffer_id).and_then(|buffer| {
+                            buffer.begin_query_replace(view_id.cursor_id, regex, replacement)
+                        })
+                    }
+                    (Err(error), _) => {
+                        self.context.log(error.to_string());
+                        None
+                    }
+                    _ => None,
+                };
+                self.ask_query_replace(pending);
+            }
+            Message::AnswerQueryReplace(answer) => {
+                let answer = match answer {
+                    'y' => QueryReplaceAnswer::Replace,
+                    'n' => QueryReplaceAnswer::Skip,
+                    '!' => QueryReplaceAnswer::ReplaceAll,
+                    _ => QueryReplaceAnswer::Quit,
+                };
+                let pending = self.windows.get_focused().and_then(|view_id| {
+                    self.buffers
+                        .get_mut(view_id.buffer_id)?
+                        .answer_query_replace(answer)
+                });
+                self.ask_query_replace(pending);
+            }
+            Message::EndSearch => {
+                self.end_search();
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+            }
             Message::OpenFile(path) => {
                 self.prompt_action = self.open_file(path).map_or_else(
                     |error| PromptAction::Log {
@@ -311,6 +1035,391 @@ impl Component for Editor {
                 );
                 self.prompt_height = self.prompt_action.initial_height();
             }
+            Message::OpenRelatedFile if !self.prompt_action.is_interactive() => {
+                if let Some((buffer_id, file_path)) = self.focused_file_buffer() {
+                    let related_files: Vec<_> = self
+                        .buffers
+                        .get(buffer_id)
+                        .unwrap()
+                        .mode()
+                        .related_files(&file_path)
+                        .into_iter()
+                        .filter(|path| path.exists())
+                        .collect();
+                    match related_files.as_slice() {
+                        [] => self
+                            .context
+                            .log(format!("No related file found for {}", file_path.display())),
+                        [path] => self.context.link.send(Message::OpenFile(path.clone())),
+                        // Let the user choose, starting from the longest
+                        // prefix the candidates have in common
+                        _ => {
+                            self.prompt_action = PromptAction::PickPath {
+                                action_name: "related".into(),
+                                initial_path: common_prefix(&related_files).into(),
+                                on_pick: self.context.link.callback(Message::OpenFile),
+                                on_change_height: self
+                                    .context
+                                    .link
+                                    .callback(Message::ChangePromptHeight),
+                            };
+                            self.prompt_height = self.prompt_action.initial_height();
+                        }
+                    }
+                }
+            }
+            Message::OpenTestFile if !self.prompt_action.is_interactive() => {
+                self.open_test_file();
+            }
+            Message::RenameFilePrompt if !self.prompt_action.is_interactive() => {
+                if let Some((buffer_id, file_path)) = self.focused_file_buffer() {
+                    self.prompt_action = PromptAction::PickPath {
+                        action_name: "rename".into(),
+                        initial_path: file_path,
+                        on_pick: self.context.link.callback(move |path| Message::RenameFile {
+                            buffer_id,
+                            path,
+                            overwrite: false,
+                        }),
+                        on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                    };
+                    self.prompt_height = self.prompt_action.initial_height();
+                }
+            }
+            Message::RenameFile {
+                buffer_id,
+                path,
+                overwrite,
+            } => {
+                let is_same_file = self
+                    .buffers
+                    .get(buffer_id)
+                    .and_then(|buffer| buffer.file_path())
+                    .map_or(false, |file_path| *file_path == path);
+                if is_same_file {
+                    self.prompt_action = PromptAction::None;
+                    self.prompt_height = self.prompt_action.initial_height();
+                } else if path.exists() && !overwrite {
+                    self.confirm(
+                        format!("{} already exists. Overwrite?", path.display()),
+                        Message::RenameFile {
+                            buffer_id,
+                            path,
+                            overwrite: true,
+                        },
+                    );
+                } else {
+                    self.prompt_action = PromptAction::None;
+                    self.prompt_height = self.prompt_action.initial_height();
+                    if let Err(error) = self.rename_file(buffer_id, path) {
+                        self.context
+                            .log(format!("Could not rename file: {}", error));
+                    }
+                }
+            }
+            Message::DeleteFilePrompt if !self.prompt_action.is_interactive() => {
+                if let Some((buffer_id, file_path)) = self.focused_file_buffer() {
+                    let modified = self.buffers.get(buffer_id).map_or(false, |buffer| {
+                        buffer.modified_status() != ModifiedStatus::Unchanged
+                    });
+                    if self.confirms("delete-file", modified) {
+                        self.confirm(
+                            format!("Delete {}?", file_path.display()),
+                            Message::DeleteFile(buffer_id),
+                        );
+                    } else {
+                        self.context.link.send(Message::DeleteFile(buffer_id));
+                    }
+                }
+            }
+            Message::DeleteFile(buffer_id) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                let file_path = self
+                    .buffers
+                    .get(buffer_id)
+                    .and_then(|buffer| buffer.file_path().cloned());
+                if let Some(file_path) = file_path {
+                    match fs::remove_file(&file_path) {
+                        Ok(()) => {}
+                        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                        Err(error) => {
+                            self.context
+                                .log(format!("Could not delete file: {}", error));
+                            return ShouldRender::Yes;
+                        }
+                    }
+                    // Killing the buffer clears the prompt, log afterwards
+                    self.context.link.send(Message::KillBuffer(buffer_id));
+                    self.context.log(format!("Deleted {}", file_path.display()));
+                }
+            }
+            Message::CreateDirectoryPrompt if !self.prompt_action.is_interactive() => {
+                self.prompt_action = PromptAction::PickPath {
+                    action_name: "mkdir".into(),
+                    initial_path: self.context.current_working_dir.join(""),
+                    on_pick: self.context.link.callback(Message::CreateDirectory),
+                    on_change_height: self.context.link.callback(Message::ChangePromptHeight),
+                };
+                self.prompt_height = self.prompt_action.initial_height();
+            }
+            Message::CreateDirectory(path) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                if path.is_dir() {
+                    self.context
+                        .log(format!("{} already exists", path.display()));
+                } else {
+                    match fs::create_dir_all(&path) {
+                        Ok(()) => self
+                            .context
+                            .log(format!("Created directory {}", path.display())),
+                        Err(error) => self
+                            .context
+                            .log(format!("Could not create directory: {}", error)),
+                    }
+                }
+            }
+            Message::GotoLinePrompt
+                if !self.prompt_action.is_interactive() && !self.windows.is_empty() =>
+            {
+                // Line numbers past the end go to the last line
+                let line_number = input::integer_in(1..=usize::MAX);
+                self.prompt_input(
+                    "goto line".into(),
+                    None,
+                    Some((move |input: String| line_number.emit(input.trim().into())).into()),
+                    self.context.link.callback(Message::GotoLine),
+                );
+            }
+            Message::GotoLine(input) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                if let (Ok(line_number), Some(cursor)) =
+                    (input.trim().parse::<usize>(), self.focused_cursor())
+                {
+                    cursor.move_to_line(line_number.saturating_sub(1));
+                }
+            }
+            Message::GotoPercentPrompt
+                if !self.prompt_action.is_interactive() && !self.windows.is_empty() =>
+            {
+                let percent = input::integer_in(0..=100);
+                self.prompt_input(
+                    "goto %".into(),
+                    None,
+                    Some(
+                        (move |input: String| {
+                            percent.emit(input.trim_end_matches('%').trim().into())
+                        })
+                        .into(),
+                    ),
+                    self.context.link.callback(Message::GotoPercent),
+                );
+            }
+            Message::GotoPercent(input) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                if let (Ok(percent), Some(cursor)) = (
+                    input.trim_end_matches('%').trim().parse::<u8>(),
+                    self.focused_cursor(),
+                ) {
+                    cursor.move_to_percent(percent);
+                }
+            }
+            Message::InsertSnippetPrompt
+                if !self.prompt_action.is_interactive() && !self.windows.is_empty() =>
+            {
+                let context = self.context.clone();
+                self.prompt_input(
+                    "snippet".into(),
+                    None,
+                    Some(
+                        (move |name: String| {
+                            if context.config.get().snippets.contains_key(&name) {
+                                Ok(())
+                            } else {
+                                Err(format!("Unknown snippet `{}`", name))
+                            }
+                        })
+                        .into(),
+                    ),
+                    self.context.link.callback(Message::InsertSnippet),
+                );
+            }
+            Message::InsertSnippet(name) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                match self.context.config.get().snippets.get(&name) {
+                    Some(template) => {
+                        if let Some(cursor) = self.focused_cursor() {
+                            cursor.insert_snippet(Snippet::parse(template));
+                        }
+                    }
+                    None => self.context.log(format!("Unknown snippet `{}`", name)),
+                }
+            }
+            Message::ShellCommandPrompt
+                if !self.prompt_action.is_interactive() && !self.windows.is_empty() =>
+            {
+                self.prompt_input(
+                    "shell command on region".into(),
+                    None,
+                    None,
+                    self.context.link.callback(Message::ShellCommand),
+                );
+            }
+            Message::ShellCommand(command_line) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                self.filter_region(command_line);
+            }
+            Message::ShellCommandDone {
+                buffer_id,
+                cursor_id,
+                region,
+                version,
+                result,
+            } => match result {
+                Ok(output) if output.status.success() => {
+                    let replaced = self.buffers.get_mut(buffer_id).map(|buffer| {
+                        buffer.replace_filtered_region(cursor_id, region, version, &output.stdout)
+                    });
+                    if replaced == Some(false) {
+                        self.context
+                            .log("The buffer changed while the command ran, it was left unchanged");
+                    }
+                }
+                Ok(output) => self.context.log(format!(
+                    "Command failed ({}): {}",
+                    output.status,
+                    output.stderr.trim()
+                )),
+                Err(error) => self.context.log(error.to_string()),
+            },
+            Message::InsertCharPrompt
+                if !self.prompt_action.is_interactive() && !self.windows.is_empty() =>
+            {
+                self.prompt_input(
+                    "insert char".into(),
+                    None,
+                    Some(
+                        (|input: String| {
+                            unicode::lookup_char(&input)
+                                .map(|_| ())
+                                .map_err(|error| error.to_string())
+                        })
+                        .into(),
+                    ),
+                    self.context.link.callback(Message::InsertCharByName),
+                );
+            }
+            Message::InsertCharByName(input) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                match unicode::lookup_char(&input) {
+                    Ok(character) => {
+                        if let Some(cursor) = self.focused_cursor() {
+                            cursor.insert_char(character, true);
+                        }
+                    }
+                    Err(error) => self.context.log(error.to_string()),
+                }
+            }
+            Message::RevertAllBuffers if !self.prompt_action.is_interactive() => {
+                // Unmodified buffers are reverted right away, modified ones
+                // only after confirming. Deleted files are left alone.
+                let (mut unmodified, mut modified, mut num_deleted) = (Vec::new(), Vec::new(), 0);
+                for buffer in self.buffers.iter_mut() {
+                    match buffer.check_disk() {
+                        DiskStatus::InSync => {}
+                        DiskStatus::Deleted => num_deleted += 1,
+                        DiskStatus::Changed
+                            if buffer.modified_status() == ModifiedStatus::Unchanged =>
+                        {
+                            unmodified.push(buffer.id())
+                        }
+                        DiskStatus::Changed => modified.push(buffer.id()),
+                    }
+                }
+                let num_reverted = self.revert_buffers(unmodified);
+                if modified.is_empty() {
+                    self.context.log(format!(
+                        "Reverted {} buffer{}{}",
+                        num_reverted,
+                        if num_reverted == 1 { "" } else { "s" },
+                        match num_deleted {
+                            0 => String::new(),
+                            1 => ", 1 file was deleted".into(),
+                            _ => format!(", {} files were deleted", num_deleted),
+                        }
+                    ));
+                } else {
+                    let link = self.context.link.clone();
+                    self.prompt_yes_no(
+                        format!(
+                            "Reverted {} buffer{}. {} modified buffer{} changed on disk. Discard \
+                             the changes and revert?",
+                            num_reverted,
+                            if num_reverted == 1 { "" } else { "s" },
+                            modified.len(),
+                            if modified.len() == 1 {
+                                " also"
+                            } else {
+                                "s also"
+                            },
+                        ),
+                        (move |discard| {
+                            link.send(if discard {
+                                Message::RevertBuffers(modified.clone())
+                            } else {
+                                Message::Cancel
+                            })
+                        })
+                        .into(),
+                    );
+                }
+            }
+            Message::RevertBuffers(buffer_ids) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                let num_reverted = self.revert_buffers(buffer_ids);
+                self.context.log(format!(
+                    "Reverted {} modified buffer{}",
+                    num_reverted,
+                    if num_reverted == 1 { "" } else { "s" }
+                ));
+            }
+            // Buffers idle since an edit are saved, unless a prompt opened
+            // in the meantime
+            Message::Autosave if !self.prompt_action.is_interactive() => {
+                let now = Instant::now();
+                for buffer in self.buffers.iter_mut() {
+                    if buffer.autosave_due(now) {
+                        buffer.autosave();
+                    }
+                }
+            }
+            Message::CheckExternalChanges if !self.prompt_action.is_interactive() => {
+                let (mut num_changed, mut num_deleted) = (0, 0);
+                for buffer in self.buffers.iter_mut() {
+                    match buffer.check_disk() {
+                        DiskStatus::InSync => {}
+                        DiskStatus::Changed => num_changed += 1,
+                        DiskStatus::Deleted => num_deleted += 1,
+                    }
+                }
+                self.context.log(match (num_changed, num_deleted) {
+                    (0, 0) => "No files changed on disk".into(),
+                    _ => format!(
+                        "{} file{} changed and {} deleted on disk, C-x R reverts unmodified \
+                         buffers",
+                        num_changed,
+                        if num_changed == 1 { "" } else { "s" },
+                        num_deleted
+                    ),
+                });
+            }
             Message::SelectBufferPicker if !self.prompt_action.is_interactive() => {
                 self.open_buffer_picker(
                     "buffer".into(),
@@ -325,9 +1434,30 @@ impl Component for Editor {
             Message::KillBufferPicker if !self.prompt_action.is_interactive() => {
                 self.open_buffer_picker(
                     "kill buffer".into(),
-                    self.context.link.callback(Message::KillBuffer),
+                    self.context.link.callback(Message::KillBufferPrompt),
                 );
             }
+            Message::KillBufferPrompt(buffer_id) => {
+                let (name, modified) = match self.buffers.get(buffer_id) {
+                    Some(buffer) => (
+                        buffer
+                            .file_path()
+                            .map_or("The buffer".into(), |path| path.display().to_string()),
+                        buffer.modified_status() != ModifiedStatus::Unchanged,
+                    ),
+                    None => return ShouldRender::No,
+                };
+                if self.confirms("kill-buffer", modified) {
+                    let message = if modified {
+                        format!("{} has unsaved changes. Kill it anyway?", name)
+                    } else {
+                        format!("Kill {}?", name)
+                    };
+                    self.confirm(message, Message::KillBuffer(buffer_id));
+                } else {
+                    self.context.link.send(Message::KillBuffer(buffer_id));
+                }
+            }
             Message::KillBuffer(buffer_id) => {
                 self.prompt_action = PromptAction::None;
                 self.prompt_height = self.prompt_action.initial_height();
@@ -362,12 +1492,61 @@ impl Component for Editor {
                     );
                 }
             }
+            // Show another view of the focused buffer, with its own cursor and
+            // scroll position, in the next window or a new one
+            Message::CloneViewOtherWindow if !self.buffers.is_empty() => {
+                if let Some(view_id) = self.windows.get_focused() {
+                    let buffer = self.buffers.get_mut(view_id.buffer_id).unwrap();
+                    let clone_id = BufferViewId::new(
+                        view_id.buffer_id,
+                        buffer.duplicate_cursor(view_id.cursor_id),
+                    );
+                    if !self.windows.set_next(clone_id) {
+                        self.windows.insert_at_focused(clone_id, FlexDirection::Row);
+                        self.windows.cycle_focus(CycleFocus::Next);
+                    }
+                }
+            }
             Message::FullscreenWindow if !self.buffers.is_empty() => {
                 self.windows.delete_all_except_focused();
             }
             Message::DeleteWindow if !self.buffers.is_empty() => {
                 self.windows.delete_focused();
             }
+            Message::QuotedInsert(quoted_insert) => {
+                self.quoted_insert = quoted_insert;
+            }
+            Message::QuotedKey(key) => {
+                // A key bound by both the editor and a buffer arrives twice,
+                // it's inserted once
+                if let Some(quoted_insert) = self.quoted_insert.take() {
+                    match quoted_insert.key(key) {
+                        QuotedKey::Digit(quoted_insert) => self.quoted_insert = Some(quoted_insert),
+                        QuotedKey::Insert(characters) => {
+                            if let Some(cursor) = self.focused_cursor() {
+                                for character in characters {
+                                    cursor.insert_char(character, true);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            Message::PendingKeys(keys) => {
+                // Clear the prefix echoed while typing a command once it's
+                // complete, unless the command logged something itself
+                let echoes_prefix = match (&self.prompt_action, &self.pending_keys) {
+                    (PromptAction::Log { message }, Some(pending_keys)) => {
+                        keys.is_none() && message.starts_with(&format!("{} (", pending_keys))
+                    }
+                    _ => false,
+                };
+                if echoes_prefix {
+                    self.prompt_action = PromptAction::None;
+                    self.prompt_height = self.prompt_action.initial_height();
+                }
+                self.pending_keys = keys;
+            }
             Message::Log(message) if !self.prompt_action.is_interactive() => {
                 self.prompt_action = message
                     .map(|message| PromptAction::Log { message })
@@ -377,22 +1556,38 @@ impl Component for Editor {
 
             // Quit zee but prompt to save changed buffers first
             Message::Quit => {
-                if self
+                let modified = self
                     .buffers
                     .iter()
-                    .any(|buffer| buffer.modified_status() != ModifiedStatus::Unchanged)
-                {
-                    let message = "One or more buffers have changed. Exit anyway?";
-                    self.prompt_action = PromptAction::InteractiveMessage {
-                        on_input: self.context.link.callback(Message::PostInteractionQuit),
-                        message: Cow::from(message),
+                    .any(|buffer| buffer.modified_status() != ModifiedStatus::Unchanged);
+                if self.confirms("quit", modified) {
+                    let message = if modified {
+                        "One or more buffers have changed. Exit anyway?"
+                    } else {
+                        "Exit?"
                     };
-                    self.prompt_height = self.prompt_action.initial_height();
+                    self.prompt_char(
+                        message.into(),
+                        &['y', 'n'],
+                        self.context
+                            .link
+                            .callback(|answer| Message::PostInteractionQuit(answer == 'y')),
+                    );
                 } else {
                     self.context.link.exit();
                 }
             }
 
+            Message::Confirm(message, on_confirm) => self.confirm_with(message, on_confirm),
+            Message::Confirmed(true) => {
+                self.prompt_action = PromptAction::None;
+                self.prompt_height = self.prompt_action.initial_height();
+                if let Some(OnConfirm(on_confirm)) = self.on_confirm.take() {
+                    on_confirm();
+                }
+            }
+            Message::Confirmed(false) => self.context.link.send(Message::Cancel),
+
             // Quit regardless of the buffer modify status
             Message::PostInteractionQuit(quit_anyway) => {
                 if quit_anyway {
@@ -403,6 +1598,7 @@ impl Component for Editor {
                 }
             }
             Message::Buffer(message) => self.buffers.handle_message(message),
+            Message::Operation(message) => self.operations.handle_message(message),
             _ => {}
         }
         ShouldRender::Yes
@@ -439,27 +1635,72 @@ impl Component for Editor {
                         ),
                         parse_tree: buffer.parse_tree().cloned(),
                         modified_status: buffer.modified_status(),
+                        disk_status: buffer.disk_status(),
+                        large_file: buffer.is_large_file(),
+                        loading_progress: buffer.loading_progress(),
+                        read_only: buffer.is_read_only(),
+                        search: buffer.search_status(),
+                        edit_point_jumps: buffer.edit_point_jumps(),
+                        case_mode: self.search_options.case_mode,
+                        rectangle: buffer.rectangle(id.cursor_id).cloned(),
+                        multi_cursor: buffer.multi_cursor(id.cursor_id).cloned(),
+                        pending_keys: self.pending_keys.clone().filter(|_| focused),
+                        quoted_insert: self.quoted_insert.is_some() && focused,
                     },
                 )
             }))
         };
 
-        Layout::column([
-            buffers,
-            Prompt::item_with_key(
-                FlexBasis::Fixed(if self.prompt_action.is_none() {
-                    PROMPT_INACTIVE_HEIGHT
-                } else {
-                    self.prompt_height
-                }),
-                "prompt",
-                PromptProperties {
-                    context: self.context.clone(),
-                    theme: Cow::Borrowed(&self.themes[self.theme_index].0.prompt),
-                    action: self.prompt_action.clone(),
-                },
-            ),
-        ])
+        let prompt_height = if self.prompt_action.is_none() {
+            PROMPT_INACTIVE_HEIGHT
+        } else {
+            self.prompt_height
+        };
+        let prompt = Prompt::with_key(
+            "prompt",
+            PromptProperties {
+                context: self.context.clone(),
+                theme: Cow::Borrowed(&self.themes[self.theme_index].0.prompt),
+                action: self.prompt_action.clone(),
+            },
+        );
+
+        // Operations in progress are shown at the end of the echo area
+        let prompt = match self
+            .operations
+            .latest()
+            .filter(|_| !self.prompt_action.is_interactive())
+        {
+            Some(latest) => {
+                let label = ProgressIndicator::label(latest, self.operations.len());
+                Item::fixed(prompt_height)(Layout::row([
+                    Item::auto(prompt),
+                    Item::fixed(ProgressIndicator::width(&label))(ProgressIndicator::with(
+                        ProgressIndicatorProperties {
+                            label,
+                            style: self.themes[self.theme_index].0.prompt.action,
+                        },
+                    )),
+                ]))
+            }
+            None => Item::fixed(prompt_height)(prompt),
+        };
+
+        Layout::column([buffers, prompt])
+    }
+
+    fn tick(&self) -> Option<Message> {
+        // Autosave is paused while a prompt is open
+        if self.prompt_action.is_interactive() {
+            return None;
+        }
+        // Ticks are subscribed to when the editor is drawn, so ask for them
+        // while any buffer waits to be autosaved, rather than once it's due.
+        // The editor isn't redrawn while it's idle.
+        self.buffers
+            .iter()
+            .any(|buffer| buffer.autosave_pending())
+            .then(|| Message::Autosave)
     }
 
     fn bindings(&self, bindings: &mut Bindings<Self>) {
@@ -490,6 +1731,16 @@ impl Component for Editor {
             .flatten();
         let merged_all = queries.iter().cloned().reduce(merge_queries).flatten();
 
+        let pending_keys = match merged_all {
+            Some(NamedBindingQuery::PrefixOf(_)) => {
+                Some(KeySequenceSlice::new(keys, true).to_string())
+            }
+            _ => None,
+        };
+        if pending_keys != self.pending_keys {
+            self.context.link.send(Message::PendingKeys(pending_keys));
+        }
+
         match merged_all {
             Some(NamedBindingQuery::Match(_command)) => match merged_without_self {
                 Some(NamedBindingQuery::Match(_command)) if self.prompt_action.is_log() => {
@@ -539,3 +1790,160 @@ impl Display for BufferViewId {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use zi::Key;
+
+    use super::*;
+    use crate::testing::{temp_file, TestEditor};
+
+    #[test]
+    fn idle_buffers_are_autosaved_on_tick() {
+        let path = temp_file("autosave.txt", "notes\n");
+        let config = EditorConfig {
+            autosave: true,
+            autosave_idle_seconds: 1,
+            ..EditorConfig::default()
+        };
+        let mut editor = TestEditor::with_config(&[&path], config);
+        editor.type_text("x");
+        editor.tick();
+        assert_eq!("notes\n", fs::read_to_string(&path).unwrap());
+
+        // The editor isn't redrawn while idle, the next tick saves it
+        std::thread::sleep(Duration::from_secs(1));
+        editor.tick();
+        editor.wait_for(|_| fs::read_to_string(&path).unwrap() == "xnotes\n");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn large_files_open_read_only_without_parsing() {
+        let path = temp_file("large-file.rs", &"fn main() {}\n".repeat(10));
+        let config = EditorConfig {
+            large_file_threshold: 64,
+            ..EditorConfig::default()
+        };
+        let mut editor = TestEditor::with_config(&[&path], config);
+        editor.wait_for(|screen| screen.contains("[read-only]"));
+        editor.type_text("x");
+        let screen = editor.screen();
+        assert!(screen.contains("Buffer is read-only, C-x C-q enables editing"));
+        assert!(!screen.contains("xfn main"));
+
+        // Parsing only starts once all features are enabled
+        editor.press([Key::Ctrl('x'), Key::Ctrl('q')]);
+        assert!(editor
+            .screen()
+            .contains("Enabled syntax parsing and editing"));
+        editor.type_text("x");
+        assert!(editor.screen().contains("xfn main() {}"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn shell_command_replaces_the_buffer_with_its_output() {
+        let path = temp_file("shell-command.txt", "one\ntwo\n");
+        let mut editor = TestEditor::open(&[&path]);
+        editor.press([Key::Alt('|')]);
+        editor.type_text("tr a-z A-Z\n");
+        editor.wait_for(|screen| screen.contains("ONE") && screen.contains("TWO"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn shell_command_past_the_timeout_is_killed_leaving_the_buffer() {
+        let path = temp_file("shell-command-timeout.txt", "one\ntwo\n");
+        let config = EditorConfig {
+            command_timeout_seconds: 1,
+            ..EditorConfig::default()
+        };
+        let mut editor = TestEditor::with_config(&[&path], config);
+        editor.press([Key::Alt('|')]);
+        editor.type_text("sleep 5; echo replaced\n");
+        editor.wait_for(|screen| screen.contains("`sh` was killed after running for 1s"));
+        let screen = editor.screen();
+        assert!(screen.contains("one") && !screen.contains("replaced"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn declining_to_kill_a_modified_buffer_keeps_it_open() {
+        let path = temp_file("kill-modified.txt", "one\n");
+        let mut editor = TestEditor::open(&[&path]);
+        editor.type_text("x");
+        editor.press([Key::Ctrl('x'), Key::Char('k')]);
+        editor.press([Key::Char('\n')]);
+        assert!(editor
+            .screen()
+            .contains("has unsaved changes. Kill it anyway?"));
+
+        editor.type_text("n");
+        let screen = editor.screen();
+        assert!(screen.contains("xone") && screen.contains("kill-modified.txt"));
+        assert!(!screen.contains("Kill it anyway?"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn cloned_views_share_the_text_but_not_the_scroll_position() {
+        let text: String = (1..=60).map(|line| format!("l{:03}\n", line)).collect();
+        let path = temp_file("clone-view.txt", &text);
+        let mut editor = TestEditor::open(&[&path]);
+        editor.press([Key::Ctrl('x'), Key::Char('4'), Key::Char('c')]);
+        editor.type_text("x");
+        assert_eq!(2, editor.screen().matches("xl001").count());
+
+        // Moving to the end in the clone leaves the first view where it was
+        editor.press([Key::Alt('>')]);
+        let screen = editor.screen();
+        assert_eq!(1, screen.matches("xl001").count());
+        assert_eq!(1, screen.matches("l060").count());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn each_query_replacement_is_undone_on_its_own() {
+        let path = temp_file("query-replace.txt", "a a a a\n");
+        let mut editor = TestEditor::open(&[&path]);
+        editor.press([Key::Alt('%')]);
+        editor.type_text("a\nb\n");
+        editor.type_text("yy!");
+        assert!(editor.screen().contains("b b b b\n"));
+
+        // Replacing the rest is a single edit
+        editor.press([Key::Ctrl('_')]);
+        assert!(editor.screen().contains("b b a a\n"));
+        editor.press([Key::Ctrl('_')]);
+        assert!(editor.screen().contains("b a a a\n"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn query_replace_moves_past_empty_matches() {
+        let path = temp_file("query-replace-empty.txt", "ab\n");
+        let mut editor = TestEditor::open(&[&path]);
+        editor.press([Key::Alt('%')]);
+        editor.type_text("x*\n\n");
+        editor.type_text("yyyy");
+        assert!(editor.screen().contains("Replaced 4 occurrences"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn quoted_insert_takes_keys_bound_by_the_editor() {
+        let path = temp_file("quoted-insert.txt", "end\n");
+        let mut editor = TestEditor::open(&[&path]);
+        editor.press([Key::Ctrl('q')]);
+        editor.press([Key::Ctrl('x'), Key::Ctrl('f')]);
+        assert!(!editor.screen().contains("open"));
+        editor.press([Key::Ctrl('q')]);
+        editor.type_text("012");
+        editor.press([Key::Ctrl('q')]);
+        editor.press([Key::Char('\t')]);
+        editor.press([Key::Ctrl('x'), Key::Ctrl('s')]);
+        editor.wait_for(|_| fs::read_to_string(&path).unwrap() == "\x18\n\tend\n");
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use zi::ComponentLink;
+
+use super::{Editor, Message};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OperationId(usize);
+
+/// Allows cancelling an operation from another thread
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+pub enum OperationMessage {
+    Start {
+        id: OperationId,
+        name: Cow<'static, str>,
+        cancel: CancelToken,
+    },
+    Progress {
+        id: OperationId,
+        percent: usize,
+    },
+    End(OperationId),
+}
+
+/// A long running operation, shown in the echo area until the handle is
+/// dropped. The handle can be moved to the thread doing the work.
+pub struct Operation {
+    id: OperationId,
+    cancel: CancelToken,
+    percent: Option<usize>,
+    link: ComponentLink<Editor>,
+}
+
+impl Operation {
+    pub(super) fn start(
+        next_id: &AtomicUsize,
+        link: ComponentLink<Editor>,
+        name: Cow<'static, str>,
+    ) -> Self {
+        let id = OperationId(next_id.fetch_add(1, Ordering::SeqCst));
+        let cancel = CancelToken::default();
+        link.send(Message::Operation(OperationMessage::Start {
+            id,
+            name,
+            cancel: cancel.clone(),
+        }));
+        Self {
+            id,
+            cancel,
+            percent: None,
+            link,
+        }
+    }
+
+    /// Reports how much of the operation is done, in the interval [0, 100]
+    pub fn progress(&mut self, percent: usize) {
+        if self.percent != Some(percent) {
+            self.percent = Some(percent);
+            self.link
+                .send(Message::Operation(OperationMessage::Progress {
+                    id: self.id,
+                    percent,
+                }));
+        }
+    }
+
+    /// Whether the user cancelled the operation, the work should stop early
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        self.link
+            .send(Message::Operation(OperationMessage::End(self.id)));
+    }
+}
+
+/// An operation in progress, as tracked by the editor
+#[derive(Clone, Debug)]
+pub struct ActiveOperation {
+    pub id: OperationId,
+    pub name: Cow<'static, str>,
+    pub percent: Option<usize>,
+    cancel: CancelToken,
+}
+
+/// The operations in progress, in the order they started
+#[derive(Default)]
+pub struct Operations {
+    active: Vec<ActiveOperation>,
+}
+
+impl Operations {
+    pub fn handle_message(&mut self, message: OperationMessage) {
+        match message {
+            OperationMessage::Start { id, name, cancel } => {
+                self.active.push(ActiveOperation {
+                    id,
+                    name,
+                    percent: None,
+                    cancel,
+                });
+            }
+            OperationMessage::Progress { id, percent } => {
+                if let Some(operation) = self.active.iter_mut().find(|operation| operation.id == id)
+                {
+                    operation.percent = Some(percent);
+                }
+            }
+            OperationMessage::End(id) => {
+                self.active.retain(|operation| operation.id != id);
+            }
+        }
+    }
+
+    /// The most recently started operation
+    pub fn latest(&self) -> Option<&ActiveOperation> {
+        self.active.last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.active.len()
+    }
+
+    /// Cancels the most recently started operation, returning its name
+    pub fn cancel_latest(&mut self) -> Option<Cow<'static, str>> {
+        let operation = self.active.pop()?;
+        operation.cancel.cancel();
+        Some(operation.name)
+    }
+}
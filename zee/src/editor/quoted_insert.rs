@@ -0,0 +1,118 @@
+use zi::terminal::Key;
+
+/// The state of `quoted-insert`, which inserts the next key typed literally
+/// rather than running the command bound to it, e.g. a real tab where tab
+/// indents. Octal digits typed first insert the character with that code
+/// instead, e.g. `0 1 2` for a line feed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QuotedInsert {
+    // The octal code typed so far and its number of digits
+    code: u32,
+    num_digits: usize,
+}
+
+/// What typing a key does during a quoted insert
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuotedKey {
+    /// A digit of an octal code, waiting for more
+    Digit(QuotedInsert),
+    /// Ends the quoted insert, inserting the characters
+    Insert(Vec<char>),
+}
+
+impl QuotedInsert {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the next key typed. An octal code ends after `MAX_OCTAL_DIGITS`
+    /// digits or at the first other key, which is inserted as well unless
+    /// it's Enter. Keys without a character, like arrows, insert nothing.
+    pub fn key(self, key: Key) -> QuotedKey {
+        let code = (self.num_digits > 0)
+            .then(|| char::from_u32(self.code))
+            .flatten();
+        match key {
+            Key::Char(digit @ '0'..='7') => {
+                let next = Self {
+                    code: self.code * 8 + digit.to_digit(8).unwrap_or(0),
+                    num_digits: self.num_digits + 1,
+                };
+                if next.num_digits < MAX_OCTAL_DIGITS {
+                    QuotedKey::Digit(next)
+                } else {
+                    QuotedKey::Insert(char::from_u32(next.code).into_iter().collect())
+                }
+            }
+            Key::Char('\n') if code.is_some() => QuotedKey::Insert(code.into_iter().collect()),
+            key => QuotedKey::Insert(code.into_iter().chain(literal(key)).collect()),
+        }
+    }
+}
+
+/// The character a key types, with `Ctrl` giving the control characters
+/// of caret notation, e.g. `C-j` for a line feed
+fn literal(key: Key) -> Option<char> {
+    match key {
+        Key::Char(character) => Some(character),
+        Key::Ctrl(letter @ 'a'..='z') => char::from_u32(u32::from(letter) - u32::from('a') + 1),
+        Key::Ctrl('@' | ' ') | Key::Null => Some('\0'),
+        Key::Ctrl('[') | Key::Esc => Some('\x1b'),
+        Key::Ctrl('\\') => Some('\x1c'),
+        Key::Ctrl(']') => Some('\x1d'),
+        Key::Ctrl('^') => Some('\x1e'),
+        Key::Ctrl('_') => Some('\x1f'),
+        Key::Ctrl('?') | Key::Backspace => Some('\x7f'),
+        _ => None,
+    }
+}
+
+const MAX_OCTAL_DIGITS: usize = 3;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_keys(keys: &[Key]) -> Option<Vec<char>> {
+        let mut quoted_insert = QuotedInsert::new();
+        for &key in keys {
+            match quoted_insert.key(key) {
+                QuotedKey::Digit(next) => quoted_insert = next,
+                QuotedKey::Insert(characters) => return Some(characters),
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn insert_literal_keys() {
+        assert_eq!(Some(vec!['\t']), type_keys(&[Key::Char('\t')]));
+        assert_eq!(Some(vec!['x']), type_keys(&[Key::Char('x')]));
+        assert_eq!(Some(vec!['8']), type_keys(&[Key::Char('8')]));
+        assert_eq!(Some(vec!['\x0c']), type_keys(&[Key::Ctrl('l')]));
+        assert_eq!(Some(vec!['\x1b']), type_keys(&[Key::Esc]));
+        assert_eq!(Some(vec![]), type_keys(&[Key::Up]));
+    }
+
+    #[test]
+    fn insert_octal_codes() {
+        use Key::Char;
+
+        assert_eq!(
+            Some(vec!['\n']),
+            type_keys(&[Char('0'), Char('1'), Char('2')])
+        );
+        assert_eq!(
+            Some(vec!['A']),
+            type_keys(&[Char('1'), Char('0'), Char('1')])
+        );
+        assert_eq!(None, type_keys(&[Char('1'), Char('2')]));
+
+        // Enter ends a shorter code, other keys are inserted after it
+        assert_eq!(
+            Some(vec!['\n']),
+            type_keys(&[Char('1'), Char('2'), Char('\n')])
+        );
+        assert_eq!(Some(vec!['\x07', 'x']), type_keys(&[Char('7'), Char('x')]));
+    }
+}
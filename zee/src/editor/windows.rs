@@ -180,6 +180,17 @@ impl<IdT: Clone + Copy + Display> WindowTree<IdT> {
         }
     }
 
+    /// Shows `id` in the window after the focused one, wrapping around, and
+    /// focuses it. Returns `false` if there's no other window.
+    pub fn set_next(&mut self, id: IdT) -> bool {
+        if self.num_windows <= WindowIndex(1) {
+            return false;
+        }
+        self.cycle_focus(CycleFocus::Next);
+        self.set_focused(id);
+        true
+    }
+
     fn find_focused_window(&self) -> NodeRef {
         self.find_window_node(self.focused_index)
     }
@@ -274,3 +285,31 @@ impl Rem<Self> for WindowIndex {
         Self(self.0 % modulus.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn show_a_view_in_the_next_window() {
+        let mut windows = WindowTree::new();
+        windows.add(1);
+        assert!(!windows.set_next(2));
+        assert_eq!(Some(1), windows.get_focused());
+
+        windows.insert_at_focused(3, FlexDirection::Column);
+        assert!(windows.set_next(2));
+        assert_eq!(Some(2), windows.get_focused());
+        assert_eq!(
+            vec![1, 2],
+            windows.nodes_mut().map(|id| *id).collect::<Vec<_>>()
+        );
+
+        // The next window of the last one is the first
+        assert!(windows.set_next(4));
+        assert_eq!(
+            vec![4, 2],
+            windows.nodes_mut().map(|id| *id).collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,14 +1,18 @@
 #![allow(clippy::reversed_empty_ranges)]
 
 mod clipboard;
+mod commands;
 mod components;
 mod config;
 mod editor;
 mod error;
 mod logging;
 mod panicking;
+mod process;
 mod syntax;
 mod task;
+#[cfg(test)]
+mod testing;
 mod utils;
 mod versioned;
 
@@ -84,7 +88,8 @@ fn start_editor() -> Result<()> {
 
     // Finds the editor configuration. If we cannot for any reason, we'll use the
     // default ones to ensure the editor opens in any environment.
-    let editor_config = config::find_editor_config(args.config_dir);
+    let config_path = config::editor_config_path(args.config_dir);
+    let editor_config = config::find_editor_config(config_path.as_deref());
 
     // Download and build tree sitter parsers if requested
     if args.build {
@@ -102,9 +107,10 @@ fn start_editor() -> Result<()> {
     zi_term::incremental()?.run_event_loop(Editor::with(EditorProperties {
         args_files: args.files,
         current_working_dir: env::current_dir()?,
+        clipboard: clipboard::create(editor_config.clipboard)?,
         config: editor_config,
+        config_path,
         task_pool: TaskPool::new()?,
-        clipboard: clipboard::create()?,
     }))?;
 
     Ok(())
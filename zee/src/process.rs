@@ -0,0 +1,197 @@
+use std::{
+    io::{self, Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+use crate::editor::operations::CancelToken;
+
+/// The output of an external program that ran to completion
+#[derive(Debug)]
+pub struct CommandOutput {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("could not run `{program}` ({source})")]
+    Spawn { program: String, source: io::Error },
+    #[error("`{program}` was killed after running for {}s", .timeout.as_secs())]
+    TimedOut { program: String, timeout: Duration },
+    #[error("`{program}` was cancelled")]
+    Cancelled { program: String },
+    #[error("`{program}` failed ({source})")]
+    Io { program: String, source: io::Error },
+}
+
+/// Runs `command` to completion, writing `input` to its standard input. The
+/// process is killed if it runs for longer than `timeout`, or as soon as the
+/// operation is cancelled.
+///
+/// Blocks the calling thread, it's meant to run on the task pool. The output is
+/// read on separate threads so a program writing more than the pipe's buffer
+/// can't deadlock.
+pub fn run_command(
+    mut command: Command,
+    input: Option<String>,
+    timeout: Option<Duration>,
+    cancel: &CancelToken,
+) -> Result<CommandOutput, CommandError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|source| CommandError::Spawn {
+            program: program.clone(),
+            source,
+        })?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        // A program may exit without reading all its input, ignore the error
+        thread::spawn(move || stdin.write_all(input.as_bytes()));
+    }
+    let stdout = read_on_thread(child.stdout.take());
+    let stderr = read_on_thread(child.stderr.take());
+
+    let status = wait_with_timeout(&mut child, &program, timeout, cancel)?;
+
+    let join = |output: JoinHandle<io::Result<String>>| {
+        output
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "reader panicked")))
+            .map_err(|source| CommandError::Io {
+                program: program.clone(),
+                source,
+            })
+    };
+    Ok(CommandOutput {
+        status,
+        stdout: join(stdout)?,
+        stderr: join(stderr)?,
+    })
+}
+
+/// Waits for a spawned program to exit. It's killed if it runs for longer
+/// than `timeout`, or as soon as the operation is cancelled.
+///
+/// Blocks the calling thread like [`run_command`], for programs whose output
+/// isn't read, e.g. ones forking to keep running in the background.
+pub fn wait_with_timeout(
+    child: &mut Child,
+    program: &str,
+    timeout: Option<Duration>,
+    cancel: &CancelToken,
+) -> Result<ExitStatus, CommandError> {
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().map_err(|source| CommandError::Io {
+            program: program.to_owned(),
+            source,
+        })? {
+            return Ok(status);
+        }
+        if cancel.is_cancelled() {
+            kill(child);
+            return Err(CommandError::Cancelled {
+                program: program.to_owned(),
+            });
+        }
+        if let Some(timeout) = timeout.filter(|&timeout| started.elapsed() >= timeout) {
+            kill(child);
+            return Err(CommandError::TimedOut {
+                program: program.to_owned(),
+                timeout,
+            });
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn read_on_thread(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<io::Result<String>> {
+    thread::spawn(move || {
+        let mut output = String::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_string(&mut output)?;
+        }
+        Ok(output)
+    })
+}
+
+/// Kills a process and reaps it. The threads reading its output are left to
+/// finish on their own, the pipes may be held open by its children.
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[test]
+    fn run_command_with_input() {
+        let output = run_command(
+            shell("tr a-z A-Z"),
+            Some("zee\n".into()),
+            Some(Duration::from_secs(10)),
+            &CancelToken::default(),
+        )
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!("ZEE\n", output.stdout);
+    }
+
+    #[test]
+    fn run_command_kills_after_timeout() {
+        let started = Instant::now();
+        let result = run_command(
+            shell("sleep 10"),
+            None,
+            Some(Duration::from_millis(100)),
+            &CancelToken::default(),
+        );
+        assert!(matches!(result, Err(CommandError::TimedOut { .. })));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn wait_with_timeout_kills_a_program_whose_output_is_not_read() {
+        let mut child = shell("sleep 10").stdout(Stdio::null()).spawn().unwrap();
+        let started = Instant::now();
+        let result = wait_with_timeout(
+            &mut child,
+            "sh",
+            Some(Duration::from_millis(100)),
+            &CancelToken::default(),
+        );
+        assert!(matches!(result, Err(CommandError::TimedOut { .. })));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn run_command_cancelled() {
+        let cancel = CancelToken::default();
+        cancel.cancel();
+        let result = run_command(shell("sleep 10"), None, None, &cancel);
+        assert!(matches!(result, Err(CommandError::Cancelled { .. })));
+    }
+}
@@ -1,12 +1,17 @@
 use zi::terminal::{Background, Style};
 
-use zee_edit::{CharIndex, Cursor};
-
 #[derive(Clone, Debug, PartialEq)]
 pub struct Theme {
     pub cursor_focused: Style,
     pub cursor_unfocused: Style,
     pub selection_background: Background,
+    pub symbol_highlight_background: Background,
+    /// The bracket under the cursor and the one matching it
+    pub matching_bracket_background: Background,
+    /// Occurrences of the search query
+    pub search_match: Style,
+    /// The occurrence of the search query under the cursor
+    pub search_current_match: Style,
     pub text: Style,
     pub text_current_line: Style,
     pub code_char: Style,
@@ -23,14 +28,16 @@ pub struct Theme {
     pub code_string: Style,
     pub code_type: Style,
     pub code_variant: Style,
+    /// Keywords like `TODO` in comments
+    pub code_todo: Style,
 }
 
+/// The style of a character from its syntax highlighting `scope`, on the
+/// background of the current line if it's under the cursor. Selections,
+/// cursors and other highlights are drawn over it as overlays.
 #[inline]
 pub fn text_style_at_char(
     theme: &Theme,
-    cursor: &Cursor,
-    char_index: CharIndex,
-    focused: bool,
     line_under_cursor: bool,
     scope: &str,
     is_error: bool,
@@ -76,32 +83,13 @@ pub fn text_style_at_char(
 
         _ => theme.text,
     };
-
-    if char_index == cursor.range().start || cursor.range().contains(&char_index) {
-        let cursor_style = if focused {
-            theme.cursor_focused
-        } else {
-            theme.cursor_unfocused
-        };
-        Style {
-            background: cursor_style.background,
-            foreground: cursor_style.foreground,
-            bold: style.bold,
-            underline: style.underline,
-        }
+    let background = if line_under_cursor {
+        theme.text_current_line.background
     } else {
-        let background = if cursor.selection().contains(&char_index) {
-            theme.selection_background
-        } else if line_under_cursor && focused {
-            theme.text_current_line.background
-        } else {
-            theme.text.background
-        };
-        Style {
-            background,
-            foreground: style.foreground,
-            bold: style.bold,
-            underline: style.underline,
-        }
+        theme.text.background
+    };
+    Style {
+        background,
+        ..style
     }
 }
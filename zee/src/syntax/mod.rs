@@ -1,4 +1,7 @@
 pub mod highlight;
 pub mod parse;
+pub mod spelling;
+pub mod structure;
+pub mod todo;
 
 pub use parse::{ParseTree, ParserPool};
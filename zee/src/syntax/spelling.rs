@@ -0,0 +1,128 @@
+use parking_lot::RwLock;
+use ropey::Rope;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use zee_edit::{
+    spell::{self, Dictionary},
+    CharIndex,
+};
+use zee_grammar::Mode;
+
+use super::{structure, ParseTree};
+use crate::error::{Context as _, Result};
+
+/// Modes whose text is prose, checked everywhere rather than only in
+/// comments and strings
+const PROSE_MODES: &[&str] = &["Markdown"];
+
+/// The dictionary used to spell check buffers. It's loaded the first time
+/// it's needed, from the configured word list and the personal dictionary.
+pub struct SpellChecker {
+    word_list: RwLock<PathBuf>,
+    personal_dictionary: Option<PathBuf>,
+    dictionary: RwLock<Option<Dictionary>>,
+}
+
+impl SpellChecker {
+    pub fn new(word_list: PathBuf) -> Self {
+        Self {
+            word_list: RwLock::new(word_list),
+            personal_dictionary: zee_grammar::config::config_dir()
+                .ok()
+                .map(|config_dir| config_dir.join("personal_dictionary.txt")),
+            dictionary: RwLock::new(None),
+        }
+    }
+
+    /// Loads the dictionary if it isn't loaded yet
+    pub fn load(&self) -> Result<()> {
+        if self.dictionary.read().is_some() {
+            return Ok(());
+        }
+        let mut dictionary = Dictionary::new();
+        dictionary.add_word_list(&read_word_list(&self.word_list.read())?);
+        if let Some(ref path) = self.personal_dictionary {
+            if path.exists() {
+                dictionary.add_word_list(&read_word_list(path)?);
+            }
+        }
+        *self.dictionary.write() = Some(dictionary);
+        Ok(())
+    }
+
+    /// Switches to the word list at `word_list`, e.g. after the configuration
+    /// is reloaded. A dictionary already loaded is loaded again from it.
+    pub fn set_word_list(&self, word_list: PathBuf) -> Result<()> {
+        if *self.word_list.read() == word_list {
+            return Ok(());
+        }
+        *self.word_list.write() = word_list;
+        let loaded = self.dictionary.write().take().is_some();
+        if loaded {
+            self.load()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Calls `f` with the dictionary, if it's loaded
+    pub fn with_dictionary<T>(&self, f: impl FnOnce(&Dictionary) -> T) -> Option<T> {
+        self.dictionary.read().as_ref().map(f)
+    }
+
+    /// Adds `word` to the personal dictionary, saving it to its file
+    pub fn add_word(&self, word: &str) -> Result<()> {
+        let path = self
+            .personal_dictionary
+            .as_ref()
+            .context("Could not find the configuration directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Could not create directory `{}`", parent.display()))?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", word))
+            .with_context(|| format!("Could not write to `{}`", path.display()))?;
+        if let Some(ref mut dictionary) = *self.dictionary.write() {
+            dictionary.insert(word);
+        }
+        Ok(())
+    }
+}
+
+/// Finds the misspelled words in the `range` of `text`, ordered by position.
+/// With a parse tree only the words in comments and strings are checked,
+/// unless the mode is for prose like Markdown.
+pub fn find_misspellings(
+    dictionary: &Dictionary,
+    mode: &Mode,
+    text: &Rope,
+    range: Range<CharIndex>,
+    parse_tree: Option<&ParseTree>,
+) -> Vec<Range<CharIndex>> {
+    let comments = parse_tree
+        .filter(|_| !PROSE_MODES.contains(&mode.name.as_str()))
+        .map(|tree| structure::comment_ranges(tree, text, range.clone(), true));
+    spell::misspelled(dictionary, &text.slice(range.clone()).to_string())
+        .into_iter()
+        .map(|found| range.start + found.start..range.start + found.end)
+        .filter(|found| {
+            comments
+                .as_ref()
+                .map_or(true, |comments| structure::is_inside(comments, found))
+        })
+        .collect()
+}
+
+fn read_word_list(path: &Path) -> Result<String> {
+    fs::read_to_string(path)
+        .with_context(|| format!("Could not read the word list `{}`", path.display()))
+}
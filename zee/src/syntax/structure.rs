@@ -0,0 +1,347 @@
+use ropey::Rope;
+use std::{iter, ops::Range};
+use tree_sitter::Node;
+
+use zee_edit::{CharIndex, Direction, LineIndex};
+
+use super::ParseTree;
+
+/// The syntax node structural commands act on for the text in `range`, e.g.
+/// the selection. It's the smallest named node spanning the range, or its
+/// largest ancestor covering exactly the same text.
+pub fn node_at<'tree>(
+    tree: &'tree ParseTree,
+    text: &Rope,
+    range: Range<CharIndex>,
+) -> Option<Node<'tree>> {
+    let mut node = tree.root_node().named_descendant_for_byte_range(
+        text.char_to_byte(range.start),
+        text.char_to_byte(range.end),
+    )?;
+    while let Some(parent) = node.parent() {
+        if parent.byte_range() != node.byte_range() {
+            break;
+        }
+        node = parent;
+    }
+    Some(node)
+}
+
+/// The next or previous named sibling of a node, e.g. the next element of an
+/// array or argument of a function call
+pub fn sibling(node: Node, direction: Direction) -> Option<Node> {
+    match direction {
+        Direction::Forward => node.next_named_sibling(),
+        Direction::Backward => node.prev_named_sibling(),
+    }
+}
+
+/// The closest ancestor of a node which spans more text than it
+pub fn parent(node: Node) -> Option<Node> {
+    let mut parent = node.parent();
+    while let Some(current) = parent {
+        if current.byte_range() != node.byte_range() {
+            return Some(current);
+        }
+        parent = current.parent();
+    }
+    None
+}
+
+/// The text a node spans, as a char range
+pub fn char_range(text: &Rope, node: Node) -> Range<CharIndex> {
+    text.byte_to_char(node.start_byte())..text.byte_to_char(node.end_byte())
+}
+
+/// The comments overlapping the `range` of `text` as char ranges, ordered by
+/// position, and the strings too if `strings` is set. Grammars name these
+/// nodes differently, e.g. `line_comment` or `string_literal`, so any kind
+/// containing `comment` or `string` counts.
+pub fn comment_ranges(
+    tree: &ParseTree,
+    text: &Rope,
+    range: Range<CharIndex>,
+    strings: bool,
+) -> Vec<Range<CharIndex>> {
+    let byte_range = text.char_to_byte(range.start)..text.char_to_byte(range.end);
+    let is_comment = |node: Node| {
+        let kind = node.kind();
+        kind.contains("comment") || (strings && kind.contains("string"))
+    };
+
+    let mut ranges = Vec::new();
+    let mut cursor = tree.root_node().walk();
+    loop {
+        let node = cursor.node();
+        let overlaps = node.start_byte() < byte_range.end && byte_range.start < node.end_byte();
+        if overlaps && is_comment(node) {
+            ranges.push(char_range(text, node));
+        } else if overlaps && cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return ranges;
+            }
+        }
+    }
+}
+
+/// Whether `range` is inside one of `ranges`
+pub fn is_inside(ranges: &[Range<CharIndex>], range: &Range<CharIndex>) -> bool {
+    ranges
+        .iter()
+        .any(|outer| outer.start <= range.start && range.end <= outer.end)
+}
+
+/// The first lines of the scopes enclosing `line`, like functions, classes
+/// and impls, outermost first. Only scopes starting above the line count, as
+/// their first line is out of view when `line` is the top of a window. At
+/// most `max_depth` of the innermost are kept.
+pub fn enclosing_scope_lines(
+    tree: &ParseTree,
+    text: &Rope,
+    line: LineIndex,
+    max_depth: usize,
+) -> Vec<LineIndex> {
+    let line_start = match text.try_line_to_byte(line) {
+        Ok(line_start) => line_start,
+        Err(_) => return Vec::new(),
+    };
+    let mut node = tree
+        .root_node()
+        .named_descendant_for_byte_range(line_start, line_start);
+    let spans = iter::from_fn(|| {
+        let current = node?;
+        node = current.parent();
+        Some(current)
+    })
+    .filter(|&node| is_scope(node))
+    .map(|node| node.start_position().row..node.end_position().row + 1);
+    scope_lines(spans, line, max_depth)
+}
+
+/// Whether a node is a scope shown for context: a named definition, e.g. a
+/// function or class, or an impl, which has a type and a body
+fn is_scope(node: Node) -> bool {
+    let field = |name| node.child_by_field_name(name).is_some();
+    node.is_named()
+        && !node.kind().ends_with("expression")
+        && (field("name") || field("type") && field("body"))
+}
+
+/// The first lines of the scopes spanning `spans` which enclose `line`,
+/// given from the innermost scope outwards, as returned by
+/// [`enclosing_scope_lines`]
+fn scope_lines(
+    spans: impl Iterator<Item = Range<LineIndex>>,
+    line: LineIndex,
+    max_depth: usize,
+) -> Vec<LineIndex> {
+    let mut lines: Vec<_> = spans
+        .filter(|span| span.start < line && line < span.end)
+        .map(|span| span.start)
+        .take(max_depth)
+        .collect();
+    lines.reverse();
+    lines.dedup();
+    lines
+}
+
+/// The names of the scopes enclosing `position`, outermost first, e.g.
+/// `["mod foo", "impl Bar", "fn baz"]`. Nodes without a name, like blocks
+/// and expressions, are skipped.
+pub fn syntax_path(tree: &ParseTree, text: &Rope, position: CharIndex) -> Vec<String> {
+    let byte = text.char_to_byte(position);
+    let mut node = tree.root_node().named_descendant_for_byte_range(byte, byte);
+    let nodes = iter::from_fn(|| {
+        let current = node?;
+        node = current.parent();
+        Some(current)
+    })
+    .map(|node| (node.byte_range(), scope_label(text, node)));
+    path_at(nodes, byte)
+}
+
+/// The keyword and name of a scope, e.g. `fn baz`, or just its name if it
+/// doesn't start with a keyword
+fn scope_label(text: &Rope, node: Node) -> Option<String> {
+    if !is_scope(node) {
+        return None;
+    }
+    let name = node
+        .child_by_field_name("name")
+        .or_else(|| node.child_by_field_name("type"))?;
+    let name = text
+        .slice(char_range(text, name))
+        .lines()
+        .next()?
+        .to_string();
+    let keyword = (0..node.child_count())
+        .filter_map(|index| node.child(index))
+        .find(|child| !child.is_named() && child.kind().chars().all(|c| c.is_ascii_alphabetic()));
+    Some(match keyword {
+        Some(keyword) => format!("{} {}", keyword.kind(), name.trim()),
+        None => name.trim().to_owned(),
+    })
+}
+
+/// The labels of the nodes spanning `byte`, given from the innermost node
+/// outwards with their byte ranges, as returned by [`syntax_path`]
+fn path_at(
+    nodes: impl Iterator<Item = (Range<usize>, Option<String>)>,
+    byte: usize,
+) -> Vec<String> {
+    let mut path: Vec<_> = nodes
+        .filter(|(range, _)| range.contains(&byte))
+        .filter_map(|(_, label)| label)
+        .collect();
+    path.reverse();
+    path
+}
+
+/// Joins a syntax path with `›`, leaving out scopes from the middle with `…`
+/// until it's at most `max_width` characters. The outermost and innermost
+/// scopes are always kept.
+pub fn elide_path(path: &[String], max_width: usize) -> String {
+    let join = |labels: &[&str]| labels.join(PATH_SEPARATOR);
+    let mut labels: Vec<&str> = path.iter().map(String::as_str).collect();
+    let mut joined = join(&labels);
+    while joined.chars().count() > max_width && labels.len() > 2 {
+        if labels[1] == ELLIPSIS {
+            if labels.len() == 3 {
+                break;
+            }
+            labels.remove(2);
+        } else {
+            labels[1] = ELLIPSIS;
+        }
+        joined = join(&labels);
+    }
+    joined
+}
+
+const PATH_SEPARATOR: &str = " › ";
+const ELLIPSIS: &str = "…";
+
+/// The first node of a kind whose `name` field is `name`, in document order,
+/// e.g. the `mod_item` named `tests` of a Rust file
+pub fn find_named_node<'tree>(
+    tree: &'tree ParseTree,
+    text: &Rope,
+    kind: &str,
+    name: &str,
+) -> Option<Node<'tree>> {
+    let mut cursor = tree.root_node().walk();
+    loop {
+        let node = cursor.node();
+        if node.kind() == kind
+            && node.child_by_field_name("name").map_or(false, |name_node| {
+                text.slice(char_range(text, name_node)) == name
+            })
+        {
+            return Some(node);
+        }
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        loop {
+            if !cursor.goto_parent() {
+                return None;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::parse_rust;
+
+    #[test]
+    fn comments_and_strings_in_a_range() {
+        let text = Rope::from(
+            "// first\nfn main() {\n    let s = \"a string\"; // second\n}\n/* last */\n",
+        );
+        let tree = parse_rust(&text);
+        let slice = |range: &Range<CharIndex>| text.slice(range.clone()).to_string();
+        let all = 0..text.len_chars();
+
+        let comments = comment_ranges(&tree, &text, all.clone(), false);
+        assert_eq!(
+            vec!["// first", "// second", "/* last */"],
+            comments.iter().map(slice).collect::<Vec<_>>()
+        );
+        let with_strings = comment_ranges(&tree, &text, all, true);
+        assert_eq!(
+            vec!["// first", "\"a string\"", "// second", "/* last */"],
+            with_strings.iter().map(slice).collect::<Vec<_>>()
+        );
+
+        // Only the comments overlapping the range, e.g. the lines in view
+        let line = text.line_to_char(2)..text.line_to_char(3);
+        let in_line = comment_ranges(&tree, &text, line, false);
+        assert_eq!(
+            vec!["// second"],
+            in_line.iter().map(slice).collect::<Vec<_>>()
+        );
+        assert!(is_inside(&in_line, &(in_line[0].start + 3..in_line[0].end)));
+        assert!(!is_inside(&in_line, &(0..3)));
+    }
+
+    #[test]
+    fn scopes_enclosing_a_line() {
+        // A method on lines 10-40 of an impl on lines 5-60 of a module
+        let spans = || vec![10..41, 5..61, 0..100].into_iter();
+        assert_eq!(vec![0, 5, 10], scope_lines(spans(), 20, 3));
+        assert_eq!(vec![5, 10], scope_lines(spans(), 20, 2));
+
+        // A scope starting at the line is in view, one ending above it is
+        // left out
+        assert_eq!(vec![0, 5], scope_lines(spans(), 10, 3));
+        assert_eq!(vec![0, 5], scope_lines(spans(), 41, 3));
+        assert_eq!(vec![0, 5, 10], scope_lines(spans(), 40, 3));
+        assert_eq!(Vec::<LineIndex>::new(), scope_lines(spans(), 0, 3));
+        assert_eq!(Vec::<LineIndex>::new(), scope_lines(spans(), 20, 0));
+
+        // Scopes starting on the same line show once
+        assert_eq!(vec![5], scope_lines(vec![5..10, 5..20].into_iter(), 7, 3));
+    }
+
+    #[test]
+    fn path_to_the_cursor() {
+        // A block in `fn baz` of `impl Bar` in `mod foo`
+        let nodes = |byte| {
+            path_at(
+                vec![
+                    (25..45, None),
+                    (20..50, Some("fn baz".to_owned())),
+                    (10..90, Some("impl Bar".to_owned())),
+                    (0..100, Some("mod foo".to_owned())),
+                    (0..120, None),
+                ]
+                .into_iter(),
+                byte,
+            )
+        };
+        assert_eq!(vec!["mod foo", "impl Bar", "fn baz"], nodes(30));
+        assert_eq!(vec!["mod foo", "impl Bar"], nodes(50));
+        assert_eq!(Vec::<String>::new(), nodes(110));
+
+        let path: Vec<_> = ["mod foo", "impl Bar", "fn baz", "fn inner"]
+            .iter()
+            .map(|label| label.to_string())
+            .collect();
+        assert_eq!(
+            "mod foo › impl Bar › fn baz › fn inner",
+            elide_path(&path, 80)
+        );
+        assert_eq!("mod foo › … › fn baz › fn inner", elide_path(&path, 35));
+        assert_eq!("mod foo › … › fn inner", elide_path(&path, 25));
+        assert_eq!("mod foo › … › fn inner", elide_path(&path, 5));
+        assert_eq!("mod foo › impl Bar", elide_path(&path[..2], 5));
+    }
+}
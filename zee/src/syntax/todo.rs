@@ -0,0 +1,38 @@
+use ropey::Rope;
+use std::ops::Range;
+
+use zee_edit::{
+    search::{self, CaseMode},
+    CharIndex,
+};
+
+use super::{structure, ParseTree};
+use crate::config::EditorConfig;
+
+/// Finds the `todo_keywords` in the `range` of `text`, e.g. `TODO` and
+/// `FIXME`, ordered by position. With a parse tree only the keywords inside
+/// comments count, without one they're found anywhere.
+pub fn find_todos(
+    config: &EditorConfig,
+    text: &Rope,
+    range: Range<CharIndex>,
+    parse_tree: Option<&ParseTree>,
+) -> Vec<Range<CharIndex>> {
+    let case_mode = if config.todo_keywords_ignore_case {
+        CaseMode::Insensitive
+    } else {
+        CaseMode::Sensitive
+    };
+    let comments =
+        parse_tree.map(|tree| structure::comment_ranges(tree, text, range.clone(), false));
+    let in_range = Rope::from(text.slice(range.clone()));
+    search::find_keywords(&in_range, &config.todo_keywords, case_mode)
+        .into_iter()
+        .map(|found| range.start + found.start..range.start + found.end)
+        .filter(|found| {
+            comments
+                .as_ref()
+                .map_or(true, |comments| structure::is_inside(comments, found))
+        })
+        .collect()
+}
@@ -0,0 +1,161 @@
+//! Runs the editor without a terminal, so tests can press keys and read what
+//! it draws
+
+use ropey::Rope;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+use tree_sitter::Parser;
+use zi::{
+    app::{App, ComponentMessage, MessageSender},
+    terminal::{Event, Key, Size},
+    ComponentExt,
+};
+
+use crate::{
+    clipboard::{self, ClipboardSetting},
+    config::EditorConfig,
+    editor::{Editor, Properties},
+    syntax::ParseTree,
+    task::TaskPool,
+};
+
+#[derive(Clone, Debug)]
+struct MessageQueue(mpsc::Sender<ComponentMessage>);
+
+impl MessageSender for MessageQueue {
+    fn send(&self, message: ComponentMessage) {
+        // The receiver is only gone once the test is over
+        let _ = self.0.send(message);
+    }
+
+    fn clone_box(&self) -> Box<dyn MessageSender> {
+        Box::new(self.clone())
+    }
+}
+
+pub struct TestEditor {
+    app: App,
+    messages: mpsc::Receiver<ComponentMessage>,
+}
+
+impl TestEditor {
+    /// Starts the editor with the default configuration, opening `files`
+    pub fn open(files: &[&Path]) -> Self {
+        Self::with_config(files, EditorConfig::default())
+    }
+
+    pub fn with_config(files: &[&Path], config: EditorConfig) -> Self {
+        let (sender, messages) = mpsc::channel();
+        let app = App::new(
+            MessageQueue(sender),
+            Size::new(80, 24),
+            Editor::with(Properties {
+                args_files: files.iter().map(|path| path.to_path_buf()).collect(),
+                current_working_dir: std::env::temp_dir(),
+                clipboard: clipboard::create(ClipboardSetting::Native).unwrap(),
+                config,
+                config_path: None,
+                task_pool: TaskPool::new().unwrap(),
+            }),
+        );
+        let mut editor = Self { app, messages };
+        editor.settle();
+        editor
+    }
+
+    /// Delivers the editor's messages until none arrive for a while
+    pub fn settle(&mut self) {
+        self.app.draw();
+        while let Ok(message) = self.messages.recv_timeout(IDLE) {
+            self.app.handle_message(message);
+            self.app.draw();
+        }
+    }
+
+    /// Delivers the editor's messages until `done` holds for the screen, e.g.
+    /// once a background task finished. Panics after a few seconds.
+    pub fn wait_for(&mut self, done: impl Fn(&str) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done(&self.screen()) {
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting, the screen is:\n{}",
+                self.screen()
+            );
+            if let Ok(message) = self.messages.recv_timeout(IDLE) {
+                self.app.handle_message(message);
+            }
+        }
+    }
+
+    /// Runs the components' ticks, as the terminal's event loop does
+    /// periodically
+    pub fn tick(&mut self) {
+        self.app.tick();
+        self.settle();
+    }
+
+    pub fn press(&mut self, keys: impl IntoIterator<Item = Key>) {
+        for key in keys {
+            self.app.handle_input(Event::KeyPress(key));
+            self.settle();
+        }
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.press(text.chars().map(Key::Char));
+    }
+
+    /// Sends the keys of `text` without pausing in between, the way a
+    /// terminal sends a paste
+    pub fn paste(&mut self, text: &str) {
+        for character in text.chars() {
+            self.app.handle_input(Event::KeyPress(Key::Char(character)));
+        }
+        self.settle();
+    }
+
+    /// The text drawn on screen, a line per row without trailing spaces
+    pub fn screen(&mut self) -> String {
+        let canvas = self.app.draw();
+        canvas
+            .buffer()
+            .chunks(canvas.size().width)
+            .map(|row| {
+                let line: String = row
+                    .iter()
+                    .map(|textel| {
+                        textel
+                            .as_ref()
+                            .map_or(" ", |textel| textel.grapheme.as_str())
+                    })
+                    .collect();
+                line.trim_end().to_owned()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Parses `text` as Rust, for tests of syntax-aware commands
+pub fn parse_rust(text: &Rope) -> ParseTree {
+    let mut parser = Parser::new();
+    parser.set_language(tree_sitter_rust::language()).unwrap();
+    ParseTree {
+        version: 0,
+        tree: parser.parse(text.to_string(), None).unwrap(),
+    }
+}
+
+/// Writes `contents` to a file in the temporary directory, named after
+/// `name` and the process so that concurrent test runs don't share it
+pub fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("zee-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+const IDLE: Duration = Duration::from_millis(50);
@@ -1,4 +1,5 @@
 use ropey::Rope;
+use std::{fs::OpenOptions, io::Write};
 
 #[derive(Copy)]
 pub struct StaticRefEq<T: 'static>(&'static T);
@@ -34,3 +35,12 @@ pub fn ensure_trailing_newline_with_content(text: &mut Rope) {
         text.insert_char(text.len_chars(), '\n');
     }
 }
+
+/// Rings the terminal bell to signal invalid input. Failing to do so isn't
+/// worth reporting, e.g. there may be no controlling terminal.
+pub fn ring_bell() {
+    let _ = OpenOptions::new()
+        .write(true)
+        .open("/dev/tty")
+        .and_then(|mut terminal| terminal.write_all(b"\x07"));
+}